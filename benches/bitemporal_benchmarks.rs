@@ -1,114 +1,59 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
 use pprof::criterion::{Output, PProfProfiler};
+use pytemporal::testing::{scenario_batch, ScenarioRow, SCENARIO_ID_COLUMNS, SCENARIO_VALUE_COLUMNS};
 use pytemporal::*;
-use chrono::NaiveDate;
-use arrow::array::{TimestampMicrosecondArray, Int32Array, StringBuilder};
-use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use chrono::{Days, NaiveDate};
 use arrow::record_batch::RecordBatch;
-use std::sync::Arc;
-
-// Helper function to create test batches
-fn create_test_batch(
-    data: Vec<(i32, &str, i32, i32, &str, &str, &str, &str)>,
-) -> Result<RecordBatch, String> {
-    let mut id_builder = Int32Array::builder(data.len());
-    let mut field_builder = arrow::array::StringBuilder::new();
-    let mut mv_builder = Int32Array::builder(data.len());
-    let mut price_builder = Int32Array::builder(data.len());
-    let mut eff_from_builder = TimestampMicrosecondArray::builder(data.len());
-    let mut eff_to_builder = TimestampMicrosecondArray::builder(data.len());
-    let mut as_of_from_builder = TimestampMicrosecondArray::builder(data.len());
-    let mut as_of_to_builder = TimestampMicrosecondArray::builder(data.len());
-    let mut value_hash_builder = StringBuilder::new();
-
-    // Constants for date conversion
-    const MAX_DATE: NaiveDate = match NaiveDate::from_ymd_opt(2262, 4, 11) {
-        Some(date) => date,
-        None => panic!("Invalid max date"),
-    };
-    let _epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
-
-    for (id, field, mv, price, eff_from, eff_to, as_of_from, as_of_to) in data {
-        id_builder.append_value(id);
-        field_builder.append_value(field);
-        mv_builder.append_value(mv);
-        price_builder.append_value(price);
-        
-        let eff_from_date = NaiveDate::parse_from_str(eff_from, "%Y-%m-%d")
-            .map_err(|e| e.to_string())?;
-        let eff_from_datetime = eff_from_date.and_hms_opt(0, 0, 0).unwrap();
-        let epoch_datetime = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-        let eff_from_micros = (eff_from_datetime - epoch_datetime).num_microseconds().unwrap();
-        eff_from_builder.append_value(eff_from_micros);
-        
-        let eff_to_date = if eff_to == "max" {
-            MAX_DATE
-        } else {
-            NaiveDate::parse_from_str(eff_to, "%Y-%m-%d")
-                .map_err(|e| e.to_string())?
-        };
-        let eff_to_datetime = eff_to_date.and_hms_opt(0, 0, 0).unwrap();
-        let eff_to_micros = (eff_to_datetime - epoch_datetime).num_microseconds().unwrap();
-        eff_to_builder.append_value(eff_to_micros);
-        
-        let as_of_from_date = NaiveDate::parse_from_str(as_of_from, "%Y-%m-%d")
-            .map_err(|e| e.to_string())?
-            .and_hms_opt(0, 0, 0).unwrap();
-        let epoch_datetime = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-        let as_of_from_microseconds = (as_of_from_date - epoch_datetime).num_microseconds().unwrap();
-        as_of_from_builder.append_value(as_of_from_microseconds);
-        
-        let as_of_to_date = if as_of_to == "max" {
-            MAX_DATE.and_hms_opt(23, 59, 59).unwrap()
-        } else {
-            NaiveDate::parse_from_str(as_of_to, "%Y-%m-%d")
-                .map_err(|e| e.to_string())?
-                .and_hms_opt(23, 59, 59).unwrap()
-        };
-        let epoch_datetime = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-        let as_of_to_microseconds = (as_of_to_date - epoch_datetime).num_microseconds().unwrap();
-        as_of_to_builder.append_value(as_of_to_microseconds);
-        
-        // Compute hash based on mv and price (value columns)
-        use sha2::{Sha256, Digest};
-        let mut hasher = Sha256::new();
-        hasher.update(&mv.to_le_bytes());
-        hasher.update(&price.to_le_bytes());
-        let hash = format!("{:x}", hasher.finalize());
-        value_hash_builder.append_value(&hash);
-    }
 
-    let schema = Arc::new(Schema::new(vec![
-        Field::new("id", DataType::Int32, false),
-        Field::new("field", DataType::Utf8, false),
-        Field::new("mv", DataType::Int32, false),
-        Field::new("price", DataType::Int32, false),
-        Field::new("effective_from", DataType::Timestamp(TimeUnit::Microsecond, None), false),
-        Field::new("effective_to", DataType::Timestamp(TimeUnit::Microsecond, None), false),
-        Field::new("as_of_from", DataType::Timestamp(TimeUnit::Microsecond, None), false),
-        Field::new("as_of_to", DataType::Timestamp(TimeUnit::Microsecond, None), false),
-        Field::new("value_hash", DataType::Utf8, false),
-    ]));
-
-    RecordBatch::try_new(
-        schema,
-        vec![
-            Arc::new(id_builder.finish()),
-            Arc::new(field_builder.finish()),
-            Arc::new(mv_builder.finish()),
-            Arc::new(price_builder.finish()),
-            Arc::new(eff_from_builder.finish()),
-            Arc::new(eff_to_builder.finish()),
-            Arc::new(as_of_from_builder.finish()),
-            Arc::new(as_of_to_builder.finish()),
-            Arc::new(value_hash_builder.finish()),
-        ],
-    )
-    .map_err(|e| e.to_string())
+// The small / medium / conflation / scaling / parallel inputs are mirrored by
+// benches/test_python_benchmarks.py; keep the two in step when changing them.
+
+fn system_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 7, 21).unwrap()
+}
+
+/// Run one `process_updates_with_options` call over scenario batches
+fn run(current_state: &RecordBatch, updates: &RecordBatch, update_mode: UpdateMode, options: &ProcessingOptions) -> ChangeSet {
+    process_updates_with_options(
+        black_box(current_state.clone()),
+        black_box(updates.clone()),
+        SCENARIO_ID_COLUMNS.map(String::from).to_vec(),
+        SCENARIO_VALUE_COLUMNS.map(String::from).to_vec(),
+        black_box(system_date()),
+        black_box(update_mode),
+        options,
+    ).unwrap()
+}
+
+/// `count` consecutive day boundaries from 2020-01-01, as scenario time strings
+fn day_boundaries(count: usize) -> Vec<String> {
+    let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    (0..count as u64)
+        .map(|day| (start + Days::new(day)).format("%Y-%m-%d").to_string())
+        .collect()
+}
+
+/// `ids` timelines of `segments` contiguous one-day rows each, the last one open-ended.
+/// Values come from `value(id, segment)`.
+fn timeline_batch(
+    ids: i32,
+    segments: usize,
+    as_of_from: &str,
+    value: impl Fn(i32, usize) -> i32,
+) -> RecordBatch {
+    let days = day_boundaries(segments + 1);
+    let mut rows: Vec<ScenarioRow> = Vec::with_capacity(ids as usize * segments);
+    for id in 0..ids {
+        for segment in 0..segments {
+            let effective_to = if segment + 1 == segments { "max" } else { days[segment + 1].as_str() };
+            rows.push((id, "field", value(id, segment), 1000, days[segment].as_str(), effective_to, as_of_from, "max"));
+        }
+    }
+    scenario_batch(&rows).unwrap()
 }
 
 fn bench_small_dataset(c: &mut Criterion) {
-    let current_state = create_test_batch(vec![
+    let current_state = scenario_batch(&[
         (1, "A", 100, 1000, "2024-01-01", "2024-04-01", "2024-01-01", "max"),
         (1, "A", 200, 2000, "2024-04-01", "2024-08-01", "2024-01-01", "max"),
         (1, "A", 300, 3000, "2024-08-01", "2024-12-31", "2024-01-01", "max"),
@@ -116,85 +61,40 @@ fn bench_small_dataset(c: &mut Criterion) {
         (2, "B", 250, 2500, "2024-06-01", "2024-12-31", "2024-01-01", "max"),
     ]).unwrap();
 
-    let updates = create_test_batch(vec![
+    let updates = scenario_batch(&[
         (1, "A", 999, 9999, "2024-03-01", "2024-09-01", "2024-07-21", "max"),
         (2, "B", 888, 8888, "2024-05-01", "2024-07-01", "2024-07-21", "max"),
     ]).unwrap();
 
-    let system_date = NaiveDate::from_ymd_opt(2024, 7, 21).unwrap();
-    let id_columns = vec!["id".to_string(), "field".to_string()];
-    let value_columns = vec!["mv".to_string(), "price".to_string()];
-
+    let options = ProcessingOptions::default();
     c.bench_function("small_dataset", |b| {
-        b.iter(|| {
-            black_box(process_updates(
-                black_box(current_state.clone()),
-                black_box(updates.clone()),
-                black_box(id_columns.clone()),
-                black_box(value_columns.clone()),
-                black_box(system_date),
-                black_box(UpdateMode::Delta),
-                black_box(false),
-            ).unwrap())
-        })
+        b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
     });
 }
 
-fn bench_medium_dataset(c: &mut Criterion) {
-    // Create a medium-sized dataset with 100 current records and 20 updates
-    let mut current_data = Vec::new();
-    for i in 0..100 {
-        current_data.push((
-            i / 10,  // 10 records per ID
-            "field",
-            100 + i,
-            1000 + i,
-            "2024-01-01",
-            "2024-12-31", 
-            "2024-01-01",
-            "max"
-        ));
-    }
-
-    let mut update_data = Vec::new();
-    for i in 0..20 {
-        update_data.push((
-            i / 2,  // 2 updates per ID
-            "field",
-            999,
-            9999,
-            "2024-06-01",
-            "2024-08-01",
-            "2024-07-21",
-            "max"
-        ));
-    }
-
-    let current_state = create_test_batch(current_data).unwrap();
-    let updates = create_test_batch(update_data).unwrap();
-
-    let system_date = NaiveDate::from_ymd_opt(2024, 7, 21).unwrap();
-    let id_columns = vec!["id".to_string(), "field".to_string()];
-    let value_columns = vec!["mv".to_string(), "price".to_string()];
+/// `size` current rows (ten per ID) and `size / 5` updates (two per ID) over 2024
+fn sized_inputs(size: i32) -> (RecordBatch, RecordBatch) {
+    let current_data: Vec<ScenarioRow> = (0..size)
+        .map(|i| (i / 10, "field", 100 + i, 1000 + i, "2024-01-01", "2024-12-31", "2024-01-01", "max"))
+        .collect();
+    let update_data: Vec<ScenarioRow> = (0..size / 5)
+        .map(|i| (i / 2, "field", 999, 9999, "2024-06-01", "2024-08-01", "2024-07-21", "max"))
+        .collect();
+    (scenario_batch(&current_data).unwrap(), scenario_batch(&update_data).unwrap())
+}
 
+fn bench_medium_dataset(c: &mut Criterion) {
+    // 100 current records and 20 updates
+    let (current_state, updates) = sized_inputs(100);
+    let options = ProcessingOptions::default();
     c.bench_function("medium_dataset", |b| {
-        b.iter(|| {
-            black_box(process_updates(
-                black_box(current_state.clone()),
-                black_box(updates.clone()),
-                black_box(id_columns.clone()),
-                black_box(value_columns.clone()),
-                black_box(system_date),
-                black_box(UpdateMode::Delta),
-                black_box(false),
-            ).unwrap())
-        })
+        b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
     });
 }
 
 fn bench_conflation_effectiveness(c: &mut Criterion) {
-    // Test conflation effectiveness with many adjacent same-value segments
-    let current_state = create_test_batch(vec![
+    // Many adjacent same-value segments
+    let current_state = scenario_batch(&[
         (1, "A", 100, 1000, "2024-01-01", "2024-02-01", "2024-01-01", "max"),
         (1, "A", 100, 1000, "2024-02-01", "2024-03-01", "2024-01-01", "max"),
         (1, "A", 100, 1000, "2024-03-01", "2024-04-01", "2024-01-01", "max"),
@@ -202,91 +102,30 @@ fn bench_conflation_effectiveness(c: &mut Criterion) {
         (1, "A", 100, 1000, "2024-05-01", "2024-06-01", "2024-01-01", "max"),
     ]).unwrap();
 
-    let updates = create_test_batch(vec![
+    let updates = scenario_batch(&[
         (1, "A", 999, 9999, "2024-01-15", "2024-05-15", "2024-07-21", "max"),
     ]).unwrap();
 
-    let system_date = NaiveDate::from_ymd_opt(2024, 7, 21).unwrap();
-    let id_columns = vec!["id".to_string(), "field".to_string()];
-    let value_columns = vec!["mv".to_string(), "price".to_string()];
-
+    let options = ProcessingOptions::default();
     c.bench_function("conflation_effectiveness", |b| {
-        b.iter(|| {
-            black_box(process_updates(
-                black_box(current_state.clone()),
-                black_box(updates.clone()),
-                black_box(id_columns.clone()),
-                black_box(value_columns.clone()),
-                black_box(system_date),
-                black_box(UpdateMode::Delta),
-                black_box(false),
-            ).unwrap())
-        })
+        b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
     });
 }
 
 fn bench_scaling_by_size(c: &mut Criterion) {
     let mut group = c.benchmark_group("scaling_by_dataset_size");
+    let options = ProcessingOptions::default();
 
-    for size in [10, 50, 100, 500, 500_000].iter() {
-        // Adjust the sample size based on the dataset size. Larger datasets
-        // take longer per iteration, so we reduce the number of samples to
-        // keep total benchmark time reasonable. Tune these values as needed.
-        let sample_size = match *size {
-            500_000 => 10,
-            500     => 10,
-            100     => 20,
-            _       => 30,
-        };
-        group.sample_size(sample_size);
-
-        let mut current_data = Vec::new();
-        for i in 0..*size {
-            current_data.push((
-                i / 10,  // Multiple records per ID
-                "field",
-                100 + i,
-                1000 + i,
-                "2024-01-01",
-                "2024-12-31",
-                "2024-01-01",
-                "max"
-            ));
-        }
-
-        let mut update_data = Vec::new();
-        let num_updates = size / 5; // 20% of records get updates
-        for i in 0..num_updates {
-            update_data.push((
-                i / 2,
-                "field",
-                999,
-                9999,
-                "2024-06-01",
-                "2024-08-01",
-                "2024-07-21",
-                "max"
-            ));
-        }
-
-        let current_state = create_test_batch(current_data).unwrap();
-        let updates = create_test_batch(update_data).unwrap();
-        let system_date = NaiveDate::from_ymd_opt(2024, 7, 21).unwrap();
-        let id_columns = vec!["id".to_string(), "field".to_string()];
-        let value_columns = vec!["mv".to_string(), "price".to_string()];
-
-        group.bench_with_input(BenchmarkId::new("records", size), size, |b, _size| {
-            b.iter(|| {
-                black_box(process_updates(
-                    black_box(current_state.clone()),
-                    black_box(updates.clone()),
-                    black_box(id_columns.clone()),
-                    black_box(value_columns.clone()),
-                    black_box(system_date),
-                    black_box(UpdateMode::Delta),
-                    black_box(false),
-                ).unwrap())
-            })
+    for size in [10, 50, 100, 500, 500_000] {
+        // Larger datasets take longer per iteration, so take fewer samples
+        group.sample_size(match size {
+            500_000 | 500 => 10,
+            100 => 20,
+            _ => 30,
+        });
+        let (current_state, updates) = sized_inputs(size);
+        group.bench_with_input(BenchmarkId::new("records", size), &size, |b, _size| {
+            b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
         });
     }
 
@@ -295,65 +134,124 @@ fn bench_scaling_by_size(c: &mut Criterion) {
 
 fn bench_parallel_effectiveness(c: &mut Criterion) {
     let mut group = c.benchmark_group("parallel_effectiveness");
-    
-    // Test scenarios with different ID distributions
+    let options = ProcessingOptions::default();
+
+    // Different ID distributions
     for (scenario, num_ids, records_per_id) in [
         ("few_ids_many_records", 10, 1000),    // Low parallelism
-        ("many_ids_few_records", 1000, 10),    // High parallelism  
+        ("many_ids_few_records", 1000, 10),    // High parallelism
         ("balanced_workload", 100, 100),       // Balanced
-    ].iter() {
-        let mut current_data = Vec::new();
-        let mut update_data = Vec::new();
-        
-        // Create test data with specified ID distribution
-        for id in 0..*num_ids {
-            for record in 0..*records_per_id {
-                current_data.push((
-                    id,
-                    "field",
-                    100 + record,
-                    1000 + record,
-                    "2024-01-01",
-                    "2024-12-31",
-                    "2024-01-01",
-                    "max"
-                ));
+    ] {
+        let mut current_data: Vec<ScenarioRow> = Vec::new();
+        let mut update_data: Vec<ScenarioRow> = Vec::new();
+        for id in 0..num_ids {
+            for record in 0..records_per_id {
+                current_data.push((id, "field", 100 + record, 1000 + record, "2024-01-01", "2024-12-31", "2024-01-01", "max"));
             }
-            
-            // Add some updates for each ID
-            for update in 0..(*records_per_id / 10).max(1) {
-                update_data.push((
-                    id,
-                    "field", 
-                    999 + update,
-                    9999 + update,
-                    "2024-06-01",
-                    "2024-08-01",
-                    "2024-07-21",
-                    "max"
-                ));
+            for update in 0..(records_per_id / 10).max(1) {
+                update_data.push((id, "field", 999 + update, 9999 + update, "2024-06-01", "2024-08-01", "2024-07-21", "max"));
             }
         }
+        let current_state = scenario_batch(&current_data).unwrap();
+        let updates = scenario_batch(&update_data).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("scenario", scenario), &scenario, |b, _scenario| {
+            b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_full_state(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full_state");
+    group.sample_size(10);
+    let options = ProcessingOptions::default();
+
+    // 2,000 IDs × 10 segments: a feed restating everything, one changing every segment, and
+    // one where half the universe disappeared and is tombstoned
+    let current_state = timeline_batch(2_000, 10, "2024-01-01", |id, segment| id + segment as i32);
+    let unchanged = timeline_batch(2_000, 10, "2024-07-21", |id, segment| id + segment as i32);
+    let changed = timeline_batch(2_000, 10, "2024-07-21", |id, segment| id + segment as i32 + 1);
+    let half_disappeared = timeline_batch(1_000, 10, "2024-07-21", |id, segment| id + segment as i32);
+
+    for (scenario, updates) in [("unchanged", &unchanged), ("changed", &changed), ("half_disappeared", &half_disappeared)] {
+        group.bench_with_input(BenchmarkId::new("scenario", scenario), &scenario, |b, _scenario| {
+            b.iter(|| black_box(run(&current_state, updates, UpdateMode::FullState, &options)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_conflate_inputs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conflate_inputs");
+    group.sample_size(10);
+
+    // 1,000 IDs whose 20 daily updates repeat each value for five days, over a current
+    // state holding other values
+    let current_state = timeline_batch(1_000, 20, "2024-01-01", |_, _| 1);
+    let updates = timeline_batch(1_000, 20, "2024-07-21", |_, segment| 100 + (segment / 5) as i32);
+
+    for conflate_inputs in [false, true] {
+        let options = ProcessingOptions { conflate_inputs, ..Default::default() };
+        let label = if conflate_inputs { "on" } else { "off" };
+        group.bench_with_input(BenchmarkId::new("conflate", label), &label, |b, _label| {
+            b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_hashing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hashing");
+    group.sample_size(10);
+
+    // Inputs without value_hash, so every run hashes 100,000 rows
+    let without_hash = |batch: RecordBatch| {
+        let keep: Vec<usize> = (0..batch.num_columns() - 1).collect();
+        batch.project(&keep).unwrap()
+    };
+    let current_state = without_hash(timeline_batch(5_000, 10, "2024-01-01", |id, _| id));
+    let updates = without_hash(timeline_batch(5_000, 10, "2024-07-21", |id, segment| id + segment as i32));
+
+    let algorithms = [
+        HashAlgorithm::XxHash,
+        #[cfg(feature = "legacy-hash")]
+        HashAlgorithm::Sha256,
+    ];
+    for algorithm in algorithms {
+        let options = ProcessingOptions { hash_algorithm: algorithm, ..Default::default() };
+        group.bench_with_input(BenchmarkId::new("process_updates", algorithm.as_str()), &algorithm, |b, _algorithm| {
+            b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
+        });
+    }
+
+    #[cfg(feature = "legacy-hash")]
+    {
+        let value_columns = SCENARIO_VALUE_COLUMNS.map(String::from);
+        let sha256_table = timeline_batch(5_000, 20, "2024-01-01", |id, segment| id + segment as i32);
+        let sha256_table = rehash_table(&sha256_table, &value_columns, HashAlgorithm::XxHash, HashAlgorithm::Sha256).unwrap();
+        group.bench_function("rehash_table/sha256_to_xxhash", |b| {
+            b.iter(|| black_box(rehash_table(&sha256_table, &value_columns, HashAlgorithm::Sha256, HashAlgorithm::XxHash).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_consolidation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consolidation");
+    group.sample_size(10);
+
+    // 20,000 single-row IDs, each update splitting its row into three inserts
+    let current_state = timeline_batch(20_000, 1, "2024-01-01", |_, _| 1);
+    let update_rows: Vec<ScenarioRow> = (0..20_000)
+        .map(|id| (id, "field", 2, 1000, "2020-03-01", "2020-06-01", "2024-07-21", "max"))
+        .collect();
+    let updates = scenario_batch(&update_rows).unwrap();
 
-        let current_state = create_test_batch(current_data).unwrap();
-        let updates = create_test_batch(update_data).unwrap();
-
-        let system_date = NaiveDate::from_ymd_opt(2024, 7, 21).unwrap();
-        let id_columns = vec!["id".to_string(), "field".to_string()];
-        let value_columns = vec!["mv".to_string(), "price".to_string()];
-
-        group.bench_with_input(BenchmarkId::new("scenario", scenario), scenario, |b, _scenario| {
-            b.iter(|| {
-                black_box(process_updates(
-                    black_box(current_state.clone()),
-                    black_box(updates.clone()),
-                    black_box(id_columns.clone()),
-                    black_box(value_columns.clone()),
-                    black_box(system_date),
-                    black_box(UpdateMode::Delta),
-                    black_box(false),
-                ).unwrap())
-            })
+    for (label, threshold) in [("threshold_200", Some(200)), ("deferred", None)] {
+        let options = ProcessingOptions { consolidation_threshold: threshold, ..Default::default() };
+        group.bench_with_input(BenchmarkId::new("inserts", label), &label, |b, _label| {
+            b.iter(|| black_box(run(&current_state, &updates, UpdateMode::Delta, &options)))
         });
     }
     group.finish();
@@ -366,6 +264,7 @@ fn profiled() -> Criterion {
 criterion_group! {
     name = benches;
     config = profiled();
-    targets = bench_small_dataset, bench_medium_dataset, bench_conflation_effectiveness, bench_scaling_by_size, bench_parallel_effectiveness
+    targets = bench_small_dataset, bench_medium_dataset, bench_conflation_effectiveness, bench_scaling_by_size,
+        bench_parallel_effectiveness, bench_full_state, bench_conflate_inputs, bench_hashing, bench_consolidation
 }
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,45 @@
+/*
+ * C interface to the pytemporal bitemporal engine (build with `--features ffi`).
+ *
+ * Inputs and outputs use the Arrow C stream interface:
+ * https://arrow.apache.org/docs/format/CStreamInterface.html
+ */
+#ifndef PYTEMPORAL_H
+#define PYTEMPORAL_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+struct ArrowArrayStream;
+
+/*
+ * Process `updates` against `current_state` (both streams are consumed and released).
+ * id_columns / value_columns are comma separated, system_date is YYYY-MM-DD and
+ * update_mode is "delta" or "full_state".
+ *
+ * On success returns 0 and initialises the three output streams:
+ *   out_expire_indices  - single uint64 column "index" (rows of current_state to expire)
+ *   out_to_insert       - rows to insert
+ *   out_expired_records - expired rows with as_of_to set
+ * On failure returns -1 and sets *out_error (free with pytemporal_free_error).
+ */
+int pytemporal_process_updates_ffi(
+    struct ArrowArrayStream* current_state,
+    struct ArrowArrayStream* updates,
+    const char* id_columns,
+    const char* value_columns,
+    const char* system_date,
+    const char* update_mode,
+    struct ArrowArrayStream* out_expire_indices,
+    struct ArrowArrayStream* out_to_insert,
+    struct ArrowArrayStream* out_expired_records,
+    char** out_error);
+
+void pytemporal_free_error(char* error);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PYTEMPORAL_H */
@@ -2,9 +2,40 @@
 from .pytemporal import (
     compute_changes,
     compute_changes_with_hash_algorithm,
-    add_hash_key_with_algorithm
+    compute_changes_reader,
+    compute_changes_chunked,
+    compute_changes_sequence,
+    estimate_processing,
+    changeset_to_sql,
+    changeset_to_cdc,
+    invert_changeset,
+    merge_changesets,
+    write_plan,
+    temporal_join,
+    snapshot_series,
+    latest_view,
+    known_between,
+    diff_as_of,
+    find_gaps,
+    fill_gaps_forward,
+    purge_history,
+    rebase_dates,
+    repair_overlaps,
+    carve_out,
+    validate_updates,
+    rehash_table,
+    select_columns,
+    add_hash_key_with_algorithm,
+    add_hash_key_reader,
+    HashCache
 )
 
+# Present when the extension is built with the `python-logging` feature
+try:
+    from .pytemporal import enable_logging
+except ImportError:
+    enable_logging = None
+
 # Import Python wrapper classes from the local processor module
 from .processor import BitemporalTimeseriesProcessor, INFINITY_TIMESTAMP, add_hash_key
 
@@ -13,8 +44,34 @@ __all__ = [
     'INFINITY_TIMESTAMP',
     'compute_changes',
     'compute_changes_with_hash_algorithm',
+    'compute_changes_reader',
+    'compute_changes_chunked',
+    'compute_changes_sequence',
+    'estimate_processing',
+    'changeset_to_sql',
+    'changeset_to_cdc',
+    'invert_changeset',
+    'merge_changesets',
+    'write_plan',
+    'temporal_join',
+    'snapshot_series',
+    'latest_view',
+    'known_between',
+    'diff_as_of',
+    'find_gaps',
+    'fill_gaps_forward',
+    'purge_history',
+    'rebase_dates',
+    'repair_overlaps',
+    'carve_out',
+    'validate_updates',
+    'rehash_table',
+    'select_columns',
     'add_hash_key',
-    'add_hash_key_with_algorithm'
+    'add_hash_key_with_algorithm',
+    'add_hash_key_reader',
+    'HashCache',
+    'enable_logging'
 ]
 
 # Dynamically get version from installed package metadata
@@ -4,15 +4,17 @@ Bitemporal timeseries processor.
 This module provides a high-level interface for processing bitemporal timeseries
 data using the underlying Rust implementation.
 """
+import uuid
 import pyarrow as pa
 import pandas as pd
-from typing import List, Tuple, Optional, Literal
+from typing import Any, Callable, Dict, List, Tuple, Optional, Literal, Union
 from datetime import datetime, date
 
 # Import the Rust functions
 from .pytemporal import (
     compute_changes as _compute_changes,
     add_hash_key as _add_hash_key,
+    select_columns as _select_columns,
     add_hash_key_with_algorithm as _add_hash_key_with_algorithm
 )
 
@@ -23,6 +25,9 @@ INFINITY_TIMESTAMP = pd.Timestamp('2260-12-31 23:59:59')
 # Use a timestamp well before pandas max to avoid overflow during tz operations
 SAFE_MAX_TIMESTAMP = pd.Timestamp('2260-12-31 23:59:59')
 
+# Columns never picked up by id/value column patterns
+RESERVED_COLUMNS = {'effective_from', 'effective_to', 'effective_date', 'as_of_from', 'as_of_to', 'value_hash'}
+
 # Pandas maximum timestamp (approximately 2262-04-11) - use cautiously
 PANDAS_MAX_TIMESTAMP = pd.Timestamp.max
 
@@ -35,18 +40,31 @@ class BitemporalTimeseriesProcessor:
     (complete replacement of state for given IDs).
     """
     
-    def __init__(self, id_columns: List[str], value_columns: List[str], conflate_inputs: bool = False):
+    def __init__(
+        self,
+        id_columns: List[str],
+        value_columns: Optional[List[str]] = None,
+        conflate_inputs: bool = False,
+        id_column_patterns: Optional[List[str]] = None,
+        value_column_patterns: Optional[List[str]] = None
+    ):
         """
         Initialize the processor with column definitions.
 
         Args:
             id_columns: List of column names that identify a unique timeseries
-            value_columns: List of column names containing the values to track
+            value_columns: List of column names containing the values to track. When omitted, every column
+                other than the ID, temporal and hash columns is tracked.
             conflate_inputs: Whether to conflate consecutive input updates with same ID and values (default: False)
+            id_column_patterns: Optional glob ('px_*') or 're:'-prefixed regex patterns selecting further ID
+                columns from the updates; each must match at least one column.
+            value_column_patterns: Same for value columns (ID columns are never matched).
         """
         self.id_columns = id_columns
-        self.value_columns = value_columns
+        self.value_columns = value_columns or []
         self.conflate_inputs = conflate_inputs
+        self.id_column_patterns = id_column_patterns or []
+        self.value_column_patterns = value_column_patterns or []
     
     def compute_changes(
         self,
@@ -54,29 +72,204 @@ class BitemporalTimeseriesProcessor:
         updates: pd.DataFrame,
         system_date: Optional[str] = None,
         update_mode: Literal["delta", "full_state"] = "delta",
-        conflate_inputs: Optional[bool] = None
-    ) -> Tuple[pd.DataFrame, pd.DataFrame]:
+        conflate_inputs: Optional[bool] = None,
+        conflate_gap_days: int = 0,
+        merge_adjacent_current: bool = False,
+        tenant_column: Optional[str] = None,
+        full_state_window: Optional[Tuple[str, str]] = None,
+        partition_columns: Optional[List[str]] = None,
+        conflict_resolution: Optional[Literal["event_order", "latest_as_of_from", "priority"]] = None,
+        priority_column: Optional[str] = None,
+        report_skipped_updates: bool = False,
+        report_expire_reasons: bool = False,
+        report_changed_columns: bool = False,
+        min_effective_from: Optional[str] = None,
+        watermark_policy: Literal["clamp", "reject"] = "clamp",
+        clamp_from: Optional[str] = None,
+        clamp_to: Optional[str] = None,
+        validation: Literal["off", "lenient", "strict"] = "off",
+        strict_schemas: bool = False,
+        empty_ranges: Literal["drop", "error", "report"] = "drop",
+        null_ids: Literal["group", "reject"] = "group",
+        open_current_rows_only: bool = False,
+        missing_temporal_columns: Union[bool, Dict[str, str], None] = None,
+        input_mode: Literal["ranges", "events"] = "ranges",
+        effective_timezone: Optional[str] = None,
+        interval_end: Literal["exclusive", "inclusive"] = "exclusive",
+        tombstone_cutoff_column: Optional[str] = None,
+        deletion_column: Optional[str] = None,
+        emit_tombstones: bool = True,
+        preserve_as_of_from: bool = False,
+        lineage_columns: Optional[Dict[str, Any]] = None,
+        verify_hash_matches: bool = False,
+        exact_float_columns: Optional[List[str]] = None,
+        lazy_hashing: bool = False,
+        hash_column: str = 'value_hash',
+        exclude_hash_column: bool = False,
+        hash_groups: Optional[Dict[str, List[str]]] = None,
+        hash_group_change: Literal["any", "all"] = "any",
+        value_tolerances: Optional[Dict[str, Tuple[float, float]]] = None,
+        consolidation_threshold: Optional[int] = 200,
+        consolidation_target_rows: int = 10000,
+        segment_boundary: Optional[Literal["month", "quarter"]] = None,
+        parallelism: Literal["auto", "serial", "parallel"] = "auto",
+        progress: Optional[Callable[[float], None]] = None,
+        checkpoint_dir: Optional[str] = None,
+        checkpoint_every: int = 10000,
+        ledger_dir: Optional[str] = None,
+        batch_id: Optional[str] = None,
+        max_memory_bytes: Optional[int] = None,
+        verify_invariants: bool = False,
+        fixed_clock: Optional[Union[str, datetime, pd.Timestamp]] = None
+    ) -> Union[Tuple[pd.DataFrame, pd.DataFrame], Tuple[pd.DataFrame, pd.DataFrame, pd.DataFrame]]:
         """
         Compute the changes needed to update the bitemporal timeseries.
 
         Args:
             current_state: DataFrame with current database state
             updates: DataFrame with incoming updates
-            system_date: Optional system date (YYYY-MM-DD format)
+            system_date: Optional system date (YYYY-MM-DD), or a full timestamp (YYYY-MM-DD HH:MM:SS)
+                for intraday loads so full_state tombstones end at that instant rather than midnight
             update_mode: "delta" for incremental updates, "full_state" for complete state replacement (only expires/inserts when values change)
             conflate_inputs: Whether to conflate consecutive input updates with same ID and values (default: use class-level setting)
+            conflate_gap_days: Largest gap in days between same-valued updates that conflation still merges across,
+                e.g. 3 to bridge weekends (default 0: only touching rows). The merged row covers the gap.
+            merge_adjacent_current: Stretch inserted rows over untouched current rows of the same ID that they abut
+                with identical values, expiring those as 'MERGED', so no two adjacent segments carry the same values.
+            tenant_column: Optional column naming the tenant (client) of each row. Raises ValueError if an ID
+                carries more than one tenant across current_state and updates, so tenants are never merged together.
+            full_state_window: Optional (from, to) effective window (YYYY-MM-DD) that a full_state update covers.
+                Current rows outside the window are left untouched instead of being tombstoned, and rows
+                straddling it keep their coverage outside the window.
+            partition_columns: Optional columns that partition a full_state feed (e.g. ['source_system']).
+                Only current rows in partitions present in the updates are compared or tombstoned.
+            conflict_resolution: How overlapping updates for the same ID in one batch are resolved in delta mode:
+                "event_order" (default), "latest_as_of_from", or "priority" (highest priority_column value wins).
+            priority_column: Integer column used to rank overlapping updates (implies conflict_resolution="priority").
+            report_skipped_updates: Also return a DataFrame of update rows that were intentionally ignored.
+            report_expire_reasons: Add an 'expire_reason' column to rows_to_expire ('OVERWRITTEN', 'TOMBSTONED',
+                'MERGED' or 'CARVED').
+            report_changed_columns: Add a 'changed_columns' list to rows_to_insert naming the value columns that differ
+                from the rows they replace (None for rows that replace nothing).
+            min_effective_from: Optional backfill watermark (YYYY-MM-DD). Current rows ending on or before it
+                are never expired.
+            watermark_policy: What to do with updates starting before min_effective_from: "clamp" (default)
+                trims them to the watermark, "reject" raises ValueError.
+            clamp_from, clamp_to: Optional processing window [clamp_from, clamp_to) (YYYY-MM-DD) for updates. Updates
+                straddling a bound are trimmed to it (counted in the 'clamped_updates' metric); updates entirely
+                outside it are skipped ('outside_clamp_window'). Current rows are not affected.
+            validation: Row-level checks on updates (null IDs, unreadable timestamps, effective_from > effective_to):
+                "off" (default), "lenient" drops invalid rows (reported as 'invalid_row' skips), "strict" raises
+                with the offending row indices and values.
+            strict_schemas: Raise up front, listing every difference, unless current_state and updates (after
+                alignment) have the same columns with identical Arrow types, timestamp units and timezones included.
+            empty_ranges: Update rows with effective_from >= effective_to: "drop" (default) skips them during
+                processing, "error" raises with the offending row indices and values, "report" drops them up front
+                as validation issues whatever the validation setting (listed as 'empty_range' skips).
+            null_ids: "group" (default) treats null as an ID value of its own, so rows with nulls in the same ID
+                columns match each other; "reject" raises with the row indices. Null effective_from, effective_to
+                or as_of_from values always raise.
+            open_current_rows_only: Accept a full history table as current_state; only rows whose as_of_to is
+                null or the max sentinel are compared, closed versions are left untouched. The open rows
+                are copied on every call, so a HashCache never hits for such a current_state.
+            missing_temporal_columns: Populate temporal columns absent from updates instead of failing. True fills
+                effective_to and as_of_to with the max sentinel and as_of_from with the batch timestamp; a dict maps
+                'effective_to' / 'as_of_from' / 'as_of_to' to "max", "batch_timestamp", "required" or a date string.
+            input_mode: "ranges" (default) or "events", where each update is an observation at a single
+                effective_date (or effective_from) that runs until the next observation for the same ID.
+            effective_timezone: IANA timezone (e.g. "Europe/London") that system_date is a business date in;
+                full_state tombstones then end at local midnight rather than UTC midnight.
+            interval_end: "exclusive" (default) for [effective_from, effective_to) tables, or "inclusive" for tables
+                storing the last covered day in effective_to; outputs use the same convention as the inputs.
+            tombstone_cutoff_column: Optional current_state column (e.g. 'last_seen') giving each row's own
+                full_state tombstone date; rows where it is null fall back to system_date.
+            deletion_column: Optional boolean updates column (e.g. 'is_deleted') for full_state feeds. Flagged rows
+                are not inserted; an ID whose rows are all flagged is tombstoned as if it were missing from the feed.
+            emit_tombstones: Set False to only expire rows that disappear from a full_state load, without
+                inserting closing tombstone rows (for consumers that close them on the database side).
+            preserve_as_of_from: Keep each update row's own as_of_from on full_state segments merged with an
+                adjacent current row of the same values, instead of stamping the batch's as_of_from.
+            lineage_columns: Optional constant columns (e.g. {'batch_id': 'b-42', 'source_file': 'feed.csv'})
+                stamped onto every row of rows_to_insert and rows_to_expire.
+            verify_hash_matches: Compare the value columns whenever hashes match, so a hash collision
+                is treated as a change rather than a no-op (slower; off by default, but always on
+                when hashing with sha256, whose digests are narrowed for comparison).
+            exact_float_columns: Float value columns hashed by their exact bits. By default a float
+                with no fractional part hashes like the equal integer (1.0 and 1 are the same value);
+                listed columns opt out. Hashes computed with a different list are refused.
+            lazy_hashing: When current_state has no value_hash column, hash only the rows of IDs that
+                also appear in the updates (delta mode; ignored for full_state or conflate_inputs).
+            hash_column: Name of the value hash column in the inputs and outputs (default 'value_hash').
+            exclude_hash_column: Leave the hash column out of rows_to_expire and rows_to_insert.
+            hash_groups: Optional further hash columns, each over its own value columns
+                (e.g. {'price_hash': ['price'], 'attr_hash': ['sector', 'rating']}). They are computed
+                where missing or empty and returned alongside the value hash.
+            hash_group_change: "any" (default) treats a row as changed when any group's hash differs; "all"
+                only when every group's does. Under "all" an update sharing a group hash with an overlapping
+                current row of its ID is skipped as 'no_change'.
+            value_tolerances: Optional {column: (absolute, relative)} tolerances for numeric value columns. An
+                update overlapping a current row of its ID, equal in every other value column and within either
+                bound on these, takes that row's values and is treated as unchanged (counted in the
+                'tolerated_updates' metric).
+            consolidation_threshold: Consolidate pending insert batches whenever more than this many accumulate
+                (default 200). None consolidates once at the end: faster, but with a higher memory peak.
+            consolidation_target_rows: Rows per consolidated output batch (default 10000).
+            segment_boundary: "month" or "quarter" splits inserted rows at calendar boundaries so each lies
+                within one period, matching month-partitioned tables. Open-ended rows are split at their first
+                boundary only (default None: no splitting).
+            parallelism: "auto" (default) processes ID groups in parallel for larger inputs; "serial" or
+                "parallel" force the strategy (e.g. serial for small interactive calls).
+            progress: Optional callable receiving the percentage (0-100) of ID groups processed, once per
+                whole percent. Raising from it cancels the run. Ctrl-C (KeyboardInterrupt) also stops the run
+                cleanly between ID groups.
+            checkpoint_dir: Directory to checkpoint finished ID groups to (Arrow IPC plus a manifest). Rerunning
+                with the same inputs after a crash resumes from the last checkpoint; it is removed on success.
+            checkpoint_every: ID groups per checkpoint (default 10000).
+            ledger_dir: Directory of an idempotency ledger, used together with batch_id (e.g. the queue message ID).
+                Each batch ID's changes are recorded there; a redelivered batch ID returns the recorded changes
+                instead of recomputing them against a state that may already hold them.
+            batch_id: Caller-supplied ID of this update batch, recorded in ledger_dir.
+            max_memory_bytes: Optional budget for insert batches held while ID groups are processed. Past it
+                they are spilled to temporary Arrow IPC files and read back at the end, so the returned
+                changes are still held in memory in full.
+            verify_invariants: Check the changes for overlapping or empty ranges, lost coverage and as_of
+                running backwards before returning, raising on a violation (default False; a debugging aid).
+            fixed_clock: Optional instant (UTC) to use as the batch timestamp instead of the current time,
+                so tests can assert exact as_of_to / filled as_of_from values.
 
         Returns:
-            Tuple of (rows_to_expire, rows_to_insert)
+            Tuple of (rows_to_expire, rows_to_insert[, skipped_updates])
             - rows_to_expire: DataFrame with rows that need as_of_to set
             - rows_to_insert: DataFrame with new rows to insert
+            - skipped_updates: only when report_skipped_updates=True; DataFrame with 'update_index'
+              (row position in updates) and 'reason' ('empty_range', 'no_change', 'exact_match',
+              'covered_by_current', 'before_watermark', 'invalid_row', 'outside_clamp_window' or
+              'conflated', a row merged into its neighbour by conflate_inputs)
         """
+        id_columns, value_columns = self._resolve_columns(updates, hash_column)
+
+        # Event observations carry a single date; Rust chains them into effective ranges
+        if input_mode == 'events':
+            updates = updates.drop(columns=['effective_to'], errors='ignore')
+            if 'effective_date' in updates.columns:
+                updates = updates.drop(columns=['effective_from'], errors='ignore')
+                updates = updates.rename(columns={'effective_date': 'effective_from'})
+
         # Prepare DataFrames for processing
-        current_state = self._prepare_dataframe(current_state)
-        updates = self._prepare_dataframe(updates)
+        group_hash_columns = list(hash_groups or {})
+        current_state = self._prepare_dataframe(current_state, hash_column, group_hash_columns)
+        updates = self._prepare_dataframe(updates, hash_column, group_hash_columns)
 
         # Align schemas: reorder columns and validate compatibility
-        current_state, updates = self._align_schemas(current_state, updates)
+        # (temporal columns the updates omit stay on current_state when they will be populated,
+        # as does the per-row tombstone cutoff column)
+        fill_columns = self._populated_temporal_columns(missing_temporal_columns, updates)
+        if input_mode == 'events':
+            fill_columns.append('effective_to')
+        if tombstone_cutoff_column:
+            fill_columns.append(tombstone_cutoff_column)
+        update_only_columns = [deletion_column] if deletion_column else []
+        current_state, updates = self._align_schemas(current_state, updates, fill_columns, update_only_columns)
 
         # Normalize schemas to ensure timezone consistency between DataFrames
         current_state, updates = self._normalize_schemas(current_state, updates)
@@ -96,15 +289,64 @@ class BitemporalTimeseriesProcessor:
 
         # Call Rust function
         actual_system_date = system_date or datetime.now().strftime('%Y-%m-%d')
-        expire_indices, insert_batch, expired_batch = _compute_changes(
+        result = _compute_changes(
             current_batch,
             updates_batch,
-            self.id_columns,
-            self.value_columns,
+            id_columns,
+            value_columns,
             actual_system_date,
             update_mode,
-            actual_conflate_inputs
+            actual_conflate_inputs,
+            conflate_gap_days=conflate_gap_days,
+            merge_adjacent_current=merge_adjacent_current,
+            tenant_column=tenant_column,
+            full_state_window=full_state_window,
+            partition_columns=partition_columns,
+            conflict_resolution=conflict_resolution,
+            priority_column=priority_column,
+            report_skipped_updates=report_skipped_updates,
+            report_expire_reasons=report_expire_reasons,
+            report_changed_columns=report_changed_columns,
+            min_effective_from=min_effective_from,
+            watermark_policy=watermark_policy,
+            clamp_from=clamp_from,
+            clamp_to=clamp_to,
+            validation=validation,
+            strict_schemas=strict_schemas,
+            empty_ranges=empty_ranges,
+            null_ids=null_ids,
+            open_current_rows_only=open_current_rows_only,
+            missing_temporal_columns=missing_temporal_columns,
+            input_mode=input_mode,
+            effective_timezone=effective_timezone,
+            interval_end=interval_end,
+            tombstone_cutoff_column=tombstone_cutoff_column,
+            deletion_column=deletion_column,
+            emit_tombstones=emit_tombstones,
+            preserve_as_of_from=preserve_as_of_from,
+            lineage_columns=lineage_columns,
+            verify_hash_matches=verify_hash_matches,
+            exact_float_columns=exact_float_columns or [],
+            lazy_hashing=lazy_hashing,
+            hash_column=hash_column,
+            exclude_hash_column=exclude_hash_column,
+            hash_groups=hash_groups,
+            hash_group_change=hash_group_change,
+            value_tolerances=value_tolerances,
+            consolidation_threshold=consolidation_threshold,
+            consolidation_target_rows=consolidation_target_rows,
+            segment_boundary=segment_boundary,
+            parallelism=parallelism,
+            progress=progress,
+            checkpoint_dir=checkpoint_dir,
+            checkpoint_every=checkpoint_every if checkpoint_dir is not None else None,
+            ledger_dir=ledger_dir,
+            batch_id=batch_id,
+            max_memory_bytes=max_memory_bytes,
+            verify_invariants=verify_invariants,
+            fixed_clock=None if fixed_clock is None else pd.Timestamp(fixed_clock).strftime('%Y-%m-%d %H:%M:%S.%f')
         )
+        expire_indices, insert_batch, expired_batch = result[:3]
         
         # Use expired records from Rust (with updated as_of_to timestamps)
         # Convert using zero-copy Arrow PyCapsule interface for optimal performance
@@ -122,7 +364,7 @@ class BitemporalTimeseriesProcessor:
         # In full_state mode, adjust effective_to for records that have temporal changes
         if update_mode == 'full_state' and not rows_to_expire.empty and not updates.empty:
             # Create a lookup for updates by ID values and effective_from using vectorized operations
-            id_cols = self.id_columns
+            id_cols = id_columns
 
             # Build lookup using vectorized zip (much faster than iterrows)
             update_id_keys = list(zip(*[updates[col].values for col in id_cols]))
@@ -160,10 +402,39 @@ class BitemporalTimeseriesProcessor:
             rows_to_insert = self._convert_from_internal_format(rows_to_insert)
             # Sort by effective_from for consistent ordering
             rows_to_insert = rows_to_insert.sort_values(by=['effective_from']).reset_index(drop=True)
-        
+
+        # Empty results are built from current_state's columns, which still carry the hash
+        if exclude_hash_column:
+            rows_to_expire = rows_to_expire.drop(columns=[hash_column], errors='ignore')
+            rows_to_insert = rows_to_insert.drop(columns=[hash_column], errors='ignore')
+
+        if report_skipped_updates:
+            skipped_indices, skip_reasons = result[3]
+            skipped_updates = pd.DataFrame({'update_index': skipped_indices, 'reason': skip_reasons})
+            return rows_to_expire, rows_to_insert, skipped_updates
+
         return rows_to_expire, rows_to_insert
     
-    def _prepare_dataframe(self, df: pd.DataFrame) -> pd.DataFrame:
+    def _resolve_columns(self, updates: pd.DataFrame, hash_column: str) -> Tuple[List[str], List[str]]:
+        """
+        ID and value columns, with any column patterns expanded against the updates' columns.
+        """
+        candidates = [col for col in updates.columns if col not in RESERVED_COLUMNS and col != hash_column]
+
+        id_columns = list(self.id_columns)
+        if self.id_column_patterns:
+            matched = _select_columns(candidates, self.id_column_patterns)
+            id_columns += [col for col in matched if col not in id_columns]
+
+        value_columns = list(self.value_columns)
+        if self.value_column_patterns:
+            matched = _select_columns([col for col in candidates if col not in id_columns], self.value_column_patterns)
+            value_columns += [col for col in matched if col not in value_columns]
+
+        return id_columns, value_columns
+
+    def _prepare_dataframe(self, df: pd.DataFrame, hash_column: str = 'value_hash',
+                           group_hash_columns: Optional[List[str]] = None) -> pd.DataFrame:
         """
         Prepare DataFrame for processing by converting infinity dates.
         """
@@ -226,18 +497,42 @@ class BitemporalTimeseriesProcessor:
                 # Keep as timestamp for microsecond precision
                 # Note: pandas uses nanosecond precision, which is compatible with Arrow timestamp[ns]
         
-        # Add value_hash column if it doesn't exist (it will be computed by Rust)
-        if 'value_hash' not in df.columns:
-            df['value_hash'] = ""  # Placeholder, will be computed by Rust
+        # uuid.UUID objects can't go through Arrow; the Rust layer keys and hashes their
+        # canonical text the same as FixedSizeBinary(16) UUIDs
+        for col in df.columns:
+            if df[col].dtype == object:
+                first = df[col].dropna()
+                if not first.empty and isinstance(first.iloc[0], uuid.UUID):
+                    df[col] = df[col].map(lambda v: str(v) if isinstance(v, uuid.UUID) else v)
+
+        # Add the hash columns if they don't exist (they will be computed by Rust)
+        for column in [hash_column] + (group_hash_columns or []):
+            if column not in df.columns:
+                df[column] = ""  # Placeholder, will be computed by Rust
         
         return df
     
-    def _align_schemas(self, current_state: pd.DataFrame, updates: pd.DataFrame) -> tuple[pd.DataFrame, pd.DataFrame]:
+    @staticmethod
+    def _populated_temporal_columns(missing_temporal_columns, updates: pd.DataFrame) -> List[str]:
+        """Temporal columns missing from updates that the Rust layer will populate."""
+        if not missing_temporal_columns:
+            return []
+        if missing_temporal_columns is True:
+            candidates = ['effective_to', 'as_of_from', 'as_of_to']
+        else:
+            candidates = [col for col, rule in missing_temporal_columns.items() if rule != 'required']
+        return [col for col in candidates if col not in updates.columns]
+
+    def _align_schemas(self, current_state: pd.DataFrame, updates: pd.DataFrame,
+                       fill_columns: Optional[List[str]] = None,
+                       update_only_columns: Optional[List[str]] = None) -> tuple[pd.DataFrame, pd.DataFrame]:
         """
         Align schemas between current_state and updates by reordering columns.
 
         This ensures Arrow schema compatibility by making both DataFrames have
-        the same column order for their common columns.
+        the same column order for their common columns. Columns in fill_columns
+        are kept on current_state even though updates lack them, and columns in
+        update_only_columns are kept on updates.
         """
         current_cols = set(current_state.columns)
         updates_cols = set(updates.columns)
@@ -253,8 +548,10 @@ class BitemporalTimeseriesProcessor:
         canonical_order = [c for c in current_state.columns if c in common_cols]
 
         # Filter and reorder both DataFrames to have same columns in same order
-        current_state = current_state[canonical_order]
-        updates = updates[canonical_order]
+        fill_columns = fill_columns or []
+        current_state = current_state[[c for c in current_state.columns if c in common_cols or c in fill_columns]]
+        update_only_columns = [c for c in (update_only_columns or []) if c in updates_cols and c not in common_cols]
+        updates = updates[canonical_order + update_only_columns]
 
         return current_state, updates
 
@@ -452,7 +749,8 @@ class BitemporalTimeseriesProcessor:
         return required_cols.issubset(set(df.columns))
 
 
-def add_hash_key(df: pd.DataFrame, value_fields: List[str], hash_algorithm: str = 'xxhash') -> pd.DataFrame:
+def add_hash_key(df: pd.DataFrame, value_fields: List[str], hash_algorithm: str = 'xxhash',
+                 exact_float_columns: Optional[List[str]] = None) -> pd.DataFrame:
     """
     Add a hash key column to a pandas DataFrame based on specified value fields.
 
@@ -467,6 +765,8 @@ def add_hash_key(df: pd.DataFrame, value_fields: List[str], hash_algorithm: str
         hash_algorithm: Hash algorithm to use. Options:
             - 'xxhash' (default): Fast, high-quality non-cryptographic hash
             - 'sha256': Cryptographic hash for legacy compatibility
+        exact_float_columns: Float columns hashed by their exact bits instead of hashing
+            whole-numbered floats like the equal integer (must match compute_changes)
 
     Returns:
         DataFrame with an additional 'value_hash' column containing hash hex strings
@@ -501,7 +801,7 @@ def add_hash_key(df: pd.DataFrame, value_fields: List[str], hash_algorithm: str
     record_batch = pa.RecordBatch.from_pandas(df, preserve_index=False)
 
     # Call the Rust function with the specified algorithm
-    result_batch = _add_hash_key_with_algorithm(record_batch, value_fields, hash_algorithm)
+    result_batch = _add_hash_key_with_algorithm(record_batch, value_fields, hash_algorithm, exact_float_columns)
 
     # Convert back to pandas using zero-copy Arrow PyCapsule interface
     pa_batch = pa.record_batch(result_batch)
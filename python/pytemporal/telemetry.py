@@ -0,0 +1,77 @@
+"""
+Export per-run processing metrics through the OpenTelemetry metrics API.
+
+The extension calls ``record`` after every successful ``compute_changes`` run. When the
+``opentelemetry`` package is not installed this is a no-op; when it is installed but no
+meter provider is configured, the API's own no-op provider discards the measurements.
+"""
+
+_COUNTERS = (
+    ("pytemporal.id_groups", "id_groups", "ID groups processed"),
+    ("pytemporal.rows_expired", "expired_rows", "Current rows expired"),
+    ("pytemporal.rows_inserted", "inserted_rows", "Rows inserted"),
+    ("pytemporal.updates_conflated", "conflated_updates", "Update rows merged by conflate_inputs"),
+    ("pytemporal.updates_clamped", "clamped_updates", "Update rows trimmed to the clamp window"),
+    ("pytemporal.updates_tolerated", "tolerated_updates", "Update rows within value tolerances of current state"),
+    ("pytemporal.current_rows_unhashed", "unhashed_current_rows", "Current rows left unhashed by lazy_hashing"),
+)
+
+_PHASES = (
+    ("group_ids", "group_ids_seconds"),
+    ("process_id_groups", "process_id_groups_seconds"),
+    ("build_changeset", "build_changeset_seconds"),
+)
+
+_instruments = None
+
+
+def _get_instruments():
+    """Create the instruments on first use, or return False if OpenTelemetry is unavailable."""
+    global _instruments
+    if _instruments is None:
+        try:
+            from opentelemetry import metrics
+        except ImportError:
+            _instruments = False
+            return _instruments
+        meter = metrics.get_meter("pytemporal")
+        _instruments = {
+            "rows_in": meter.create_counter(
+                "pytemporal.rows_in", description="Rows passed in, by side (current or updates)"
+            ),
+            "counters": [
+                (key, meter.create_counter(name, description=description))
+                for name, key, description in _COUNTERS
+            ],
+            "tenant_rows_expired": meter.create_counter(
+                "pytemporal.tenant.rows_expired", description="Current rows expired, by tenant"
+            ),
+            "tenant_rows_inserted": meter.create_counter(
+                "pytemporal.tenant.rows_inserted", description="Rows inserted, by tenant"
+            ),
+            "phases": meter.create_histogram(
+                "pytemporal.phase.duration", unit="s", description="Time spent in each processing phase"
+            ),
+            "duration": meter.create_histogram(
+                "pytemporal.duration", unit="s", description="Total time of a compute_changes call"
+            ),
+        }
+    return _instruments
+
+
+def record(values, update_mode):
+    """Record one run's metrics (a dict of counts and ``*_seconds`` timings)."""
+    instruments = _get_instruments()
+    if not instruments:
+        return
+    mode = {"mode": update_mode}
+    instruments["rows_in"].add(values["current_rows"], {**mode, "side": "current"})
+    instruments["rows_in"].add(values["update_rows"], {**mode, "side": "updates"})
+    for key, counter in instruments["counters"]:
+        counter.add(values[key], mode)
+    for tenant, expired, inserted in values.get("tenants", ()):
+        instruments["tenant_rows_expired"].add(expired, {**mode, "tenant": tenant})
+        instruments["tenant_rows_inserted"].add(inserted, {**mode, "tenant": tenant})
+    for phase, key in _PHASES:
+        instruments["phases"].record(values[key], {**mode, "phase": phase})
+    instruments["duration"].record(values["total_seconds"], mode)
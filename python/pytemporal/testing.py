@@ -0,0 +1,119 @@
+"""
+Test helpers for code built on pytemporal.
+
+Scenarios are written as tuples over a canonical schema (``id``, ``field``, ``mv``, ``price``
+and the four temporal columns) and run straight through the engine, the same way the crate's
+own integration tests do:
+
+    from pytemporal.testing import assert_scenario
+
+    assert_scenario(
+        current=[(1, 'test', 100, 10, '2020-01-01', 'max', '2025-01-01')],
+        updates=[(1, 'test', 200, 10, '2020-06-01', 'max', '2025-07-27')],
+        expected_expire=[(1, 'test', 100, 10, '2020-01-01', 'max', '2025-01-01')],
+        expected_insert=[(1, 'test', 100, 10, '2020-01-01', '2020-06-01', '2025-07-27'),
+                         (1, 'test', 200, 10, '2020-06-01', 'max', '2025-07-27')],
+    )
+
+Times are 'YYYY-MM-DD', 'YYYY-MM-DD HH:MM:SS' or 'max'; ``as_of_to`` may be left off and
+defaults to 'max'. Failures raise ``AssertionError`` with a row-by-row diff.
+"""
+from typing import Any, List, Optional, Sequence, Tuple, Union
+
+import pyarrow as pa
+
+from .pytemporal import (
+    compute_changes as _compute_changes,
+    diff_rows as _diff_rows,
+    scenario_batch as _scenario_batch
+)
+
+ID_COLUMNS = ['id', 'field']
+VALUE_COLUMNS = ['mv', 'price']
+# Columns compared by default: everything except as_of_to (set by the engine) and value_hash
+COMPARED_COLUMNS = ID_COLUMNS + VALUE_COLUMNS + ['effective_from', 'effective_to', 'as_of_from']
+
+ScenarioRow = Tuple[Any, ...]
+Batches = Union[pa.RecordBatch, Sequence[pa.RecordBatch]]
+
+
+def scenario_batch(rows: Sequence[ScenarioRow]) -> pa.RecordBatch:
+    """Build a canonical-schema RecordBatch (with ``value_hash``) from tuple rows."""
+    rows = [tuple(row) + ('max',) if len(row) == 7 else tuple(row) for row in rows]
+    return pa.record_batch(_scenario_batch(rows))
+
+
+def scenario_schema() -> pa.Schema:
+    """The canonical scenario schema."""
+    return scenario_batch([]).schema
+
+
+def run_scenario(
+    current: Sequence[ScenarioRow],
+    updates: Sequence[ScenarioRow],
+    update_mode: str = 'delta',
+    system_date: str = '2025-07-27',
+    **options
+) -> Tuple[pa.RecordBatch, List[pa.RecordBatch]]:
+    """
+    Run tuple rows through ``compute_changes``.
+
+    Returns the expired current rows (as passed in, before ``as_of_to`` is set) and the
+    inserted batches. Keyword options are forwarded to ``compute_changes``.
+    """
+    current_batch = scenario_batch(current)
+    result = _compute_changes(
+        current_batch, scenario_batch(updates), ID_COLUMNS, VALUE_COLUMNS,
+        system_date, update_mode, **options
+    )
+    expire_indices, insert_batches = result[0], result[1]
+    expired = current_batch.take(expire_indices)
+    return expired, [pa.record_batch(batch) for batch in insert_batches]
+
+
+def diff_changes(
+    actual: Batches,
+    expected: Union[Batches, Sequence[ScenarioRow]],
+    columns: Optional[List[str]] = None
+) -> Optional[str]:
+    """
+    Compare rows ignoring order and batch boundaries. ``expected`` may be batches or tuple
+    rows. Returns None when they match, otherwise a diff of missing and unexpected rows.
+    """
+    return _diff_rows(_as_batches(actual), _as_batches(expected), columns or COMPARED_COLUMNS)
+
+
+def assert_changes(
+    actual: Batches,
+    expected: Union[Batches, Sequence[ScenarioRow]],
+    columns: Optional[List[str]] = None,
+    label: str = 'rows'
+) -> None:
+    """Raise ``AssertionError`` with a readable diff unless ``actual`` holds the expected rows."""
+    diff = diff_changes(actual, expected, columns)
+    if diff is not None:
+        raise AssertionError(f"{label}: {diff}")
+
+
+def assert_scenario(
+    current: Sequence[ScenarioRow],
+    updates: Sequence[ScenarioRow],
+    expected_expire: Sequence[ScenarioRow],
+    expected_insert: Sequence[ScenarioRow],
+    update_mode: str = 'delta',
+    system_date: str = '2025-07-27',
+    **options
+) -> None:
+    """Run a scenario and check both the expired and the inserted rows."""
+    expired, inserted = run_scenario(current, updates, update_mode, system_date, **options)
+    assert_changes(expired, expected_expire, label='expired rows')
+    assert_changes(inserted, expected_insert, label='inserted rows')
+
+
+def _as_batches(value) -> List[pa.RecordBatch]:
+    if isinstance(value, pa.RecordBatch):
+        return [value]
+    rows = list(value)
+    if rows and isinstance(rows[0], pa.RecordBatch):
+        return rows
+    return [scenario_batch(rows)]
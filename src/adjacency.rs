@@ -0,0 +1,101 @@
+use crate::types::{ExpireReason, VALUE_HASH_COLUMN};
+use crate::{create_id_key_with_buffer, create_timestamp_array_from_values, datetime_from_micros, get_temporal_bounds_micros, should_prevent_merge};
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// A current row left in place by the run, as a candidate to merge into an insert.
+/// Endpoints are microseconds since the epoch.
+struct Neighbor {
+    row: usize,
+    from: i64,
+    to: i64,
+}
+
+/// Under `ProcessingOptions::merge_adjacent_current`, stretch each inserted row over the
+/// current rows of its ID that it touches and that carry the same value hash, expiring them
+/// as merged. Current rows already being expired are left alone. Returns the rewritten
+/// batches and whether any row was extended.
+pub(crate) fn merge_with_adjacent_current(
+    to_insert: Vec<RecordBatch>,
+    to_expire: &mut Vec<(usize, ExpireReason)>,
+    current_state: &RecordBatch,
+    id_columns: &[String],
+) -> Result<(Vec<RecordBatch>, bool), String> {
+    let Some(current_hashes) = current_state.column_by_name(VALUE_HASH_COLUMN) else {
+        return Ok((to_insert, false));
+    };
+    if to_insert.is_empty() || current_state.num_rows() == 0 {
+        return Ok((to_insert, false));
+    }
+    let current_hashes = crate::batch_utils::downcast_column::<StringArray>(current_hashes, VALUE_HASH_COLUMN, "Utf8")?;
+    let id_arrays = |batch: &RecordBatch| -> Result<Vec<ArrayRef>, String> {
+        id_columns.iter().map(|column| crate::batch_utils::required_column(batch, column).cloned()).collect()
+    };
+
+    let expiring: FxHashSet<usize> = to_expire.iter().map(|&(row, _)| row).collect();
+    let current_ids = id_arrays(current_state)?;
+    let mut neighbors: FxHashMap<String, Vec<Neighbor>> = FxHashMap::default();
+    let mut key = String::new();
+    for row in 0..current_state.num_rows() {
+        if expiring.contains(&row) {
+            continue;
+        }
+        let (from, to) = get_temporal_bounds_micros(current_state, row)?;
+        create_id_key_with_buffer(&current_ids, row, &mut key);
+        neighbors.entry(key.clone()).or_default().push(Neighbor { row, from, to });
+    }
+    if neighbors.is_empty() {
+        return Ok((to_insert, false));
+    }
+
+    let mut merged_current: FxHashSet<usize> = FxHashSet::default();
+    let mut any_merged = false;
+    let mut batches = Vec::with_capacity(to_insert.len());
+    for batch in to_insert {
+        let insert_ids = id_arrays(&batch)?;
+        let hashes = crate::batch_utils::required_column(&batch, VALUE_HASH_COLUMN)?;
+        let hashes = crate::batch_utils::downcast_column::<StringArray>(hashes, VALUE_HASH_COLUMN, "Utf8")?;
+        let mut ranges = Vec::with_capacity(batch.num_rows());
+        let mut extended = false;
+        for row in 0..batch.num_rows() {
+            let (mut from, mut to) = get_temporal_bounds_micros(&batch, row)?;
+            create_id_key_with_buffer(&insert_ids, row, &mut key);
+            let candidates = neighbors.get(&key).map(Vec::as_slice).unwrap_or_default();
+            // Keep stretching, so inserts touching a run of same-valued rows absorb all of them
+            loop {
+                let next = candidates.iter().find(|neighbor| {
+                    !merged_current.contains(&neighbor.row)
+                        && current_hashes.value(neighbor.row) == hashes.value(row)
+                        && (neighbor.to == from || neighbor.from == to)
+                        && !should_prevent_merge(neighbor.to, to)
+                });
+                let Some(neighbor) = next else {
+                    break;
+                };
+                merged_current.insert(neighbor.row);
+                to_expire.push((neighbor.row, ExpireReason::Merged));
+                from = from.min(neighbor.from);
+                to = to.max(neighbor.to);
+                extended = true;
+            }
+            ranges.push((from, to));
+        }
+
+        if !extended {
+            batches.push(batch);
+            continue;
+        }
+        any_merged = true;
+        let schema = batch.schema();
+        let mut columns = batch.columns().to_vec();
+        for (column, values) in [
+            ("effective_from", ranges.iter().map(|&(from, _)| datetime_from_micros(from)).collect::<Result<Vec<_>, String>>()?),
+            ("effective_to", ranges.iter().map(|&(_, to)| datetime_from_micros(to)).collect::<Result<Vec<_>, String>>()?),
+        ] {
+            let idx = schema.index_of(column).map_err(|e| e.to_string())?;
+            columns[idx] = create_timestamp_array_from_values(columns[idx].data_type(), &values)?;
+        }
+        batches.push(RecordBatch::try_new(schema, columns).map_err(|e| e.to_string())?);
+    }
+    Ok((batches, any_merged))
+}
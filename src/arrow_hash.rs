@@ -1,5 +1,6 @@
+use crate::types::VALUE_HASH_COLUMN;
 use crate::HashAlgorithm;
-use arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch, StringArray};
 use arrow::array::{Int8Array, Int16Array, Int32Array, Int64Array};
 use arrow::array::{Float32Array, Float64Array, BooleanArray};
 use arrow::array::{Date32Array, Date64Array, Decimal128Array};
@@ -7,20 +8,115 @@ use arrow::array::{TimestampSecondArray, TimestampMillisecondArray, TimestampMic
 use arrow::datatypes::DataType;
 use std::sync::Arc;
 
+/// Field metadata key on the `value_hash` column naming the algorithm that produced it
+pub const HASH_ALGORITHM_METADATA_KEY: &str = "pytemporal.hash_algorithm";
+
+/// Field metadata key on the `value_hash` column listing, comma-separated, the float columns
+/// hashed by their exact bits. Absent when every float column was normalized.
+pub const HASH_EXACT_FLOATS_METADATA_KEY: &str = "pytemporal.hash_exact_floats";
+
+/// How value hashes are computed: the algorithm, and the float columns hashed by their exact
+/// bits instead of hashing whole-numbered floats as Int64 (`ProcessingOptions::exact_float_columns`)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HashSpec<'a> {
+    pub(crate) algorithm: HashAlgorithm,
+    pub(crate) exact_floats: &'a [String],
+}
+
+impl From<HashAlgorithm> for HashSpec<'static> {
+    fn from(algorithm: HashAlgorithm) -> Self {
+        HashSpec { algorithm, exact_floats: &[] }
+    }
+}
+
+impl HashSpec<'_> {
+    /// The metadata value recording `exact_floats`, sorted so the tag doesn't depend on order
+    fn exact_floats_tag(&self) -> Option<String> {
+        if self.exact_floats.is_empty() {
+            return None;
+        }
+        let mut columns: Vec<&str> = self.exact_floats.iter().map(String::as_str).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        Some(columns.join(","))
+    }
+}
+
+/// `value_hash` field tagged with the algorithm and float policy that fill it
+pub(crate) fn hash_field(spec: HashSpec<'_>) -> arrow::datatypes::Field {
+    let mut metadata: std::collections::HashMap<String, String> =
+        [(HASH_ALGORITHM_METADATA_KEY.to_string(), spec.algorithm.as_str().to_string())].into();
+    if let Some(tag) = spec.exact_floats_tag() {
+        metadata.insert(HASH_EXACT_FLOATS_METADATA_KEY.to_string(), tag);
+    }
+    arrow::datatypes::Field::new(VALUE_HASH_COLUMN, DataType::Utf8, false).with_metadata(metadata)
+}
+
+/// Fixed-width key for a `value_hash` string, used for all internal comparisons.
+/// xxhash hex digests parse losslessly; anything else (sha256, producer-supplied
+/// hashes) is folded to 64 bits, which is why runs hashing with sha256 always verify
+/// matches on the value columns (`ProcessingOptions::verify_hash_matches`).
+pub(crate) fn value_hash_key(hash: &str) -> u64 {
+    if hash.len() == 16 {
+        if let Ok(key) = u64::from_str_radix(hash, 16) {
+            return key;
+        }
+    }
+    xxhash_rust::xxh64::xxh64(hash.as_bytes(), 0)
+}
+
+/// Algorithm recorded on the batch's `value_hash` field, if any
+pub(crate) fn hash_algorithm_tag(batch: &RecordBatch) -> Result<Option<HashAlgorithm>, String> {
+    let schema = batch.schema();
+    let Ok(field) = schema.field_with_name(VALUE_HASH_COLUMN) else {
+        return Ok(None);
+    };
+    field.metadata().get(HASH_ALGORITHM_METADATA_KEY)
+        .map(|tag| tag.parse::<HashAlgorithm>())
+        .transpose()
+}
+
+/// Exact float columns recorded on the batch's `value_hash` field, sorted. Empty when the
+/// field carries no tag; `None` when there is no tagged `value_hash` field at all.
+pub(crate) fn hash_exact_floats_tag(batch: &RecordBatch) -> Option<Vec<String>> {
+    let schema = batch.schema();
+    let field = schema.field_with_name(VALUE_HASH_COLUMN).ok()?;
+    field.metadata().get(HASH_ALGORITHM_METADATA_KEY)?;
+    Some(field.metadata().get(HASH_EXACT_FLOATS_METADATA_KEY)
+        .map(|tag| tag.split(',').map(str::to_string).collect())
+        .unwrap_or_default())
+}
+
+/// Fail when the batch's `value_hash` was tagged with a different float policy than `spec`,
+/// since its hashes of whole-numbered floats would never match ours
+pub(crate) fn check_exact_floats_tag(batch: &RecordBatch, spec: HashSpec<'_>) -> Result<(), String> {
+    let Some(tagged) = hash_exact_floats_tag(batch) else {
+        return Ok(());
+    };
+    let ours = spec.exact_floats_tag().unwrap_or_default();
+    if tagged.join(",") != ours {
+        return Err(format!(
+            "value_hash was computed with exact float columns [{}] but this run uses [{}]; recompute it with add_hash_key",
+            tagged.join(", "), ours.replace(',', ", ")
+        ));
+    }
+    Ok(())
+}
+
 /// Fast hash computation directly on Arrow arrays without deserialization
 pub fn hash_values_batch_arrow_direct(
     record_batch: &RecordBatch, 
     row_indices: &[usize], 
     value_columns: &[String],
-    algorithm: HashAlgorithm,
+    spec: HashSpec<'_>,
 ) -> Vec<String> {
     let mut hashes = Vec::with_capacity(row_indices.len());
     
     // Pre-compute column indices and arrays to avoid repeated lookups
-    let col_data: Vec<(&str, &ArrayRef)> = value_columns.iter()
+    let col_data: Vec<(bool, &ArrayRef)> = value_columns.iter()
         .map(|col_name| {
             let col_idx = record_batch.schema().index_of(col_name).unwrap();
-            (col_name.as_str(), record_batch.column(col_idx))
+            (spec.exact_floats.contains(col_name), record_batch.column(col_idx))
         })
         .collect();
     
@@ -28,15 +124,16 @@ pub fn hash_values_batch_arrow_direct(
         let mut hasher_input = Vec::with_capacity(1024); // Pre-allocate reasonable buffer
         
         // Hash each column's raw bytes directly without conversion to ScalarValue
-        for (_col_name, array) in &col_data {
-            hash_array_value_direct(array, row_idx, &mut hasher_input);
+        for &(exact_floats, array) in &col_data {
+            hash_array_value_direct(array, row_idx, exact_floats, &mut hasher_input);
         }
         
-        let hash_result = match algorithm {
+        let hash_result = match spec.algorithm {
             HashAlgorithm::XxHash => {
                 use xxhash_rust::xxh64::xxh64;
                 format!("{:016x}", xxh64(&hasher_input, 0))
             },
+            #[cfg(feature = "legacy-hash")]
             HashAlgorithm::Sha256 => {
                 use sha2::{Sha256, Digest};
                 let mut hasher = Sha256::new();
@@ -50,8 +147,9 @@ pub fn hash_values_batch_arrow_direct(
     hashes
 }
 
-/// Hash a single array value directly without Arrow→Rust conversion
-fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, hasher_input: &mut Vec<u8>) {
+/// Hash a single array value directly without Arrow→Rust conversion.
+/// Whole-numbered floats hash as Int64, so 1.0 and 1 agree, unless `exact_floats` is set.
+fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, exact_floats: bool, hasher_input: &mut Vec<u8>) {
     // Handle null values consistently
     if array.is_null(row_idx) {
         hasher_input.extend_from_slice(b"NULL");
@@ -95,7 +193,7 @@ fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, hasher_input: &mut
             let value = float_array.value(row_idx);
             
             // Check if this is actually an integer value stored as float
-            if value.fract() == 0.0 && value.is_finite() && value >= i64::MIN as f32 && value <= i64::MAX as f32 {
+            if !exact_floats && value.fract() == 0.0 && value.is_finite() && value >= i64::MIN as f32 && value <= i64::MAX as f32 {
                 // This is an integer value - normalize to Int64 for consistency  
                 let i64_val = value as i64;
                 hasher_input.extend_from_slice(&i64_val.to_le_bytes());
@@ -111,7 +209,7 @@ fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, hasher_input: &mut
             let value = float_array.value(row_idx);
             
             // Check if this is actually an integer value stored as float
-            if value.fract() == 0.0 && value.is_finite() && value >= i64::MIN as f64 && value <= i64::MAX as f64 {
+            if !exact_floats && value.fract() == 0.0 && value.is_finite() && value >= i64::MIN as f64 && value <= i64::MAX as f64 {
                 // This is an integer value - normalize to Int64 for consistency  
                 let i64_val = value as i64;
                 hasher_input.extend_from_slice(&i64_val.to_le_bytes());
@@ -171,6 +269,33 @@ fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, hasher_input: &mut
             let value = decimal_array.value(row_idx);
             hasher_input.extend_from_slice(&value.to_le_bytes());
         },
+
+        DataType::Decimal256(_, _) => {
+            let value = array.as_primitive::<arrow::datatypes::Decimal256Type>().value(row_idx);
+            hasher_input.extend_from_slice(&value.to_le_bytes());
+        },
+        
+        DataType::LargeUtf8 => {
+            hasher_input.extend_from_slice(array.as_string::<i64>().value(row_idx).as_bytes());
+        },
+
+        DataType::Utf8View => {
+            hasher_input.extend_from_slice(array.as_string_view().value(row_idx).as_bytes());
+        },
+
+        // UUIDs hash as their canonical text, so binary and string UUID columns agree
+        DataType::FixedSizeBinary(16) => {
+            hasher_input.extend_from_slice(&crate::canonical_uuid(array.as_fixed_size_binary().value(row_idx)));
+        },
+
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::FixedSizeBinary(_) => {
+            hasher_input.extend_from_slice(binary_value(array, row_idx));
+        },
+
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _)
+        | DataType::Struct(_) | DataType::Map(_, _) | DataType::Dictionary(_, _) => {
+            hash_nested_value(array, row_idx, exact_floats, hasher_input);
+        },
         
         _ => {
             // Fallback to string representation for unsupported types
@@ -181,16 +306,101 @@ fn hash_array_value_direct(array: &ArrayRef, row_idx: usize, hasher_input: &mut
     }
 }
 
+fn binary_value(array: &ArrayRef, row_idx: usize) -> &[u8] {
+    match array.data_type() {
+        DataType::Binary => array.as_binary::<i32>().value(row_idx),
+        DataType::LargeBinary => array.as_binary::<i64>().value(row_idx),
+        DataType::BinaryView => array.as_binary_view().value(row_idx),
+        DataType::FixedSizeBinary(_) => array.as_fixed_size_binary().value(row_idx),
+        dt => unreachable!("not a binary type: {:?}", dt),
+    }
+}
+
+/// Canonical encoding of nested values, independent of Arrow's Debug output.
+/// Lists write their length then each element, structs each field name and value, and maps
+/// their entries as a list of key/value structs. Every element carries a null marker and
+/// variable-width leaves are length-prefixed, so different nestings can't encode alike.
+/// Dictionaries hash their decoded value, matching the plain column.
+fn hash_nested_value(array: &ArrayRef, row_idx: usize, exact_floats: bool, hasher_input: &mut Vec<u8>) {
+    match array.data_type() {
+        DataType::List(_) => hash_list(b'L', &array.as_list::<i32>().value(row_idx), exact_floats, hasher_input),
+        DataType::LargeList(_) => hash_list(b'L', &array.as_list::<i64>().value(row_idx), exact_floats, hasher_input),
+        DataType::FixedSizeList(_, _) => hash_list(b'L', &array.as_fixed_size_list().value(row_idx), exact_floats, hasher_input),
+        DataType::Map(_, _) => {
+            let entries: ArrayRef = Arc::new(array.as_map().value(row_idx));
+            hash_list(b'M', &entries, exact_floats, hasher_input);
+        },
+        DataType::Struct(fields) => {
+            let struct_array = array.as_struct();
+            hasher_input.push(b'S');
+            hasher_input.extend_from_slice(&(fields.len() as u64).to_le_bytes());
+            for (field, column) in fields.iter().zip(struct_array.columns()) {
+                hash_length_prefixed(field.name().as_bytes(), hasher_input);
+                hash_nested_element(column, row_idx, exact_floats, hasher_input);
+            }
+        },
+        DataType::Dictionary(_, _) => {
+            let dictionary = array.as_any_dictionary();
+            let key = dictionary.normalized_keys()[row_idx];
+            hash_array_value_direct(dictionary.values(), key, exact_floats, hasher_input);
+        },
+        _ => hash_array_value_direct(array, row_idx, exact_floats, hasher_input),
+    }
+}
+
+fn hash_list(tag: u8, values: &ArrayRef, exact_floats: bool, hasher_input: &mut Vec<u8>) {
+    hasher_input.push(tag);
+    hasher_input.extend_from_slice(&(values.len() as u64).to_le_bytes());
+    for idx in 0..values.len() {
+        hash_nested_element(values, idx, exact_floats, hasher_input);
+    }
+}
+
+/// One element inside a nested value: a null marker, then the value
+fn hash_nested_element(array: &ArrayRef, row_idx: usize, exact_floats: bool, hasher_input: &mut Vec<u8>) {
+    if let DataType::Dictionary(_, _) = array.data_type() {
+        if array.is_null(row_idx) {
+            hasher_input.push(0);
+            return;
+        }
+        let dictionary = array.as_any_dictionary();
+        return hash_nested_element(dictionary.values(), dictionary.normalized_keys()[row_idx], exact_floats, hasher_input);
+    }
+    if array.is_null(row_idx) {
+        hasher_input.push(0);
+        return;
+    }
+    hasher_input.push(1);
+    match array.data_type() {
+        DataType::Utf8 => hash_length_prefixed(array.as_string::<i32>().value(row_idx).as_bytes(), hasher_input),
+        DataType::LargeUtf8 => hash_length_prefixed(array.as_string::<i64>().value(row_idx).as_bytes(), hasher_input),
+        DataType::Utf8View => hash_length_prefixed(array.as_string_view().value(row_idx).as_bytes(), hasher_input),
+        DataType::FixedSizeBinary(16) => {
+            hash_length_prefixed(&crate::canonical_uuid(array.as_fixed_size_binary().value(row_idx)), hasher_input);
+        },
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::FixedSizeBinary(_) => {
+            hash_length_prefixed(binary_value(array, row_idx), hasher_input);
+        },
+        _ => hash_nested_value(array, row_idx, exact_floats, hasher_input),
+    }
+}
+
+fn hash_length_prefixed(bytes: &[u8], hasher_input: &mut Vec<u8>) {
+    hasher_input.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    hasher_input.extend_from_slice(bytes);
+}
+
 /// Fast add hash column using direct Arrow hashing
 pub fn add_hash_column_arrow_direct(
     record_batch: &RecordBatch,
     value_columns: &[String],
-    algorithm: HashAlgorithm,
+    spec: HashSpec<'_>,
 ) -> Result<RecordBatch, String> {
     let num_rows = record_batch.num_rows();
     if num_rows == 0 {
         return Err("Cannot add hash column to empty RecordBatch".to_string());
     }
+    let _span = tracing::debug_span!("hash_values", rows = num_rows, algorithm = spec.algorithm.as_str()).entered();
     
     // Validate that all value columns exist
     for col_name in value_columns {
@@ -201,18 +411,25 @@ pub fn add_hash_column_arrow_direct(
     
     // Use the fast Arrow-direct hash computation
     let row_indices: Vec<usize> = (0..num_rows).collect();
-    let hash_values_string = hash_values_batch_arrow_direct(record_batch, &row_indices, value_columns, algorithm);
-    
-    // Create the hash column
-    let hash_array = Arc::new(StringArray::from(hash_values_string));
+    let hash_values_string = hash_values_batch_arrow_direct(record_batch, &row_indices, value_columns, spec);
     
+    with_hash_column(record_batch, Arc::new(StringArray::from(hash_values_string)), spec)
+}
+
+/// `record_batch` with `hash_array` as its value_hash column, replacing an existing one
+pub(crate) fn with_hash_column(
+    record_batch: &RecordBatch,
+    hash_array: ArrayRef,
+    spec: HashSpec<'_>,
+) -> Result<RecordBatch, String> {
     // Check if value_hash column already exists
-    let hash_column_index = record_batch.schema().index_of("value_hash");
+    let hash_column_index = record_batch.schema().index_of(VALUE_HASH_COLUMN);
     
     let (new_schema, new_columns) = if let Ok(hash_idx) = hash_column_index {
         // Replace existing value_hash column
-        let new_fields: Vec<Arc<arrow::datatypes::Field>> = record_batch.schema().fields().iter().cloned().collect();
-        let new_schema = Arc::new(arrow::datatypes::Schema::new(new_fields));
+        let mut new_fields: Vec<Arc<arrow::datatypes::Field>> = record_batch.schema().fields().iter().cloned().collect();
+        new_fields[hash_idx] = Arc::new(hash_field(spec));
+        let new_schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(new_fields, record_batch.schema().metadata().clone()));
         
         let mut new_columns: Vec<ArrayRef> = record_batch.columns().to_vec();
         new_columns[hash_idx] = hash_array;
@@ -220,8 +437,8 @@ pub fn add_hash_column_arrow_direct(
     } else {
         // Add new value_hash column
         let mut new_fields: Vec<Arc<arrow::datatypes::Field>> = record_batch.schema().fields().iter().cloned().collect();
-        new_fields.push(Arc::new(arrow::datatypes::Field::new("value_hash", arrow::datatypes::DataType::Utf8, false)));
-        let new_schema = Arc::new(arrow::datatypes::Schema::new(new_fields));
+        new_fields.push(Arc::new(hash_field(spec)));
+        let new_schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(new_fields, record_batch.schema().metadata().clone()));
         
         let mut new_columns: Vec<ArrayRef> = record_batch.columns().to_vec();
         new_columns.push(hash_array);
@@ -230,4 +447,113 @@ pub fn add_hash_column_arrow_direct(
     
     RecordBatch::try_new(new_schema, new_columns)
         .map_err(|e| e.to_string())
+}
+
+/// Recompute `value_hash` with `to` for a batch hashed with `from`, e.g. to migrate history
+/// from sha256 to xxhash one batch at a time. Every row must already hold either the `from` or
+/// the `to` hash of its value columns, so half-migrated tables can be re-run safely and rows
+/// hashed over different columns are reported instead of silently rewritten.
+/// The output field is tagged with `to` under `HASH_ALGORITHM_METADATA_KEY`; exact float
+/// columns recorded under `HASH_EXACT_FLOATS_METADATA_KEY` are kept.
+pub fn rehash_table(
+    batch: &RecordBatch,
+    value_columns: &[String],
+    from: HashAlgorithm,
+    to: HashAlgorithm,
+) -> Result<RecordBatch, String> {
+    if let Some(tagged) = hash_algorithm_tag(batch)? {
+        if tagged != from && tagged != to {
+            return Err(format!(
+                "value_hash is tagged {} but the migration is from {} to {}",
+                tagged.as_str(), from.as_str(), to.as_str()
+            ));
+        }
+    }
+    let existing = batch.column_by_name(VALUE_HASH_COLUMN)
+        .ok_or("value_hash column not found")?
+        .as_any().downcast_ref::<StringArray>()
+        .ok_or("value_hash column must be Utf8")?;
+    for col_name in value_columns {
+        if batch.schema().index_of(col_name).is_err() {
+            return Err(format!("Column '{}' not found in RecordBatch", col_name));
+        }
+    }
+
+    let exact_floats = hash_exact_floats_tag(batch).unwrap_or_default();
+    let from = HashSpec { algorithm: from, exact_floats: &exact_floats };
+    let to = HashSpec { algorithm: to, exact_floats: &exact_floats };
+    let rows: Vec<usize> = (0..batch.num_rows()).collect();
+    let from_hashes = hash_values_batch_arrow_direct(batch, &rows, value_columns, from);
+    let to_hashes = hash_values_batch_arrow_direct(batch, &rows, value_columns, to);
+    let unmatched: Vec<usize> = rows.into_iter()
+        .filter(|&row| {
+            existing.is_null(row)
+                || (existing.value(row) != from_hashes[row] && existing.value(row) != to_hashes[row])
+        })
+        .collect();
+    if !unmatched.is_empty() {
+        return Err(format!(
+            "Rows {:?} have a value_hash that is neither the {} nor the {} hash of {:?}",
+            &unmatched[..unmatched.len().min(20)], from.algorithm.as_str(), to.algorithm.as_str(), value_columns
+        ));
+    }
+
+    let mut fields: Vec<Arc<arrow::datatypes::Field>> = batch.schema().fields().iter().cloned().collect();
+    let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+    let hash_idx = batch.schema().index_of(VALUE_HASH_COLUMN).map_err(|e| e.to_string())?;
+    fields[hash_idx] = Arc::new(hash_field(to));
+    columns[hash_idx] = Arc::new(StringArray::from(to_hashes));
+    let schema = arrow::datatypes::Schema::new_with_metadata(fields, batch.schema().metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), columns)
+        .map_err(|e| e.to_string())
+}
+
+/// Row-encoded value columns of current state and updates, compared when their hashes match.
+/// Both sides share one `RowConverter`, so equal values encode to equal bytes.
+pub(crate) struct ValueRows {
+    current: arrow::row::Rows,
+    updates: arrow::row::Rows,
+}
+
+impl ValueRows {
+    pub(crate) fn new(current: &RecordBatch, updates: &RecordBatch, value_columns: &[String]) -> Result<Self, String> {
+        let mut current_arrays = Vec::with_capacity(value_columns.len());
+        let mut update_arrays = Vec::with_capacity(value_columns.len());
+        for column in value_columns {
+            let current_array = current.column_by_name(column)
+                .ok_or_else(|| format!("Value column '{}' not found in current state", column))?;
+            let update_array = updates.column_by_name(column)
+                .ok_or_else(|| format!("Value column '{}' not found in updates", column))?;
+            let update_array = if update_array.data_type() == current_array.data_type() {
+                update_array.clone()
+            } else {
+                arrow::compute::cast(update_array, current_array.data_type())
+                    .map_err(|e| format!("Failed to cast value column '{}' for hash verification: {}", column, e))?
+            };
+            current_arrays.push(current_array.clone());
+            update_arrays.push(update_array);
+        }
+
+        let converter = arrow::row::RowConverter::new(
+            current_arrays.iter().map(|a| arrow::row::SortField::new(a.data_type().clone())).collect(),
+        ).map_err(|e| format!("Failed to build row converter for hash verification: {}", e))?;
+        let current = converter.convert_columns(&current_arrays)
+            .map_err(|e| format!("Failed to encode current values for hash verification: {}", e))?;
+        let updates = converter.convert_columns(&update_arrays)
+            .map_err(|e| format!("Failed to encode update values for hash verification: {}", e))?;
+        Ok(ValueRows { current, updates })
+    }
+
+    /// Whether a current row and an update row hold identical values
+    pub(crate) fn same_values(&self, current_row: usize, update_row: usize) -> bool {
+        self.current.row(current_row) == self.updates.row(update_row)
+    }
+}
+
+/// Whether two rows with matching hashes really match: always true unless verification is on
+pub(crate) fn confirm_hash_match(value_rows: Option<&ValueRows>, current_row: usize, update_row: usize) -> bool {
+    match value_rows {
+        Some(rows) => rows.same_values(current_row, update_row),
+        None => true,
+    }
 }
\ No newline at end of file
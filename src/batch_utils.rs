@@ -1,8 +1,6 @@
 use crate::types::*;
-use arrow::array::{Array, ArrayRef, RecordBatch, TimestampMicrosecondArray, StringBuilder};
-use arrow::array::{Int8Builder, Int16Builder, Int32Builder, Float32Builder, Float64Builder, Date32Builder, Date64Builder, BooleanBuilder, Decimal128Builder};
-use arrow::array::{Int8Array, Int16Array, Float32Array, Date32Array, Date64Array, BooleanArray, Decimal128Array};
-use arrow::array::{TimestampSecondBuilder, TimestampMillisecondBuilder, TimestampMicrosecondBuilder, TimestampNanosecondBuilder};
+use arrow::array::{Array, ArrayRef, RecordBatch, TimestampMicrosecondArray, StringBuilder, UInt64Array};
+use arrow::array::{Date32Array, Date64Array};
 use arrow::array::{TimestampSecondArray, TimestampMillisecondArray, TimestampNanosecondArray};
 use arrow::datatypes::{DataType};
 use std::sync::Arc;
@@ -56,7 +54,6 @@ fn create_timestamp_array_with_unit(
         }
         TimeUnit::Nanosecond => {
             let nanos = (datetime - EPOCH).num_nanoseconds()
-                .ok_or_else(|| "Timestamp overflow in nanosecond conversion")
                 .unwrap_or(i64::MAX); // Fallback to max value on overflow
             let values = vec![Some(nanos)];
             let array = TimestampNanosecondArray::from(values).with_timezone_opt(timezone);
@@ -66,12 +63,6 @@ fn create_timestamp_array_with_unit(
 }
 
 // Helper function to create a value hash array
-fn create_value_hash_array(hash: &str) -> ArrayRef {
-    let mut builder = StringBuilder::new();
-    builder.append_value(hash);
-    Arc::new(builder.finish())
-}
-
 // Common function to build columns with temporal data from a BitemporalRecord
 fn build_temporal_columns(
     record: &BitemporalRecord,
@@ -81,7 +72,7 @@ fn build_temporal_columns(
 ) -> Result<Vec<ArrayRef>, String> {
     let mut columns: Vec<ArrayRef> = Vec::new();
     
-    for field in schema.fields() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let column_name = field.name();
         
         match column_name.as_str() {
@@ -161,13 +152,9 @@ fn build_temporal_columns(
                     _ => return Err(format!("Unsupported data type for as_of_to: {:?}", field.data_type()))
                 }
             }
-            "value_hash" => {
-                columns.push(create_value_hash_array(&record.value_hash));
-            }
             _ => {
                 // Copy from source batch
-                let orig_array = source_batch.column_by_name(column_name).unwrap();
-                let new_array = orig_array.slice(source_row, 1);
+                let new_array = source_batch.column(col_idx).slice(source_row, 1);
                 columns.push(new_array);
             }
         }
@@ -262,6 +249,25 @@ fn create_timestamp_array_for_records(
     }
 }
 
+/// Take indices for `rows`. Rows are always addressed with 64 bits, so batches past
+/// `u32::MAX` rows gather the right rows instead of wrapping around.
+pub(crate) fn take_indices(rows: impl IntoIterator<Item = usize>) -> UInt64Array {
+    UInt64Array::from_iter_values(rows.into_iter().map(|row| row as u64))
+}
+
+/// A 64-bit row index (from an Arrow array, a serialized frame or Python) as a `usize`,
+/// failing instead of truncating on platforms where `usize` is narrower
+pub(crate) fn row_index(index: u64) -> Result<usize, String> {
+    usize::try_from(index)
+        .map_err(|_| format!("Row index {} does not fit in a {}-bit usize on this platform", index, usize::BITS))
+}
+
+/// Gather `indices` rows of a column; works for every Arrow type, nested ones included
+fn take_rows(array: &ArrayRef, indices: &UInt64Array, column_name: &str) -> Result<ArrayRef, String> {
+    arrow::compute::take(array.as_ref(), indices, None)
+        .map_err(|e| format!("Failed to copy column '{}': {}", column_name, e))
+}
+
 /// Batch-oriented version that creates a single RecordBatch from multiple BitemporalRecords
 /// This avoids the overhead of creating many single-row batches
 pub fn create_record_batch_from_records(
@@ -274,12 +280,10 @@ pub fn create_record_batch_from_records(
     }
     
     let schema = source_batch.schema();
-    let num_records = records.len();
-    
-    // Pre-allocate builders with exact capacity
+    let take_indices = self::take_indices(source_rows.iter().copied());
     let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
     
-    for field in schema.fields() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let column_name = field.name();
         
         match column_name.as_str() {
@@ -311,238 +315,9 @@ pub fn create_record_batch_from_records(
                     field.data_type()
                 )?);
             }
-            "value_hash" => {
-                let mut builder = StringBuilder::new();
-                for record in records {
-                    builder.append_value(&record.value_hash);
-                }
-                columns.push(Arc::new(builder.finish()));
-            }
             _ => {
-                // Copy data columns from source batch
-                let orig_array = source_batch.column_by_name(column_name).unwrap();
-                
-                // Handle different column types with pre-allocated builders
-                match orig_array.data_type() {
-                    arrow::datatypes::DataType::Utf8 => {
-                        let string_array = orig_array.as_any()
-                            .downcast_ref::<arrow::array::StringArray>().unwrap();
-                        let mut builder = StringBuilder::new();
-                        for &source_row in source_rows {
-                            if string_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(string_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Int32 => {
-                        let int32_array = orig_array.as_any()
-                            .downcast_ref::<arrow::array::Int32Array>().unwrap();
-                        let mut builder = Int32Builder::new();
-                        for &source_row in source_rows {
-                            if int32_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(int32_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Int64 => {
-                        let int64_array = orig_array.as_any()
-                            .downcast_ref::<arrow::array::Int64Array>().unwrap();
-                        let mut builder = arrow::array::Int64Builder::new();
-                        for &source_row in source_rows {
-                            if int64_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(int64_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Float64 => {
-                        let float64_array = orig_array.as_any()
-                            .downcast_ref::<arrow::array::Float64Array>().unwrap();
-                        let mut builder = Float64Builder::new();
-                        for &source_row in source_rows {
-                            if float64_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(float64_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Int8 => {
-                        let int8_array = orig_array.as_any()
-                            .downcast_ref::<Int8Array>().unwrap();
-                        let mut builder = Int8Builder::new();
-                        for &source_row in source_rows {
-                            if int8_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(int8_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Int16 => {
-                        let int16_array = orig_array.as_any()
-                            .downcast_ref::<Int16Array>().unwrap();
-                        let mut builder = Int16Builder::new();
-                        for &source_row in source_rows {
-                            if int16_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(int16_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Float32 => {
-                        let float32_array = orig_array.as_any()
-                            .downcast_ref::<Float32Array>().unwrap();
-                        let mut builder = Float32Builder::new();
-                        for &source_row in source_rows {
-                            if float32_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(float32_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Date32 => {
-                        let date32_array = orig_array.as_any()
-                            .downcast_ref::<Date32Array>().unwrap();
-                        let mut builder = Date32Builder::new();
-                        for &source_row in source_rows {
-                            if date32_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(date32_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Date64 => {
-                        let date64_array = orig_array.as_any()
-                            .downcast_ref::<Date64Array>().unwrap();
-                        let mut builder = Date64Builder::new();
-                        for &source_row in source_rows {
-                            if date64_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(date64_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Boolean => {
-                        let bool_array = orig_array.as_any()
-                            .downcast_ref::<BooleanArray>().unwrap();
-                        let mut builder = BooleanBuilder::new();
-                        for &source_row in source_rows {
-                            if bool_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(bool_array.value(source_row));
-                            }
-                        }
-                        columns.push(Arc::new(builder.finish()));
-                    }
-                    arrow::datatypes::DataType::Timestamp(unit, timezone) => {
-                        use arrow::datatypes::TimeUnit;
-                        match unit {
-                            TimeUnit::Second => {
-                                let ts_array = orig_array.as_any()
-                                    .downcast_ref::<TimestampSecondArray>().unwrap();
-                                let mut builder = TimestampSecondBuilder::new();
-                                for &source_row in source_rows {
-                                    if ts_array.is_null(source_row) {
-                                        builder.append_null();
-                                    } else {
-                                        builder.append_value(ts_array.value(source_row));
-                                    }
-                                }
-                                let array = builder.finish().with_timezone_opt(timezone.clone());
-                                columns.push(Arc::new(array));
-                            }
-                            TimeUnit::Millisecond => {
-                                let ts_array = orig_array.as_any()
-                                    .downcast_ref::<TimestampMillisecondArray>().unwrap();
-                                let mut builder = TimestampMillisecondBuilder::new();
-                                for &source_row in source_rows {
-                                    if ts_array.is_null(source_row) {
-                                        builder.append_null();
-                                    } else {
-                                        builder.append_value(ts_array.value(source_row));
-                                    }
-                                }
-                                let array = builder.finish().with_timezone_opt(timezone.clone());
-                                columns.push(Arc::new(array));
-                            }
-                            TimeUnit::Microsecond => {
-                                let ts_array = orig_array.as_any()
-                                    .downcast_ref::<TimestampMicrosecondArray>().unwrap();
-                                let mut builder = TimestampMicrosecondBuilder::new();
-                                for &source_row in source_rows {
-                                    if ts_array.is_null(source_row) {
-                                        builder.append_null();
-                                    } else {
-                                        builder.append_value(ts_array.value(source_row));
-                                    }
-                                }
-                                let array = builder.finish().with_timezone_opt(timezone.clone());
-                                columns.push(Arc::new(array));
-                            }
-                            TimeUnit::Nanosecond => {
-                                let ts_array = orig_array.as_any()
-                                    .downcast_ref::<TimestampNanosecondArray>().unwrap();
-                                let mut builder = TimestampNanosecondBuilder::new();
-                                for &source_row in source_rows {
-                                    if ts_array.is_null(source_row) {
-                                        builder.append_null();
-                                    } else {
-                                        builder.append_value(ts_array.value(source_row));
-                                    }
-                                }
-                                let array = builder.finish().with_timezone_opt(timezone.clone());
-                                columns.push(Arc::new(array));
-                            }
-                        }
-                    }
-                    arrow::datatypes::DataType::Decimal128(precision, scale) => {
-                        let decimal_array = orig_array.as_any()
-                            .downcast_ref::<Decimal128Array>().unwrap();
-                        let mut builder = Decimal128Builder::new();
-                        for &source_row in source_rows {
-                            if decimal_array.is_null(source_row) {
-                                builder.append_null();
-                            } else {
-                                builder.append_value(decimal_array.value(source_row));
-                            }
-                        }
-                        let array = builder.finish().with_precision_and_scale(*precision, *scale)
-                            .map_err(|e| format!("Failed to create Decimal128 array: {}", e))?;
-                        columns.push(Arc::new(array));
-                    }
-                    _ => {
-                        // Fallback to slice method for unsupported types
-                        let mut slices = Vec::with_capacity(num_records);
-                        for &source_row in source_rows {
-                            slices.push(orig_array.slice(source_row, 1));
-                        }
-                        // Concatenate slices - this is less efficient but handles all types
-                        let arrays: Vec<&dyn arrow::array::Array> = slices.iter().map(|a| a.as_ref()).collect();
-                        let result = arrow::compute::concat(&arrays)
-                            .map_err(|e| format!("Failed to concatenate arrays: {}", e))?;
-                        columns.push(result);
-                    }
-                }
+                // Copy data and hash columns from source batch
+                columns.push(take_rows(source_batch.column(col_idx), &take_indices, column_name)?);
             }
         }
     }
@@ -553,256 +328,448 @@ pub fn create_record_batch_from_records(
 
 // Old ScalarValue-based implementations removed - now using fast Arrow-direct hashing
 
-/// Create a RecordBatch of expired records with updated as_of_to timestamp
+/// Create a RecordBatch of expired records with updated as_of_to timestamp.
+/// With `expire_reasons` (parallel to `expire_indices`) an `expire_reason` column is appended.
 pub fn create_expired_records_batch(
     current_state: &RecordBatch,
     expire_indices: &[usize],
     expiry_timestamp: chrono::NaiveDateTime,
+    expire_reasons: Option<&[ExpireReason]>,
 ) -> Result<RecordBatch, String> {
     if expire_indices.is_empty() {
         return Err("Cannot create batch from empty expire indices".to_string());
     }
     
     let schema = current_state.schema();
-    let num_records = expire_indices.len();
-    
-    // Pre-allocate builders with exact capacity
+    let take_indices = self::take_indices(expire_indices.iter().copied());
     let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
     
-    for field in schema.fields() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let column_name = field.name();
         
         if column_name == "as_of_to" {
             // Set as_of_to to the expiry timestamp for all records, matching the field's precision
-            match field.data_type() {
-                DataType::Timestamp(unit, tz) => {
-                    let timezone_str = tz.as_ref().map(|t| t.to_string());
-                    
-                    match unit {
-                        arrow::datatypes::TimeUnit::Second => {
-                            let seconds = (expiry_timestamp - EPOCH).num_seconds();
-                            let values: Vec<Option<i64>> = expire_indices.iter()
-                                .map(|_| Some(seconds))
-                                .collect();
-                            let array = TimestampSecondArray::from(values).with_timezone_opt(timezone_str);
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Millisecond => {
-                            let millis = (expiry_timestamp - EPOCH).num_milliseconds();
-                            let values: Vec<Option<i64>> = expire_indices.iter()
-                                .map(|_| Some(millis))
-                                .collect();
-                            let array = TimestampMillisecondArray::from(values).with_timezone_opt(timezone_str);
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Microsecond => {
-                            let microseconds = (expiry_timestamp - EPOCH).num_microseconds().unwrap();
-                            let values: Vec<Option<i64>> = expire_indices.iter()
-                                .map(|_| Some(microseconds))
-                                .collect();
-                            let array = TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str);
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Nanosecond => {
-                            let nanoseconds = (expiry_timestamp - EPOCH).num_nanoseconds()
-                                .unwrap_or(i64::MAX); // Handle overflow
-                            let values: Vec<Option<i64>> = expire_indices.iter()
-                                .map(|_| Some(nanoseconds))
-                                .collect();
-                            let array = TimestampNanosecondArray::from(values).with_timezone_opt(timezone_str);
-                            columns.push(Arc::new(array));
-                        }
-                    }
-                }
-                DataType::Date32 => {
-                    let days = (expiry_timestamp.date() - chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32;
-                    let values: Vec<Option<i32>> = expire_indices.iter()
-                        .map(|_| Some(days))
-                        .collect();
-                    columns.push(Arc::new(Date32Array::from(values)));
-                }
-                DataType::Date64 => {
-                    let millis = (expiry_timestamp - EPOCH).num_milliseconds();
-                    let values: Vec<Option<i64>> = expire_indices.iter()
-                        .map(|_| Some(millis))
-                        .collect();
-                    columns.push(Arc::new(Date64Array::from(values)));
-                }
-                _ => return Err(format!("Unexpected data type for as_of_to: {:?}", field.data_type()))
-            }
+            columns.push(crate::create_timestamp_array(field.data_type(), expiry_timestamp, expire_indices.len())
+                .map_err(|e| format!("Unexpected data type for as_of_to: {}", e))?);
         } else {
             // Copy data from original records at the specified indices
-            let orig_array = current_state.column_by_name(column_name).unwrap();
-            
-            // Handle different column types with pre-allocated builders
-            match orig_array.data_type() {
-                arrow::datatypes::DataType::Utf8 => {
-                    let string_array = orig_array.as_any()
-                        .downcast_ref::<arrow::array::StringArray>().unwrap();
-                    let mut builder = StringBuilder::new();
-                    for &idx in expire_indices {
-                        if string_array.is_null(idx) {
-                            builder.append_null();
-                        } else {
-                            builder.append_value(string_array.value(idx));
-                        }
-                    }
-                    columns.push(Arc::new(builder.finish()));
-                }
-                arrow::datatypes::DataType::Int32 => {
-                    let int32_array = orig_array.as_any()
-                        .downcast_ref::<arrow::array::Int32Array>().unwrap();
-                    let mut builder = Int32Builder::new();
-                    for &idx in expire_indices {
-                        if int32_array.is_null(idx) {
-                            builder.append_null();
-                        } else {
-                            builder.append_value(int32_array.value(idx));
-                        }
-                    }
-                    columns.push(Arc::new(builder.finish()));
-                }
-                arrow::datatypes::DataType::Int64 => {
-                    let int64_array = orig_array.as_any()
-                        .downcast_ref::<arrow::array::Int64Array>().unwrap();
-                    let mut builder = arrow::array::Int64Builder::new();
-                    for &idx in expire_indices {
-                        if int64_array.is_null(idx) {
-                            builder.append_null();
-                        } else {
-                            builder.append_value(int64_array.value(idx));
-                        }
-                    }
-                    columns.push(Arc::new(builder.finish()));
-                }
-                arrow::datatypes::DataType::Float64 => {
-                    let float64_array = orig_array.as_any()
-                        .downcast_ref::<arrow::array::Float64Array>().unwrap();
-                    let mut builder = Float64Builder::new();
-                    for &idx in expire_indices {
-                        if float64_array.is_null(idx) {
-                            builder.append_null();
-                        } else {
-                            builder.append_value(float64_array.value(idx));
-                        }
-                    }
-                    columns.push(Arc::new(builder.finish()));
-                }
-                arrow::datatypes::DataType::Date32 => {
-                    let date32_array = orig_array.as_any()
-                        .downcast_ref::<arrow::array::Date32Array>().unwrap();
-                    let mut builder = Date32Builder::new();
-                    for &idx in expire_indices {
-                        if date32_array.is_null(idx) {
-                            builder.append_null();
-                        } else {
-                            builder.append_value(date32_array.value(idx));
-                        }
-                    }
-                    columns.push(Arc::new(builder.finish()));
-                }
-                arrow::datatypes::DataType::Timestamp(time_unit, timezone) => {
-                    match time_unit {
-                        arrow::datatypes::TimeUnit::Microsecond => {
-                            let timestamp_array = orig_array.as_any()
-                                .downcast_ref::<TimestampMicrosecondArray>().unwrap();
-                            
-                            // Extract values and nulls
-                            let mut values = Vec::with_capacity(num_records);
-                            for &idx in expire_indices {
-                                if timestamp_array.is_null(idx) {
-                                    values.push(None);
-                                } else {
-                                    values.push(Some(timestamp_array.value(idx)));
-                                }
-                            }
-                            
-                            // Create array with proper timezone information  
-                            let timezone_str = timezone.as_ref().map(|tz| tz.to_string());
-                            let array = TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str);
-                            
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Nanosecond => {
-                            let timestamp_array = orig_array.as_any()
-                                .downcast_ref::<arrow::array::TimestampNanosecondArray>().unwrap();
-                            
-                            // Extract values and nulls
-                            let mut values = Vec::with_capacity(num_records);
-                            for &idx in expire_indices {
-                                if timestamp_array.is_null(idx) {
-                                    values.push(None);
-                                } else {
-                                    values.push(Some(timestamp_array.value(idx)));
-                                }
-                            }
-                            
-                            // Create array with proper timezone information  
-                            let timezone_str = timezone.as_ref().map(|tz| tz.to_string());
-                            let array = arrow::array::TimestampNanosecondArray::from(values).with_timezone_opt(timezone_str);
-                            
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Millisecond => {
-                            let timestamp_array = orig_array.as_any()
-                                .downcast_ref::<arrow::array::TimestampMillisecondArray>().unwrap();
-                            
-                            // Extract values and nulls
-                            let mut values = Vec::with_capacity(num_records);
-                            for &idx in expire_indices {
-                                if timestamp_array.is_null(idx) {
-                                    values.push(None);
-                                } else {
-                                    values.push(Some(timestamp_array.value(idx)));
-                                }
-                            }
-                            
-                            // Create array with proper timezone information  
-                            let timezone_str = timezone.as_ref().map(|tz| tz.to_string());
-                            let array = arrow::array::TimestampMillisecondArray::from(values).with_timezone_opt(timezone_str);
-                            
-                            columns.push(Arc::new(array));
-                        }
-                        arrow::datatypes::TimeUnit::Second => {
-                            let timestamp_array = orig_array.as_any()
-                                .downcast_ref::<arrow::array::TimestampSecondArray>().unwrap();
-                            
-                            // Extract values and nulls
-                            let mut values = Vec::with_capacity(num_records);
-                            for &idx in expire_indices {
-                                if timestamp_array.is_null(idx) {
-                                    values.push(None);
-                                } else {
-                                    values.push(Some(timestamp_array.value(idx)));
-                                }
-                            }
-                            
-                            // Create array with proper timezone information  
-                            let timezone_str = timezone.as_ref().map(|tz| tz.to_string());
-                            let array = arrow::array::TimestampSecondArray::from(values).with_timezone_opt(timezone_str);
-                            
-                            columns.push(Arc::new(array));
-                        }
-                    }
-                }
-                _ => {
-                    // Fallback to slice method for unsupported types
-                    let mut slices = Vec::with_capacity(num_records);
-                    for &idx in expire_indices {
-                        slices.push(orig_array.slice(idx, 1));
-                    }
-                    // Concatenate slices - this is less efficient but handles all types
-                    let arrays: Vec<&dyn arrow::array::Array> = slices.iter().map(|a| a.as_ref()).collect();
-                    let result = arrow::compute::concat(&arrays)
-                        .map_err(|e| format!("Failed to concatenate arrays: {}", e))?;
-                    columns.push(result);
-                }
-            }
+            columns.push(take_rows(current_state.column(col_idx), &take_indices, column_name)?);
         }
     }
     
-    RecordBatch::try_new(schema.clone(), columns)
+    let schema = match expire_reasons {
+        Some(reasons) => {
+            let mut fields = schema.fields().to_vec();
+            fields.push(Arc::new(arrow::datatypes::Field::new(EXPIRE_REASON_COLUMN, DataType::Utf8, false)));
+            columns.push(Arc::new(arrow::array::StringArray::from_iter_values(reasons.iter().map(|r| r.as_str()))));
+            Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone()))
+        }
+        None => schema,
+    };
+
+    RecordBatch::try_new(schema, columns)
         .map_err(|e| format!("Failed to create expired records batch: {}", e))
 }
 
-// Old add_hash_column implementations removed - now using fast Arrow-direct hashing from arrow_hash.rs
\ No newline at end of file
+// Old add_hash_column implementations removed - now using fast Arrow-direct hashing from arrow_hash.rs
+
+/// Reader over output batches, cast to one unified schema (or `fallback_schema` when empty)
+/// so stream and file consumers see a single consistent schema
+#[cfg(any(feature = "python", feature = "ffi", feature = "cli"))]
+pub fn unified_batch_reader(
+    batches: Vec<RecordBatch>,
+    fallback_schema: arrow::datatypes::SchemaRef,
+) -> Result<arrow::record_batch::RecordBatchIterator<std::vec::IntoIter<Result<RecordBatch, arrow::error::ArrowError>>>, String> {
+    let schema = if batches.is_empty() {
+        fallback_schema
+    } else {
+        Arc::new(crate::conflation::compute_unified_schema(&batches))
+    };
+    let batches = batches.iter()
+        .map(|batch| crate::conflation::cast_batch_to_schema(batch, &schema).map(Ok))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(arrow::record_batch::RecordBatchIterator::new(batches.into_iter(), schema))
+}
+
+/// Add the temporal columns the updates batch is missing, per the configured fill rules.
+/// New columns take their type and position from `current_schema` when it has them.
+pub(crate) fn populate_missing_temporal_columns(
+    updates: RecordBatch,
+    current_schema: &arrow::datatypes::Schema,
+    fills: &crate::options::MissingTemporalColumns,
+    batch_timestamp: NaiveDateTime,
+) -> Result<RecordBatch, String> {
+    let schema = updates.schema();
+    let mut fields: Vec<arrow::datatypes::Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    let mut columns = updates.columns().to_vec();
+
+    for (name, fill) in [("effective_to", fills.effective_to), ("as_of_from", fills.as_of_from), ("as_of_to", fills.as_of_to)] {
+        if schema.column_with_name(name).is_some() {
+            continue;
+        }
+        let Some(value) = fill.resolve(batch_timestamp) else {
+            continue;
+        };
+
+        let data_type = match (current_schema.field_with_name(name), schema.field_with_name("effective_from")) {
+            (Ok(field), _) => field.data_type().clone(),
+            (Err(_), Ok(field)) if name == "effective_to" => field.data_type().clone(),
+            _ => DataType::Timestamp(arrow::datatypes::TimeUnit::Microsecond, None),
+        };
+        let array = crate::create_timestamp_array(&data_type, value, updates.num_rows())?;
+        let position = current_schema.index_of(name).unwrap_or(fields.len()).min(fields.len());
+        fields.insert(position, arrow::datatypes::Field::new(name, data_type, false));
+        columns.insert(position, array);
+    }
+
+    if fields.len() == schema.fields().len() {
+        return Ok(updates);
+    }
+    RecordBatch::try_new(Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone())), columns)
+        .map_err(|e| format!("Failed to populate missing temporal columns: {}", e))
+}
+
+/// Move every bounded `effective_to` by `delta`, leaving open-ended rows at the max sentinel.
+/// Used to translate inclusive interval ends to and from the engine's exclusive ends.
+pub(crate) fn shift_effective_to(batch: RecordBatch, delta: chrono::Duration) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let Ok(idx) = schema.index_of("effective_to") else {
+        return Ok(batch);
+    };
+    let effective_to = batch.column(idx);
+
+    let shifted = (0..batch.num_rows())
+        .map(|row| {
+            let value = crate::extract_datetime_flexible(effective_to.as_ref(), row)?;
+            Ok(if crate::is_open_ended(value) { value } else { value + delta })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut columns = batch.columns().to_vec();
+    columns[idx] = crate::create_timestamp_array_from_values(effective_to.data_type(), &shifted)?;
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to shift effective_to: {}", e))
+}
+
+/// Keep the rows still open in system time (`as_of_to` null or at the max sentinel), returning
+/// the filtered batch and the original position of every kept row.
+pub(crate) fn filter_open_rows(batch: RecordBatch) -> Result<(RecordBatch, Vec<usize>), String> {
+    let as_of_to = batch.column_by_name("as_of_to")
+        .ok_or("as_of_to column not found (required to filter current state to open rows)")?;
+    let mut open_rows = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        if as_of_to.is_null(row) || crate::is_open_ended(crate::extract_datetime_flexible(as_of_to.as_ref(), row)?) {
+            open_rows.push(row);
+        }
+    }
+    if open_rows.len() == batch.num_rows() {
+        return Ok((batch, open_rows));
+    }
+
+    let indices = take_indices(open_rows.iter().copied());
+    let filtered = arrow::compute::take_record_batch(&batch, &indices)
+        .map_err(|e| format!("Failed to filter current state to open rows: {}", e))?;
+    Ok((filtered, open_rows))
+}
+
+/// Expand run-end encoded columns to their plain value type and read a StringView or
+/// LargeUtf8 `value_hash` as Utf8, so the engine only sees layouts it handles natively.
+/// StringView / BinaryView data columns are left as they are.
+pub(crate) fn normalize_input_layouts(batch: RecordBatch) -> Result<RecordBatch, String> {
+    let needs_work = batch.schema().fields().iter().any(|field| {
+        matches!(field.data_type(), DataType::RunEndEncoded(_, _))
+            || (field.name() == VALUE_HASH_COLUMN && field.data_type() != &DataType::Utf8)
+    });
+    if !needs_work {
+        return Ok(batch);
+    }
+
+    let schema = batch.schema();
+    let mut fields = schema.fields().to_vec();
+    let mut columns = batch.columns().to_vec();
+    for (field, column) in fields.iter_mut().zip(columns.iter_mut()) {
+        let decoded = match field.data_type() {
+            DataType::RunEndEncoded(_, _) => decode_run_end_encoded(column)
+                .map_err(|e| format!("Failed to decode run-end encoded column '{}': {}", field.name(), e))?,
+            _ if field.name() == VALUE_HASH_COLUMN && field.data_type() != &DataType::Utf8 => {
+                arrow::compute::cast(column, &DataType::Utf8)
+                    .map_err(|e| format!("value_hash must be a string column: {}", e))?
+            }
+            _ => continue,
+        };
+        *field = Arc::new(field.as_ref().clone().with_data_type(decoded.data_type().clone()));
+        *column = decoded;
+    }
+
+    let schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to normalize input layouts: {}", e))
+}
+
+fn decode_run_end_encoded(array: &ArrayRef) -> Result<ArrayRef, arrow::error::ArrowError> {
+    use arrow::array::RunArray;
+    use arrow::datatypes::{Int16Type, Int32Type, Int64Type};
+
+    let logical: Vec<u64> = (0..array.len() as u64).collect();
+    let (values, physical) = if let Some(run) = array.as_any().downcast_ref::<RunArray<Int16Type>>() {
+        (run.values(), run.get_physical_indices(&logical)?)
+    } else if let Some(run) = array.as_any().downcast_ref::<RunArray<Int32Type>>() {
+        (run.values(), run.get_physical_indices(&logical)?)
+    } else if let Some(run) = array.as_any().downcast_ref::<RunArray<Int64Type>>() {
+        (run.values(), run.get_physical_indices(&logical)?)
+    } else {
+        return Err(arrow::error::ArrowError::InvalidArgumentError(format!(
+            "unsupported run end type in {:?}", array.data_type()
+        )));
+    };
+    let physical = take_indices(physical);
+    arrow::compute::take(values.as_ref(), &physical, None)
+}
+
+/// Rename column `from` to `to`, keeping its field metadata. Batches without `from` are
+/// returned unchanged; a batch that already has a different `to` column is an error.
+pub(crate) fn rename_column(batch: RecordBatch, from: &str, to: &str) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    if schema.index_of(to).is_ok() {
+        return Err(format!("Cannot rename '{}' to '{}': the batch already has a '{}' column", from, to, to));
+    }
+    let Ok(idx) = schema.index_of(from) else {
+        return Ok(batch);
+    };
+
+    let mut fields = schema.fields().to_vec();
+    fields[idx] = Arc::new(fields[idx].as_ref().clone().with_name(to));
+    let schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(schema, batch.columns().to_vec())
+        .map_err(|e| format!("Failed to rename column '{}': {}", from, e))
+}
+
+/// Remove column `name` from `batch` if present
+pub(crate) fn drop_column(batch: RecordBatch, name: &str) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let Ok(idx) = schema.index_of(name) else {
+        return Ok(batch);
+    };
+    let keep: Vec<usize> = (0..schema.fields().len()).filter(|&i| i != idx).collect();
+    batch.project(&keep)
+        .map_err(|e| format!("Failed to drop column '{}': {}", name, e))
+}
+
+/// Stamp constant columns onto every row of `batch`. Columns the batch already has are
+/// overwritten (the value is cast to the column's type); new columns are appended as Utf8.
+pub(crate) fn stamp_constant_columns(batch: RecordBatch, constants: &[(String, String)]) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let mut fields = schema.fields().to_vec();
+    let mut columns = batch.columns().to_vec();
+
+    for (name, value) in constants {
+        let values: ArrayRef = Arc::new(arrow::array::StringArray::from(vec![value.as_str(); batch.num_rows()]));
+        match schema.index_of(name) {
+            Ok(idx) => {
+                let cast_options = arrow::compute::CastOptions { safe: false, ..Default::default() };
+                columns[idx] = arrow::compute::cast_with_options(&values, fields[idx].data_type(), &cast_options)
+                    .map_err(|e| format!("Cannot stamp '{}' onto column '{}': {}", value, name, e))?;
+            }
+            Err(_) => {
+                fields.push(Arc::new(arrow::datatypes::Field::new(name, DataType::Utf8, false)));
+                columns.push(values);
+            }
+        }
+    }
+
+    let schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to stamp lineage columns: {}", e))
+}
+
+/// Append a `changed_columns` list to each insert batch. An inserted row is compared with every
+/// expired row of the same ID whose effective range intersects its own; the list holds the value
+/// columns that differ from any of them, in `value_columns` order. Rows replacing nothing get null.
+pub(crate) fn append_changed_columns(
+    to_insert: Vec<RecordBatch>,
+    expired_records: &[RecordBatch],
+    id_columns: &[String],
+    value_columns: &[String],
+) -> Result<Vec<RecordBatch>, String> {
+    // ID key -> (expired batch, row, effective_from, effective_to)
+    let mut expired_by_id: rustc_hash::FxHashMap<String, Vec<(usize, usize, NaiveDateTime, NaiveDateTime)>> =
+        rustc_hash::FxHashMap::default();
+    let mut key = String::with_capacity(64);
+    for (batch_idx, batch) in expired_records.iter().enumerate() {
+        let id_arrays = column_arrays(batch, id_columns)?;
+        let (from_array, to_array) = effective_arrays(batch)?;
+        for row in 0..batch.num_rows() {
+            crate::create_id_key_with_buffer(&id_arrays, row, &mut key);
+            let from = crate::extract_datetime_flexible(from_array.as_ref(), row)?;
+            let to = crate::extract_datetime_flexible(to_array.as_ref(), row)?;
+            expired_by_id.entry(key.clone()).or_default().push((batch_idx, row, from, to));
+        }
+    }
+
+    to_insert.into_iter().map(|batch| {
+        let id_arrays = column_arrays(&batch, id_columns)?;
+        let (from_array, to_array) = effective_arrays(&batch)?;
+        let insert_values = column_arrays(&batch, value_columns)?;
+        // One comparator per (expired batch, value column)
+        let comparators = expired_records.iter().map(|expired| {
+            let expired_values = column_arrays(expired, value_columns)?;
+            insert_values.iter().zip(expired_values.iter())
+                .map(|(left, right)| arrow::array::make_comparator(left.as_ref(), right.as_ref(), Default::default())
+                    .map_err(|e| format!("Cannot compare value columns for changed_columns: {}", e)))
+                .collect::<Result<Vec<_>, String>>()
+        }).collect::<Result<Vec<_>, String>>()?;
+
+        let mut builder = arrow::array::ListBuilder::new(StringBuilder::new());
+        let mut key = String::with_capacity(64);
+        for row in 0..batch.num_rows() {
+            crate::create_id_key_with_buffer(&id_arrays, row, &mut key);
+            let from = crate::extract_datetime_flexible(from_array.as_ref(), row)?;
+            let to = crate::extract_datetime_flexible(to_array.as_ref(), row)?;
+            let replaced: Vec<(usize, usize)> = expired_by_id.get(&key).into_iter().flatten()
+                .filter(|(_, _, expired_from, expired_to)| from < *expired_to && *expired_from < to)
+                .map(|&(batch_idx, expired_row, _, _)| (batch_idx, expired_row))
+                .collect();
+            if replaced.is_empty() {
+                builder.append_null();
+                continue;
+            }
+            for (col_idx, column) in value_columns.iter().enumerate() {
+                let changed = replaced.iter().any(|&(batch_idx, expired_row)| {
+                    comparators[batch_idx][col_idx](row, expired_row) != std::cmp::Ordering::Equal
+                });
+                if changed {
+                    builder.values().append_value(column);
+                }
+            }
+            builder.append(true);
+        }
+
+        let changed_columns = builder.finish();
+        let mut fields = batch.schema().fields().to_vec();
+        fields.push(Arc::new(arrow::datatypes::Field::new(CHANGED_COLUMNS_COLUMN, changed_columns.data_type().clone(), true)));
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(changed_columns));
+        RecordBatch::try_new(Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, batch.schema().metadata().clone())), columns)
+            .map_err(|e| format!("Failed to append changed_columns: {}", e))
+    }).collect()
+}
+
+/// Most row indices spelled out by `null_rows`
+const MAX_REPORTED_NULL_ROWS: usize = 20;
+
+/// Comma separated indices of the null rows of `array` (truncated), or `None` without nulls
+pub(crate) fn null_rows(array: &ArrayRef) -> Option<String> {
+    let nulls = array.logical_nulls().filter(|nulls| nulls.null_count() > 0)?;
+    let rows: Vec<usize> = (0..array.len()).filter(|&row| nulls.is_null(row)).collect();
+    let mut listed = rows.iter().take(MAX_REPORTED_NULL_ROWS).map(|row| row.to_string()).collect::<Vec<_>>().join(", ");
+    if rows.len() > MAX_REPORTED_NULL_ROWS {
+        listed.push_str(&format!(" and {} more", rows.len() - MAX_REPORTED_NULL_ROWS));
+    }
+    Some(listed)
+}
+
+/// Look up a column the pipeline relies on, erroring instead of panicking when it is missing
+pub(crate) fn required_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef, String> {
+    batch.column_by_name(name).ok_or_else(|| format!("Column '{}' not found", name))
+}
+
+/// Look up the ID columns of a batch, in order
+pub(crate) fn id_arrays(batch: &RecordBatch, id_columns: &[String]) -> Result<Vec<ArrayRef>, String> {
+    named_columns(batch, id_columns, "ID")
+}
+
+/// Look up several columns by name, in order; `kind` names them in the error ("ID", "Shard", ...)
+pub(crate) fn named_columns(batch: &RecordBatch, names: &[String], kind: &str) -> Result<Vec<ArrayRef>, String> {
+    names.iter()
+        .map(|name| batch.column_by_name(name).cloned()
+            .ok_or_else(|| format!("{} column '{}' not found", kind, name)))
+        .collect()
+}
+
+/// Positions of the columns read per row on the hot path, resolved once per input in
+/// `prepare_inputs` so ID groups index columns directly instead of searching the schema by
+/// name. Missing columns are only reported where they are used.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ColumnIndices {
+    effective_from: Option<usize>,
+    effective_to: Option<usize>,
+    as_of_from: Option<usize>,
+    value_hash: Option<usize>,
+    ids: Vec<(String, Option<usize>)>,
+}
+
+impl ColumnIndices {
+    pub(crate) fn new(batch: &RecordBatch, id_columns: &[String]) -> Self {
+        let schema = batch.schema();
+        let index = |name: &str| schema.index_of(name).ok();
+        ColumnIndices {
+            effective_from: index("effective_from"),
+            effective_to: index("effective_to"),
+            as_of_from: index("as_of_from"),
+            value_hash: index(VALUE_HASH_COLUMN),
+            ids: id_columns.iter().map(|name| (name.clone(), index(name))).collect(),
+        }
+    }
+
+    pub(crate) fn effective_from<'a>(&self, batch: &'a RecordBatch) -> Result<&'a ArrayRef, String> {
+        indexed_column(batch, self.effective_from, "effective_from")
+    }
+
+    pub(crate) fn effective_to<'a>(&self, batch: &'a RecordBatch) -> Result<&'a ArrayRef, String> {
+        indexed_column(batch, self.effective_to, "effective_to")
+    }
+
+    pub(crate) fn as_of_from<'a>(&self, batch: &'a RecordBatch) -> Result<&'a ArrayRef, String> {
+        indexed_column(batch, self.as_of_from, "as_of_from")
+    }
+
+    /// The value hash column, if the batch has one
+    pub(crate) fn value_hash<'a>(&self, batch: &'a RecordBatch) -> Option<&'a ArrayRef> {
+        self.value_hash.map(|idx| batch.column(idx))
+    }
+
+    pub(crate) fn id_arrays<'a>(&self, batch: &'a RecordBatch) -> Result<Vec<&'a ArrayRef>, String> {
+        self.ids.iter()
+            .map(|(name, idx)| idx.map(|idx| batch.column(idx)).ok_or_else(|| format!("ID column {} not found", name)))
+            .collect()
+    }
+
+    /// effective_from and effective_to of one row, in microseconds since the epoch
+    #[inline]
+    pub(crate) fn temporal_micros(&self, batch: &RecordBatch, row: usize) -> Result<(i64, i64), String> {
+        let from = crate::extract_micros_flexible(self.effective_from(batch)?.as_ref(), row)?;
+        let to = crate::extract_micros_flexible(self.effective_to(batch)?.as_ref(), row)?;
+        Ok((from, to))
+    }
+}
+
+/// `ColumnIndices` of the current state and of the updates
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InputColumns {
+    pub current: ColumnIndices,
+    pub updates: ColumnIndices,
+}
+
+fn indexed_column<'a>(batch: &'a RecordBatch, idx: Option<usize>, name: &str) -> Result<&'a ArrayRef, String> {
+    idx.map(|idx| batch.column(idx)).ok_or_else(|| format!("{} column not found", name))
+}
+
+/// Downcast a column to its concrete array type, naming the column and both types on mismatch
+pub(crate) fn downcast_column<'a, T: Array + 'static>(array: &'a ArrayRef, name: &str, expected: &str) -> Result<&'a T, String> {
+    array.as_any().downcast_ref::<T>()
+        .ok_or_else(|| format!("Column '{}' has type {:?}, expected {}", name, array.data_type(), expected))
+}
+
+fn column_arrays(batch: &RecordBatch, columns: &[String]) -> Result<Vec<ArrayRef>, String> {
+    columns.iter()
+        .map(|name| batch.column_by_name(name).cloned()
+            .ok_or_else(|| format!("Column '{}' not found", name)))
+        .collect()
+}
+
+fn effective_arrays(batch: &RecordBatch) -> Result<(ArrayRef, ArrayRef), String> {
+    let from = batch.column_by_name("effective_from").ok_or("effective_from column not found")?;
+    let to = batch.column_by_name("effective_to").ok_or("effective_to column not found")?;
+    Ok((from.clone(), to.clone()))
+}
\ No newline at end of file
@@ -0,0 +1,270 @@
+//! File-based front end for the bitemporal engine.
+//!
+//! Reads current state and updates from Parquet or CSV, runs `process_updates_with_options`
+//! and writes `to_insert`, `expired_records` and `to_expire` files into an output directory.
+
+use arrow::array::{ArrayRef, RecordBatch};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::NaiveDate;
+use pytemporal::{process_updates_with_options, unified_batch_reader, ProcessingOptions, UpdateMode, TEMPORAL_COLUMNS};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const USAGE: &str = "\
+Usage: pytemporal-cli --current <path> --updates <path> --id-columns <a,b> [--value-columns <x,y>]
+                      --system-date <YYYY-MM-DD> --output-dir <dir>
+                      [--mode delta|full_state] [--format parquet|csv] [--conflate-inputs]
+
+Input format is taken from the file extension (.parquet or .csv). Output defaults to the
+format of the updates file. Without --value-columns every non-ID, non-temporal column is tracked.";
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileFormat {
+    Parquet,
+    Csv,
+}
+
+impl FileFormat {
+    fn from_str(s: &str) -> Result<FileFormat, String> {
+        match s.to_lowercase().as_str() {
+            "parquet" | "pq" => Ok(FileFormat::Parquet),
+            "csv" => Ok(FileFormat::Csv),
+            _ => Err(format!("Unknown file format: {}", s)),
+        }
+    }
+
+    fn from_path(path: &Path) -> Result<FileFormat, String> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        FileFormat::from_str(ext)
+            .map_err(|_| format!("Cannot infer file format of '{}' (expected .parquet or .csv)", path.display()))
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "parquet",
+            FileFormat::Csv => "csv",
+        }
+    }
+}
+
+struct CliArgs {
+    current: PathBuf,
+    updates: PathBuf,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    output_dir: PathBuf,
+    output_format: FileFormat,
+    conflate_inputs: bool,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<CliArgs, String> {
+    let mut current = None;
+    let mut updates = None;
+    let mut id_columns = None;
+    let mut value_columns = None;
+    let mut system_date = None;
+    let mut update_mode = UpdateMode::Delta;
+    let mut output_dir = None;
+    let mut output_format = None;
+    let mut conflate_inputs = false;
+
+    let mut args = args.peekable();
+    while let Some(flag) = args.next() {
+        if flag == "--conflate-inputs" {
+            conflate_inputs = true;
+            continue;
+        }
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or_else(|| format!("Missing value for {}", flag))?;
+        match flag.as_str() {
+            "--current" => current = Some(PathBuf::from(value)),
+            "--updates" => updates = Some(PathBuf::from(value)),
+            "--id-columns" => id_columns = Some(split_columns(&value)),
+            "--value-columns" => value_columns = Some(split_columns(&value)),
+            "--system-date" => {
+                system_date = Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid system date '{}': {}", value, e))?);
+            }
+            "--mode" => {
+                update_mode = match value.as_str() {
+                    "delta" => UpdateMode::Delta,
+                    "full_state" => UpdateMode::FullState,
+                    _ => return Err("Invalid mode. Must be 'delta' or 'full_state'".to_string()),
+                };
+            }
+            "--output-dir" => output_dir = Some(PathBuf::from(value)),
+            "--format" => output_format = Some(FileFormat::from_str(&value)?),
+            _ => return Err(format!("Unknown argument: {}\n\n{}", flag, USAGE)),
+        }
+    }
+
+    let missing = |name: &str| format!("Missing required argument {}\n\n{}", name, USAGE);
+    let updates = updates.ok_or_else(|| missing("--updates"))?;
+    let output_format = match output_format {
+        Some(format) => format,
+        None => FileFormat::from_path(&updates)?,
+    };
+
+    Ok(CliArgs {
+        current: current.ok_or_else(|| missing("--current"))?,
+        updates,
+        id_columns: id_columns.ok_or_else(|| missing("--id-columns"))?,
+        value_columns: value_columns.unwrap_or_default(),
+        system_date: system_date.ok_or_else(|| missing("--system-date"))?,
+        update_mode,
+        output_dir: output_dir.ok_or_else(|| missing("--output-dir"))?,
+        output_format,
+        conflate_inputs,
+    })
+}
+
+fn split_columns(value: &str) -> Vec<String> {
+    value.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+}
+
+/// Read a whole file into one batch, normalising timestamp columns to microseconds
+/// (date columns stay dates)
+fn read_batch(path: &Path) -> Result<RecordBatch, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let (schema, batches) = match FileFormat::from_path(path)? {
+        FileFormat::Parquet => {
+            let reader = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)
+                .and_then(|builder| builder.build())
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            let schema = arrow::record_batch::RecordBatchReader::schema(&reader);
+            let batches = reader.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            (schema, batches)
+        }
+        FileFormat::Csv => {
+            let format = arrow::csv::reader::Format::default().with_header(true);
+            let (schema, _) = format.infer_schema(&file, None)
+                .map_err(|e| format!("Failed to infer schema of '{}': {}", path.display(), e))?;
+            let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+            let schema = Arc::new(schema);
+            let reader = arrow::csv::ReaderBuilder::new(schema.clone())
+                .with_format(format)
+                .build(file)
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            let batches = reader.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            (schema, batches)
+        }
+    };
+
+    let batch = arrow::compute::concat_batches(&schema, &batches)
+        .map_err(|e| format!("Failed to combine batches of '{}': {}", path.display(), e))?;
+    normalize_temporal_columns(batch)
+}
+
+fn normalize_temporal_columns(batch: RecordBatch) -> Result<RecordBatch, String> {
+    let target = DataType::Timestamp(TimeUnit::Microsecond, None);
+    let mut fields = Vec::with_capacity(batch.num_columns());
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.num_columns());
+
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        let is_date = matches!(field.data_type(), DataType::Date32 | DataType::Date64);
+        if TEMPORAL_COLUMNS.contains(&field.name().as_str()) && field.data_type() != &target && !is_date {
+            let cast = arrow::compute::cast(column, &target)
+                .map_err(|e| format!("Cannot convert column '{}' to timestamps: {}", field.name(), e))?;
+            fields.push(Field::new(field.name(), target.clone(), field.is_nullable()));
+            columns.push(cast);
+        } else {
+            fields.push(field.as_ref().clone());
+            columns.push(column.clone());
+        }
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|e| format!("Failed to normalise temporal columns: {}", e))
+}
+
+/// Write batches as one file, cast to a unified schema. Without batches the file is still
+/// written, empty, with `fallback_schema`.
+fn write_batches(path: &Path, format: FileFormat, batches: Vec<RecordBatch>, fallback_schema: SchemaRef) -> Result<(), String> {
+    let reader = unified_batch_reader(batches, fallback_schema)?;
+    let schema = arrow::record_batch::RecordBatchReader::schema(&reader);
+    let mut batches = reader.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    if batches.is_empty() {
+        batches.push(RecordBatch::new_empty(schema.clone()));
+    }
+    let file = File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+
+    match format {
+        FileFormat::Parquet => {
+            let mut writer = parquet::arrow::ArrowWriter::try_new(file, schema, None)
+                .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+            for batch in &batches {
+                writer.write(batch).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+            }
+            writer.close().map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        }
+        FileFormat::Csv => {
+            let mut writer = arrow::csv::Writer::new(file);
+            for batch in &batches {
+                writer.write(batch).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run(args: CliArgs) -> Result<(), String> {
+    let current_state = read_batch(&args.current)?;
+    let updates = read_batch(&args.updates)?;
+    // Empty outputs are written with the schema the run reports for them, else the input's
+    let insert_schema = updates.schema();
+    let expired_schema = current_state.schema();
+
+    let options = ProcessingOptions {
+        conflate_inputs: args.conflate_inputs,
+        ..Default::default()
+    };
+    let changeset = process_updates_with_options(
+        current_state,
+        updates,
+        args.id_columns,
+        args.value_columns,
+        args.system_date,
+        args.update_mode,
+        &options,
+    )?;
+
+    std::fs::create_dir_all(&args.output_dir)
+        .map_err(|e| format!("Failed to create '{}': {}", args.output_dir.display(), e))?;
+    let ext = args.output_format.extension();
+
+    let expire_indices = changeset.expire_indices_array();
+    let expire_batch = RecordBatch::try_from_iter([("index", Arc::new(expire_indices) as ArrayRef)])
+        .map_err(|e| format!("Failed to build expire index batch: {}", e))?;
+    let expire_schema = expire_batch.schema();
+    let expired_rows = changeset.to_expire.len();
+    let inserted_rows = changeset.to_insert.iter().map(|b| b.num_rows()).sum::<usize>();
+    let insert_schema = changeset.insert_schema.unwrap_or(insert_schema);
+    let expired_schema = changeset.expired_schema.unwrap_or(expired_schema);
+
+    write_batches(&args.output_dir.join(format!("to_expire.{}", ext)), args.output_format, vec![expire_batch], expire_schema)?;
+    write_batches(&args.output_dir.join(format!("to_insert.{}", ext)), args.output_format, changeset.to_insert, insert_schema)?;
+    write_batches(&args.output_dir.join(format!("expired_records.{}", ext)), args.output_format, changeset.expired_records, expired_schema)?;
+
+    println!("expired {} rows, inserted {} rows", expired_rows, inserted_rows);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::options::CalendarBoundary;
+use crate::types::ChangeSet;
+use crate::{create_timestamp_array_from_values, get_temporal_bounds, is_open_ended};
+use arrow::array::RecordBatch;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+
+/// Split every inserted row whose effective range crosses a calendar boundary into one row
+/// per period. Open-ended rows are split up to their first boundary only.
+pub(crate) fn split_inserts_at_boundaries(mut changeset: ChangeSet, boundary: Option<CalendarBoundary>) -> Result<ChangeSet, String> {
+    let Some(boundary) = boundary else {
+        return Ok(changeset);
+    };
+    changeset.to_insert = std::mem::take(&mut changeset.to_insert).into_iter()
+        .map(|batch| split_batch(batch, boundary))
+        .collect::<Result<_, _>>()?;
+    Ok(changeset)
+}
+
+fn split_batch(batch: RecordBatch, boundary: CalendarBoundary) -> Result<RecordBatch, String> {
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    let mut froms = Vec::with_capacity(batch.num_rows());
+    let mut tos = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let (effective_from, effective_to) = get_temporal_bounds(&batch, row)?;
+        let mut from = effective_from;
+        loop {
+            let next = next_boundary(from, boundary);
+            if next >= effective_to {
+                break;
+            }
+            rows.push(row);
+            froms.push(from);
+            tos.push(next);
+            from = next;
+            if is_open_ended(effective_to) {
+                break;
+            }
+        }
+        rows.push(row);
+        froms.push(from);
+        tos.push(effective_to);
+    }
+    if rows.len() == batch.num_rows() {
+        return Ok(batch);
+    }
+
+    let indices = crate::batch_utils::take_indices(rows);
+    let schema = batch.schema();
+    let mut columns = batch.columns().iter()
+        .map(|column| arrow::compute::take(column.as_ref(), &indices, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to split inserts at calendar boundaries: {}", e))?;
+    for (column, values) in [("effective_from", froms), ("effective_to", tos)] {
+        let idx = schema.index_of(column)
+            .map_err(|_| format!("{} column not found", column))?;
+        columns[idx] = create_timestamp_array_from_values(columns[idx].data_type(), &values)?;
+    }
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to split inserts at calendar boundaries: {}", e))
+}
+
+/// Start of the calendar period after the one holding `at`
+fn next_boundary(at: NaiveDateTime, boundary: CalendarBoundary) -> NaiveDateTime {
+    let months = match boundary {
+        CalendarBoundary::Month => 1,
+        CalendarBoundary::Quarter => 3,
+    };
+    let month0 = at.month0() / months * months + months;
+    let (year, month0) = (at.year() + (month0 / 12) as i32, month0 % 12);
+    NaiveDate::from_ymd_opt(year, month0 + 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap_or(NaiveDateTime::MAX)
+}
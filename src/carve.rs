@@ -0,0 +1,109 @@
+use crate::types::ChangeSet;
+use crate::{create_id_key_with_buffer, create_timestamp_array, create_timestamp_array_from_values, get_temporal_bounds};
+use arrow::array::{RecordBatch, UInt64Array};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+
+/// Remove effective coverage for the `(ID, effective_from, effective_to)` rows of `ranges`
+/// without inserting new values - the inverse of an overwrite, for targeted removals.
+///
+/// Every current row intersecting a range of its ID is expired at `as_of` and the parts of it
+/// outside the ranges are inserted again, known from `as_of`. Rows the ranges miss are left
+/// alone. `ranges` needs the ID columns plus `effective_from` and `effective_to`; its other
+/// columns are ignored.
+pub fn carve_out(
+    current_state: &RecordBatch,
+    ranges: &RecordBatch,
+    id_columns: &[String],
+    as_of: NaiveDateTime,
+) -> Result<ChangeSet, String> {
+    let id_arrays = |batch: &RecordBatch, side: &str| crate::batch_utils::id_arrays(batch, id_columns)
+        .map_err(|e| format!("{} in {}", e, side));
+    let mut id_key_buffer = String::with_capacity(64);
+
+    let range_ids = id_arrays(ranges, "ranges")?;
+    let mut removed: FxHashMap<String, Vec<(NaiveDateTime, NaiveDateTime)>> = FxHashMap::default();
+    for row in 0..ranges.num_rows() {
+        let (from, to) = get_temporal_bounds(ranges, row)?;
+        if from >= to {
+            return Err(format!("Carve-out range at row {} is empty or inverted", row));
+        }
+        create_id_key_with_buffer(&range_ids, row, &mut id_key_buffer);
+        removed.entry(id_key_buffer.clone()).or_default().push((from, to));
+    }
+    for id_ranges in removed.values_mut() {
+        id_ranges.sort_unstable();
+    }
+
+    let current_ids = id_arrays(current_state, "current state")?;
+    let mut to_expire = Vec::new();
+    let mut kept_rows = Vec::new();
+    let mut kept_from = Vec::new();
+    let mut kept_to = Vec::new();
+    for row in 0..current_state.num_rows() {
+        create_id_key_with_buffer(&current_ids, row, &mut id_key_buffer);
+        let Some(id_ranges) = removed.get(id_key_buffer.as_str()) else {
+            continue;
+        };
+        let (from, to) = get_temporal_bounds(current_state, row)?;
+        if !id_ranges.iter().any(|&(cut_from, cut_to)| cut_from < to && cut_to > from) {
+            continue;
+        }
+        to_expire.push(row);
+        // Walk the sorted ranges, keeping what lies between them
+        let mut start = from;
+        for &(cut_from, cut_to) in id_ranges {
+            if cut_from > start {
+                let end = cut_from.min(to);
+                if start < end {
+                    kept_rows.push(row);
+                    kept_from.push(start);
+                    kept_to.push(end);
+                }
+            }
+            start = start.max(cut_to);
+            if start >= to {
+                break;
+            }
+        }
+        if start < to {
+            kept_rows.push(row);
+            kept_from.push(start);
+            kept_to.push(to);
+        }
+    }
+
+    if to_expire.is_empty() {
+        return Ok(ChangeSet::default());
+    }
+    let expired_records = vec![crate::batch_utils::create_expired_records_batch(current_state, &to_expire, as_of, None)?];
+    let to_insert = if kept_rows.is_empty() {
+        Vec::new()
+    } else {
+        vec![kept_pieces(current_state, crate::batch_utils::take_indices(kept_rows), &kept_from, &kept_to, as_of)?]
+    };
+    Ok(ChangeSet { to_expire, to_insert, expired_records, ..Default::default() })
+}
+
+/// The given rows of `current_state` cut to `[from, to)` and known from `as_of`
+fn kept_pieces(
+    current_state: &RecordBatch,
+    rows: UInt64Array,
+    from: &[NaiveDateTime],
+    to: &[NaiveDateTime],
+    as_of: NaiveDateTime,
+) -> Result<RecordBatch, String> {
+    let schema = current_state.schema();
+    let mut columns = current_state.columns().iter()
+        .map(|column| arrow::compute::take(column.as_ref(), &rows, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to carve out ranges: {}", e))?;
+    let index_of = |column: &str| schema.index_of(column)
+        .map_err(|_| format!("{} column not found", column));
+    let (from_idx, to_idx, as_of_idx) = (index_of("effective_from")?, index_of("effective_to")?, index_of("as_of_from")?);
+    columns[from_idx] = create_timestamp_array_from_values(columns[from_idx].data_type(), from)?;
+    columns[to_idx] = create_timestamp_array_from_values(columns[to_idx].data_type(), to)?;
+    columns[as_of_idx] = create_timestamp_array(columns[as_of_idx].data_type(), as_of, rows.len())?;
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to carve out ranges: {}", e))
+}
@@ -0,0 +1,182 @@
+use crate::types::{ChangeSet, TEMPORAL_COLUMNS};
+use crate::{create_id_key_with_buffer, get_temporal_bounds};
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Expired record facts needed to pair inserts with what they replace
+struct ExpiredEntry {
+    effective_from: NaiveDateTime,
+    effective_to: NaiveDateTime,
+    value_hash: Option<String>,
+}
+
+/// How one inserted row relates to the expired records
+pub(crate) struct InsertMatch {
+    /// Value hash of the expired record the insert replaces (overlapping, same ID)
+    pub before_hash: Option<String>,
+    /// The only insert for its ID, and a truncated copy of the record it replaces
+    pub is_tombstone: bool,
+}
+
+impl ChangeSet {
+    /// Flatten the changeset into a single Debezium-style change stream batch.
+    ///
+    /// Columns: `op` (`EXPIRE`, `INSERT` or `TOMBSTONE`), the ID columns, the four temporal
+    /// columns, then `before_hash` / `after_hash`. Expiries come first so the stream can be
+    /// applied in order. An insert is reported as a `TOMBSTONE` when it is the only insert for
+    /// its ID and is a truncated copy (same values and `effective_from`) of an expired record.
+    ///
+    /// The hashes are read from `hash_column` (`ProcessingOptions::hash_column`), which every
+    /// batch must carry; changesets computed with `exclude_hash_column` are rejected.
+    pub fn to_cdc_batch(&self, id_columns: &[String], hash_column: &str) -> Result<RecordBatch, String> {
+        if self.expired_records.iter().chain(&self.to_insert).any(|batch| batch.column_by_name(hash_column).is_none()) {
+            return Err(format!("Hash column '{}' not found in changeset batch", hash_column));
+        }
+        let insert_matches = self.match_inserts(id_columns, hash_column)?;
+
+        // (batch, row) positions into the combined expired + insert batch list
+        let source_batches: Vec<&RecordBatch> = self.expired_records.iter().chain(&self.to_insert).collect();
+        let mut positions = Vec::new();
+        let mut ops = StringBuilder::new();
+        let mut before_hashes = StringBuilder::new();
+        let mut after_hashes = StringBuilder::new();
+
+        for (batch_idx, batch) in self.expired_records.iter().enumerate() {
+            let hashes = value_hashes(batch, hash_column)?;
+            for row in 0..batch.num_rows() {
+                positions.push((batch_idx, row));
+                ops.append_value("EXPIRE");
+                before_hashes.append_option(hash_at(hashes, row));
+                after_hashes.append_null();
+            }
+        }
+
+        for (insert_idx, (batch, matches)) in self.to_insert.iter().zip(insert_matches).enumerate() {
+            let hashes = value_hashes(batch, hash_column)?;
+            for (row, insert_match) in matches.into_iter().enumerate() {
+                positions.push((self.expired_records.len() + insert_idx, row));
+                before_hashes.append_option(insert_match.before_hash);
+                if insert_match.is_tombstone {
+                    ops.append_value("TOMBSTONE");
+                    after_hashes.append_null();
+                } else {
+                    ops.append_value("INSERT");
+                    after_hashes.append_option(hash_at(hashes, row));
+                }
+            }
+        }
+
+        let mut fields = vec![Field::new("op", DataType::Utf8, false)];
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(ops.finish())];
+        for column in id_columns.iter().map(String::as_str).chain(TEMPORAL_COLUMNS) {
+            let (field, array) = interleave_column(&source_batches, &positions, column)?;
+            fields.push(field);
+            columns.push(array);
+        }
+        fields.push(Field::new("before_hash", DataType::Utf8, true));
+        columns.push(Arc::new(before_hashes.finish()));
+        fields.push(Field::new("after_hash", DataType::Utf8, true));
+        columns.push(Arc::new(after_hashes.finish()));
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(|e| format!("Failed to build CDC batch: {}", e))
+    }
+
+    /// Match every inserted row against the expired records of its ID, per insert batch.
+    /// Without hashes in `hash_column` no insert can be shown to be a tombstone.
+    pub(crate) fn match_inserts(&self, id_columns: &[String], hash_column: &str) -> Result<Vec<Vec<InsertMatch>>, String> {
+        let mut id_key_buffer = String::with_capacity(64);
+
+        // Index expired records by ID so inserts can find the rows they replace
+        let mut expired_by_id: FxHashMap<String, Vec<ExpiredEntry>> = FxHashMap::default();
+        for batch in &self.expired_records {
+            let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+            let hashes = value_hashes(batch, hash_column)?;
+            for row in 0..batch.num_rows() {
+                create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+                let (effective_from, effective_to) = get_temporal_bounds(batch, row)?;
+                expired_by_id.entry(id_key_buffer.clone()).or_default().push(ExpiredEntry {
+                    effective_from,
+                    effective_to,
+                    value_hash: hash_at(hashes, row),
+                });
+            }
+        }
+
+        let mut insert_counts: FxHashMap<String, usize> = FxHashMap::default();
+        let mut insert_keys = Vec::with_capacity(self.to_insert.len());
+        for batch in &self.to_insert {
+            let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+            let keys: Vec<String> = (0..batch.num_rows())
+                .map(|row| {
+                    create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+                    id_key_buffer.clone()
+                })
+                .collect();
+            for key in &keys {
+                *insert_counts.entry(key.clone()).or_default() += 1;
+            }
+            insert_keys.push(keys);
+        }
+
+        self.to_insert.iter().zip(&insert_keys)
+            .map(|(batch, keys)| {
+                let hashes = value_hashes(batch, hash_column)?;
+                keys.iter().enumerate()
+                    .map(|(row, key)| {
+                        let (effective_from, effective_to) = get_temporal_bounds(batch, row)?;
+                        let after_hash = hash_at(hashes, row);
+                        let replaced = expired_by_id.get(key).and_then(|entries| {
+                            entries.iter().find(|e| e.effective_from < effective_to && e.effective_to > effective_from)
+                        });
+                        let is_tombstone = insert_counts[key] == 1 && replaced.is_some_and(|e| {
+                            e.value_hash.is_some() && e.value_hash == after_hash
+                                && e.effective_from == effective_from && effective_to < e.effective_to
+                        });
+                        Ok(InsertMatch { before_hash: replaced.and_then(|e| e.value_hash.clone()), is_tombstone })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn value_hashes<'a>(batch: &'a RecordBatch, hash_column: &str) -> Result<Option<&'a StringArray>, String> {
+    batch.column_by_name(hash_column)
+        .map(|col| crate::batch_utils::downcast_column::<StringArray>(col, hash_column, "Utf8"))
+        .transpose()
+}
+
+fn hash_at(hashes: Option<&StringArray>, row: usize) -> Option<String> {
+    hashes.filter(|h| !h.is_null(row)).map(|h| h.value(row).to_string())
+}
+
+/// Gather one column across all source batches in output row order
+fn interleave_column(
+    batches: &[&RecordBatch],
+    positions: &[(usize, usize)],
+    column: &str,
+) -> Result<(Field, ArrayRef), String> {
+    let arrays = batches.iter()
+        .map(|batch| batch.column_by_name(column).cloned()
+            .ok_or_else(|| format!("Column '{}' not found in changeset batch", column)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let Some(first) = arrays.first() else {
+        // Empty changeset: keep the column with a placeholder type
+        return Ok((Field::new(column, DataType::Null, true), arrow::array::new_null_array(&DataType::Null, 0)));
+    };
+    let data_type = first.data_type().clone();
+    let arrays = arrays.iter()
+        .map(|array| arrow::compute::cast(array, &data_type))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Column '{}' has incompatible types across batches: {}", column, e))?;
+    let array_refs: Vec<&dyn Array> = arrays.iter().map(|a| a.as_ref()).collect();
+
+    let interleaved = arrow::compute::interleave(&array_refs, positions)
+        .map_err(|e| format!("Failed to gather column '{}': {}", column, e))?;
+    Ok((Field::new(column, data_type, true), interleaved))
+}
@@ -0,0 +1,248 @@
+//! Checkpoint / resume of ID group processing (`ProcessingOptions::checkpoint`).
+//!
+//! ID groups are processed in key order, a chunk of `groups_per_chunk` at a time. After each
+//! chunk its results are written to `chunk-NNNNNN.ipc` and `manifest` is updated, both via a
+//! temporary file and a rename so a crash never leaves a half-written checkpoint behind. A
+//! rerun over the same inputs loads the finished chunks instead of reprocessing them and reuses
+//! the original batch timestamp, so resumed output is identical to an uninterrupted run.
+
+use crate::ipc::{skip_reason_code, skip_reason_from_code, write_batches, write_indices, write_u64, FrameReader};
+use crate::options::Checkpoint;
+use crate::types::{ExpireReason, SkippedUpdate, UpdateMode};
+use crate::{IdGroupProcessingResult, IdGroups, GroupProgress};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh64::Xxh64;
+
+const MANIFEST: &str = "manifest";
+const MANIFEST_HEADER: &str = "pytemporal-checkpoint 1";
+const CHUNK_MAGIC: &[u8; 8] = b"PTCKPT01";
+
+/// Progress recorded in the manifest
+struct Manifest {
+    fingerprint: u64,
+    batch_timestamp: NaiveDateTime,
+    groups_per_chunk: usize,
+    completed_chunks: usize,
+}
+
+/// Inputs that identify a run; a checkpoint only resumes a run with the same fingerprint
+pub(crate) struct RunIdentity<'a> {
+    pub system_date: NaiveDate,
+    pub update_mode: UpdateMode,
+    pub id_columns: &'a [String],
+    pub value_columns: &'a [String],
+}
+
+/// Batch timestamp of the run a checkpoint in `checkpoint.dir` belongs to, if there is one
+pub(crate) fn resumed_batch_timestamp(checkpoint: &Checkpoint) -> Result<Option<NaiveDateTime>, String> {
+    Ok(read_manifest(&checkpoint.dir)?.map(|manifest| manifest.batch_timestamp))
+}
+
+/// Process `id_groups` chunk by chunk with `process`, checkpointing after every chunk and
+/// skipping the chunks an earlier attempt finished. The checkpoint is removed once every
+/// group is processed.
+pub(crate) fn process_with_checkpoints(
+    checkpoint: &Checkpoint,
+    id_groups: IdGroups,
+    identity: RunIdentity<'_>,
+    batch_timestamp: NaiveDateTime,
+    progress: &GroupProgress<'_>,
+    mut process: impl FnMut(IdGroups) -> Result<IdGroupProcessingResult, String>,
+) -> Result<IdGroupProcessingResult, String> {
+    if checkpoint.groups_per_chunk == 0 {
+        return Err("Checkpoint groups_per_chunk must be at least 1".to_string());
+    }
+    let mut groups: Vec<KeyedGroup> = id_groups.into_iter().collect();
+    groups.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let fingerprint = fingerprint(&groups, &identity);
+
+    let mut manifest = match read_manifest(&checkpoint.dir)? {
+        Some(manifest) if manifest.fingerprint != fingerprint => {
+            return Err(format!(
+                "Checkpoint in {} was written for different inputs; remove it to start over",
+                checkpoint.dir.display()
+            ));
+        }
+        Some(manifest) => manifest,
+        None => {
+            std::fs::create_dir_all(&checkpoint.dir)
+                .map_err(|e| format!("Failed to create checkpoint directory {}: {}", checkpoint.dir.display(), e))?;
+            Manifest { fingerprint, batch_timestamp, groups_per_chunk: checkpoint.groups_per_chunk, completed_chunks: 0 }
+        }
+    };
+
+    // Chunk boundaries come from the manifest, so a resumed run splits the groups the same way
+    let chunk_count = groups.len().div_ceil(manifest.groups_per_chunk);
+    let mut combined = IdGroupProcessingResult::default();
+    let mut remaining = groups.into_iter();
+    for chunk in 0..chunk_count {
+        let chunk_groups: IdGroups = remaining.by_ref().take(manifest.groups_per_chunk).collect();
+        let result = if chunk < manifest.completed_chunks {
+            progress.groups_done(chunk_groups.len());
+            read_chunk(&chunk_path(&checkpoint.dir, chunk))?
+        } else {
+            let result = process(chunk_groups)?;
+            write_atomic(&chunk_path(&checkpoint.dir, chunk), &encode_chunk(&result)?)?;
+            manifest.completed_chunks = chunk + 1;
+            write_atomic(&checkpoint.dir.join(MANIFEST), encode_manifest(&manifest).as_bytes())?;
+            result
+        };
+        combined.expire_indices.extend(result.expire_indices);
+        combined.insert_batches.extend(result.insert_batches);
+        combined.overridden_updates.extend(result.overridden_updates);
+        combined.skipped_updates.extend(result.skipped_updates);
+        combined.spilled.append(result.spilled);
+    }
+
+    clear(&checkpoint.dir, chunk_count)?;
+    Ok(combined)
+}
+
+/// One ID group: key -> (current state rows, update rows)
+type KeyedGroup = (String, (Vec<usize>, Vec<usize>));
+
+fn fingerprint(groups: &[KeyedGroup], identity: &RunIdentity<'_>) -> u64 {
+    let mut hasher = Xxh64::new(0);
+    hasher.update(identity.system_date.to_string().as_bytes());
+    hasher.update(&[identity.update_mode as u8]);
+    for column in identity.id_columns.iter().chain(["|".to_string()].iter()).chain(identity.value_columns) {
+        hasher.update(column.as_bytes());
+        hasher.update(&[0]);
+    }
+    for (key, (current_rows, update_rows)) in groups {
+        hasher.update(key.as_bytes());
+        hasher.update(&[0]);
+        for &row in current_rows.iter().chain([usize::MAX].iter()).chain(update_rows) {
+            hasher.update(&(row as u64).to_le_bytes());
+        }
+    }
+    hasher.digest()
+}
+
+fn chunk_path(dir: &Path, chunk: usize) -> PathBuf {
+    dir.join(format!("chunk-{:06}.ipc", chunk))
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write checkpoint file {}: {}", path.display(), e))
+}
+
+fn clear(dir: &Path, chunk_count: usize) -> Result<(), String> {
+    let remove = |path: PathBuf| match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove checkpoint file {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    };
+    // The manifest goes first: without it the chunk files are never read
+    remove(dir.join(MANIFEST))?;
+    (0..chunk_count).try_for_each(|chunk| remove(chunk_path(dir, chunk)))
+}
+
+fn encode_manifest(manifest: &Manifest) -> String {
+    format!(
+        "{}\nfingerprint {:016x}\nbatch_timestamp {}\ngroups_per_chunk {}\ncompleted_chunks {}\n",
+        MANIFEST_HEADER,
+        manifest.fingerprint,
+        manifest.batch_timestamp.and_utc().timestamp_nanos_opt().unwrap_or(i64::MAX),
+        manifest.groups_per_chunk,
+        manifest.completed_chunks,
+    )
+}
+
+fn read_manifest(dir: &Path) -> Result<Option<Manifest>, String> {
+    let path = dir.join(MANIFEST);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read checkpoint manifest {}: {}", path.display(), e)),
+    };
+    let invalid = || format!("Invalid checkpoint manifest {}", path.display());
+
+    let mut lines = text.lines();
+    if lines.next() != Some(MANIFEST_HEADER) {
+        return Err(invalid());
+    }
+    let mut field = |name: &str| {
+        lines.next()
+            .and_then(|line| line.strip_prefix(name))
+            .and_then(|value| value.strip_prefix(' '))
+            .map(str::to_string)
+            .ok_or_else(invalid)
+    };
+    let fingerprint = u64::from_str_radix(&field("fingerprint")?, 16).map_err(|_| invalid())?;
+    let nanos: i64 = field("batch_timestamp")?.parse().map_err(|_| invalid())?;
+    let groups_per_chunk = field("groups_per_chunk")?.parse().map_err(|_| invalid())?;
+    let completed_chunks = field("completed_chunks")?.parse().map_err(|_| invalid())?;
+    let batch_timestamp = chrono::DateTime::from_timestamp_nanos(nanos).naive_utc();
+    Ok(Some(Manifest { fingerprint, batch_timestamp, groups_per_chunk, completed_chunks }))
+}
+
+fn expire_reason_code(reason: ExpireReason) -> u8 {
+    match reason {
+        ExpireReason::Overwritten => 0,
+        ExpireReason::Tombstoned => 1,
+        ExpireReason::Merged => 2,
+        ExpireReason::Carved => 3,
+    }
+}
+
+fn expire_reason_from_code(code: u8) -> Result<ExpireReason, String> {
+    match code {
+        0 => Ok(ExpireReason::Overwritten),
+        1 => Ok(ExpireReason::Tombstoned),
+        2 => Ok(ExpireReason::Merged),
+        3 => Ok(ExpireReason::Carved),
+        _ => Err(format!("Unknown expire reason code {}", code)),
+    }
+}
+
+fn encode_chunk(result: &IdGroupProcessingResult) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    out.extend_from_slice(CHUNK_MAGIC);
+    write_u64(&mut out, result.expire_indices.len() as u64);
+    for &(idx, reason) in &result.expire_indices {
+        write_u64(&mut out, idx as u64);
+        out.push(expire_reason_code(reason));
+    }
+    let mut insert_batches = result.spilled.read_back()?;
+    insert_batches.extend_from_slice(&result.insert_batches);
+    write_batches(&mut out, &insert_batches)?;
+    write_indices(&mut out, &result.overridden_updates);
+    write_u64(&mut out, result.skipped_updates.len() as u64);
+    for skipped in &result.skipped_updates {
+        write_u64(&mut out, skipped.update_index as u64);
+        out.push(skip_reason_code(skipped.reason));
+    }
+    Ok(out)
+}
+
+fn read_chunk(path: &Path) -> Result<IdGroupProcessingResult, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read checkpoint file {}: {}", path.display(), e))?;
+    let mut reader = FrameReader::new(&bytes);
+    if reader.take(CHUNK_MAGIC.len())? != CHUNK_MAGIC {
+        return Err(format!("Invalid checkpoint file {}", path.display()));
+    }
+
+    let expire_count = reader.read_usize()?;
+    let mut expire_indices = Vec::with_capacity(expire_count.min(bytes.len()));
+    for _ in 0..expire_count {
+        let idx = reader.read_usize()?;
+        expire_indices.push((idx, expire_reason_from_code(reader.take(1)?[0])?));
+    }
+    let insert_batches = reader.read_batches()?;
+    let overridden_updates = reader.read_indices()?;
+    let skipped_count = reader.read_usize()?;
+    let mut skipped_updates = Vec::with_capacity(skipped_count.min(bytes.len()));
+    for _ in 0..skipped_count {
+        let update_index = reader.read_usize()?;
+        let reason = skip_reason_from_code(reader.take(1)?[0])?;
+        skipped_updates.push(SkippedUpdate { update_index, reason });
+    }
+    Ok(IdGroupProcessingResult { expire_indices, insert_batches, overridden_updates, skipped_updates, ..Default::default() })
+}
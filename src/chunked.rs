@@ -0,0 +1,108 @@
+//! Current state supplied as a list of batches (e.g. as chunked by a database driver)
+
+use crate::types::{ChangeSet, UpdateMode};
+use crate::{create_id_key_with_buffer, process_updates_with_options, ProcessingOptions};
+use arrow::array::RecordBatch;
+use chrono::NaiveDate;
+use rustc_hash::FxHashSet;
+
+/// `process_updates_with_options` over a current state split across `current_state` batches.
+///
+/// `to_expire` holds global offsets: row `r` of batch `b` is `r` plus the lengths of batches
+/// `0..b` (see `ChangeSet::expire_addresses`). In delta mode only the rows whose IDs appear in
+/// the updates are copied out of the chunks, so the full current state is never concatenated;
+/// full_state runs need every row and concatenate them.
+pub fn process_updates_chunked(
+    current_state: &[RecordBatch],
+    updates: RecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    let first = current_state.first()
+        .ok_or("current_state must contain at least one batch (it may be empty)")?;
+
+    let (current, offsets) = match update_mode {
+        UpdateMode::Delta => {
+            let (key_columns, _) = crate::selection::resolve_column_patterns(
+                &updates.schema(), id_columns.clone(), value_columns.clone(), options
+            )?;
+            rows_with_update_ids(current_state, &updates, &key_columns)?
+        }
+        UpdateMode::FullState => (current_state.to_vec(), None),
+    };
+    let current = arrow::compute::concat_batches(&first.schema(), &current)
+        .map_err(|e| format!("Failed to combine current state batches: {}", e))?;
+
+    let mut changeset = process_updates_with_options(
+        current, updates, id_columns, value_columns, system_date, update_mode, options
+    )?;
+    if let Some(offsets) = offsets {
+        for idx in changeset.to_expire.iter_mut() {
+            *idx = offsets[*idx];
+        }
+    }
+    Ok(changeset)
+}
+
+/// The rows of every chunk whose ID key occurs in the updates, with their global offsets
+type SelectedRows = (Vec<RecordBatch>, Option<Vec<usize>>);
+
+fn rows_with_update_ids(chunks: &[RecordBatch], updates: &RecordBatch, id_columns: &[String]) -> Result<SelectedRows, String> {
+    let id_arrays = |batch: &RecordBatch, side: &str| {
+        let batch = crate::batch_utils::normalize_input_layouts(batch.clone())?;
+        crate::batch_utils::id_arrays(&batch, id_columns).map_err(|e| format!("{} in {}", e, side))
+    };
+
+    let mut buffer = String::with_capacity(64);
+    let update_ids = id_arrays(updates, "updates")?;
+    let mut update_keys = FxHashSet::default();
+    for row in 0..updates.num_rows() {
+        create_id_key_with_buffer(&update_ids, row, &mut buffer);
+        update_keys.insert(buffer.clone());
+    }
+
+    let mut selected = Vec::with_capacity(chunks.len());
+    let mut offsets = Vec::new();
+    let mut chunk_start = 0;
+    for (batch_index, chunk) in chunks.iter().enumerate() {
+        let chunk_ids = id_arrays(chunk, &format!("current state batch {}", batch_index))?;
+        let rows: Vec<usize> = (0..chunk.num_rows())
+            .filter(|&row| {
+                create_id_key_with_buffer(&chunk_ids, row, &mut buffer);
+                update_keys.contains(buffer.as_str())
+            })
+            .collect();
+        offsets.extend(rows.iter().map(|&row| chunk_start + row));
+        if !rows.is_empty() {
+            let rows = arrow::compute::take_record_batch(chunk, &crate::batch_utils::take_indices(rows))
+                .map_err(|e| format!("Failed to select current state rows: {}", e))?;
+            selected.push(rows);
+        }
+        chunk_start += chunk.num_rows();
+    }
+    Ok((selected, Some(offsets)))
+}
+
+impl ChangeSet {
+    /// `to_expire` as `(batch_index, row_index)` pairs into the batches passed to
+    /// `process_updates_chunked`
+    pub fn expire_addresses(&self, current_state: &[RecordBatch]) -> Vec<(usize, usize)> {
+        let mut starts = Vec::with_capacity(current_state.len());
+        let mut total = 0;
+        for batch in current_state {
+            starts.push(total);
+            total += batch.num_rows();
+        }
+        self.to_expire.iter()
+            .map(|&offset| {
+                // Last batch starting at or before the offset; an empty batch shares its start with
+                // the next one, so this is always the batch holding the row
+                let batch_index = starts.partition_point(|&start| start <= offset) - 1;
+                (batch_index, offset - starts[batch_index])
+            })
+            .collect()
+    }
+}
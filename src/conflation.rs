@@ -1,24 +1,17 @@
 use crate::types::*;
-use crate::batch_utils::extract_date_as_datetime;
-use arrow::array::{RecordBatch, TimestampMicrosecondArray, TimestampNanosecondArray, StringArray, ArrayRef, Array};
+use crate::batch_utils::{downcast_column, extract_date_as_datetime, required_column};
+use arrow::array::{RecordBatch, TimestampMicrosecondArray, StringArray, ArrayRef, Array};
 use arrow::datatypes::{DataType, Schema, Field};
 use std::sync::Arc;
 use std::collections::HashMap;
 use chrono::NaiveDateTime;
 
-/// Extract timestamp from any timestamp array type
+/// Extract timestamp from any timestamp or date array type
 fn extract_timestamp_as_datetime(array: &dyn arrow::array::Array, idx: usize) -> Result<NaiveDateTime, String> {
     if let Some(arr) = array.as_any().downcast_ref::<TimestampMicrosecondArray>() {
         Ok(extract_date_as_datetime(arr, idx))
-    } else if let Some(arr) = array.as_any().downcast_ref::<TimestampNanosecondArray>() {
-        let value = arr.value(idx);
-        let seconds = value / 1_000_000_000;
-        let nanos = (value % 1_000_000_000) as u32;
-        Ok(chrono::DateTime::from_timestamp(seconds, nanos)
-            .ok_or_else(|| "Failed to convert nanosecond timestamp".to_string())?
-            .naive_utc())
     } else {
-        Err("Unsupported timestamp array type".to_string())
+        crate::extract_datetime_flexible(array, idx)
     }
 }
 
@@ -62,7 +55,8 @@ fn schemas_compatible(schema1: &Schema, schema2: &Schema) -> bool {
 
 /// Compute a unified schema from multiple batches, promoting Null types to concrete types
 /// Also promotes integers to Float64 when mixed (pandas behavior for nullable int columns)
-fn compute_unified_schema(batches: &[RecordBatch]) -> Schema {
+/// Schema and field metadata are merged across batches, the first batch winning on conflicts
+pub(crate) fn compute_unified_schema(batches: &[RecordBatch]) -> Schema {
     if batches.is_empty() {
         return Schema::empty();
     }
@@ -98,15 +92,28 @@ fn compute_unified_schema(batches: &[RecordBatch]) -> Schema {
     let unified_fields: Vec<Field> = first_schema.fields().iter()
         .enumerate()
         .map(|(i, field)| {
+            let mut metadata = field.metadata().clone();
+            for batch in batches.iter().skip(1) {
+                for (key, value) in batch.schema().field(i).metadata() {
+                    metadata.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
             Field::new(field.name(), unified_types[i].clone(), true) // nullable for safety
+                .with_metadata(metadata)
         })
         .collect();
 
-    Schema::new(unified_fields)
+    let mut metadata = first_schema.metadata().clone();
+    for batch in batches.iter().skip(1) {
+        for (key, value) in batch.schema().metadata() {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    Schema::new_with_metadata(unified_fields, metadata)
 }
 
 /// Cast a batch to the unified schema, promoting Null-typed columns and numeric types
-fn cast_batch_to_schema(batch: &RecordBatch, target_schema: &Schema) -> Result<RecordBatch, String> {
+pub(crate) fn cast_batch_to_schema(batch: &RecordBatch, target_schema: &Schema) -> Result<RecordBatch, String> {
     let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.num_columns());
     let source_schema = batch.schema();
 
@@ -136,179 +143,203 @@ fn cast_batch_to_schema(batch: &RecordBatch, target_schema: &Schema) -> Result<R
         .map_err(|e| format!("Failed to create batch with unified schema: {}", e))
 }
 
-/// Create a clean schema without metadata for consolidation
-fn create_clean_schema(original_schema: &Schema) -> Schema {
-    let clean_fields: Vec<Field> = original_schema.fields()
-        .iter()
-        .map(|field| {
-            Field::new(
-                field.name(),
-                field.data_type().clone(),
-                field.is_nullable()
-            )
-        })
-        .collect();
-    
-    Schema::new(clean_fields)
-}
-
-
-pub fn simple_conflate_batches(mut batches: Vec<RecordBatch>) -> Result<Vec<RecordBatch>, String> {
-    if batches.len() <= 1 {
+/// Merge adjacent insert rows of the same ID carrying identical values into one row
+/// spanning both ranges, across and within batches.
+///
+/// Rows of all batches are sorted by ID and `effective_from`; each run of touching rows
+/// (`effective_to` of one equal to `effective_from` of the next) whose non-temporal columns
+/// all match is folded into its first row, which takes the run's last `effective_to`. Batches
+/// left empty are dropped and the rest keep their schema, ordered by their first
+/// `effective_from`.
+pub fn simple_conflate_batches(batches: Vec<RecordBatch>, id_columns: &[String]) -> Result<Vec<RecordBatch>, String> {
+    let _span = tracing::debug_span!("conflate_outputs", batches = batches.len()).entered();
+    let total_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    if total_rows <= 1 {
         return Ok(batches);
     }
 
-    // Sort batches by effective_from for processing
-    batches.sort_by(|a, b| {
-        let a_eff_from = extract_timestamp_as_datetime(
-            a.column_by_name("effective_from").unwrap(), 0
-        ).unwrap();
-        let b_eff_from = extract_timestamp_as_datetime(
-            b.column_by_name("effective_from").unwrap(), 0
-        ).unwrap();
-        a_eff_from.cmp(&b_eff_from)
-    });
-
-    let mut result = Vec::new();
-    let mut batches_iter = batches.into_iter();
-    let mut current_batch = batches_iter.next().unwrap();
-
-    for next_batch in batches_iter {
-        // Check if we can merge current_batch with next_batch
-        if can_merge_batches(&current_batch, &next_batch)? {
-            // Merge by extending current_batch's effective_to
-            let next_eff_to = extract_timestamp_as_datetime(
-                next_batch.column_by_name("effective_to").unwrap(), 0
-            )?;
-            current_batch = extend_batch_to_date(current_batch, next_eff_to)?;
+    // (ID key, effective_from, effective_to, batch, row) for every row
+    let mut rows: Vec<(String, NaiveDateTime, NaiveDateTime, usize, usize)> = Vec::with_capacity(total_rows);
+    let mut id_key_buffer = String::with_capacity(64);
+    for (batch_idx, batch) in batches.iter().enumerate() {
+        let id_arrays = id_columns.iter()
+            .map(|col| required_column(batch, col).cloned())
+            .collect::<Result<Vec<_>, _>>()?;
+        let eff_from_col = required_column(batch, "effective_from")?;
+        let eff_to_col = required_column(batch, "effective_to")?;
+        for row in 0..batch.num_rows() {
+            crate::create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+            rows.push((
+                id_key_buffer.clone(),
+                extract_timestamp_as_datetime(eff_from_col, row)?,
+                extract_timestamp_as_datetime(eff_to_col, row)?,
+                batch_idx,
+                row,
+            ));
+        }
+    }
+    rows.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+    // Fold each mergeable run into its head row
+    let mut keep: Vec<Vec<bool>> = batches.iter().map(|batch| vec![true; batch.num_rows()]).collect();
+    let mut extended: Vec<HashMap<usize, NaiveDateTime>> = vec![HashMap::new(); batches.len()];
+    let mut head = 0;
+    let mut head_to = rows[0].2;
+    for next in 1..rows.len() {
+        let (head_key, _, _, head_batch, head_row) = &rows[head];
+        let (next_key, next_from, next_to, next_batch, next_row) = &rows[next];
+        if head_key == next_key && head_to == *next_from
+            && can_merge_rows(&batches[*head_batch], *head_row, &batches[*next_batch], *next_row)? {
+            keep[*next_batch][*next_row] = false;
+            head_to = *next_to;
+            extended[*head_batch].insert(*head_row, head_to);
         } else {
-            // Can't merge, add current to result and make next the new current
-            result.push(current_batch);
-            current_batch = next_batch;
+            head = next;
+            head_to = *next_to;
         }
     }
-    
-    // Add the final batch
-    result.push(current_batch);
-    
-    Ok(result)
-}
 
-fn can_merge_batches(batch1: &RecordBatch, batch2: &RecordBatch) -> Result<bool, String> {
-    if batch1.num_rows() != 1 || batch2.num_rows() != 1 {
-        return Ok(false);
+    let mut result = Vec::with_capacity(batches.len());
+    for ((batch, keep), extended) in batches.into_iter().zip(keep).zip(extended) {
+        if extended.is_empty() && keep.iter().all(|&kept| kept) {
+            result.push(batch);
+            continue;
+        }
+        if let Some(merged) = apply_merges(&batch, &keep, &extended)? {
+            result.push(merged);
+        }
     }
 
-    // Check if they have the same ID values and value hash
+    // Order batches by effective_from for stable output
+    let mut keyed = result.into_iter()
+        .map(|batch| {
+            let eff_from = extract_timestamp_as_datetime(required_column(&batch, "effective_from")?, 0)?;
+            Ok((eff_from, batch))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    keyed.sort_by_key(|(eff_from, _)| *eff_from);
+    Ok(keyed.into_iter().map(|(_, batch)| batch).collect())
+}
+
+/// Whether two rows agree on every non-temporal column
+fn can_merge_rows(batch1: &RecordBatch, row1: usize, batch2: &RecordBatch, row2: usize) -> Result<bool, String> {
+    // (batches cut from the same input share a schema and can be compared position by position)
     let schema = batch1.schema();
-    for field in schema.fields() {
+    let same_layout = Arc::ptr_eq(&schema, &batch2.schema());
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let field_name = field.name();
         if !matches!(field_name.as_str(), "effective_from" | "effective_to" | "as_of_from" | "as_of_to") {
-            let array1 = batch1.column_by_name(field_name).unwrap();
-            let array2 = batch2.column_by_name(field_name).unwrap();
-            
-            let value1 = ScalarValue::from_array(array1, 0);
-            let value2 = ScalarValue::from_array(array2, 0);
-            
-            if value1 != value2 {
+            let array1 = batch1.column(col_idx);
+            let array2 = if same_layout { batch2.column(col_idx) } else { required_column(batch2, field_name)? };
+
+            if ScalarValue::from_array(array1, row1) != ScalarValue::from_array(array2, row2) {
                 return Ok(false);
             }
         }
     }
-
-    // Check if they are adjacent
-    let batch1_eff_to = extract_timestamp_as_datetime(
-        batch1.column_by_name("effective_to").unwrap(), 0
-    )?;
-    let batch2_eff_from = extract_timestamp_as_datetime(
-        batch2.column_by_name("effective_from").unwrap(), 0
-    )?;
-
-    Ok(batch1_eff_to == batch2_eff_from)
+    Ok(true)
 }
 
-fn extend_batch_to_date(batch: RecordBatch, new_effective_to: NaiveDateTime) -> Result<RecordBatch, String> {
+/// The kept rows of `batch` with merged runs' `effective_to` applied, or `None` when no row
+/// is kept
+fn apply_merges(
+    batch: &RecordBatch,
+    keep: &[bool],
+    extended: &HashMap<usize, NaiveDateTime>,
+) -> Result<Option<RecordBatch>, String> {
+    let kept_rows: Vec<usize> = (0..batch.num_rows())
+        .filter(|&row| keep[row])
+        .collect();
+    if kept_rows.is_empty() {
+        return Ok(None);
+    }
+    let eff_to_col = required_column(batch, "effective_to")?;
+    let effective_to = kept_rows.iter()
+        .map(|&row| match extended.get(&row) {
+            Some(new_to) => Ok(*new_to),
+            None => extract_timestamp_as_datetime(eff_to_col, row),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let indices = crate::batch_utils::take_indices(kept_rows);
     let schema = batch.schema();
-    let mut columns: Vec<ArrayRef> = Vec::new();
-    
-    for field in schema.fields() {
-        let column_name = field.name();
-        
-        if column_name == "effective_to" {
-            let timezone_str = if let DataType::Timestamp(_, tz) = field.data_type() {
-                tz.as_ref().map(|t| t.to_string())
-            } else { None };
-            
-            let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-            let microseconds = (new_effective_to - epoch).num_microseconds().unwrap();
-            let values = vec![Some(microseconds)];
-            let array = TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str);
-            columns.push(Arc::new(array));
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
+    for (col_idx, field) in schema.fields().iter().enumerate() {
+        if field.name() == "effective_to" {
+            columns.push(crate::create_timestamp_array_from_values(field.data_type(), &effective_to)?);
         } else {
-            // Copy original column
-            columns.push(batch.column_by_name(column_name).unwrap().clone());
+            columns.push(arrow::compute::take(batch.column(col_idx), &indices, None)
+                .map_err(|e| format!("Failed to select rows: {}", e))?);
         }
     }
-    
-    RecordBatch::try_new(schema.clone(), columns)
-        .map_err(|e| e.to_string())
+    RecordBatch::try_new(schema, columns)
+        .map(Some)
+        .map_err(|e| format!("Failed to create conflated batch: {}", e))
 }
 
+/// Drop exact duplicate insert rows (same ID, effective range and value hash) across all
+/// batches, whatever their size.
+///
+/// The first occurrence of each row, in batch then row order, is kept; batches keep their
+/// order and schema, and those left empty are dropped.
 pub fn deduplicate_record_batches(batches: Vec<RecordBatch>, id_columns: &[String]) -> Result<Vec<RecordBatch>, String> {
     if batches.is_empty() {
         return Ok(Vec::new());
     }
 
-    // Convert RecordBatches to a more workable format for deduplication
     // KEY FIX: Include ID columns in the deduplication key to prevent incorrectly
     // deduplicating records with same temporal bounds/hash but different IDs
-    let mut records: Vec<(String, NaiveDateTime, NaiveDateTime, String, RecordBatch)> = Vec::new();
-
-    for batch in batches {
-        if batch.num_rows() == 1 {
-            // Extract ID key from the batch
-            let id_key = extract_id_key(&batch, 0, id_columns)?;
-
-            // Extract timestamps handling both microsecond and nanosecond precision
-            let eff_from = extract_timestamp_as_datetime(batch.column_by_name("effective_from").unwrap(), 0)?;
-            let eff_to = extract_timestamp_as_datetime(batch.column_by_name("effective_to").unwrap(), 0)?;
-
-            let hash_array = batch.column_by_name("value_hash").unwrap()
-                .as_any().downcast_ref::<StringArray>().unwrap();
-
-            let hash = hash_array.value(0).to_string();
+    let mut records: Vec<(String, NaiveDateTime, NaiveDateTime, u64, usize, usize)> = Vec::new();
 
-            records.push((id_key, eff_from, eff_to, hash, batch));
+    for (batch_idx, batch) in batches.iter().enumerate() {
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        // Extract timestamps handling both microsecond and nanosecond precision
+        let eff_from_col = required_column(batch, "effective_from")?;
+        let eff_to_col = required_column(batch, "effective_to")?;
+        let hash_array = downcast_column::<StringArray>(required_column(batch, VALUE_HASH_COLUMN)?, VALUE_HASH_COLUMN, "Utf8")?;
+
+        for row in 0..batch.num_rows() {
+            records.push((
+                extract_id_key(batch, row, id_columns)?,
+                extract_timestamp_as_datetime(eff_from_col, row)?,
+                extract_timestamp_as_datetime(eff_to_col, row)?,
+                crate::arrow_hash::value_hash_key(hash_array.value(row)),
+                batch_idx,
+                row,
+            ));
         }
     }
 
-    // Sort by id_key, then effective_from, then effective_to, then hash
-    records.sort_by(|a, b| {
-        match a.0.cmp(&b.0) {
-            std::cmp::Ordering::Equal => {
-                match a.1.cmp(&b.1) {
-                    std::cmp::Ordering::Equal => {
-                        match a.2.cmp(&b.2) {
-                            std::cmp::Ordering::Equal => a.3.cmp(&b.3),
-                            other => other,
-                        }
-                    }
-                    other => other,
-                }
-            }
-            other => other,
+    // Sort by id_key, then effective_from, then effective_to, then hash; the stable sort keeps
+    // earlier rows first among equal keys
+    records.sort_by(|a, b| (&a.0, a.1, a.2, a.3).cmp(&(&b.0, b.1, b.2, b.3)));
+
+    // Mark exact duplicates (same ID + temporal bounds + hash) after the first
+    let mut keep: Vec<Vec<bool>> = batches.iter().map(|batch| vec![true; batch.num_rows()]).collect();
+    let mut duplicates = 0;
+    for pair in records.windows(2) {
+        let (previous, current) = (&pair[0], &pair[1]);
+        if (&previous.0, previous.1, previous.2, previous.3) == (&current.0, current.1, current.2, current.3) {
+            keep[current.4][current.5] = false;
+            duplicates += 1;
         }
-    });
-
-    // Remove exact duplicates (same ID + temporal bounds + hash)
-    let mut deduped: Vec<RecordBatch> = Vec::new();
-    let mut last_key: Option<(String, NaiveDateTime, NaiveDateTime, String)> = None;
+    }
 
-    for (id_key, eff_from, eff_to, hash, batch) in records {
-        let current_key = (id_key, eff_from, eff_to, hash);
-        if last_key != Some(current_key.clone()) {
+    let mut deduped: Vec<RecordBatch> = Vec::with_capacity(batches.len());
+    for (batch, keep) in batches.into_iter().zip(keep) {
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        if duplicates == 0 || keep.iter().all(|&kept| kept) {
             deduped.push(batch);
-            last_key = Some(current_key);
+            continue;
+        }
+        let filtered = arrow::compute::filter_record_batch(&batch, &arrow::array::BooleanArray::from(keep))
+            .map_err(|e| format!("Failed to drop duplicate rows: {}", e))?;
+        if filtered.num_rows() > 0 {
+            deduped.push(filtered);
         }
     }
 
@@ -317,85 +348,85 @@ pub fn deduplicate_record_batches(batches: Vec<RecordBatch>, id_columns: &[Strin
 
 /// Extract ID column values as a string key for deduplication
 fn extract_id_key(batch: &RecordBatch, row_idx: usize, id_columns: &[String]) -> Result<String, String> {
-    let mut key_parts: Vec<String> = Vec::new();
-
-    for col_name in id_columns {
+    let mut key = String::with_capacity(64);
+    for (i, col_name) in id_columns.iter().enumerate() {
         let column = batch.column_by_name(col_name)
             .ok_or_else(|| format!("Missing ID column: {}", col_name))?;
-
-        let value_str = extract_column_value(column.as_ref(), row_idx)?;
-        key_parts.push(value_str);
+        if i > 0 {
+            key.push('|');
+        }
+        crate::push_id_value(column.as_ref(), row_idx, &mut key);
     }
-
-    Ok(key_parts.join("|"))
+    Ok(key)
 }
 
-/// Extract a single column value as a string
-fn extract_column_value(column: &dyn arrow::array::Array, idx: usize) -> Result<String, String> {
-    use arrow::array::*;
-    use arrow::datatypes::DataType;
+/// Rows that start a merged run, keyed to the effective_to each run extends to
+type ConflationPlan = (Vec<usize>, HashMap<usize, NaiveDateTime>);
 
-    if column.is_null(idx) {
-        return Ok("NULL".to_string());
-    }
+/// Whether two consecutive segments of one ID merge: same value hash and a gap of at most
+/// `gap_tolerance`
+fn segments_conflate(hash: u64, to: NaiveDateTime, next_hash: u64, next_from: NaiveDateTime, gap_tolerance: chrono::Duration) -> bool {
+    let gap = next_from - to;
+    hash == next_hash && gap >= chrono::Duration::zero() && gap <= gap_tolerance
+}
 
-    match column.data_type() {
-        DataType::Int32 => {
-            let arr = column.as_any().downcast_ref::<Int32Array>()
-                .ok_or("Failed to downcast to Int32Array")?;
-            Ok(arr.value(idx).to_string())
-        }
-        DataType::Int64 => {
-            let arr = column.as_any().downcast_ref::<Int64Array>()
-                .ok_or("Failed to downcast to Int64Array")?;
-            Ok(arr.value(idx).to_string())
-        }
-        DataType::Utf8 => {
-            let arr = column.as_any().downcast_ref::<StringArray>()
-                .ok_or("Failed to downcast to StringArray")?;
-            Ok(arr.value(idx).to_string())
-        }
-        DataType::LargeUtf8 => {
-            let arr = column.as_any().downcast_ref::<LargeStringArray>()
-                .ok_or("Failed to downcast to LargeStringArray")?;
-            Ok(arr.value(idx).to_string())
-        }
-        _ => {
-            // For other types, use debug format (uncommon for ID columns)
-            Ok(format!("{:?}@{}", column.data_type(), idx))
+/// Plan conflation in a single pass when rows are already sorted by ID columns, then
+/// effective_from. Returns None as soon as a row is out of order.
+fn sorted_conflation_plan(
+    id_arrays: &[ArrayRef],
+    effective_from_col: &ArrayRef,
+    effective_to_col: &ArrayRef,
+    value_hash_col: &StringArray,
+    gap_tolerance: chrono::Duration,
+) -> Result<Option<ConflationPlan>, String> {
+    let comparators = id_arrays.iter()
+        .map(|array| arrow::array::make_comparator(array.as_ref(), array.as_ref(), arrow::compute::SortOptions::default()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to compare ID columns: {}", e))?;
+
+    let mut rows_to_keep = vec![0];
+    let mut rows_to_extend = HashMap::new();
+    let mut run_start = 0;
+    let mut previous_from = extract_timestamp_as_datetime(effective_from_col, 0)?;
+    let mut previous_to = extract_timestamp_as_datetime(effective_to_col, 0)?;
+    let mut previous_hash = crate::arrow_hash::value_hash_key(value_hash_col.value(0));
+
+    for row_idx in 1..value_hash_col.len() {
+        let ordering = comparators.iter()
+            .map(|compare| compare(row_idx - 1, row_idx))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal);
+        let effective_from = extract_timestamp_as_datetime(effective_from_col, row_idx)?;
+        let effective_to = extract_timestamp_as_datetime(effective_to_col, row_idx)?;
+        let value_hash = crate::arrow_hash::value_hash_key(value_hash_col.value(row_idx));
+        let same_id = match ordering {
+            std::cmp::Ordering::Greater => return Ok(None),
+            std::cmp::Ordering::Equal if effective_from < previous_from => return Ok(None),
+            std::cmp::Ordering::Equal => true,
+            std::cmp::Ordering::Less => false,
+        };
+
+        if same_id && segments_conflate(previous_hash, previous_to, value_hash, effective_from, gap_tolerance) {
+            rows_to_extend.insert(run_start, effective_to);
+        } else {
+            rows_to_keep.push(row_idx);
+            run_start = row_idx;
         }
+        previous_from = effective_from;
+        previous_to = effective_to;
+        previous_hash = value_hash;
     }
+    Ok(Some((rows_to_keep, rows_to_extend)))
 }
 
-/// Conflate consecutive input update records with same ID and value hash
-/// This merges rows that have:
-/// - Same ID column values
-/// - Same value_hash
-/// - Consecutive effective dates (row[i].effective_to == row[i+1].effective_from)
-pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Result<RecordBatch, String> {
-    // Handle edge cases
-    if updates.num_rows() <= 1 {
-        return Ok(updates);
-    }
-
-    // Extract necessary columns
-    let effective_from_col = updates.column_by_name("effective_from")
-        .ok_or_else(|| "Missing effective_from column".to_string())?;
-    let effective_to_col = updates.column_by_name("effective_to")
-        .ok_or_else(|| "Missing effective_to column".to_string())?;
-    let value_hash_col = updates.column_by_name("value_hash")
-        .ok_or_else(|| "Missing value_hash column".to_string())?
-        .as_any().downcast_ref::<StringArray>()
-        .ok_or_else(|| "value_hash must be StringArray".to_string())?;
-
-    // Extract ID columns
-    let mut id_arrays: Vec<ArrayRef> = Vec::new();
-    for id_col in id_columns {
-        let array = updates.column_by_name(id_col)
-            .ok_or_else(|| format!("Missing ID column: {}", id_col))?;
-        id_arrays.push(array.clone());
-    }
-
+/// Plan conflation for input in any order by grouping rows per ID and sorting each group
+fn grouped_conflation_plan(
+    id_arrays: &[ArrayRef],
+    effective_from_col: &ArrayRef,
+    effective_to_col: &ArrayRef,
+    value_hash_col: &StringArray,
+    gap_tolerance: chrono::Duration,
+) -> Result<ConflationPlan, String> {
     // Build row information: (row_idx, id_key, effective_from, effective_to, value_hash)
     #[derive(Clone)]
     struct RowInfo {
@@ -403,57 +434,20 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
         id_key: String,
         effective_from: NaiveDateTime,
         effective_to: NaiveDateTime,
-        value_hash: String,
+        value_hash: u64,
     }
 
     let mut rows: Vec<RowInfo> = Vec::new();
     let mut buffer = String::with_capacity(64);
 
-    for row_idx in 0..updates.num_rows() {
-        // Create ID key
-        buffer.clear();
-        for (i, array) in id_arrays.iter().enumerate() {
-            if i > 0 {
-                buffer.push('|');
-            }
-            match array.data_type() {
-                DataType::Utf8 => {
-                    let string_array = array.as_any().downcast_ref::<StringArray>().unwrap();
-                    if string_array.is_null(row_idx) {
-                        buffer.push_str("NULL");
-                    } else {
-                        buffer.push_str(string_array.value(row_idx));
-                    }
-                }
-                DataType::Int32 => {
-                    let int_array = array.as_any().downcast_ref::<arrow::array::Int32Array>().unwrap();
-                    if int_array.is_null(row_idx) {
-                        buffer.push_str("NULL");
-                    } else {
-                        buffer.push_str(&int_array.value(row_idx).to_string());
-                    }
-                }
-                DataType::Int64 => {
-                    let int_array = array.as_any().downcast_ref::<arrow::array::Int64Array>().unwrap();
-                    if int_array.is_null(row_idx) {
-                        buffer.push_str("NULL");
-                    } else {
-                        buffer.push_str(&int_array.value(row_idx).to_string());
-                    }
-                }
-                _ => {
-                    // Fallback to ScalarValue for other types
-                    let scalar = ScalarValue::from_array(array, row_idx);
-                    buffer.push_str(&format!("{:?}", scalar));
-                }
-            }
-        }
+    for row_idx in 0..value_hash_col.len() {
+        crate::create_id_key_with_buffer(id_arrays, row_idx, &mut buffer);
         let id_key = buffer.clone();
 
         // Extract timestamps
         let effective_from = extract_timestamp_as_datetime(effective_from_col, row_idx)?;
         let effective_to = extract_timestamp_as_datetime(effective_to_col, row_idx)?;
-        let value_hash = value_hash_col.value(row_idx).to_string();
+        let value_hash = crate::arrow_hash::value_hash_key(value_hash_col.value(row_idx));
 
         rows.push(RowInfo {
             row_idx,
@@ -467,7 +461,7 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
     // Group by ID key
     let mut id_groups: HashMap<String, Vec<RowInfo>> = HashMap::new();
     for row in rows {
-        id_groups.entry(row.id_key.clone()).or_insert_with(Vec::new).push(row);
+        id_groups.entry(row.id_key.clone()).or_default().push(row);
     }
 
     // Process each ID group: sort and identify rows to keep
@@ -476,7 +470,7 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
 
     for (_id_key, mut group) in id_groups {
         // Sort by effective_from
-        group.sort_by(|a, b| a.effective_from.cmp(&b.effective_from));
+        group.sort_by_key(|a| a.effective_from);
 
         let mut i = 0;
         while i < group.len() {
@@ -487,8 +481,8 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
                 let current = &group[segment_end];
                 let next = &group[segment_end + 1];
 
-                // Check if consecutive (same value_hash and adjacent dates)
-                if current.value_hash == next.value_hash && current.effective_to == next.effective_from {
+                // Check if consecutive (same value_hash and adjacent dates, up to the gap tolerance)
+                if segments_conflate(current.value_hash, current.effective_to, next.value_hash, next.effective_from, gap_tolerance) {
                     segment_end += 1;
                 } else {
                     break;
@@ -509,6 +503,58 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
         }
     }
 
+    Ok((rows_to_keep, rows_to_extend))
+}
+
+/// Conflate consecutive input update records with same ID and value hash
+/// This merges rows that have:
+/// - Same ID column values
+/// - Same value_hash
+/// - Consecutive effective dates (row[i+1].effective_from no more than `gap_tolerance` after
+///   row[i].effective_to; a zero tolerance needs them to touch)
+///
+/// When any rows were merged, the input row behind each output row is returned alongside.
+pub fn conflate_input_updates(
+    updates: RecordBatch,
+    id_columns: &[String],
+    gap_tolerance: chrono::Duration,
+) -> Result<(RecordBatch, Option<Vec<usize>>), String> {
+    let _span = tracing::debug_span!("conflate_inputs", rows = updates.num_rows()).entered();
+    // Handle edge cases
+    if updates.num_rows() <= 1 {
+        return Ok((updates, None));
+    }
+
+    // Extract necessary columns
+    let effective_from_col = updates.column_by_name("effective_from")
+        .ok_or_else(|| "Missing effective_from column".to_string())?;
+    let effective_to_col = updates.column_by_name("effective_to")
+        .ok_or_else(|| "Missing effective_to column".to_string())?;
+    let value_hash_col = updates.column_by_name(VALUE_HASH_COLUMN)
+        .ok_or_else(|| "Missing value_hash column".to_string())?
+        .as_any().downcast_ref::<StringArray>()
+        .ok_or_else(|| "value_hash must be StringArray".to_string())?;
+
+    // Extract ID columns
+    let mut id_arrays: Vec<ArrayRef> = Vec::new();
+    for id_col in id_columns {
+        let array = updates.column_by_name(id_col)
+            .ok_or_else(|| format!("Missing ID column: {}", id_col))?;
+        id_arrays.push(array.clone());
+    }
+
+    // Most feeds arrive sorted by (ID, effective_from) with nothing left to merge: plan those
+    // in one pass over neighbouring rows, without building ID keys or grouping
+    let plan = match sorted_conflation_plan(&id_arrays, effective_from_col, effective_to_col, value_hash_col, gap_tolerance)? {
+        Some(plan) => plan,
+        None => grouped_conflation_plan(&id_arrays, effective_from_col, effective_to_col, value_hash_col, gap_tolerance)?,
+    };
+    let (mut rows_to_keep, rows_to_extend) = plan;
+    if rows_to_extend.is_empty() {
+        // Every row is kept as is
+        return Ok((updates, None));
+    }
+
     // Sort rows to keep by original index to maintain order
     rows_to_keep.sort_unstable();
 
@@ -516,70 +562,91 @@ pub fn conflate_input_updates(updates: RecordBatch, id_columns: &[String]) -> Re
     let schema = updates.schema();
     let mut new_columns: Vec<ArrayRef> = Vec::new();
 
-    for field in schema.fields() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let col_name = field.name();
-        let original_col = updates.column_by_name(col_name).unwrap();
+        let original_col = updates.column(col_idx);
 
         if col_name == "effective_to" {
-            // Build effective_to column with extensions
-            let mut values: Vec<Option<i64>> = Vec::new();
-            let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-
-            for &row_idx in &rows_to_keep {
-                let effective_to = if let Some(new_to) = rows_to_extend.get(&row_idx) {
-                    *new_to
-                } else {
-                    extract_timestamp_as_datetime(effective_to_col, row_idx)?
-                };
-                let microseconds = (effective_to - epoch).num_microseconds().unwrap();
-                values.push(Some(microseconds));
-            }
-
-            // Match the original field's data type and timezone
-            let array: ArrayRef = match field.data_type() {
-                DataType::Timestamp(unit, tz) => {
-                    let timezone_str = tz.as_ref().map(|t| t.to_string());
-                    use arrow::datatypes::TimeUnit;
-
-                    match unit {
-                        TimeUnit::Microsecond => {
-                            Arc::new(TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str))
-                        }
-                        TimeUnit::Nanosecond => {
-                            // Convert microseconds to nanoseconds
-                            let nanos: Vec<Option<i64>> = values.iter()
-                                .map(|&v| v.map(|us| us * 1000))
-                                .collect();
-                            Arc::new(TimestampNanosecondArray::from(nanos).with_timezone_opt(timezone_str))
-                        }
-                        _ => {
-                            return Err(format!("Unsupported timestamp unit for effective_to: {:?}", unit));
-                        }
-                    }
-                }
-                _ => {
-                    return Err(format!("Expected Timestamp type for effective_to, got {:?}", field.data_type()));
-                }
-            };
+            // Build effective_to column with extensions, matching the original field's data type
+            let values = rows_to_keep.iter()
+                .map(|row_idx| match rows_to_extend.get(row_idx) {
+                    Some(new_to) => Ok(*new_to),
+                    None => extract_timestamp_as_datetime(effective_to_col, *row_idx),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let array = crate::create_timestamp_array_from_values(field.data_type(), &values)?;
             new_columns.push(array);
         } else {
             // Copy selected rows from original column
-            let indices = arrow::array::UInt32Array::from(
-                rows_to_keep.iter().map(|&i| i as u32).collect::<Vec<u32>>()
-            );
+            let indices = crate::batch_utils::take_indices(rows_to_keep.iter().copied());
             let selected = arrow::compute::take(original_col, &indices, None)
                 .map_err(|e| format!("Failed to select rows: {}", e))?;
             new_columns.push(selected);
         }
     }
 
-    RecordBatch::try_new(schema, new_columns)
-        .map_err(|e| format!("Failed to create conflated RecordBatch: {}", e))
+    let conflated = RecordBatch::try_new(schema, new_columns)
+        .map_err(|e| format!("Failed to create conflated RecordBatch: {}", e))?;
+    Ok((conflated, Some(rows_to_keep)))
+}
+
+/// Turn point observations into effective ranges.
+/// Each row's date (`effective_date`, or `effective_from` when that is absent) becomes its
+/// `effective_from`, and its `effective_to` is the next observation's date for the same ID
+/// (rows sorted by date as in `conflate_input_updates`); the last observation is open-ended.
+/// Row order is preserved. Repeated dates give the earlier row an empty range, so the later
+/// observation for a date wins.
+pub fn chain_event_updates(updates: RecordBatch, id_columns: &[String]) -> Result<RecordBatch, String> {
+    // Rename effective_date to effective_from and drop any effective_to the producer sent
+    let schema = updates.schema();
+    let date_column = if schema.column_with_name("effective_date").is_some() { "effective_date" } else { "effective_from" };
+    let mut fields: Vec<Field> = Vec::with_capacity(schema.fields().len() + 1);
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len() + 1);
+    for (field, column) in schema.fields().iter().zip(updates.columns()) {
+        match field.name().as_str() {
+            "effective_to" => continue,
+            "effective_from" if date_column == "effective_date" => continue,
+            name if name == date_column => {
+                fields.push(field.as_ref().clone().with_name("effective_from"));
+            }
+            _ => fields.push(field.as_ref().clone()),
+        }
+        columns.push(column.clone());
+    }
+    let from_position = fields.iter().position(|f| f.name() == "effective_from")
+        .ok_or_else(|| "Event updates need an effective_date or effective_from column".to_string())?;
+    let events = RecordBatch::try_new(Arc::new(Schema::new_with_metadata(fields.clone(), schema.metadata().clone())), columns.clone())
+        .map_err(|e| format!("Failed to rename event date column: {}", e))?;
+
+    // Group rows by ID and chain each group in date order
+    let effective_from_col = events.column(from_position);
+    let mut id_groups: HashMap<String, Vec<(NaiveDateTime, usize)>> = HashMap::new();
+    for row_idx in 0..events.num_rows() {
+        let id_key = extract_id_key(&events, row_idx, id_columns)?;
+        let effective_from = extract_timestamp_as_datetime(effective_from_col, row_idx)?;
+        id_groups.entry(id_key).or_default().push((effective_from, row_idx));
+    }
+
+    let mut effective_to = vec![MAX_DATETIME; events.num_rows()];
+    for (_id_key, mut group) in id_groups {
+        group.sort_by_key(|&(effective_from, _)| effective_from);
+        for pair in group.windows(2) {
+            effective_to[pair[0].1] = pair[1].0;
+        }
+    }
+
+    let (to_field, to_column) = crate::coverage::timestamp_column(&events, "effective_to", &effective_to)?;
+    fields.insert(from_position + 1, to_field);
+    columns.insert(from_position + 1, to_column);
+    RecordBatch::try_new(Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())), columns)
+        .map_err(|e| format!("Failed to build chained event updates: {}", e))
 }
 
 /// Consolidate multiple RecordBatches into fewer large batches to reduce Python conversion overhead
 /// This combines smaller batches from different ID groups into larger consolidated batches
-pub fn consolidate_final_batches(batches: Vec<RecordBatch>) -> Result<Vec<RecordBatch>, String> {
+/// of `target_batch_size` rows
+pub fn consolidate_final_batches(batches: Vec<RecordBatch>, target_batch_size: usize) -> Result<Vec<RecordBatch>, String> {
+    let _span = tracing::debug_span!("consolidate_batches", batches = batches.len()).entered();
     
     if batches.is_empty() {
         return Ok(Vec::new());
@@ -615,9 +682,8 @@ pub fn consolidate_final_batches(batches: Vec<RecordBatch>) -> Result<Vec<Record
     let table = arrow::compute::concat_batches(&Arc::new(unified_schema), &unified_batches)
         .map_err(|e| format!("Failed to consolidate batches: {}", e))?;
     
-    // Split the consolidated data into reasonably-sized batches (target ~10k rows per batch)
+    // Split the consolidated data into reasonably-sized batches
     let mut result_batches = Vec::new();
-    let target_batch_size = 10000;
     let total_rows = table.num_rows();
     
     if total_rows <= target_batch_size {
@@ -0,0 +1,173 @@
+use crate::{create_id_key_with_buffer, get_temporal_bounds};
+use arrow::array::{Array, ArrayRef, BooleanArray, RecordBatch};
+use arrow::datatypes::{DataType, Field, Schema};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Row index and effective range of one input row
+pub(crate) type Segment = (usize, NaiveDateTime, NaiveDateTime);
+
+/// Group a batch's rows by ID key, keeping each row's effective range
+pub(crate) fn group_segments(batch: &RecordBatch, id_columns: &[String]) -> Result<FxHashMap<String, Vec<Segment>>, String> {
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+
+    let mut groups: FxHashMap<String, Vec<Segment>> = FxHashMap::default();
+    let mut id_key_buffer = String::with_capacity(64);
+    for row in 0..batch.num_rows() {
+        let (from, to) = get_temporal_bounds(batch, row)?;
+        create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+        groups.entry(id_key_buffer.clone()).or_default().push((row, from, to));
+    }
+    Ok(groups)
+}
+
+/// Report, per ID, the effective-time ranges inside `window` `[from, to)` with no coverage.
+///
+/// Output has the ID columns followed by `gap_from` / `gap_to`, grouped by ID and in time order.
+/// Only IDs present in the batch can be reported. Pass a single as-of view of the table
+/// (e.g. current rows only), otherwise superseded rows count as coverage.
+pub fn find_gaps(
+    batch: &RecordBatch,
+    id_columns: &[String],
+    window: (NaiveDateTime, NaiveDateTime),
+) -> Result<RecordBatch, String> {
+    let (window_from, window_to) = window;
+    let mut groups: Vec<(String, Vec<Segment>)> = group_segments(batch, id_columns)?.into_iter().collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut id_rows = Vec::new();
+    let mut gap_from = Vec::new();
+    let mut gap_to = Vec::new();
+
+    for (_id_key, mut segments) in groups {
+        segments.sort_by_key(|&(_, from, _)| from);
+        let representative_row = segments[0].0;
+
+        // Walk covered ranges in start order; anything between the covered frontier and
+        // the next segment start is a gap
+        let mut covered_until = window_from;
+        for &(_, from, to) in &segments {
+            if from >= window_to {
+                break;
+            }
+            if from > covered_until {
+                id_rows.push(representative_row);
+                gap_from.push(covered_until);
+                gap_to.push(from);
+            }
+            covered_until = covered_until.max(to);
+        }
+        if covered_until < window_to {
+            id_rows.push(representative_row);
+            gap_from.push(covered_until);
+            gap_to.push(window_to);
+        }
+    }
+
+    let take_indices = crate::batch_utils::take_indices(id_rows);
+    let mut fields = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+    for id_col in id_columns {
+        let column = batch.column_by_name(id_col)
+            .ok_or_else(|| format!("ID column '{}' not found", id_col))?;
+        let taken = arrow::compute::take(column.as_ref(), &take_indices, None)
+            .map_err(|e| format!("Failed to gather column '{}': {}", id_col, e))?;
+        fields.push(Field::new(id_col, column.data_type().clone(), column.is_nullable()));
+        columns.push(taken);
+    }
+
+    let (field, column) = timestamp_column(batch, "gap_from", &gap_from)?;
+    fields.push(field);
+    columns.push(column);
+    let (field, column) = timestamp_column(batch, "gap_to", &gap_to)?;
+    fields.push(field);
+    columns.push(column);
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|e| format!("Failed to build gap report: {}", e))
+}
+
+/// Marker column added by `fill_gaps_forward`: true for synthetic filler rows
+pub const FILLER_MARKER_COLUMN: &str = "is_filler";
+
+/// Fill effective-time gaps between an ID's segments by carrying the previous segment forward.
+///
+/// Each gap gets one synthetic row copying the preceding segment (all columns, including
+/// `value_hash` and as-of columns) with `effective_from` / `effective_to` set to the gap.
+/// Output is the original rows followed by the fillers, with a boolean `is_filler` column.
+/// Nothing is added before an ID's first segment or after its last one.
+pub fn fill_gaps_forward(batch: &RecordBatch, id_columns: &[String]) -> Result<RecordBatch, String> {
+    let mut groups: Vec<(String, Vec<Segment>)> = group_segments(batch, id_columns)?.into_iter().collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut source_rows = Vec::new();
+    let mut filler_from = Vec::new();
+    let mut filler_to = Vec::new();
+
+    for (_id_key, mut segments) in groups {
+        segments.sort_by_key(|&(_, from, _)| from);
+
+        // Track the segment that reaches furthest so far - that's what a gap carries forward
+        let mut frontier: Option<(usize, NaiveDateTime)> = None;
+        for &(row, from, to) in &segments {
+            if let Some((carry_row, covered_until)) = frontier {
+                if from > covered_until {
+                    source_rows.push(carry_row);
+                    filler_from.push(covered_until);
+                    filler_to.push(from);
+                }
+            }
+            let reaches_further = match frontier {
+                Some((_, covered_until)) => to >= covered_until,
+                None => true,
+            };
+            if reaches_further {
+                frontier = Some((row, to));
+            }
+        }
+    }
+
+    let fillers = arrow::compute::take_record_batch(batch, &crate::batch_utils::take_indices(source_rows))
+        .map_err(|e| format!("Failed to copy filler rows: {}", e))?;
+
+    let schema = batch.schema();
+    let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    let mut columns = Vec::with_capacity(fields.len() + 1);
+    for (idx, field) in schema.fields().iter().enumerate() {
+        let filler_column = match field.name().as_str() {
+            "effective_from" => timestamp_column(batch, "effective_from", &filler_from)?.1,
+            "effective_to" => timestamp_column(batch, "effective_to", &filler_to)?.1,
+            _ => fillers.column(idx).clone(),
+        };
+        let filler_column = arrow::compute::cast(&filler_column, field.data_type())
+            .map_err(|e| format!("Failed to build filler column '{}': {}", field.name(), e))?;
+        let combined = arrow::compute::concat(&[batch.column(idx).as_ref(), filler_column.as_ref()])
+            .map_err(|e| format!("Failed to append filler rows to '{}': {}", field.name(), e))?;
+        columns.push(combined);
+    }
+
+    let marker: BooleanArray = (0..batch.num_rows() + fillers.num_rows())
+        .map(|row| Some(row >= batch.num_rows()))
+        .collect();
+    fields.push(Field::new(FILLER_MARKER_COLUMN, DataType::Boolean, false));
+    columns.push(Arc::new(marker));
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|e| format!("Failed to build gap-filled batch: {}", e))
+}
+
+/// Build a timestamp column with the same type as the batch's `effective_from`
+pub(crate) fn timestamp_column(
+    batch: &RecordBatch,
+    name: &str,
+    values: &[NaiveDateTime],
+) -> Result<(Field, ArrayRef), String> {
+    let target_type = batch.column_by_name("effective_from")
+        .ok_or_else(|| "effective_from column not found".to_string())?
+        .data_type()
+        .clone();
+    let array = crate::create_timestamp_array_from_values(&target_type, values)
+        .map_err(|e| format!("Failed to build '{}' column: {}", name, e))?;
+    Ok((Field::new(name, target_type, false), array))
+}
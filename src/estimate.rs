@@ -0,0 +1,50 @@
+//! Rough resource estimates for a run, for schedulers sizing instances before launching one
+
+use std::time::Duration;
+
+/// Arrow bytes per input row the estimate assumes: about 20 columns of 16 bytes, the shape of
+/// the 100k × 20 benchmark in docs/PERFORMANCE.md
+const ROW_BYTES: u64 = 320;
+/// Copies of the input held at the peak: the inputs, their hashed / prepared copies and the output
+const INPUT_COPIES: u64 = 3;
+/// ID group map entry: key string, two row index vectors and hash table overhead
+const GROUP_BYTES: u64 = 96;
+/// Insert batch bookkeeping per ID group (the engine reserves room for 3 batches per group)
+const GROUP_BATCH_BYTES: u64 = 3 * 64;
+/// Benchmarked throughput band in input rows per second, parallel processing
+const PARALLEL_ROWS_PER_SEC: (f64, f64) = (60_000.0, 170_000.0);
+/// Serial processing is only picked for inputs small enough that startup dominates
+const SERIAL_ROWS_PER_SEC: (f64, f64) = (20_000.0, 60_000.0);
+
+/// Expected resource use of one `process_updates` call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessingEstimate {
+    /// Expected peak memory of the engine (inputs, intermediates and output)
+    pub peak_memory_bytes: u64,
+    /// Runtime band: best case and slow case
+    pub min_runtime: Duration,
+    pub max_runtime: Duration,
+    /// Whether `Parallelism::Auto` would process ID groups in parallel
+    pub parallel: bool,
+}
+
+/// Estimate peak memory and runtime for `current_rows` current rows and `update_rows` updates
+/// spread over `id_cardinality` distinct IDs, using the engine's own parallelism heuristic.
+/// Rows are assumed to be about 320 bytes wide; scale `peak_memory_bytes` for wider schemas.
+/// These are planning figures, not guarantees; counts too large to address saturate at `u64::MAX`.
+pub fn estimate_processing(current_rows: usize, update_rows: usize, id_cardinality: usize) -> ProcessingEstimate {
+    // Summed in u64, so two counts that each fit a 32-bit usize can't wrap
+    let rows = (current_rows as u64).saturating_add(update_rows as u64);
+    let groups = id_cardinality as u64;
+    let peak_memory_bytes = rows.saturating_mul(ROW_BYTES * INPUT_COPIES + std::mem::size_of::<usize>() as u64)
+        .saturating_add(groups.saturating_mul(GROUP_BYTES + GROUP_BATCH_BYTES));
+
+    let parallel = crate::auto_parallel(id_cardinality, current_rows.saturating_add(update_rows));
+    let (slow, fast) = if parallel { PARALLEL_ROWS_PER_SEC } else { SERIAL_ROWS_PER_SEC };
+    ProcessingEstimate {
+        peak_memory_bytes,
+        min_runtime: Duration::from_secs_f64(rows as f64 / fast),
+        max_runtime: Duration::from_secs_f64(rows as f64 / slow),
+        parallel,
+    }
+}
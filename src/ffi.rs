@@ -0,0 +1,163 @@
+//! C ABI entry point over the Arrow C stream interface, compiled only with the `ffi` feature.
+//!
+//! Lets JVM / .NET services call the engine directly: inputs and outputs are
+//! `ArrowArrayStream`s, configuration is passed as plain C strings.
+
+use crate::{process_updates_with_options, ProcessingOptions, UpdateMode};
+use arrow::array::{ArrayRef, RecordBatch, RecordBatchReader};
+use arrow::ffi_stream::{ArrowArrayStreamReader, FFI_ArrowArrayStream};
+use chrono::NaiveDate;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::sync::Arc;
+
+/// Run `process_updates` over two Arrow C streams.
+///
+/// * `current_state`, `updates` - input streams; ownership is taken (they are released)
+/// * `id_columns`, `value_columns` - comma separated column names; an empty `value_columns`
+///   tracks every non-ID, non-temporal column
+/// * `system_date` - `YYYY-MM-DD`
+/// * `update_mode` - `delta` or `full_state`
+/// * `out_expire_indices` - receives a stream with a single uint64 `index` column
+/// * `out_to_insert`, `out_expired_records` - receive the insert and expired-record batches
+/// * `out_error` - on failure receives a message to free with `pytemporal_free_error`
+///
+/// Returns 0 on success and -1 on failure (output streams are left untouched on failure).
+///
+/// # Safety
+/// All pointers must be valid and non-null, input streams must be initialised Arrow C
+/// streams, and the string arguments must be NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn pytemporal_process_updates_ffi(
+    current_state: *mut FFI_ArrowArrayStream,
+    updates: *mut FFI_ArrowArrayStream,
+    id_columns: *const c_char,
+    value_columns: *const c_char,
+    system_date: *const c_char,
+    update_mode: *const c_char,
+    out_expire_indices: *mut FFI_ArrowArrayStream,
+    out_to_insert: *mut FFI_ArrowArrayStream,
+    out_expired_records: *mut FFI_ArrowArrayStream,
+    out_error: *mut *mut c_char,
+) -> c_int {
+    // Unwinding across the C ABI aborts the host process, so panics become errors here
+    let result = std::panic::catch_unwind(|| process_streams(
+        current_state,
+        updates,
+        id_columns,
+        value_columns,
+        system_date,
+        update_mode,
+    )).unwrap_or_else(|payload| Err(crate::panic_message(payload)));
+
+    match result {
+        Ok((expire_indices, to_insert, expired_records)) => {
+            std::ptr::write(out_expire_indices, expire_indices);
+            std::ptr::write(out_to_insert, to_insert);
+            std::ptr::write(out_expired_records, expired_records);
+            0
+        }
+        Err(message) => {
+            if !out_error.is_null() {
+                *out_error = CString::new(message.replace('\0', " ")).unwrap_or_default().into_raw();
+            }
+            -1
+        }
+    }
+}
+
+/// Free an error message returned through `out_error`
+///
+/// # Safety
+/// `error` must be null or a pointer previously returned by `pytemporal_process_updates_ffi`.
+#[no_mangle]
+pub unsafe extern "C" fn pytemporal_free_error(error: *mut c_char) {
+    if !error.is_null() {
+        drop(CString::from_raw(error));
+    }
+}
+
+type OutputStreams = (FFI_ArrowArrayStream, FFI_ArrowArrayStream, FFI_ArrowArrayStream);
+
+unsafe fn process_streams(
+    current_state: *mut FFI_ArrowArrayStream,
+    updates: *mut FFI_ArrowArrayStream,
+    id_columns: *const c_char,
+    value_columns: *const c_char,
+    system_date: *const c_char,
+    update_mode: *const c_char,
+) -> Result<OutputStreams, String> {
+    // Take ownership of both streams before anything can fail, so an error on one side
+    // still releases the other
+    let current_reader = import_stream(current_state, "current_state");
+    let updates_reader = import_stream(updates, "updates");
+    let current_batch = read_stream(current_reader?, "current_state")?;
+    let updates_batch = read_stream(updates_reader?, "updates")?;
+
+    let id_columns = split_columns(c_str(id_columns, "id_columns")?);
+    let value_columns = split_columns(c_str(value_columns, "value_columns")?);
+    let system_date_str = c_str(system_date, "system_date")?;
+    let system_date = NaiveDate::parse_from_str(system_date_str, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+    let mode = match c_str(update_mode, "update_mode")? {
+        "delta" => UpdateMode::Delta,
+        "full_state" => UpdateMode::FullState,
+        _ => return Err("Invalid update_mode. Must be 'delta' or 'full_state'".to_string()),
+    };
+
+    // Empty outputs carry the schema the run reports for them, else the input's
+    let insert_schema = updates_batch.schema();
+    let expired_schema = current_batch.schema();
+
+    let changeset = process_updates_with_options(
+        current_batch,
+        updates_batch,
+        id_columns,
+        value_columns,
+        system_date,
+        mode,
+        &ProcessingOptions::default(),
+    )?;
+
+    let expire_indices = changeset.expire_indices_array();
+    let expire_batch = RecordBatch::try_from_iter([("index", Arc::new(expire_indices) as ArrayRef)])
+        .map_err(|e| format!("Failed to build expire index batch: {}", e))?;
+    let expire_schema = expire_batch.schema();
+
+    Ok((
+        output_stream(vec![expire_batch], expire_schema)?,
+        output_stream(changeset.to_insert, changeset.insert_schema.unwrap_or(insert_schema))?,
+        output_stream(changeset.expired_records, changeset.expired_schema.unwrap_or(expired_schema))?,
+    ))
+}
+
+unsafe fn import_stream(stream: *mut FFI_ArrowArrayStream, name: &str) -> Result<ArrowArrayStreamReader, String> {
+    if stream.is_null() {
+        return Err(format!("{} stream pointer is null", name));
+    }
+    ArrowArrayStreamReader::from_raw(stream)
+        .map_err(|e| format!("Failed to open {} stream: {}", name, e))
+}
+
+fn read_stream(reader: ArrowArrayStreamReader, name: &str) -> Result<RecordBatch, String> {
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read {} stream: {}", name, e))?;
+    arrow::compute::concat_batches(&schema, &batches)
+        .map_err(|e| format!("Failed to combine {} batches: {}", name, e))
+}
+
+fn output_stream(batches: Vec<RecordBatch>, fallback_schema: arrow::datatypes::SchemaRef) -> Result<FFI_ArrowArrayStream, String> {
+    let reader = crate::batch_utils::unified_batch_reader(batches, fallback_schema)?;
+    Ok(FFI_ArrowArrayStream::new(Box::new(reader)))
+}
+
+unsafe fn c_str<'a>(value: *const c_char, name: &str) -> Result<&'a str, String> {
+    if value.is_null() {
+        return Err(format!("{} is null", name));
+    }
+    CStr::from_ptr(value).to_str().map_err(|e| format!("{} is not valid UTF-8: {}", name, e))
+}
+
+fn split_columns(value: &str) -> Vec<String> {
+    value.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+}
@@ -0,0 +1,90 @@
+//! Value hashes of current-state batches reused across runs (`ProcessingOptions::hash_cache`).
+//!
+//! An entry holds the value column arrays it was computed from, so their buffers stay alive
+//! and a later batch whose value columns point at the same buffers (the same pyarrow batch
+//! passed again, or clones of one `RecordBatch`) is known to hold the same data.
+
+use crate::arrow_hash::HashSpec;
+use crate::options::HashCache;
+use crate::HashAlgorithm;
+use arrow::array::{Array, ArrayData, ArrayRef, RecordBatch};
+use std::sync::atomic::Ordering;
+
+#[derive(Debug)]
+pub(crate) struct CachedHashes {
+    algorithm: HashAlgorithm,
+    exact_floats: Vec<String>,
+    /// (value column, its array when hashed)
+    columns: Vec<(String, ArrayData)>,
+    hashes: ArrayRef,
+}
+
+impl CachedHashes {
+    fn matches(&self, batch: &RecordBatch, value_columns: &[String], spec: HashSpec<'_>) -> bool {
+        self.algorithm == spec.algorithm
+            && self.exact_floats == spec.exact_floats
+            && self.hashes.len() == batch.num_rows()
+            && self.columns.len() == value_columns.len()
+            && self.columns.iter().zip(value_columns).all(|((name, data), column)| {
+                name == column
+                    && batch.column_by_name(column).is_some_and(|array| array.to_data().ptr_eq(data))
+            })
+    }
+}
+
+/// Give `current_state` the cached hashes of its value columns when it needs hashing.
+/// Returns the batch and whether its hashes still have to be computed (and then cached).
+pub(crate) fn attach_cached_hashes(
+    current_state: RecordBatch,
+    value_columns: &[String],
+    spec: HashSpec<'_>,
+    cache: Option<&HashCache>,
+) -> Result<(RecordBatch, bool), String> {
+    let Some(cache) = cache else {
+        return Ok((current_state, false));
+    };
+    if !crate::needs_value_hash(&current_state, spec)? {
+        return Ok((current_state, false));
+    }
+    let hit = {
+        let mut entries = cache.entries.lock().map_err(|_| "Hash cache lock poisoned".to_string())?;
+        let position = entries.iter().position(|entry| entry.matches(&current_state, value_columns, spec));
+        // Most recently used entries go to the back and are evicted last
+        position.and_then(|position| entries.remove(position)).map(|entry| {
+            let hashes = entry.hashes.clone();
+            entries.push_back(entry);
+            hashes
+        })
+    };
+    match hit {
+        Some(hashes) => {
+            cache.hits.fetch_add(1, Ordering::Relaxed);
+            let current_state = crate::arrow_hash::with_hash_column(&current_state, hashes, spec)?;
+            Ok((current_state, false))
+        }
+        None => Ok((current_state, true)),
+    }
+}
+
+/// Cache the value hashes of `current_state`, evicting the least recently used entry when full
+pub(crate) fn cache_hashes(
+    cache: &HashCache,
+    current_state: &RecordBatch,
+    value_columns: &[String],
+    spec: HashSpec<'_>,
+) -> Result<(), String> {
+    if cache.capacity == 0 {
+        return Ok(());
+    }
+    let columns = value_columns.iter()
+        .map(|column| Ok((column.clone(), crate::batch_utils::required_column(current_state, column)?.to_data())))
+        .collect::<Result<Vec<_>, String>>()?;
+    let hashes = crate::batch_utils::required_column(current_state, crate::types::VALUE_HASH_COLUMN)?.clone();
+
+    let mut entries = cache.entries.lock().map_err(|_| "Hash cache lock poisoned".to_string())?;
+    while entries.len() >= cache.capacity {
+        entries.pop_front();
+    }
+    entries.push_back(CachedHashes { algorithm: spec.algorithm, exact_floats: spec.exact_floats.to_vec(), columns, hashes });
+    Ok(())
+}
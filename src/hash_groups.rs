@@ -0,0 +1,178 @@
+use crate::options::{HashGroup, HashGroupChange, ProcessingOptions};
+use crate::types::{ChangeSet, SkipReason, SkippedUpdate, VALUE_HASH_COLUMN};
+use crate::{create_id_key_with_buffer, get_temporal_bounds_micros};
+use arrow::array::{Array, ArrayRef, BooleanArray, RecordBatch, StringArray};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Update rows dropped under `HashGroupChange::All` and the position of every kept row
+pub(crate) type PartialMatches = (Vec<usize>, Vec<usize>);
+
+/// Check `options.hash_groups` against the value columns and fill each group's hash column on
+/// both inputs where it is missing or has empty values
+pub(crate) fn attach_group_hashes(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    value_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<(RecordBatch, RecordBatch), String> {
+    if options.hash_groups.is_empty() {
+        return Ok((current_state, updates));
+    }
+    check_hash_groups(&options.hash_groups, value_columns, &options.hash_column)?;
+    Ok((
+        with_group_hashes(current_state, &options.hash_groups, options.hash_spec())?,
+        with_group_hashes(updates, &options.hash_groups, options.hash_spec())?,
+    ))
+}
+
+fn check_hash_groups(groups: &[HashGroup], value_columns: &[String], hash_column: &str) -> Result<(), String> {
+    for (i, group) in groups.iter().enumerate() {
+        if group.value_columns.is_empty() {
+            return Err(format!("Hash group '{}' has no value columns", group.column));
+        }
+        if let Some(column) = group.value_columns.iter().find(|column| !value_columns.contains(column)) {
+            return Err(format!("Hash group '{}' column '{}' is not a value column", group.column, column));
+        }
+        if group.column == hash_column || group.column == VALUE_HASH_COLUMN || value_columns.contains(&group.column) {
+            return Err(format!("Hash group column '{}' clashes with the value hash or a value column", group.column));
+        }
+        if groups[..i].iter().any(|other| other.column == group.column) {
+            return Err(format!("Hash group column '{}' is configured twice", group.column));
+        }
+    }
+    Ok(())
+}
+
+fn with_group_hashes(mut batch: RecordBatch, groups: &[HashGroup], spec: crate::arrow_hash::HashSpec<'_>) -> Result<RecordBatch, String> {
+    if batch.num_rows() == 0 {
+        return Ok(batch);
+    }
+    for group in groups {
+        let populated = batch.column_by_name(&group.column)
+            .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+            .is_some_and(|hashes| hashes.null_count() == 0 && hashes.iter().all(|hash| hash.is_some_and(|h| !h.is_empty())));
+        if populated {
+            continue;
+        }
+        for column in &group.value_columns {
+            if batch.column_by_name(column).is_none() {
+                return Err(format!("Hash group '{}' column '{}' not found", group.column, column));
+            }
+        }
+        let rows: Vec<usize> = (0..batch.num_rows()).collect();
+        let hashes: ArrayRef = Arc::new(StringArray::from(
+            crate::arrow_hash::hash_values_batch_arrow_direct(&batch, &rows, &group.value_columns, spec)
+        ));
+        batch = replace_or_append(batch, crate::arrow_hash::hash_field(spec).with_name(&group.column), hashes)?;
+    }
+    Ok(batch)
+}
+
+fn replace_or_append(batch: RecordBatch, field: arrow::datatypes::Field, array: ArrayRef) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let mut fields: Vec<_> = schema.fields().iter().cloned().collect();
+    let mut columns = batch.columns().to_vec();
+    match schema.index_of(field.name()) {
+        Ok(idx) => {
+            fields[idx] = Arc::new(field);
+            columns[idx] = array;
+        }
+        Err(_) => {
+            fields.push(Arc::new(field));
+            columns.push(array);
+        }
+    }
+    let schema = Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, schema.metadata().clone()));
+    RecordBatch::try_new(schema, columns).map_err(|e| e.to_string())
+}
+
+/// Under `HashGroupChange::All`, drop the update rows that overlap a current row of their ID
+/// while sharing at least one group hash with it: not every group changed, so the current
+/// row stands. Returns the remaining updates and which rows were dropped and kept.
+pub(crate) fn drop_partial_matches(
+    current_state: &RecordBatch,
+    updates: RecordBatch,
+    id_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<(RecordBatch, Option<PartialMatches>), String> {
+    if options.hash_groups.is_empty() || options.hash_group_change == HashGroupChange::Any
+        || current_state.num_rows() == 0 || updates.num_rows() == 0
+    {
+        return Ok((updates, None));
+    }
+
+    let group_hashes = |batch: &RecordBatch| -> Result<Vec<StringArray>, String> {
+        options.hash_groups.iter()
+            .map(|group| {
+                let column = crate::batch_utils::required_column(batch, &group.column)?;
+                Ok(crate::batch_utils::downcast_column::<StringArray>(column, &group.column, "Utf8")?.clone())
+            })
+            .collect()
+    };
+    let id_arrays = |batch: &RecordBatch| -> Result<Vec<ArrayRef>, String> {
+        id_columns.iter().map(|column| crate::batch_utils::required_column(batch, column).cloned()).collect()
+    };
+
+    let current_hashes = group_hashes(current_state)?;
+    let current_ids = id_arrays(current_state)?;
+    let mut current_by_id: FxHashMap<String, Vec<usize>> = FxHashMap::default();
+    let mut key = String::new();
+    for row in 0..current_state.num_rows() {
+        create_id_key_with_buffer(&current_ids, row, &mut key);
+        current_by_id.entry(key.clone()).or_default().push(row);
+    }
+
+    let update_hashes = group_hashes(&updates)?;
+    let update_ids = id_arrays(&updates)?;
+    let (mut dropped, mut kept) = (Vec::new(), Vec::new());
+    for row in 0..updates.num_rows() {
+        create_id_key_with_buffer(&update_ids, row, &mut key);
+        let (effective_from, effective_to) = get_temporal_bounds_micros(&updates, row)?;
+        let mut partial_match = false;
+        for &current_row in current_by_id.get(&key).map(Vec::as_slice).unwrap_or_default() {
+            let (current_from, current_to) = get_temporal_bounds_micros(current_state, current_row)?;
+            if current_from < effective_to && current_to > effective_from
+                && current_hashes.iter().zip(&update_hashes)
+                    .any(|(current, update)| current.value(current_row) == update.value(row))
+            {
+                partial_match = true;
+                break;
+            }
+        }
+        if partial_match { dropped.push(row) } else { kept.push(row) }
+    }
+
+    if dropped.is_empty() {
+        return Ok((updates, None));
+    }
+    let mask = BooleanArray::from((0..updates.num_rows()).map(|row| kept.binary_search(&row).is_ok()).collect::<Vec<_>>());
+    let updates = arrow::compute::filter_record_batch(&updates, &mask).map_err(|e| e.to_string())?;
+    Ok((updates, Some((dropped, kept))))
+}
+
+/// Map update indices back past `drop_partial_matches` and report the dropped rows as
+/// `no_change` skips
+pub(crate) fn attach_partial_matches(
+    mut changeset: ChangeSet,
+    partial_matches: Option<PartialMatches>,
+    options: &ProcessingOptions,
+) -> ChangeSet {
+    let Some((dropped, kept)) = partial_matches else {
+        return changeset;
+    };
+
+    for idx in changeset.overridden_updates.iter_mut() {
+        *idx = kept[*idx];
+    }
+    for skipped in changeset.skipped_updates.iter_mut() {
+        skipped.update_index = kept[skipped.update_index];
+    }
+
+    if options.report_skipped_updates {
+        changeset.skipped_updates.extend(dropped.into_iter()
+            .map(|update_index| SkippedUpdate { update_index, reason: SkipReason::NoChange }));
+        changeset.skipped_updates.sort_by_key(|skipped| skipped.update_index);
+    }
+    changeset
+}
@@ -0,0 +1,229 @@
+//! Structural checks on a changeset against the inputs it was computed from
+//! (`verify_changeset_invariants`, `ProcessingOptions::verify_invariants`)
+
+use crate::coverage::{group_segments, Segment};
+use crate::{extract_datetime_flexible, ChangeSet, UpdateMode, MAX_DATETIME};
+use arrow::array::{Array, RecordBatch};
+use chrono::NaiveDateTime;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Most violations spelled out in the error before it is truncated
+const MAX_REPORTED_VIOLATIONS: usize = 20;
+
+/// Check that applying `changeset` to `current_state` leaves a well-formed timeline:
+///
+/// - `to_expire` names each current row at most once, and only rows that exist
+/// - no inserted row has an empty effective or as-of range
+/// - per ID, no inserted row overlaps another inserted or surviving current row
+/// - per ID, the result covers no effective time that neither the current state nor the
+///   updates covered, and loses none the updates covered (delta: none the current state
+///   covered either; full_state may end rows that disappeared)
+/// - as_of never runs backwards: inserts start no earlier in system time than the current
+///   rows of their ID being expired, and expired records do not end before they started
+///
+/// Inputs are read as closed-open effective ranges, as passed to `process_updates`. Returns an
+/// error listing the violations.
+pub fn verify_changeset_invariants(
+    current_state: &RecordBatch,
+    updates: &RecordBatch,
+    changeset: &ChangeSet,
+    id_columns: &[String],
+    update_mode: UpdateMode,
+) -> Result<(), String> {
+    verify(current_state, Some(updates), changeset, id_columns, update_mode)
+}
+
+/// `verify_changeset_invariants`, skipping the checks against the updates when `updates` is
+/// `None` (runs whose options reshape the updates before they are applied)
+pub(crate) fn verify(
+    current_state: &RecordBatch,
+    updates: Option<&RecordBatch>,
+    changeset: &ChangeSet,
+    id_columns: &[String],
+    update_mode: UpdateMode,
+) -> Result<(), String> {
+    let mut violations = Vec::new();
+
+    let mut expired = FxHashSet::default();
+    for &row in &changeset.to_expire {
+        if row >= current_state.num_rows() {
+            violations.push(format!("to_expire names row {} of a {}-row current state", row, current_state.num_rows()));
+        } else if !expired.insert(row) {
+            violations.push(format!("to_expire names current row {} twice", row));
+        }
+    }
+
+    let current = group_segments(current_state, id_columns)?;
+    let updates = updates.map(|batch| group_segments(batch, id_columns)).transpose()?;
+    let mut inserts: FxHashMap<String, Vec<Segment>> = FxHashMap::default();
+    let mut insert_as_of: FxHashMap<String, NaiveDateTime> = FxHashMap::default();
+    for (batch_idx, batch) in changeset.to_insert.iter().enumerate() {
+        let as_of_from = batch.column_by_name("as_of_from");
+        let as_of_to = batch.column_by_name("as_of_to");
+        for (id_key, segments) in group_segments(batch, id_columns)? {
+            for &(row, from, to) in &segments {
+                if from >= to {
+                    violations.push(format!("[{}] inserted row {}:{} has empty effective range [{}, {})", id_key, batch_idx, row, from, to));
+                }
+                let started = read_as_of(as_of_from, row, NaiveDateTime::MIN)?;
+                if started >= read_as_of(as_of_to, row, MAX_DATETIME)? {
+                    violations.push(format!("[{}] inserted row {}:{} has empty as_of range", id_key, batch_idx, row));
+                }
+                let earliest = insert_as_of.entry(id_key.clone()).or_insert(started);
+                *earliest = (*earliest).min(started);
+            }
+            inserts.entry(id_key).or_default().extend(segments);
+        }
+    }
+
+    // Expired rows keep their as_of_from and gain the as_of_to the run closed them at
+    for batch in &changeset.expired_records {
+        let as_of_from = batch.column_by_name("as_of_from");
+        let as_of_to = batch.column_by_name("as_of_to");
+        for row in 0..batch.num_rows() {
+            if read_as_of(as_of_to, row, MAX_DATETIME)? < read_as_of(as_of_from, row, NaiveDateTime::MIN)? {
+                violations.push(format!("expired record {} ends in system time before it starts", row));
+            }
+        }
+    }
+
+    let current_as_of = current_state.column_by_name("as_of_from");
+    let mut id_keys: Vec<&String> = current.keys().chain(inserts.keys()).collect();
+    if let Some(updates) = &updates {
+        id_keys.extend(updates.keys());
+    }
+    id_keys.sort();
+    id_keys.dedup();
+
+    let no_segments = Vec::new();
+    for id_key in id_keys {
+        let current_segments = current.get(id_key).unwrap_or(&no_segments);
+        let inserted = inserts.get(id_key).unwrap_or(&no_segments);
+        let surviving: Vec<Segment> = current_segments.iter()
+            .filter(|(row, _, _)| !expired.contains(row))
+            .copied()
+            .collect();
+
+        if let Some(&earliest_insert) = insert_as_of.get(id_key) {
+            for &(row, _, _) in current_segments.iter().filter(|(row, _, _)| expired.contains(row)) {
+                let started = read_as_of(current_as_of, row, NaiveDateTime::MIN)?;
+                if earliest_insert < started {
+                    violations.push(format!(
+                        "[{}] insert as_of_from {} precedes expired current row {} (as_of_from {})",
+                        id_key, earliest_insert, row, started
+                    ));
+                }
+            }
+        }
+
+        if let Some((a, b)) = first_insert_overlap(&surviving, inserted) {
+            violations.push(format!("[{}] overlapping rows after applying the changeset: [{}, {}) and [{}, {})", id_key, a.1, a.2, b.1, b.2));
+        }
+
+        let after = merged(surviving.iter().chain(inserted));
+        let current_coverage = merged(current_segments.iter());
+        let update_segments = updates.as_ref().map(|updates| updates.get(id_key).unwrap_or(&no_segments));
+        if let Some(update_segments) = update_segments {
+            let before = merged(current_segments.iter().chain(update_segments));
+            if let Some(gap) = first_uncovered(&after, &before) {
+                violations.push(format!("[{}] result covers [{}, {}) that no input covered", id_key, gap.0, gap.1));
+            }
+            if let Some(gap) = first_uncovered(&merged(update_segments.iter()), &after) {
+                violations.push(format!("[{}] update coverage [{}, {}) lost", id_key, gap.0, gap.1));
+            }
+        }
+        if update_mode == UpdateMode::Delta {
+            if let Some(gap) = first_uncovered(&current_coverage, &after) {
+                violations.push(format!("[{}] current coverage [{}, {}) lost by a delta update", id_key, gap.0, gap.1));
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("Changeset invariants violated ({}):", violations.len());
+    for violation in violations.iter().take(MAX_REPORTED_VIOLATIONS) {
+        message.push_str(&format!("\n  {}", violation));
+    }
+    if violations.len() > MAX_REPORTED_VIOLATIONS {
+        message.push_str(&format!("\n  ... and {} more", violations.len() - MAX_REPORTED_VIOLATIONS));
+    }
+    Err(message)
+}
+
+/// An as-of timestamp, with `missing` standing in for an absent column or null value
+fn read_as_of(array: Option<&arrow::array::ArrayRef>, row: usize, missing: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    match array {
+        Some(array) if !array.is_null(row) => extract_datetime_flexible(array.as_ref(), row),
+        _ => Ok(missing),
+    }
+}
+
+/// First pair of overlapping segments involving at least one insert; overlaps between two
+/// surviving current rows predate the changeset and are not its fault
+fn first_insert_overlap(surviving: &[Segment], inserted: &[Segment]) -> Option<(Segment, Segment)> {
+    let mut segments: Vec<(Segment, bool)> = surviving.iter().map(|&s| (s, false))
+        .chain(inserted.iter().map(|&s| (s, true)))
+        .filter(|((_, from, to), _)| from < to)
+        .collect();
+    segments.sort_by_key(|((_, from, to), _)| (*from, *to));
+
+    // Furthest-reaching segment seen so far, of any kind and among inserts
+    let mut reach_any: Option<Segment> = None;
+    let mut reach_insert: Option<Segment> = None;
+    for (segment, is_insert) in segments {
+        let other = if is_insert { reach_any } else { reach_insert };
+        if let Some(other) = other.filter(|other| segment.1 < other.2) {
+            return Some((other, segment));
+        }
+        let reaches_further = |reach: Option<Segment>| match reach {
+            Some(reach) => segment.2 > reach.2,
+            None => true,
+        };
+        if reaches_further(reach_any) {
+            reach_any = Some(segment);
+        }
+        if is_insert && reaches_further(reach_insert) {
+            reach_insert = Some(segment);
+        }
+    }
+    None
+}
+
+/// Union of the segments' effective ranges as sorted, disjoint, non-touching ranges
+fn merged<'a>(segments: impl Iterator<Item = &'a Segment>) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut ranges: Vec<(NaiveDateTime, NaiveDateTime)> = segments
+        .filter(|(_, from, to)| from < to)
+        .map(|&(_, from, to)| (from, to))
+        .collect();
+    ranges.sort();
+    let mut union: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::with_capacity(ranges.len());
+    for (from, to) in ranges {
+        match union.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => union.push((from, to)),
+        }
+    }
+    union
+}
+
+/// First part of `inner` not covered by `outer` (both as returned by `merged`)
+fn first_uncovered(
+    inner: &[(NaiveDateTime, NaiveDateTime)],
+    outer: &[(NaiveDateTime, NaiveDateTime)],
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    for &(from, to) in inner {
+        // Merged ranges never touch, so a covered range sits inside a single outer range
+        let covering = outer.iter().find(|&&(outer_from, outer_to)| outer_from <= from && from < outer_to);
+        match covering {
+            Some(&(_, outer_to)) if outer_to >= to => {}
+            Some(&(_, outer_to)) => return Some((outer_to, to)),
+            None => {
+                let next = outer.iter().map(|&(outer_from, _)| outer_from).find(|&outer_from| outer_from > from);
+                return Some((from, next.map_or(to, |next| next.min(to))));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,53 @@
+use crate::types::ChangeSet;
+use arrow::array::RecordBatch;
+
+/// Build the changeset that rolls a load back, given the current state it was computed from.
+///
+/// Every current row the load expired is reopened with its original `as_of_to`, and every row
+/// it inserted is closed at its own `as_of_from`, leaving it visible at no system time. Both
+/// are expiries keyed like any other, so the inverse applies through `changeset_to_sql` or
+/// `to_cdc_batch` unchanged. `expired_records` holds the closed inserts (one batch per insert
+/// batch) followed by the reopened rows; `to_expire` numbers the closed inserts across the
+/// load's insert batches in order. The inverse inserts nothing.
+pub fn invert_changeset(changeset: &ChangeSet, original_current_state: &RecordBatch) -> Result<ChangeSet, String> {
+    let current_rows = original_current_state.num_rows();
+    if let Some(&row) = changeset.to_expire.iter().find(|&&row| row >= current_rows) {
+        return Err(format!("to_expire names row {} of a {}-row current state", row, current_rows));
+    }
+    if !changeset.to_expire.is_empty() && original_current_state.column_by_name("as_of_to").is_none() {
+        return Err("as_of_to column not found in current state".to_string());
+    }
+
+    let mut to_expire = Vec::new();
+    let mut expired_records = Vec::with_capacity(changeset.to_insert.len() + 1);
+    for batch in &changeset.to_insert {
+        let first = to_expire.len();
+        to_expire.extend(first..first + batch.num_rows());
+        expired_records.push(close_at_as_of_from(batch)?);
+    }
+
+    if !changeset.to_expire.is_empty() {
+        let indices = crate::batch_utils::take_indices(changeset.to_expire.iter().copied());
+        let reopened = arrow::compute::take_record_batch(original_current_state, &indices)
+            .map_err(|e| format!("Failed to select expired current rows: {}", e))?;
+        expired_records.push(reopened);
+    }
+
+    Ok(ChangeSet { to_expire, expired_records, ..Default::default() })
+}
+
+/// The batch with `as_of_to` replaced by `as_of_from`
+fn close_at_as_of_from(batch: &RecordBatch) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let as_of_to_idx = schema.index_of("as_of_to")
+        .map_err(|_| "as_of_to column not found in inserted rows".to_string())?;
+    let as_of_from = batch.column_by_name("as_of_from")
+        .ok_or_else(|| "as_of_from column not found in inserted rows".to_string())?;
+
+    let closed = arrow::compute::cast(as_of_from, schema.field(as_of_to_idx).data_type())
+        .map_err(|e| format!("Failed to close inserted rows at as_of_from: {}", e))?;
+    let mut columns = batch.columns().to_vec();
+    columns[as_of_to_idx] = closed;
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to close inserted rows at as_of_from: {}", e))
+}
@@ -0,0 +1,233 @@
+use crate::types::{ChangeSet, SkipReason, SkippedUpdate, ValidationIssue, ValidationIssueKind};
+use arrow::array::{RecordBatch, UInt64Array};
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+
+// Framing: MAGIC, then the index sections and the batch sections in ChangeSet field order.
+// Index sections are a u64 count followed by u64 values; batch sections are a u64 count
+// followed by length-prefixed Arrow IPC streams (one per batch, so schemas may differ).
+// Strings are a u64 byte length followed by UTF-8 bytes.
+// All integers are little endian.
+// Version 01 frames come in two layouts: the first ones end after the skipped updates, later
+// ones carry the validation issues section too. Version 02 appended the `clamped_updates` index
+// section. Every one of these layouts still reads.
+const MAGIC: &[u8; 8] = b"PTCSET02";
+const MAGIC_V1: &[u8; 8] = b"PTCSET01";
+
+impl ChangeSet {
+    /// `to_expire` as a UInt64 array, the form handed to Arrow consumers (files, C streams)
+    pub fn expire_indices_array(&self) -> UInt64Array {
+        crate::batch_utils::take_indices(self.to_expire.iter().copied())
+    }
+
+    /// Serialize the changeset into a single framed byte buffer of Arrow IPC streams,
+    /// suitable for shipping over a queue or persisting for audit/replay
+    /// (`metrics` and the output schemas describe the producing run and are not serialized)
+    pub fn to_ipc_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+
+        write_indices(&mut out, &self.to_expire);
+        write_batches(&mut out, &self.to_insert)?;
+        write_batches(&mut out, &self.expired_records)?;
+        write_indices(&mut out, &self.overridden_updates);
+
+        write_u64(&mut out, self.skipped_updates.len() as u64);
+        for skipped in &self.skipped_updates {
+            write_u64(&mut out, skipped.update_index as u64);
+            out.push(skip_reason_code(skipped.reason));
+        }
+
+        write_u64(&mut out, self.validation_issues.len() as u64);
+        for issue in &self.validation_issues {
+            write_u64(&mut out, issue.row_index as u64);
+            out.push(validation_kind_code(issue.kind));
+            write_str(&mut out, &issue.column);
+            write_str(&mut out, &issue.value);
+        }
+
+        write_indices(&mut out, &self.clamped_updates);
+
+        Ok(out)
+    }
+
+    /// Rebuild a changeset produced by `to_ipc_bytes`
+    pub fn from_ipc_bytes(bytes: &[u8]) -> Result<ChangeSet, String> {
+        let mut reader = FrameReader::new(bytes);
+        let magic = reader.take(MAGIC.len())?;
+        if magic != MAGIC && magic != MAGIC_V1 {
+            return Err("Not a serialized ChangeSet (bad magic header)".to_string());
+        }
+
+        let to_expire = reader.read_indices()?;
+        let to_insert = reader.read_batches()?;
+        let expired_records = reader.read_batches()?;
+        let overridden_updates = reader.read_indices()?;
+
+        let skipped_count = reader.read_usize()?;
+        let mut skipped_updates = Vec::with_capacity(skipped_count.min(bytes.len()));
+        for _ in 0..skipped_count {
+            let update_index = reader.read_usize()?;
+            let reason = skip_reason_from_code(reader.take(1)?[0])?;
+            skipped_updates.push(SkippedUpdate { update_index, reason });
+        }
+
+        // The earliest version 01 frames stop here, before the validation issues section
+        let issue_count = if magic == MAGIC || reader.pos != bytes.len() { reader.read_usize()? } else { 0 };
+        let mut validation_issues = Vec::with_capacity(issue_count.min(bytes.len()));
+        for _ in 0..issue_count {
+            let row_index = reader.read_usize()?;
+            let kind = validation_kind_from_code(reader.take(1)?[0])?;
+            let column = reader.read_str()?;
+            let value = reader.read_str()?;
+            validation_issues.push(ValidationIssue { row_index, column, kind, value });
+        }
+
+        let clamped_updates = if magic == MAGIC { reader.read_indices()? } else { Vec::new() };
+
+        if reader.pos != bytes.len() {
+            return Err(format!("Unexpected {} trailing bytes after ChangeSet", bytes.len() - reader.pos));
+        }
+
+        Ok(ChangeSet {
+            to_expire, to_insert, expired_records, overridden_updates, skipped_updates, validation_issues, clamped_updates,
+            ..Default::default()
+        })
+    }
+}
+
+pub(crate) fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_u64(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+pub(crate) fn write_indices(out: &mut Vec<u8>, indices: &[usize]) {
+    write_u64(out, indices.len() as u64);
+    for &idx in indices {
+        write_u64(out, idx as u64);
+    }
+}
+
+pub(crate) fn write_batches(out: &mut Vec<u8>, batches: &[RecordBatch]) -> Result<(), String> {
+    write_u64(out, batches.len() as u64);
+    for batch in batches {
+        let mut stream = Vec::new();
+        {
+            let mut writer = StreamWriter::try_new(&mut stream, &batch.schema())
+                .map_err(|e| format!("Failed to start IPC stream: {}", e))?;
+            writer.write(batch)
+                .map_err(|e| format!("Failed to write IPC batch: {}", e))?;
+            writer.finish()
+                .map_err(|e| format!("Failed to finish IPC stream: {}", e))?;
+        }
+        write_u64(out, stream.len() as u64);
+        out.extend_from_slice(&stream);
+    }
+    Ok(())
+}
+
+pub(crate) fn skip_reason_code(reason: SkipReason) -> u8 {
+    match reason {
+        SkipReason::EmptyRange => 0,
+        SkipReason::NoChange => 1,
+        SkipReason::ExactMatch => 2,
+        SkipReason::CoveredByCurrent => 3,
+        SkipReason::BeforeWatermark => 4,
+        SkipReason::InvalidRow => 5,
+        SkipReason::OutsideClampWindow => 6,
+        SkipReason::Conflated => 7,
+    }
+}
+
+pub(crate) fn skip_reason_from_code(code: u8) -> Result<SkipReason, String> {
+    match code {
+        0 => Ok(SkipReason::EmptyRange),
+        1 => Ok(SkipReason::NoChange),
+        2 => Ok(SkipReason::ExactMatch),
+        3 => Ok(SkipReason::CoveredByCurrent),
+        4 => Ok(SkipReason::BeforeWatermark),
+        5 => Ok(SkipReason::InvalidRow),
+        6 => Ok(SkipReason::OutsideClampWindow),
+        7 => Ok(SkipReason::Conflated),
+        _ => Err(format!("Unknown skip reason code {}", code)),
+    }
+}
+
+fn validation_kind_code(kind: ValidationIssueKind) -> u8 {
+    match kind {
+        ValidationIssueKind::NullId => 0,
+        ValidationIssueKind::InvalidTimestamp => 1,
+        ValidationIssueKind::InvertedRange => 2,
+        ValidationIssueKind::EmptyRange => 3,
+    }
+}
+
+fn validation_kind_from_code(code: u8) -> Result<ValidationIssueKind, String> {
+    match code {
+        0 => Ok(ValidationIssueKind::NullId),
+        1 => Ok(ValidationIssueKind::InvalidTimestamp),
+        2 => Ok(ValidationIssueKind::InvertedRange),
+        3 => Ok(ValidationIssueKind::EmptyRange),
+        _ => Err(format!("Unknown validation issue code {}", code)),
+    }
+}
+
+pub(crate) struct FrameReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FrameReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        FrameReader { bytes, pos: 0 }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "Truncated ChangeSet bytes".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, String> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// A count, length or row index, which are always framed as u64
+    pub(crate) fn read_usize(&mut self) -> Result<usize, String> {
+        crate::batch_utils::row_index(self.read_u64()?)
+    }
+
+    pub(crate) fn read_str(&mut self) -> Result<String, String> {
+        let len = self.read_usize()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| format!("Invalid UTF-8 in ChangeSet bytes: {}", e))
+    }
+
+    pub(crate) fn read_indices(&mut self) -> Result<Vec<usize>, String> {
+        let count = self.read_usize()?;
+        (0..count).map(|_| self.read_usize()).collect()
+    }
+
+    pub(crate) fn read_batches(&mut self) -> Result<Vec<RecordBatch>, String> {
+        let count = self.read_usize()?;
+        let mut batches = Vec::new();
+        for _ in 0..count {
+            let len = self.read_usize()?;
+            let stream = self.take(len)?;
+            let reader = StreamReader::try_new(stream, None)
+                .map_err(|e| format!("Failed to open IPC stream: {}", e))?;
+            for batch in reader {
+                batches.push(batch.map_err(|e| format!("Failed to read IPC batch: {}", e))?);
+            }
+        }
+        Ok(batches)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::coverage::{group_segments, timestamp_column, Segment};
+use arrow::array::{ArrayRef, RecordBatch, UInt64Array};
+use arrow::datatypes::{Field, Schema};
+use crate::types::{TEMPORAL_COLUMNS, VALUE_HASH_COLUMN};
+use std::sync::Arc;
+
+/// Join two bitemporal tables on ID and overlapping effective ranges.
+///
+/// Every intersecting (left, right) pair produces one row covering the intersection:
+/// the ID columns, `effective_from` / `effective_to` of the intersection, then the remaining
+/// left columns and the remaining right columns (suffixed `_right` on name clashes).
+/// As-of columns and `value_hash` are dropped; pass a single as-of view of each table
+/// (e.g. current rows only) to get a meaningful result.
+pub fn temporal_join(
+    left: &RecordBatch,
+    right: &RecordBatch,
+    id_columns: &[String],
+) -> Result<RecordBatch, String> {
+    let mut right_by_id = group_segments(right, id_columns)?;
+    for segments in right_by_id.values_mut() {
+        segments.sort_by_key(|&(_, from, _)| from);
+    }
+
+    let mut left_indices = Vec::new();
+    let mut right_indices = Vec::new();
+    let mut segment_from = Vec::new();
+    let mut segment_to = Vec::new();
+
+    let mut left_groups: Vec<(String, Vec<Segment>)> = group_segments(left, id_columns)?.into_iter().collect();
+    left_groups.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (id_key, mut left_segments) in left_groups {
+        let Some(right_segments) = right_by_id.get(&id_key) else {
+            continue;
+        };
+        left_segments.sort_by_key(|&(_, from, _)| from);
+
+        // Sweep both sorted lists; a right segment can only overlap left segments
+        // that start before it ends
+        let mut right_start = 0;
+        for &(left_idx, left_from, left_to) in &left_segments {
+            while right_start < right_segments.len() && right_segments[right_start].2 <= left_from {
+                right_start += 1;
+            }
+            for &(right_idx, right_from, right_to) in &right_segments[right_start..] {
+                if right_from >= left_to {
+                    break;
+                }
+                let from = left_from.max(right_from);
+                let to = left_to.min(right_to);
+                if from < to {
+                    left_indices.push(left_idx);
+                    right_indices.push(right_idx);
+                    segment_from.push(from);
+                    segment_to.push(to);
+                }
+            }
+        }
+    }
+
+    let left_take = crate::batch_utils::take_indices(left_indices);
+    let right_take = crate::batch_utils::take_indices(right_indices);
+    let mut fields = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+
+    for id_col in id_columns {
+        let (field, column) = take_column(left, id_col, &left_take)?;
+        fields.push(field);
+        columns.push(column);
+    }
+
+    for (name, values) in [("effective_from", &segment_from), ("effective_to", &segment_to)] {
+        let (field, column) = timestamp_column(left, name, values)?;
+        fields.push(field);
+        columns.push(column);
+    }
+
+    let is_payload = |name: &str| {
+        !id_columns.iter().any(|c| c == name) && !TEMPORAL_COLUMNS.contains(&name) && name != VALUE_HASH_COLUMN
+    };
+    let left_schema = left.schema();
+    let left_payload: Vec<&str> = left_schema.fields().iter()
+        .map(|f| f.name().as_str())
+        .filter(|name| is_payload(name))
+        .collect();
+    for name in &left_payload {
+        let (field, column) = take_column(left, name, &left_take)?;
+        fields.push(field);
+        columns.push(column);
+    }
+
+    let right_schema = right.schema();
+    for name in right_schema.fields().iter().map(|f| f.name().as_str()).filter(|name| is_payload(name)) {
+        let (field, column) = take_column(right, name, &right_take)?;
+        let field = if left_payload.contains(&name) {
+            field.with_name(format!("{}_right", name))
+        } else {
+            field
+        };
+        fields.push(field);
+        columns.push(column);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|e| format!("Failed to build temporal join result: {}", e))
+}
+
+fn take_column(batch: &RecordBatch, name: &str, indices: &UInt64Array) -> Result<(Field, ArrayRef), String> {
+    let schema = batch.schema();
+    let field = schema.field_with_name(name)
+        .map_err(|_| format!("Column '{}' not found", name))?
+        .clone();
+    let column = arrow::compute::take(batch.column(schema.index_of(name).unwrap()).as_ref(), indices, None)
+        .map_err(|e| format!("Failed to gather column '{}': {}", name, e))?;
+    Ok((field, column))
+}
@@ -0,0 +1,69 @@
+//! Idempotency ledger for redelivered update batches (`ProcessingOptions::idempotency`).
+//!
+//! Each processed batch ID gets one file in the ledger directory holding the ID and the
+//! changeset computed for it (`ChangeSet::to_ipc_bytes`). Files are named after a hash of the
+//! ID, so any ID is a valid file name, and written via a temporary file and a rename so a
+//! crash never leaves a half-written entry behind.
+
+use crate::ipc::{write_str, write_u64, FrameReader};
+use crate::options::IdempotencyLedger;
+use crate::types::ChangeSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const ENTRY_MAGIC: &[u8; 8] = b"PTLEDG01";
+
+static NEXT_TEMP_FILE: AtomicUsize = AtomicUsize::new(0);
+
+fn entry_path(ledger: &IdempotencyLedger) -> PathBuf {
+    let digest = xxhash_rust::xxh64::xxh64(ledger.batch_id.as_bytes(), 0);
+    ledger.dir.join(format!("batch-{:016x}.ipc", digest))
+}
+
+/// The changeset recorded for `ledger.batch_id`, if it was processed before
+pub(crate) fn recorded_changeset(ledger: &IdempotencyLedger) -> Result<Option<ChangeSet>, String> {
+    let path = entry_path(ledger);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read ledger entry {}: {}", path.display(), e)),
+    };
+    let mut reader = FrameReader::new(&bytes);
+    if reader.take(ENTRY_MAGIC.len())? != ENTRY_MAGIC {
+        return Err(format!("{} is not a ledger entry (bad magic header)", path.display()));
+    }
+    let batch_id = reader.read_str()?;
+    if batch_id != ledger.batch_id {
+        return Err(format!(
+            "Ledger entry {} belongs to batch '{}', not '{}' (hash collision)",
+            path.display(), batch_id, ledger.batch_id
+        ));
+    }
+    let len = reader.read_usize()?;
+    let mut changeset = ChangeSet::from_ipc_bytes(reader.take(len)?)?;
+    changeset.metrics.replayed = true;
+    Ok(Some(changeset))
+}
+
+/// Record the changeset computed for `ledger.batch_id`
+pub(crate) fn record_changeset(ledger: &IdempotencyLedger, changeset: &ChangeSet) -> Result<(), String> {
+    std::fs::create_dir_all(&ledger.dir)
+        .map_err(|e| format!("Failed to create ledger directory {}: {}", ledger.dir.display(), e))?;
+    let changeset_bytes = changeset.to_ipc_bytes()?;
+    let mut out = Vec::with_capacity(changeset_bytes.len() + ledger.batch_id.len() + 32);
+    out.extend_from_slice(ENTRY_MAGIC);
+    write_str(&mut out, &ledger.batch_id);
+    write_u64(&mut out, changeset_bytes.len() as u64);
+    out.extend_from_slice(&changeset_bytes);
+
+    // Writers racing on the same batch ID each rename their own temporary file into place
+    let path = entry_path(ledger);
+    let tmp = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, &out)
+        .and_then(|_| std::fs::rename(&tmp, &path))
+        .map_err(|e| format!("Failed to write ledger entry {}: {}", path.display(), e))
+}
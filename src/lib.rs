@@ -1,8 +1,6 @@
 use arrow::array::{RecordBatch};
 use chrono::{Datelike, NaiveDate, NaiveDateTime};
-use pyo3::prelude::*;
-use pyo3_arrow::PyRecordBatch;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use arrow::array::Array;
 use rayon::prelude::*;
 
@@ -12,6 +10,57 @@ mod timeline;
 mod conflation;
 mod batch_utils;
 mod arrow_hash;
+mod options;
+mod sql;
+mod ipc;
+mod cdc;
+mod coverage;
+mod join;
+mod snapshot;
+mod retention;
+mod validation;
+mod selection;
+mod chunked;
+mod sequence;
+mod checkpoint;
+mod ledger;
+mod spill;
+mod estimate;
+mod pipeline;
+mod invariants;
+mod invert;
+mod merge;
+mod sharding;
+mod calendar;
+mod rebase;
+mod repair;
+mod carve;
+mod hash_groups;
+mod tolerance;
+mod adjacency;
+mod tenant;
+mod hash_cache;
+mod write_plan;
+pub mod testing;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "python-logging")]
+mod python_logging;
+#[cfg(feature = "opentelemetry")]
+mod telemetry;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(all(feature = "mimalloc", feature = "jemalloc"))]
+compile_error!("features `mimalloc` and `jemalloc` both set the global allocator; enable only one");
+
+#[cfg(all(feature = "mimalloc", not(feature = "jemalloc")))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc"), not(target_env = "msvc")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 /// Hash algorithm options for value hash computation
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -19,26 +68,131 @@ mod arrow_hash;
 pub enum HashAlgorithm {
     #[default]
     XxHash,  // Default - fast, high quality
+    #[cfg(feature = "legacy-hash")]
     Sha256,  // Legacy compatibility
 }
 
-impl HashAlgorithm {
+impl std::str::FromStr for HashAlgorithm {
+    type Err = String;
+
     fn from_str(s: &str) -> Result<HashAlgorithm, String> {
         match s.to_lowercase().as_str() {
             "xxhash" | "xx" => Ok(HashAlgorithm::XxHash),
+            #[cfg(feature = "legacy-hash")]
             "sha256" | "sha" => Ok(HashAlgorithm::Sha256),
+            #[cfg(not(feature = "legacy-hash"))]
+            "sha256" | "sha" => Err(format!(
+                "Hash algorithm '{}' is not available: pytemporal was built without the `legacy-hash` feature. \
+                 Use xxhash, or rebuild with --features legacy-hash", s
+            )),
             _ => Err(format!("Unknown hash algorithm: {}", s)),
         }
     }
 }
 
+impl HashAlgorithm {
+    /// Tag stored in the hash column's field metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::XxHash => "xxhash",
+            #[cfg(feature = "legacy-hash")]
+            HashAlgorithm::Sha256 => "sha256",
+        }
+    }
+}
+
+/// Describe a panic caught at a language boundary so it can be returned as an ordinary error
+#[cfg(any(feature = "python", feature = "ffi"))]
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("pytemporal internal error: {}", message)
+}
 
 pub use types::*;
+pub use options::{CalendarBoundary, CancellationToken, Checkpoint, Clock, ConflictResolution, EmptyRangePolicy, FixedClock, HashCache, HashGroup, HashGroupChange, IdempotencyLedger, InputMode, IntervalEnd, MissingTemporalColumns, NullIdPolicy, OutputSharding, Parallelism, ProcessingOptions, ProgressCallback, ShardKey, SystemClock, TemporalFill, ValidationMode, ValueTolerance, WatermarkPolicy};
+pub use sql::{changeset_to_sql, write_plan_to_sql};
+pub use coverage::{fill_gaps_forward, find_gaps, FILLER_MARKER_COLUMN};
+pub use join::temporal_join;
+pub use snapshot::{diff_as_of, known_between, latest_view, snapshot_series, SnapshotPoint};
+pub use retention::purge_history;
+pub use rebase::{rebase_dates, DateShift, RebaseAxes};
+pub use repair::repair_overlaps;
+pub use carve::carve_out;
+pub use validation::{diff_schemas, validate_updates};
+pub use arrow_hash::{rehash_table, HASH_ALGORITHM_METADATA_KEY, HASH_EXACT_FLOATS_METADATA_KEY};
+pub use selection::select_columns;
+pub use chunked::process_updates_chunked;
+pub use sequence::process_update_sequence;
+pub use estimate::{estimate_processing, ProcessingEstimate};
+pub use invariants::verify_changeset_invariants;
+pub use invert::invert_changeset;
+pub use merge::merge_changesets;
+pub use write_plan::{WritePlan, WriteStep, WriteStepKind};
+#[cfg(any(feature = "python", feature = "ffi", feature = "cli"))]
+pub use batch_utils::unified_batch_reader;
 use timeline::process_id_timeline;
 use conflation::{deduplicate_record_batches, simple_conflate_batches, consolidate_final_batches, conflate_input_updates};
 
-/// Type alias for processing results from ID groups
-type IdGroupProcessingResult = (Vec<usize>, Vec<RecordBatch>);
+/// Processing results from ID groups
+#[derive(Default)]
+struct IdGroupProcessingResult {
+    expire_indices: Vec<(usize, ExpireReason)>,
+    insert_batches: Vec<RecordBatch>,
+    overridden_updates: Vec<usize>,
+    skipped_updates: Vec<SkippedUpdate>,
+    /// Insert batches spilled to disk under `ProcessingOptions::max_memory_bytes`
+    spilled: crate::spill::SpilledInserts,
+}
+
+/// Buffers reused across the ID groups one worker processes, so the hot path clears and
+/// refills them instead of allocating fresh record vectors for every group
+#[derive(Default)]
+struct GroupScratch {
+    current_records: Vec<BitemporalRecord>,
+    update_records: Vec<BitemporalRecord>,
+}
+
+/// Counts finished ID groups for `ProcessingOptions::progress` and checks for cancellation
+struct GroupProgress<'a> {
+    options: &'a ProcessingOptions,
+    total: usize,
+    done: std::sync::atomic::AtomicUsize,
+    reported: std::sync::atomic::AtomicUsize,
+}
+
+impl<'a> GroupProgress<'a> {
+    fn new(options: &'a ProcessingOptions, total: usize) -> Self {
+        GroupProgress { options, total, done: Default::default(), reported: Default::default() }
+    }
+
+    fn check_cancelled(&self) -> Result<(), String> {
+        match &self.options.cancellation {
+            Some(token) if token.is_cancelled() => Err("Processing cancelled".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn group_done(&self) {
+        self.groups_done(1);
+    }
+
+    fn groups_done(&self, count: usize) {
+        use std::sync::atomic::Ordering;
+        let Some(progress) = &self.options.progress else {
+            return;
+        };
+        let done = self.done.fetch_add(count, Ordering::Relaxed) + count;
+        let percent = done * 100 / self.total;
+        if self.reported.fetch_max(percent, Ordering::Relaxed) < percent {
+            progress.report(percent as f64);
+        }
+    }
+}
+
+/// ID key -> (current state row indices, update row indices)
+type IdGroups = FxHashMap<String, (Vec<usize>, Vec<usize>)>;
 
 
 
@@ -54,6 +208,7 @@ pub fn process_updates(
     process_updates_with_algorithm(current_state, updates, id_columns, value_columns, system_date, update_mode, HashAlgorithm::default(), conflate_inputs)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn process_updates_with_algorithm(
     current_state: RecordBatch,
     updates: RecordBatch,
@@ -64,77 +219,723 @@ pub fn process_updates_with_algorithm(
     algorithm: HashAlgorithm,
     conflate_inputs: bool,
 ) -> Result<ChangeSet, String> {
-    let start_time = std::time::Instant::now();
+    let options = ProcessingOptions {
+        hash_algorithm: algorithm,
+        conflate_inputs,
+        ..Default::default()
+    };
+    process_updates_with_options(current_state, updates, id_columns, value_columns, system_date, update_mode, &options)
+}
+
+/// Empty `value_columns` are inferred from the updates schema: every column other than the
+/// ID, temporal and hash columns and the columns named by `options` (priority, tombstone
+/// cutoff, lineage) is tracked.
+pub fn process_updates_with_options(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    if let Some(system_time) = options.system_time {
+        if system_time.date() != system_date {
+            return Err(format!("system_time {} does not fall on system_date {}", system_time, system_date));
+        }
+    }
+    if options.consolidation_target_rows == 0 {
+        return Err("consolidation_target_rows must be at least 1".to_string());
+    }
+    if options.output_sharding.as_ref().is_some_and(|sharding| sharding.shards == 0) {
+        return Err("output_sharding.shards must be at least 1".to_string());
+    }
+    if options.deletion_column.is_some() && update_mode != UpdateMode::FullState {
+        return Err("deletion_column is only supported in full_state mode".to_string());
+    }
+    if let Some(ledger) = &options.idempotency {
+        if let Some(changeset) = crate::ledger::recorded_changeset(ledger)? {
+            tracing::info!(batch_id = %ledger.batch_id, "batch already processed, replaying its changeset");
+            return Ok(changeset);
+        }
+    }
+
+    let (id_columns, value_columns) = crate::selection::resolve_column_patterns(
+        &updates.schema(), id_columns, value_columns, options
+    )?;
+
+    // A custom hash column is processed as `value_hash` and renamed back on the way out
+    let custom_hash_column = options.hash_column != VALUE_HASH_COLUMN;
+    let (current_state, updates) = if custom_hash_column {
+        (
+            crate::batch_utils::rename_column(current_state, &options.hash_column, VALUE_HASH_COLUMN)?,
+            crate::batch_utils::rename_column(updates, &options.hash_column, VALUE_HASH_COLUMN)?,
+        )
+    } else {
+        (current_state, updates)
+    };
+
+    // Run-end encoded inputs are expanded; outputs carry the plain value types
+    let current_state = crate::batch_utils::normalize_input_layouts(current_state)?;
+    let updates = crate::batch_utils::normalize_input_layouts(updates)?;
+
+    // A full history table is narrowed to its open rows; `to_expire` is mapped back below
+    let (current_state, open_rows) = if options.open_current_rows_only {
+        let (current_state, open_rows) = crate::batch_utils::filter_open_rows(current_state)?;
+        (current_state, Some(open_rows))
+    } else {
+        (current_state, None)
+    };
+
+    let shard_id_columns = options.output_sharding.is_some().then(|| id_columns.clone());
+
+    // Inclusive-end tables are processed as closed-open ranges and converted back on the way out
+    let mut changeset = match options.interval_end {
+        IntervalEnd::Exclusive => {
+            process_verified_updates(current_state, updates, id_columns, value_columns, system_date, update_mode, options)?
+        }
+        IntervalEnd::Inclusive { step } => {
+            let current_state = crate::batch_utils::shift_effective_to(current_state, step)?;
+            let updates = crate::batch_utils::shift_effective_to(updates, step)?;
+            let changeset = process_verified_updates(current_state, updates, id_columns, value_columns, system_date, update_mode, options)?;
+            map_output_batches(changeset, |batch| crate::batch_utils::shift_effective_to(batch, -step))?
+        }
+    };
+
+    if let Some(open_rows) = open_rows {
+        for idx in changeset.to_expire.iter_mut() {
+            *idx = open_rows[*idx];
+        }
+    }
+
+    if options.exclude_hash_column {
+        changeset = map_output_batches(changeset, |batch| crate::batch_utils::drop_column(batch, VALUE_HASH_COLUMN))?;
+    } else if custom_hash_column {
+        changeset = map_output_batches(changeset, |batch| {
+            crate::batch_utils::rename_column(batch, VALUE_HASH_COLUMN, &options.hash_column)
+        })?;
+    }
+
+    if let Some(deletion_column) = &options.deletion_column {
+        changeset = map_output_batches(changeset, |batch| crate::batch_utils::drop_column(batch, deletion_column))?;
+    }
+
+    if !options.lineage_columns.is_empty() {
+        changeset = map_output_batches(changeset, |batch| {
+            crate::batch_utils::stamp_constant_columns(batch, &options.lineage_columns)
+        })?;
+    }
+
+    if let Some(tenant_column) = &options.tenant_column {
+        changeset.metrics.tenant_stats = crate::tenant::tenant_stats(&changeset, tenant_column)?;
+    }
+
+    if let (Some(sharding), Some(id_columns)) = (&options.output_sharding, shard_id_columns) {
+        changeset = crate::sharding::shard_output(changeset, &id_columns, sharding)?;
+    }
+
+    if let Some(ledger) = &options.idempotency {
+        crate::ledger::record_changeset(ledger, &changeset)?;
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    crate::telemetry::record(&changeset.metrics, update_mode);
+    Ok(changeset)
+}
+
+/// Apply `f` to every inserted and expired batch of a changeset, and to its output schemas
+fn map_output_batches(
+    mut changeset: ChangeSet,
+    f: impl Fn(RecordBatch) -> Result<RecordBatch, String>,
+) -> Result<ChangeSet, String> {
+    changeset.to_insert = changeset.to_insert.into_iter().map(&f).collect::<Result<_, _>>()?;
+    changeset.expired_records = changeset.expired_records.into_iter().map(&f).collect::<Result<_, _>>()?;
+    let map_schema = |schema: Option<arrow::datatypes::SchemaRef>| {
+        schema.map(|schema| f(RecordBatch::new_empty(schema)).map(|batch| batch.schema())).transpose()
+    };
+    changeset.insert_schema = map_schema(changeset.insert_schema.take())?;
+    changeset.expired_schema = map_schema(changeset.expired_schema.take())?;
+    Ok(changeset)
+}
+
+/// `process_closed_open_updates`, checking the changeset's invariants afterwards when
+/// `options.verify_invariants` is set
+fn process_verified_updates(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    if !options.verify_invariants {
+        return process_closed_open_updates(current_state, updates, id_columns, value_columns, system_date, update_mode, options);
+    }
+
+    let inputs = (current_state.clone(), updates.clone(), id_columns.clone());
+    let changeset = process_closed_open_updates(current_state, updates, id_columns, value_columns, system_date, update_mode, options)?;
+    let (current_state, updates, id_columns) = inputs;
+    // Update coverage is only comparable when the updates are applied as passed: not chained,
+    // filled, clamped, bridged across gaps, dropped or losing intra-batch conflicts
+    let updates_as_passed = options.input_mode == InputMode::Ranges
+        && updates.column_by_name("effective_to").is_some()
+        && options.min_effective_from.is_none()
+        && options.clamp_from.is_none()
+        && options.clamp_to.is_none()
+        && !(options.conflate_inputs && options.conflate_gap_tolerance > chrono::Duration::zero())
+        && options.validation == ValidationMode::Off
+        && options.deletion_column.is_none()
+        && (options.hash_groups.is_empty() || options.hash_group_change == HashGroupChange::Any)
+        && changeset.overridden_updates.is_empty();
+    crate::invariants::verify(&current_state, updates_as_passed.then_some(&updates), &changeset, &id_columns, update_mode)?;
+    Ok(changeset)
+}
+
+fn process_closed_open_updates(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    let _run_span = tracing::info_span!(
+        "process_updates",
+        mode = ?update_mode,
+        current_rows = current_state.num_rows(),
+        update_rows = updates.num_rows(),
+    ).entered();
+    let start = std::time::Instant::now();
+    let mut metrics = ProcessingMetrics {
+        current_rows: current_state.num_rows(),
+        update_rows: updates.num_rows(),
+        ..Default::default()
+    };
+
+    // Generate consistent timestamp for all operations in this batch (a resumed run keeps
+    // the one its checkpoint was started with)
+    let resumed_timestamp = match &options.checkpoint {
+        Some(checkpoint) => crate::checkpoint::resumed_batch_timestamp(checkpoint)?,
+        None => None,
+    };
+    let batch_timestamp = resumed_timestamp.unwrap_or_else(|| options.clock.now());
 
     // Phase 0: Input validation and preprocessing
-    let (current_state, updates, batch_timestamp) = prepare_inputs(
-        current_state, updates, &value_columns, algorithm, &id_columns, conflate_inputs
+    reject_null_keys(&current_state, &id_columns, "current state", options.null_ids)?;
+    // With validation on, bad update rows are reported (or dropped) by the validation pass
+    if options.validation == ValidationMode::Off {
+        reject_null_keys(&updates, &id_columns, "updates", options.null_ids)?;
+    }
+    let updates = if options.input_mode == InputMode::Events {
+        crate::conflation::chain_event_updates(updates, &id_columns)?
+    } else {
+        updates
+    };
+    let updates = crate::batch_utils::populate_missing_temporal_columns(
+        updates, &current_state.schema(), &options.missing_temporal_columns, batch_timestamp
+    )?;
+    if options.strict_schemas {
+        check_schemas_match(&current_state, &updates, options)?;
+    }
+    let (updates, validation_report) = validate_update_rows(updates, &id_columns, options)?;
+    let updates = apply_effective_watermark(updates, options.min_effective_from, options.watermark_policy)?;
+    let (updates, clamped_updates) = apply_clamp_window(updates, options.clamp_from, options.clamp_to)?;
+    metrics.clamped_updates = clamped_updates.len();
+    let value_columns = if value_columns.is_empty() {
+        infer_value_columns(&updates, &id_columns, options)?
+    } else {
+        value_columns
+    };
+    if let Some(column) = options.exact_float_columns.iter().find(|column| !value_columns.contains(column)) {
+        return Err(format!("Exact float column '{}' is not a value column", column));
+    }
+    if let Some(tenant_column) = &options.tenant_column {
+        crate::tenant::check_tenant_isolation(&current_state, &updates, &id_columns, &value_columns, tenant_column)?;
+    }
+    let (current_state, updates) = crate::hash_groups::attach_group_hashes(current_state, updates, &value_columns, options)?;
+    let (updates, partial_matches) = crate::hash_groups::drop_partial_matches(&current_state, updates, &id_columns, options)?;
+    let prepared_update_rows = updates.num_rows();
+    let phase_start = std::time::Instant::now();
+    let (current_state, cache_current_hashes) = crate::hash_cache::attach_cached_hashes(
+        current_state, &value_columns, options.hash_spec(), options.hash_cache.as_ref()
+    )?;
+    let (current_state, updates, columns, id_groups, unhashed_current_rows, conflated_rows) = prepare_inputs(
+        current_state, updates, &value_columns, options.hash_spec(), &id_columns,
+        options.conflate_inputs, options.conflate_gap_tolerance,
+        options.lazy_hashing && update_mode == UpdateMode::Delta,
+    )?;
+    let prepare_duration = phase_start.elapsed();
+    metrics.unhashed_current_rows = unhashed_current_rows;
+    if let Some(cache) = options.hash_cache.as_ref().filter(|_| cache_current_hashes && unhashed_current_rows == 0) {
+        crate::hash_cache::cache_hashes(cache, &current_state, &value_columns, options.hash_spec())?;
+    }
+    metrics.conflated_updates = prepared_update_rows - updates.num_rows();
+    let (updates, tolerated_updates) = crate::tolerance::snap_tolerated_updates(
+        &current_state, updates, &id_columns, &value_columns, options
     )?;
+    metrics.tolerated_updates = tolerated_updates;
+    let reports = UpdateReports {
+        conflated_rows, prepared_update_rows, partial_matches, clamped_updates, validation_report,
+    };
     
     // Handle quick paths for empty inputs
     if let Some(changeset) = handle_empty_inputs(
-        &current_state, &updates, &value_columns, system_date, update_mode, batch_timestamp
+        &current_state, &updates, &id_columns, &value_columns, system_date, update_mode, batch_timestamp, options
     )? {
-        return Ok(changeset);
+        return finish_changeset(
+            changeset, &current_state, &updates, &id_columns, &value_columns, reports, metrics, start, options
+        );
     }
     
-    // Phase 1: ID Grouping with performance optimizations
-    let phase1_start = std::time::Instant::now();
-    let id_groups = build_id_groups(&current_state, &updates, &id_columns)?;
-    let _phase1_total = phase1_start.elapsed();
+    // Value rows backing the collision check on matching hashes
+    let value_rows = if options.verifies_hash_matches() {
+        Some(crate::arrow_hash::ValueRows::new(&current_state, &updates, &value_columns)?)
+    } else {
+        None
+    };
+
+    // Phase 1: ID Grouping with performance optimizations (usually already done alongside hashing)
+    let phase_start = std::time::Instant::now();
+    let id_groups = match id_groups {
+        Some(id_groups) => {
+            metrics.group_ids_duration = prepare_duration;
+            id_groups
+        }
+        None => {
+            let id_groups = tracing::debug_span!("group_ids")
+                .in_scope(|| build_id_groups(&current_state, &updates, &id_columns))?;
+            metrics.group_ids_duration = phase_start.elapsed();
+            id_groups
+        }
+    };
+    metrics.id_groups = id_groups.len();
+    tracing::debug!(id_groups = id_groups.len(), "grouped rows by ID");
     
     // Phase 2: Process ID groups with optimized parallel/serial strategy
-    let phase2_start = std::time::Instant::now();
-    let (to_expire, to_insert) = process_all_id_groups(
-        id_groups, &current_state, &updates, &id_columns, &value_columns,
-        system_date, update_mode, batch_timestamp
-    )?;
-    let _phase2_total = phase2_start.elapsed();
+    let phase_start = std::time::Instant::now();
+    let progress = GroupProgress::new(options, id_groups.len());
+    let process_groups = |id_groups| process_all_id_groups(
+        id_groups, &current_state, &updates, &columns, &id_columns, &value_columns,
+        system_date, update_mode, batch_timestamp, options, value_rows.as_ref(), &progress
+    );
+    let group_results = tracing::debug_span!("process_id_groups").in_scope(|| match &options.checkpoint {
+        Some(checkpoint) => {
+            let identity = crate::checkpoint::RunIdentity {
+                system_date, update_mode, id_columns: &id_columns, value_columns: &value_columns,
+            };
+            crate::checkpoint::process_with_checkpoints(checkpoint, id_groups, identity, batch_timestamp, &progress, process_groups)
+        }
+        None => process_groups(id_groups),
+    })?;
+    metrics.process_groups_duration = phase_start.elapsed();
     
     // Phase 3: Post-processing and changeset building
-    let phase3_start = std::time::Instant::now();
-    let changeset = build_final_changeset(
-        to_expire, to_insert, &current_state, batch_timestamp, &id_columns
-    )?;
-    let _phase3_total = phase3_start.elapsed();
-    
-    let _total_time = start_time.elapsed();
+    let phase_start = std::time::Instant::now();
+    let changeset = tracing::debug_span!("build_changeset").in_scope(|| build_final_changeset(
+        group_results, &current_state, batch_timestamp, &id_columns, options
+    ))?;
+    metrics.build_changeset_duration = phase_start.elapsed();
+
+    finish_changeset(
+        changeset, &current_state, &updates, &id_columns, &value_columns, reports, metrics, start, options
+    )
+}
 
+/// Fail on null temporal values, and on null IDs under `NullIdPolicy::Reject`, naming the rows
+fn reject_null_keys(batch: &RecordBatch, id_columns: &[String], side: &str, null_ids: NullIdPolicy) -> Result<(), String> {
+    for column in ["effective_from", "effective_to", "as_of_from"] {
+        if let Some(rows) = batch.column_by_name(column).and_then(crate::batch_utils::null_rows) {
+            return Err(format!("Null {} in {} at row(s) {}", column, side, rows));
+        }
+    }
+    if null_ids == NullIdPolicy::Reject {
+        for column in id_columns {
+            if let Some(rows) = batch.column_by_name(column).and_then(crate::batch_utils::null_rows) {
+                return Err(format!("Null ID column '{}' in {} at row(s) {} (null_ids is Reject)", column, side, rows));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What preparing the updates recorded about their rows, reported once the changeset is built
+struct UpdateReports {
+    conflated_rows: Option<Vec<usize>>,
+    prepared_update_rows: usize,
+    partial_matches: Option<crate::hash_groups::PartialMatches>,
+    clamped_updates: Vec<usize>,
+    validation_report: Option<ValidationReport>,
+}
+
+/// Post-processing shared by every way a changeset is built: split inserts at calendar
+/// boundaries, then attach the changed columns, row reports, output schemas and metrics
+#[allow(clippy::too_many_arguments)]
+fn finish_changeset(
+    changeset: ChangeSet,
+    current_state: &RecordBatch,
+    updates: &RecordBatch,
+    id_columns: &[String],
+    value_columns: &[String],
+    reports: UpdateReports,
+    metrics: ProcessingMetrics,
+    start: std::time::Instant,
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    let changeset = crate::calendar::split_inserts_at_boundaries(changeset, options.segment_boundary)?;
+    let changeset = attach_changed_columns(changeset, id_columns, value_columns, options)?;
+    let changeset = attach_conflation_report(changeset, reports.conflated_rows, reports.prepared_update_rows, options);
+    let mut changeset = crate::hash_groups::attach_partial_matches(changeset, reports.partial_matches, options);
+    changeset.clamped_updates = reports.clamped_updates;
+    let changeset = attach_validation_report(changeset, reports.validation_report, options);
+    let changeset = attach_output_schemas(changeset, current_state, updates, id_columns, value_columns, options)?;
+    Ok(attach_metrics(changeset, metrics, start))
+}
+
+/// Complete `metrics` with the output counts and total time and attach them to the changeset
+fn attach_metrics(mut changeset: ChangeSet, mut metrics: ProcessingMetrics, start: std::time::Instant) -> ChangeSet {
+    metrics.expired_rows = changeset.to_expire.len();
+    metrics.inserted_rows = changeset.to_insert.iter().map(|batch| batch.num_rows()).sum();
+    metrics.total_duration = start.elapsed();
+    tracing::info!(expired = metrics.expired_rows, inserted = metrics.inserted_rows, "changeset built");
+    changeset.metrics = metrics;
+    changeset
+}
+
+/// Record the schemas inserts and expired records are written with, derived from the prepared
+/// inputs rather than the batches so a side without rows still has one
+fn attach_output_schemas(
+    mut changeset: ChangeSet,
+    current_state: &RecordBatch,
+    updates: &RecordBatch,
+    id_columns: &[String],
+    value_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    // Empty inputs are never hashed, so their templates get the hash column here
+    let template = |batch: &RecordBatch| -> Result<RecordBatch, String> {
+        let template = batch.slice(0, 0);
+        if template.column_by_name(VALUE_HASH_COLUMN).is_some() {
+            return Ok(template);
+        }
+        let hashes = std::sync::Arc::new(arrow::array::StringArray::from(Vec::<String>::new()));
+        crate::arrow_hash::with_hash_column(&template, hashes, options.hash_spec())
+    };
+
+    // Inserts are consolidated, which widens their fields to nullable
+    let consolidated = crate::conflation::compute_unified_schema(&[template(updates)?]);
+    let mut insert_template = vec![RecordBatch::new_empty(std::sync::Arc::new(consolidated))];
+    if options.report_changed_columns {
+        insert_template = crate::batch_utils::append_changed_columns(insert_template, &[], id_columns, value_columns)?;
+    }
+    changeset.insert_schema = Some(insert_template[0].schema());
+
+    let expired_schema = template(current_state)?.schema();
+    changeset.expired_schema = Some(if options.report_expire_reasons {
+        let mut fields = expired_schema.fields().to_vec();
+        fields.push(std::sync::Arc::new(arrow::datatypes::Field::new(EXPIRE_REASON_COLUMN, arrow::datatypes::DataType::Utf8, false)));
+        std::sync::Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, expired_schema.metadata().clone()))
+    } else {
+        expired_schema
+    });
+    Ok(changeset)
+}
+
+/// Append the `changed_columns` list to the inserts when `options.report_changed_columns` is set
+fn attach_changed_columns(
+    mut changeset: ChangeSet,
+    id_columns: &[String],
+    value_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<ChangeSet, String> {
+    if options.report_changed_columns {
+        changeset.to_insert = crate::batch_utils::append_changed_columns(
+            std::mem::take(&mut changeset.to_insert),
+            &changeset.expired_records,
+            id_columns,
+            value_columns,
+        )?;
+    }
     Ok(changeset)
 }
 
-/// Prepare inputs by ensuring hash columns exist and generating batch timestamp
+/// Map update indices of the conflated batch back to the rows conflation was given and, when
+/// skipped updates are reported, list the rows it merged into a neighbour
+fn attach_conflation_report(
+    mut changeset: ChangeSet,
+    conflated_rows: Option<Vec<usize>>,
+    input_rows: usize,
+    options: &ProcessingOptions,
+) -> ChangeSet {
+    let Some(kept_rows) = conflated_rows else {
+        return changeset;
+    };
+
+    for idx in changeset.overridden_updates.iter_mut() {
+        *idx = kept_rows[*idx];
+    }
+    for skipped in changeset.skipped_updates.iter_mut() {
+        skipped.update_index = kept_rows[skipped.update_index];
+    }
+
+    if options.report_skipped_updates {
+        // `kept_rows` is ascending, so one pass finds the rows missing from it
+        let mut kept = kept_rows.iter().peekable();
+        let merged_rows = (0..input_rows).filter(|row| kept.next_if_eq(&row).is_none());
+        changeset.skipped_updates.extend(merged_rows
+            .map(|update_index| SkippedUpdate { update_index, reason: SkipReason::Conflated }));
+        changeset.skipped_updates.sort_by_key(|skipped| skipped.update_index);
+    }
+    changeset
+}
+
+/// Issues found by lenient validation plus the original index of every kept update row
+type ValidationReport = (Vec<ValidationIssue>, Vec<usize>);
+
+/// `ProcessingOptions::strict_schemas`: fail with every difference between the two schemas
+fn check_schemas_match(current_state: &RecordBatch, updates: &RecordBatch, options: &ProcessingOptions) -> Result<(), String> {
+    let one_sided = |column: &str| {
+        column == options.hash_column
+            || options.hash_groups.iter().any(|group| group.column == column)
+            || options.tombstone_cutoff_column.as_deref() == Some(column)
+            || options.deletion_column.as_deref() == Some(column)
+            || matches!(&options.conflict_resolution, ConflictResolution::Priority(priority) if priority == column)
+    };
+    let differences = crate::validation::schema_differences(&current_state.schema(), &updates.schema(), one_sided);
+    match crate::validation::format_schema_error(&differences) {
+        Some(message) => Err(message),
+        None => Ok(()),
+    }
+}
+
+/// Run the optional row-level validation pass over the updates, plus the empty-range check
+/// unless `options.empty_ranges` leaves those rows to be dropped during processing.
+/// Strict mode fails on any issue; lenient mode drops the offending rows.
+fn validate_update_rows(
+    updates: RecordBatch,
+    id_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<(RecordBatch, Option<ValidationReport>), String> {
+    let mut issues = match options.validation {
+        ValidationMode::Off => Vec::new(),
+        _ => validate_updates(&updates, id_columns)?,
+    };
+    if options.empty_ranges != EmptyRangePolicy::Drop {
+        let empty_ranges = crate::validation::empty_range_issues(&updates, &issues);
+        if options.empty_ranges == EmptyRangePolicy::Error && !empty_ranges.is_empty() {
+            return Err(crate::validation::format_validation_error(&empty_ranges));
+        }
+        issues.extend(empty_ranges);
+        issues.sort_by_key(|issue| issue.row_index);
+    }
+
+    if issues.is_empty() {
+        return Ok((updates, None));
+    }
+    if options.validation == ValidationMode::Strict {
+        return Err(crate::validation::format_validation_error(&issues));
+    }
+
+    let (updates, kept_rows) = crate::validation::drop_invalid_rows(updates, &issues)?;
+    Ok((updates, Some((issues, kept_rows))))
+}
+
+/// Map update indices back to the caller's rows and attach the lenient validation issues
+fn attach_validation_report(
+    mut changeset: ChangeSet,
+    report: Option<ValidationReport>,
+    options: &ProcessingOptions,
+) -> ChangeSet {
+    let Some((issues, kept_rows)) = report else {
+        return changeset;
+    };
+
+    // Clamping runs before conflation, so its indices always map back
+    for idx in changeset.clamped_updates.iter_mut() {
+        *idx = kept_rows[*idx];
+    }
+
+    for idx in changeset.overridden_updates.iter_mut() {
+        *idx = kept_rows[*idx];
+    }
+    for skipped in changeset.skipped_updates.iter_mut() {
+        skipped.update_index = kept_rows[skipped.update_index];
+    }
+
+    if options.report_skipped_updates {
+        let mut invalid_rows: Vec<(usize, SkipReason)> = issues.iter()
+            .map(|issue| match issue.kind {
+                ValidationIssueKind::EmptyRange => (issue.row_index, SkipReason::EmptyRange),
+                _ => (issue.row_index, SkipReason::InvalidRow),
+            })
+            .collect();
+        invalid_rows.dedup_by_key(|(row, _)| *row);
+        changeset.skipped_updates.extend(invalid_rows.into_iter()
+            .map(|(update_index, reason)| SkippedUpdate { update_index, reason }));
+        changeset.skipped_updates.sort_by_key(|skipped| skipped.update_index);
+    }
+
+    changeset.validation_issues = issues;
+    changeset
+}
+
+/// Every update column that isn't an ID, temporal, hash or option-named bookkeeping column
+fn infer_value_columns(updates: &RecordBatch, id_columns: &[String], options: &ProcessingOptions) -> Result<Vec<String>, String> {
+    let mut excluded: Vec<&str> = id_columns.iter().map(String::as_str).collect();
+    excluded.extend(TEMPORAL_COLUMNS);
+    excluded.push(VALUE_HASH_COLUMN);
+    excluded.extend(options.hash_groups.iter().map(|group| group.column.as_str()));
+    excluded.extend(options.lineage_columns.iter().map(|(column, _)| column.as_str()));
+    excluded.extend(options.tombstone_cutoff_column.as_deref());
+    excluded.extend(options.tenant_column.as_deref());
+    excluded.extend(options.deletion_column.as_deref());
+    if let ConflictResolution::Priority(column) = &options.conflict_resolution {
+        excluded.push(column);
+    }
+
+    let value_columns: Vec<String> = updates.schema().fields().iter()
+        .map(|field| field.name())
+        .filter(|name| !excluded.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    if value_columns.is_empty() {
+        return Err("No value_columns given and the updates have no other columns to infer them from".to_string());
+    }
+    Ok(value_columns)
+}
+
+/// Inputs with their hashes and column positions, the ID groups when built, how many
+/// current rows lazy hashing skipped and, when conflation merged rows, the update row behind
+/// each conflated one
+type PreparedInputs = (RecordBatch, RecordBatch, crate::batch_utils::InputColumns, Option<IdGroups>, usize, Option<Vec<usize>>);
+
+/// Prepare inputs by ensuring hash columns exist and generating batch timestamp.
+/// Unless the updates are conflated (which needs their hashes first), the ID groups are built
+/// while the hashes are computed; empty inputs take the quick paths and are not grouped.
+/// With `lazy_hashing` the groups are built first and only touched current rows are hashed.
+#[allow(clippy::too_many_arguments)]
 fn prepare_inputs(
     current_state: RecordBatch,
     updates: RecordBatch,
     value_columns: &[String],
-    algorithm: HashAlgorithm,
+    spec: crate::arrow_hash::HashSpec<'_>,
     id_columns: &[String],
     conflate_inputs: bool,
-) -> Result<(RecordBatch, RecordBatch, chrono::NaiveDateTime), String> {
-    // Ensure value_hash columns are computed if missing or empty
-    let current_state = ensure_hash_column_with_algorithm(current_state, value_columns, algorithm)?;
-    let mut updates = ensure_hash_column_with_algorithm(updates, value_columns, algorithm)?;
+    conflate_gap_tolerance: chrono::Duration,
+    lazy_hashing: bool,
+) -> Result<PreparedInputs, String> {
+    let mut unhashed_current_rows = 0;
+    let (current_state, mut updates, id_groups) =
+        if !conflate_inputs && current_state.num_rows() > 0 && updates.num_rows() > 0 {
+            let (current_state, updates, id_groups) = if lazy_hashing {
+                let (current_state, updates, id_groups, unhashed) =
+                    hash_touched_groups(current_state, updates, value_columns, spec, id_columns)?;
+                unhashed_current_rows = unhashed;
+                (current_state, updates, id_groups)
+            } else {
+                hash_while_grouping(current_state, updates, value_columns, spec, id_columns)?
+            };
+            (current_state, updates, Some(id_groups))
+        } else {
+            // Ensure value_hash columns are computed if missing or empty
+            let current_state = ensure_hash_column_with_algorithm(current_state, value_columns, spec)?;
+            let updates = ensure_hash_column_with_algorithm(updates, value_columns, spec)?;
+            (current_state, updates, None)
+        };
 
     // Optionally conflate consecutive input updates with same ID and value hash
+    let mut conflated_rows = None;
     if conflate_inputs && updates.num_rows() > 1 {
-        updates = conflate_input_updates(updates, id_columns)?;
+        (updates, conflated_rows) = conflate_input_updates(updates, id_columns, conflate_gap_tolerance)?;
     }
 
-    // Generate consistent timestamp for all operations in this batch
-    let batch_timestamp = chrono::Utc::now().naive_utc();
+    // Resolve the hot-path column positions once, now that the schemas are final
+    let columns = crate::batch_utils::InputColumns {
+        current: crate::batch_utils::ColumnIndices::new(&current_state, id_columns),
+        updates: crate::batch_utils::ColumnIndices::new(&updates, id_columns),
+    };
+    Ok((current_state, updates, columns, id_groups, unhashed_current_rows, conflated_rows))
+}
+
+/// Compute the missing value hashes of both inputs on background threads while the ID
+/// groups are built from the (unchanged) ID columns
+fn hash_while_grouping(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    value_columns: &[String],
+    spec: crate::arrow_hash::HashSpec<'_>,
+    id_columns: &[String],
+) -> Result<(RecordBatch, RecordBatch, IdGroups), String> {
+    let hash_current = needs_value_hash(&current_state, spec)?;
+    let hash_updates = needs_value_hash(&updates, spec)?;
+    let to_hash: Vec<&RecordBatch> = [(hash_current, &current_state), (hash_updates, &updates)].into_iter()
+        .filter_map(|(needed, batch)| needed.then_some(batch))
+        .collect();
+
+    let (hashes, id_groups) = crate::pipeline::hash_alongside(&to_hash, value_columns, spec, || {
+        tracing::debug_span!("group_ids").in_scope(|| build_id_groups(&current_state, &updates, id_columns))
+    })?;
+    let id_groups = id_groups?;
+
+    let mut hashes = hashes.into_iter();
+    let mut attach = |batch: RecordBatch, needed: bool| match needed.then(|| hashes.next()).flatten() {
+        Some(hash_array) => crate::arrow_hash::with_hash_column(&batch, hash_array, spec),
+        None => Ok(batch),
+    };
+    let current_state = attach(current_state, hash_current)?;
+    let updates = attach(updates, hash_updates)?;
+    Ok((current_state, updates, id_groups))
+}
+
+/// Build the ID groups, then hash the updates and only those current rows whose ID also has
+/// update rows. Untouched current rows get an empty hash and are counted in the result.
+fn hash_touched_groups(
+    current_state: RecordBatch,
+    updates: RecordBatch,
+    value_columns: &[String],
+    spec: crate::arrow_hash::HashSpec<'_>,
+    id_columns: &[String],
+) -> Result<(RecordBatch, RecordBatch, IdGroups, usize), String> {
+    let id_groups = tracing::debug_span!("group_ids").in_scope(|| build_id_groups(&current_state, &updates, id_columns))?;
+    let updates = ensure_hash_column_with_algorithm(updates, value_columns, spec)?;
+    if !needs_value_hash(&current_state, spec)? {
+        return Ok((current_state, updates, id_groups, 0));
+    }
+    for col_name in value_columns {
+        if current_state.schema().index_of(col_name).is_err() {
+            return Err(format!("Column '{}' not found in RecordBatch", col_name));
+        }
+    }
 
-    Ok((current_state, updates, batch_timestamp))
+    let mut touched: Vec<usize> = id_groups.values()
+        .filter(|(_, update_rows)| !update_rows.is_empty())
+        .flat_map(|(current_rows, _)| current_rows.iter().copied())
+        .collect();
+    touched.sort_unstable();
+    let hashes = crate::arrow_hash::hash_values_batch_arrow_direct(&current_state, &touched, value_columns, spec);
+    let mut values = vec![""; current_state.num_rows()];
+    for (&row, hash) in touched.iter().zip(&hashes) {
+        values[row] = hash;
+    }
+    let unhashed = current_state.num_rows() - touched.len();
+    let current_state = crate::arrow_hash::with_hash_column(
+        &current_state, std::sync::Arc::new(arrow::array::StringArray::from(values)), spec
+    )?;
+    Ok((current_state, updates, id_groups, unhashed))
 }
 
 /// Handle quick paths for empty input cases
+#[allow(clippy::too_many_arguments)]
 fn handle_empty_inputs(
     current_state: &RecordBatch,
     updates: &RecordBatch,
+    id_columns: &[String],
     value_columns: &[String],
     system_date: NaiveDate,
     update_mode: UpdateMode,
     batch_timestamp: chrono::NaiveDateTime,
+    options: &ProcessingOptions,
 ) -> Result<Option<ChangeSet>, String> {
     // No updates - handle based on mode
     if updates.num_rows() == 0 {
@@ -143,55 +944,81 @@ fn handle_empty_inputs(
             // Filter to only include records where effective_from <= system_date
             // (records with effective_from > system_date would create invalid ranges)
             let all_indices: Vec<usize> = (0..current_state.num_rows()).collect();
-            let tombstone_indices = filter_indices_for_tombstoning(
+            let in_window_indices = filter_indices_to_window(
                 current_state,
                 &all_indices,
+                options.full_state_window,
+            )?;
+            let after_watermark_indices = filter_indices_after_watermark(
+                current_state,
+                &in_window_indices,
+                options.min_effective_from,
+            )?;
+            let present_partitions = collect_partition_keys(updates, &options.partition_columns)?;
+            let in_partition_indices = filter_indices_to_partitions(
+                current_state,
+                &after_watermark_indices,
+                &options.partition_columns,
+                present_partitions.as_ref(),
+            )?;
+            let tombstones = filter_indices_for_tombstoning(
+                current_state,
+                &in_partition_indices,
                 system_date,
+                options,
             )?;
 
             // If no valid records to tombstone, return empty changeset
-            if tombstone_indices.is_empty() {
-                return Ok(Some(ChangeSet {
-                    to_expire: Vec::new(),
-                    to_insert: Vec::new(),
-                    expired_records: Vec::new(),
-                }));
+            if tombstones.expired.is_empty() {
+                return Ok(Some(ChangeSet::default()));
             }
 
-            let tombstone_batch = create_tombstone_records_optimized(
-                &tombstone_indices,
-                current_state,
-                value_columns,
-                system_date,
-                batch_timestamp,
-            )?;
+            let to_insert = if tombstones.kept.is_empty() {
+                Vec::new()
+            } else {
+                let tombstone_records = create_tombstone_records_optimized(
+                    &tombstones.kept,
+                    current_state,
+                    value_columns,
+                    batch_timestamp,
+                )?;
+                post_process_inserts(vec![tombstone_records], id_columns, options)?
+            };
+            let tombstone_indices = tombstones.expired;
+            let reasons = vec![ExpireReason::Tombstoned; tombstone_indices.len()];
 
             let expired_batch = crate::batch_utils::create_expired_records_batch(
                 current_state,
                 &tombstone_indices,
-                batch_timestamp
+                batch_timestamp,
+                options.report_expire_reasons.then_some(&reasons[..]),
             )?;
 
             Ok(Some(ChangeSet {
                 to_expire: tombstone_indices,
-                to_insert: vec![tombstone_batch],
+                to_insert,
                 expired_records: vec![expired_batch],
+                ..Default::default()
             }))
         } else {
-            Ok(Some(ChangeSet {
-                to_expire: Vec::new(),
-                to_insert: Vec::new(),
-                expired_records: Vec::new(),
-            }))
+            Ok(Some(ChangeSet::default()))
         };
     }
     
     // No current state - all updates become inserts
-    if current_state.num_rows() == 0 {
+    // (unless overlapping updates must be resolved against each other first,
+    // updates before the watermark have to be dropped, or empty ranges skipped)
+    if current_state.num_rows() == 0
+        && options.conflict_resolution == ConflictResolution::EventOrder
+        && options.min_effective_from.is_none()
+        && options.clamp_from.is_none()
+        && options.clamp_to.is_none()
+        && options.deletion_column.is_none()
+        && crate::validation::empty_range_issues(updates, &[]).is_empty()
+    {
         return Ok(Some(ChangeSet {
-            to_expire: Vec::new(),
-            to_insert: vec![updates.clone()],
-            expired_records: Vec::new(),
+            to_insert: post_process_inserts(vec![updates.clone()], id_columns, options)?,
+            ..Default::default()
         }));
     }
     
@@ -206,20 +1033,18 @@ fn build_id_groups(
     current_state: &RecordBatch,
     updates: &RecordBatch,
     id_columns: &[String],
-) -> Result<FxHashMap<String, (Vec<usize>, Vec<usize>)>, String> {
+) -> Result<IdGroups, String> {
     // Pre-size FxHashMap with estimated capacity for better performance
     // Estimate: Most datasets have 10-50% unique ID combinations
     let estimated_unique_ids = ((current_state.num_rows() + updates.num_rows()) / 3).max(16);
-    let mut id_groups: FxHashMap<String, (Vec<usize>, Vec<usize>)> = 
+    let mut id_groups: IdGroups = 
         FxHashMap::with_capacity_and_hasher(estimated_unique_ids, Default::default());
     
     // Extract ID column arrays once for efficiency
-    let current_id_arrays: Vec<_> = id_columns.iter()
-        .map(|col| current_state.column_by_name(col).unwrap().clone())
-        .collect();
-    let updates_id_arrays: Vec<_> = id_columns.iter()
-        .map(|col| updates.column_by_name(col).unwrap().clone())
-        .collect();
+    let current_id_arrays = crate::batch_utils::id_arrays(current_state, id_columns)
+        .map_err(|e| format!("{} in current state", e))?;
+    let updates_id_arrays = crate::batch_utils::id_arrays(updates, id_columns)
+        .map_err(|e| format!("{} in updates", e))?;
     
     // PERFORMANCE OPTIMIZATION: Reusable buffer to avoid 850,000+ String allocations
     let mut id_key_buffer = String::with_capacity(64);
@@ -244,15 +1069,19 @@ fn build_id_groups(
 /// Process all ID groups with optimal parallel/serial strategy
 #[allow(clippy::too_many_arguments)]
 fn process_all_id_groups(
-    id_groups: FxHashMap<String, (Vec<usize>, Vec<usize>)>,
+    id_groups: IdGroups,
     current_state: &RecordBatch,
     updates: &RecordBatch,
+    columns: &crate::batch_utils::InputColumns,
     id_columns: &[String],
     value_columns: &[String],
     system_date: NaiveDate,
     update_mode: UpdateMode,
     batch_timestamp: chrono::NaiveDateTime,
-) -> Result<(Vec<usize>, Vec<RecordBatch>), String> {
+    options: &ProcessingOptions,
+    value_rows: Option<&crate::arrow_hash::ValueRows>,
+    progress: &GroupProgress<'_>,
+) -> Result<IdGroupProcessingResult, String> {
     // Pre-allocate vectors with estimated capacity to reduce reallocations
     // Estimate: on average, each ID group affects 1-2 current state records and creates 1-3 insert batches
     let estimated_expire_capacity = id_groups.len() * 2;
@@ -260,134 +1089,268 @@ fn process_all_id_groups(
     
     let mut to_expire = Vec::with_capacity(estimated_expire_capacity);
     let mut to_insert = Vec::with_capacity(estimated_insert_capacity);
+    let mut overridden_updates = Vec::new();
+    let mut skipped_updates = Vec::new();
     
     // PERFORMANCE OPTIMIZATION: Pre-extract array to avoid 5000+ column_by_name calls
-    let updates_as_of_from_array = updates.column_by_name("as_of_from")
-        .ok_or_else(|| "as_of_from column not found in updates".to_string())?;
+    let updates_as_of_from_array = columns.updates.as_of_from(updates)
+        .map_err(|_| "as_of_from column not found in updates".to_string())?;
+
+    // Partition values carried by this full_state feed (None when not partitioned)
+    let present_partitions = collect_partition_keys(updates, &options.partition_columns)?;
     
-    // Determine optimal processing strategy based on data size
-    // PERFORMANCE TUNING: More aggressive parallelization for modern multi-core systems
-    let use_parallel = id_groups.len() > 25 ||
-                      (current_state.num_rows() + updates.num_rows()) > 5000;
+    // Determine optimal processing strategy based on data size unless the caller forced one
+    let use_parallel = match options.parallelism {
+        Parallelism::Auto => auto_parallel(id_groups.len(), current_state.num_rows() + updates.num_rows()),
+        Parallelism::Serial => false,
+        Parallelism::Parallel => true,
+    };
     
+    let mut spilled = crate::spill::SpilledInserts::default();
+
     if use_parallel {
-        // Parallel processing for large datasets
+        // Parallel processing for large datasets; each worker accumulates its groups into
+        // one result and reuses one scratch across them. Workers share the memory budget.
+        let worker_budget = options.max_memory_bytes
+            .map(|budget| budget / rayon::current_num_threads().max(1));
         let results: Result<Vec<IdGroupProcessingResult>, String> = id_groups
             .into_par_iter()
-            .map(|(_id_key, (current_row_indices, update_row_indices))| {
-                process_id_group_optimized(
-                    &current_row_indices,
-                    &update_row_indices,
-                    current_state,
-                    updates,
-                    &updates_as_of_from_array,
-                    id_columns,
-                    value_columns,
-                    system_date,
-                    update_mode,
-                    batch_timestamp,
-                )
-            })
+            .try_fold(
+                || (IdGroupProcessingResult::default(), GroupScratch::default()),
+                |(mut result, mut scratch), (_id_key, (current_row_indices, update_row_indices))| {
+                    progress.check_cancelled()?;
+                    let inserted_before = result.insert_batches.len();
+                    process_id_group_optimized(
+                        &current_row_indices,
+                        &update_row_indices,
+                        current_state,
+                        updates,
+                        updates_as_of_from_array,
+                        columns,
+                        id_columns,
+                        value_columns,
+                        system_date,
+                        update_mode,
+                        batch_timestamp,
+                        options,
+                        present_partitions.as_ref(),
+                        value_rows,
+                        &mut result,
+                        &mut scratch,
+                    )?;
+                    progress.group_done();
+                    result.spilled.track(
+                        &mut result.insert_batches, inserted_before, worker_budget,
+                        id_columns, options.consolidation_target_rows,
+                    )?;
+                    Ok((result, scratch))
+                },
+            )
+            .map(|folded| folded.map(|(result, _scratch)| result))
             .collect();
-        
+
         let results = results?;
-        for (expire_indices, insert_batches) in results {
-            to_expire.extend(expire_indices);
-            to_insert.extend(insert_batches);
+        // Batches the workers kept in memory count against the budget until they are merged,
+        // so the merged batches spill before the two together outgrow it
+        let mut unmerged_bytes: usize = results.iter().map(|result| result.spilled.in_memory_bytes()).sum();
+        for result in results {
+            unmerged_bytes -= result.spilled.in_memory_bytes();
+            let inserted_before = to_insert.len();
+            to_expire.extend(result.expire_indices);
+            to_insert.extend(result.insert_batches);
+            overridden_updates.extend(result.overridden_updates);
+            skipped_updates.extend(result.skipped_updates);
+            spilled.append(result.spilled);
+            let merge_budget = options.max_memory_bytes.map(|budget| budget.saturating_sub(unmerged_bytes));
+            spilled.track(
+                &mut to_insert, inserted_before, merge_budget,
+                id_columns, options.consolidation_target_rows,
+            )?;
             
             // MEMORY OPTIMIZATION: Incremental consolidation to prevent memory buildup
             // Apply deduplication + consolidation when we have too many small batches
-            if to_insert.len() > 200 {
+            if options.consolidation_threshold.is_some_and(|threshold| to_insert.len() > threshold) {
                 to_insert = crate::conflation::deduplicate_record_batches(to_insert, id_columns)?;
-                to_insert = crate::conflation::consolidate_final_batches(to_insert)?;
+                to_insert = crate::conflation::consolidate_final_batches(to_insert, options.consolidation_target_rows)?;
             }
         }
     } else {
         // Serial processing for small datasets (avoids parallel overhead)
+        let mut result = IdGroupProcessingResult::default();
+        let mut scratch = GroupScratch::default();
         for (_id_key, (current_row_indices, update_row_indices)) in id_groups {
-            let (expire_indices, insert_batches) = process_id_group_optimized(
+            progress.check_cancelled()?;
+            process_id_group_optimized(
                 &current_row_indices,
                 &update_row_indices,
                 current_state,
                 updates,
-                &updates_as_of_from_array,
+                updates_as_of_from_array,
+                columns,
                 id_columns,
                 value_columns,
                 system_date,
                 update_mode,
                 batch_timestamp,
+                options,
+                present_partitions.as_ref(),
+                value_rows,
+                &mut result,
+                &mut scratch,
+            )?;
+            progress.group_done();
+
+            let inserted_before = to_insert.len();
+            to_expire.append(&mut result.expire_indices);
+            to_insert.append(&mut result.insert_batches);
+            overridden_updates.append(&mut result.overridden_updates);
+            skipped_updates.append(&mut result.skipped_updates);
+            spilled.track(
+                &mut to_insert, inserted_before, options.max_memory_bytes,
+                id_columns, options.consolidation_target_rows,
             )?;
-
-            to_expire.extend(expire_indices);
-            to_insert.extend(insert_batches);
 
             // MEMORY OPTIMIZATION: Incremental consolidation to prevent memory buildup
             // Apply deduplication + consolidation when we have too many small batches
-            if to_insert.len() > 200 {
+            if options.consolidation_threshold.is_some_and(|threshold| to_insert.len() > threshold) {
                 to_insert = crate::conflation::deduplicate_record_batches(to_insert, id_columns)?;
-                to_insert = crate::conflation::consolidate_final_batches(to_insert)?;
+                to_insert = crate::conflation::consolidate_final_batches(to_insert, options.consolidation_target_rows)?;
             }
         }
     }
 
-    Ok((to_expire, to_insert))
+    Ok(IdGroupProcessingResult {
+        expire_indices: to_expire,
+        insert_batches: to_insert,
+        overridden_updates,
+        skipped_updates,
+        spilled,
+    })
+}
+
+/// `Parallelism::Auto`: parallel once there are enough ID groups or rows to repay the overhead
+pub(crate) fn auto_parallel(id_groups: usize, total_rows: usize) -> bool {
+    // PERFORMANCE TUNING: More aggressive parallelization for modern multi-core systems
+    id_groups > 25 || total_rows > 5000
 }
 
 /// Build final changeset with all post-processing optimizations
 fn build_final_changeset(
-    mut to_expire: Vec<usize>,
-    mut to_insert: Vec<RecordBatch>,
+    group_results: IdGroupProcessingResult,
     current_state: &RecordBatch,
     batch_timestamp: chrono::NaiveDateTime,
     id_columns: &[String],
+    options: &ProcessingOptions,
 ) -> Result<ChangeSet, String> {
-    // Sort and deduplicate expiry indices
-    to_expire.sort_unstable();
-    to_expire.dedup();
+    let IdGroupProcessingResult {
+        expire_indices: mut to_expire,
+        insert_batches: mut to_insert,
+        mut overridden_updates,
+        mut skipped_updates,
+        spilled,
+    } = group_results;
+
+    overridden_updates.sort_unstable();
+    overridden_updates.dedup();
+    if options.report_skipped_updates {
+        skipped_updates.sort_unstable_by_key(|skipped| skipped.update_index);
+    } else {
+        skipped_updates.clear();
+    }
+
+    // Spilled batches rejoin the in-memory ones so they get the same post-processing
+    let mut spilled_back = spilled.read_back()?;
+    spilled_back.append(&mut to_insert);
+    to_insert = spilled_back;
 
-    // Apply all post-processing optimizations to insert batches
-    to_insert = deduplicate_record_batches(to_insert, id_columns)?;
-    to_insert = simple_conflate_batches(to_insert)?;
-    to_insert = consolidate_final_batches(to_insert)?;
+    to_insert = post_process_inserts(to_insert, id_columns, options)?;
+    if options.merge_adjacent_current {
+        let (merged, any_merged) = crate::adjacency::merge_with_adjacent_current(to_insert, &mut to_expire, current_state, id_columns)?;
+        // Stretched rows may now touch another insert of theirs
+        to_insert = if any_merged { simple_conflate_batches(merged, id_columns)? } else { merged };
+    }
+
+    // Sort and deduplicate expiry indices (a row expired for several reasons keeps the first)
+    to_expire.sort_unstable();
+    to_expire.dedup_by_key(|&mut (idx, _)| idx);
+    let (to_expire, expire_reasons): (Vec<usize>, Vec<ExpireReason>) = to_expire.into_iter().unzip();
     
     // Create expired record batches with updated as_of_to timestamp
     let expired_records = if !to_expire.is_empty() {
-        vec![crate::batch_utils::create_expired_records_batch(current_state, &to_expire, batch_timestamp)?]
+        vec![crate::batch_utils::create_expired_records_batch(
+            current_state,
+            &to_expire,
+            batch_timestamp,
+            options.report_expire_reasons.then_some(&expire_reasons[..]),
+        )?]
     } else {
         Vec::new()
     };
     
-    Ok(ChangeSet { to_expire, to_insert, expired_records })
+    Ok(ChangeSet { to_expire, to_insert, expired_records, overridden_updates, skipped_updates, ..Default::default() })
+}
+
+/// Post-processing every insert path shares: drop duplicate rows, merge touching rows of an ID
+/// with the same values, and consolidate into batches of the target size
+fn post_process_inserts(
+    to_insert: Vec<RecordBatch>,
+    id_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<Vec<RecordBatch>, String> {
+    let to_insert = deduplicate_record_batches(to_insert, id_columns)?;
+    let to_insert = simple_conflate_batches(to_insert, id_columns)?;
+    consolidate_final_batches(to_insert, options.consolidation_target_rows)
 }
 
 /// Ensures the value_hash column exists and is computed if missing or empty using fast Arrow-direct hashing
-fn ensure_hash_column_with_algorithm(batch: RecordBatch, value_columns: &[String], algorithm: HashAlgorithm) -> Result<RecordBatch, String> {
+fn ensure_hash_column_with_algorithm(batch: RecordBatch, value_columns: &[String], spec: crate::arrow_hash::HashSpec<'_>) -> Result<RecordBatch, String> {
+    if !needs_value_hash(&batch, spec)? {
+        return Ok(batch);
+    }
+
+    // Hash column is missing or has empty values, compute it using fast Arrow-direct hashing
+    crate::arrow_hash::add_hash_column_arrow_direct(&batch, value_columns, spec)
+}
+
+/// Whether the value_hash column has to be computed: it is missing or has empty values.
+/// Fails when it was tagged with another algorithm or float policy.
+fn needs_value_hash(batch: &RecordBatch, spec: crate::arrow_hash::HashSpec<'_>) -> Result<bool, String> {
     // Handle empty batches - no need to compute hashes
     if batch.num_rows() == 0 {
-        return Ok(batch);
+        return Ok(false);
     }
     
+    // Hashes tagged with another algorithm can never match ours; refuse rather than expire everything
+    if let Some(tagged) = crate::arrow_hash::hash_algorithm_tag(batch)? {
+        if tagged != spec.algorithm {
+            return Err(format!(
+                "value_hash was computed with {} but this run uses {}; migrate it with rehash_table first",
+                tagged.as_str(), spec.algorithm.as_str()
+            ));
+        }
+    }
+    crate::arrow_hash::check_exact_floats_tag(batch, spec)?;
+
     // Check if value_hash column exists and has non-empty values
-    if let Some(hash_column) = batch.column_by_name("value_hash") {
+    if let Some(hash_column) = batch.column_by_name(VALUE_HASH_COLUMN) {
         if let Some(string_array) = hash_column.as_any().downcast_ref::<arrow::array::StringArray>() {
             // Check if all values are non-empty
             let all_non_empty = (0..string_array.len())
                 .all(|i| !string_array.is_null(i) && !string_array.value(i).is_empty());
             
             if all_non_empty {
-                // Hash column exists and is populated, return as-is
-                return Ok(batch);
+                // Hash column exists and is populated, keep it
+                return Ok(false);
             }
         }
     }
-    
-    // Hash column is missing or has empty values, compute it using fast Arrow-direct hashing
-    crate::arrow_hash::add_hash_column_arrow_direct(&batch, value_columns, algorithm)
+    Ok(true)
 }
 
 // Extract ID group processing logic for reuse in parallel and serial paths
 
 /// Optimized ID group processing that works with row indices instead of expensive structures
+/// Appends the group's outcome to `result`; `scratch` holds the caller's reusable buffers
 /// PERFORMANCE: Inline hint for warm path (called once per ID group, ~5000 times)
 #[allow(clippy::too_many_arguments)]
 #[inline]
@@ -397,29 +1360,102 @@ fn process_id_group_optimized(
     current_batch: &RecordBatch,
     updates_batch: &RecordBatch,
     updates_as_of_from_array: &arrow::array::ArrayRef,
+    columns: &crate::batch_utils::InputColumns,
     id_columns: &[String],
     value_columns: &[String],
     system_date: NaiveDate,
     update_mode: UpdateMode,
     batch_timestamp: chrono::NaiveDateTime,
-) -> Result<(Vec<usize>, Vec<RecordBatch>), String> {
-    let mut expire_indices = Vec::new();
-    let mut insert_batches = Vec::new();
+    options: &ProcessingOptions,
+    present_partitions: Option<&FxHashSet<String>>,
+    value_rows: Option<&crate::arrow_hash::ValueRows>,
+    result: &mut IdGroupProcessingResult,
+    scratch: &mut GroupScratch,
+) -> Result<(), String> {
+    let IdGroupProcessingResult { expire_indices, insert_batches, overridden_updates, skipped_updates, .. } = result;
+
+    // A windowed full_state update only speaks for current rows intersecting the window
+    let windowed_current_indices;
+    let current_row_indices = if update_mode == UpdateMode::FullState && options.full_state_window.is_some() {
+        windowed_current_indices = filter_indices_to_window(
+            current_batch,
+            current_row_indices,
+            options.full_state_window,
+        )?;
+        &windowed_current_indices[..]
+    } else {
+        current_row_indices
+    };
+
+    // Only partitions carried by the feed are authoritative: current rows in other partitions
+    // are neither compared against the updates nor tombstoned
+    let in_partition_indices;
+    let current_row_indices = if update_mode == UpdateMode::FullState && present_partitions.is_some() {
+        in_partition_indices = filter_indices_to_partitions(
+            current_batch,
+            current_row_indices,
+            &options.partition_columns,
+            present_partitions,
+        )?;
+        &in_partition_indices[..]
+    } else {
+        current_row_indices
+    };
+
+    // Under a backfill watermark, history that ends before it is frozen and updates that
+    // end before it (only possible under the clamp policy) are dropped
+    let watermarked_current_indices;
+    let watermarked_update_indices;
+    let (current_row_indices, update_row_indices) = if options.min_effective_from.is_some() {
+        watermarked_current_indices = filter_indices_after_watermark(
+            current_batch,
+            current_row_indices,
+            options.min_effective_from,
+        )?;
+        watermarked_update_indices = filter_indices_after_watermark(
+            updates_batch,
+            update_row_indices,
+            options.min_effective_from,
+        )?;
+        skipped_updates.extend(update_row_indices.iter()
+            .filter(|idx| !watermarked_update_indices.contains(idx))
+            .map(|&update_index| SkippedUpdate { update_index, reason: SkipReason::BeforeWatermark }));
+        (&watermarked_current_indices[..], &watermarked_update_indices[..])
+    } else {
+        (current_row_indices, update_row_indices)
+    };
+
+    // Updates entirely outside the clamp window are dropped (straddling ones were trimmed)
+    let clamped_update_indices;
+    let update_row_indices = if options.clamp_from.is_some() || options.clamp_to.is_some() {
+        clamped_update_indices = filter_indices_in_clamp_window(
+            updates_batch,
+            update_row_indices,
+            options.clamp_from,
+            options.clamp_to,
+        )?;
+        skipped_updates.extend(update_row_indices.iter()
+            .filter(|idx| !clamped_update_indices.contains(idx))
+            .map(|&update_index| SkippedUpdate { update_index, reason: SkipReason::OutsideClampWindow }));
+        &clamped_update_indices[..]
+    } else {
+        update_row_indices
+    };
+
+    // Rows flagged deleted are never inserted; an ID left without rows is tombstoned below
+    let live_update_indices;
+    let update_row_indices = if let Some(deletion_column) = &options.deletion_column {
+        live_update_indices = filter_indices_not_deleted(updates_batch, update_row_indices, deletion_column)?;
+        &live_update_indices[..]
+    } else {
+        update_row_indices
+    };
     
-    // Extract consistent as_of_from timestamp from updates batch (if available)
-    let consistent_timestamp = if updates_batch.num_rows() > 0 {
-        // PERFORMANCE: Use pre-extracted array to avoid repeated column_by_name calls
-        if let Some(ts_array) = updates_as_of_from_array.as_any().downcast_ref::<arrow::array::TimestampMicrosecondArray>() {
-            if !ts_array.is_null(0) {
-                let micros = ts_array.value(0);
-                let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-                epoch + chrono::Duration::microseconds(micros)
-            } else {
-                batch_timestamp
-            }
-        } else {
-            batch_timestamp
-        }
+    // Extract consistent as_of_from timestamp from updates batch (if available), whatever
+    // the column's unit
+    // PERFORMANCE: Use pre-extracted array to avoid repeated column_by_name calls
+    let consistent_timestamp = if updates_batch.num_rows() > 0 && !updates_as_of_from_array.is_null(0) {
+        extract_datetime_flexible(updates_as_of_from_array.as_ref(), 0).unwrap_or(batch_timestamp)
     } else {
         batch_timestamp
     };
@@ -430,27 +1466,26 @@ fn process_id_group_optimized(
             // In full state mode, expire current records for IDs not in updates
             // Filter to only include records where effective_from <= system_date
             // (records with effective_from > system_date would create invalid ranges)
-            let tombstone_indices = filter_indices_for_tombstoning(
+            let tombstones = filter_indices_for_tombstoning(
                 current_batch,
                 current_row_indices,
                 system_date,
+                options,
             )?;
 
-            if !tombstone_indices.is_empty() {
-                expire_indices.extend(tombstone_indices.iter().cloned());
-
+            expire_indices.extend(tombstones.expired.iter().map(|&idx| (idx, ExpireReason::Tombstoned)));
+            if !tombstones.kept.is_empty() {
                 // Use the consistent timestamp from the updates batch for tombstones
                 let tombstone_records = create_tombstone_records_optimized(
-                    &tombstone_indices,
+                    &tombstones.kept,
                     current_batch,
                     value_columns,
-                    system_date,
                     consistent_timestamp,
                 )?;
                 insert_batches.push(tombstone_records);
             }
         }
-        return Ok((expire_indices, insert_batches));
+        return Ok(());
     }
     
     // Only create expensive BitemporalRecord structures when we actually need temporal processing
@@ -461,197 +1496,454 @@ fn process_id_group_optimized(
             update_row_indices,
             current_batch,
             updates_batch,
-            value_columns,
-            system_date,
-            consistent_timestamp,
-            &mut expire_indices,
-            &mut insert_batches,
+            columns,
+            (!options.preserve_as_of_from).then_some(consistent_timestamp),
+            expire_indices,
+            insert_batches,
+            skipped_updates,
+            value_rows,
         )?;
     } else {
         // For delta mode, we need temporal processing - create BitemporalRecords only here
-        let current_records = create_bitemporal_records_from_indices(
-            current_row_indices,
-            current_batch,
-            id_columns,
-            value_columns,
-        )?;
-        let update_records = create_bitemporal_records_from_indices(
-            update_row_indices,
-            updates_batch,
-            id_columns,
-            value_columns,
-        )?;
-        
+        let GroupScratch { current_records, update_records } = scratch;
+        fill_bitemporal_records(current_row_indices, current_batch, &columns.current, current_records)?;
+        fill_bitemporal_records(update_row_indices, updates_batch, &columns.updates, update_records)?;
+
         let (expire_idx, insert_batch) = process_id_timeline(
-            &current_records,
-            &update_records,
+            current_records,
+            update_records,
             current_batch,
             updates_batch,
             id_columns,
             value_columns,
             system_date,
+            &options.conflict_resolution,
+            skipped_updates,
+            value_rows,
         )?;
         
         expire_indices.extend(expire_idx);
         insert_batches.extend(insert_batch);
+        overridden_updates.extend(crate::overlap::find_overridden_updates(
+            update_records,
+            &options.conflict_resolution,
+            updates_batch,
+        )?);
     }
     
-    Ok((expire_indices, insert_batches))
+    Ok(())
 }
 
-/// Fast tombstone creation without expensive conversions
+/// Fast tombstone creation without expensive conversions: one row per kept
+/// `(row, effective_from, effective_to)` segment, carrying that current row's values
 fn create_tombstone_records_optimized(
-    current_row_indices: &[usize],
+    tombstones: &[(usize, NaiveDateTime, NaiveDateTime)],
     current_batch: &RecordBatch,
     _value_columns: &[String],
-    system_date: NaiveDate,
     batch_timestamp: chrono::NaiveDateTime,
 ) -> Result<RecordBatch, String> {
     // Create a slice of the current batch with only the relevant rows
-    if current_row_indices.is_empty() {
+    if tombstones.is_empty() {
         return Err("Cannot create tombstone records from empty indices".to_string());
     }
     
-    // Use Arrow's take operation to efficiently extract rows
-    let indices_array = arrow::array::UInt64Array::from(
-        current_row_indices.iter().map(|&i| Some(i as u64)).collect::<Vec<_>>()
-    );
-    let sliced_batch = arrow::compute::take_record_batch(current_batch, &indices_array)
-        .map_err(|e| format!("Failed to slice batch for tombstones: {}", e))?;
-    
-    // Modify the temporal columns for tombstone semantics
-    let system_date_time = system_date.and_hms_opt(0, 0, 0).unwrap();
-    
-    // Clone the schema and data, but modify effective_to and as_of_from
-    let mut columns: Vec<arrow::array::ArrayRef> = Vec::new();
-    let schema = sliced_batch.schema();
+    // Gather the tombstoned rows with Arrow's take, skipping the columns replaced below
+    let indices_array = crate::batch_utils::take_indices(tombstones.iter().map(|&(i, _, _)| i));
+    let starts: Vec<NaiveDateTime> = tombstones.iter().map(|&(_, from, _)| from).collect();
+    let cutoffs: Vec<NaiveDateTime> = tombstones.iter().map(|&(_, _, to)| to).collect();
+    let schema = current_batch.schema();
+    let mut columns: Vec<arrow::array::ArrayRef> = Vec::with_capacity(schema.fields().len());
     
-    for field in schema.fields() {
-        let column_name = field.name();
-        
-        match column_name.as_str() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
+        match field.name().as_str() {
+            "effective_from" => {
+                // Segments past the full_state window start at its end, preserving the original type
+                columns.push(create_timestamp_array_from_values(field.data_type(), &starts)?);
+            }
             "effective_to" => {
-                // Set effective_to to system_date for all tombstone records, preserving original time unit
-                match field.data_type() {
-                    arrow::datatypes::DataType::Timestamp(time_unit, tz) => {
-                        let timezone_str = tz.as_ref().map(|t| t.to_string());
-                        let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-                        
-                        use arrow::datatypes::TimeUnit;
-                        let array: arrow::array::ArrayRef = match time_unit {
-                            TimeUnit::Nanosecond => {
-                                let nanoseconds = (system_date_time - epoch).num_nanoseconds().unwrap();
-                                let values = vec![Some(nanoseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampNanosecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Microsecond => {
-                                let microseconds = (system_date_time - epoch).num_microseconds().unwrap();
-                                let values = vec![Some(microseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Millisecond => {
-                                let milliseconds = (system_date_time - epoch).num_milliseconds();
-                                let values = vec![Some(milliseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampMillisecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Second => {
-                                let seconds = (system_date_time - epoch).num_seconds();
-                                let values = vec![Some(seconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampSecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                        };
-                        columns.push(array);
-                    }
-                    _ => return Err("effective_to column must be timestamp type".to_string())
-                }
+                // Set effective_to to each record's tombstone cutoff, preserving the original type
+                columns.push(create_timestamp_array_from_values(field.data_type(), &cutoffs)?);
             }
             "as_of_from" => {
-                // Set as_of_from to batch_timestamp for all tombstone records, preserving original time unit
-                match field.data_type() {
-                    arrow::datatypes::DataType::Timestamp(time_unit, tz) => {
-                        let timezone_str = tz.as_ref().map(|t| t.to_string());
-                        let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
-                        
-                        use arrow::datatypes::TimeUnit;
-                        let array: arrow::array::ArrayRef = match time_unit {
-                            TimeUnit::Nanosecond => {
-                                let nanoseconds = (batch_timestamp - epoch).num_nanoseconds().unwrap();
-                                let values = vec![Some(nanoseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampNanosecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Microsecond => {
-                                let microseconds = (batch_timestamp - epoch).num_microseconds().unwrap();
-                                let values = vec![Some(microseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampMicrosecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Millisecond => {
-                                let milliseconds = (batch_timestamp - epoch).num_milliseconds();
-                                let values = vec![Some(milliseconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampMillisecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                            TimeUnit::Second => {
-                                let seconds = (batch_timestamp - epoch).num_seconds();
-                                let values = vec![Some(seconds); current_row_indices.len()];
-                                let array = arrow::array::TimestampSecondArray::from(values).with_timezone_opt(timezone_str);
-                                std::sync::Arc::new(array)
-                            }
-                        };
-                        columns.push(array);
-                    }
-                    _ => return Err("as_of_from column must be timestamp type".to_string())
-                }
+                // Set as_of_from to batch_timestamp for all tombstone records, preserving the original type
+                columns.push(create_timestamp_array(field.data_type(), batch_timestamp, tombstones.len())?);
             }
             _ => {
-                // Copy original column as-is
-                columns.push(sliced_batch.column_by_name(column_name).unwrap().clone());
+                columns.push(arrow::compute::take(current_batch.column(col_idx).as_ref(), &indices_array, None)
+                    .map_err(|e| format!("Failed to slice batch for tombstones: {}", e))?);
             }
         }
     }
-    
-    arrow::array::RecordBatch::try_new(schema, columns)
-        .map_err(|e| format!("Failed to create tombstone batch: {}", e))
+    
+    arrow::array::RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to create tombstone batch: {}", e))
+}
+
+/// Current rows a full_state disappearance expires, and the effective segments of them that
+/// are re-inserted with their values: the closing `[effective_from, cutoff)` row when
+/// `options.emit_tombstones` is set, plus whatever lies outside `options.full_state_window`
+struct Tombstones {
+    expired: Vec<usize>,
+    kept: Vec<(usize, NaiveDateTime, NaiveDateTime)>,
+}
+
+/// Filter row indices to only include records whose effective_from <= system_date.
+/// This prevents creating invalid tombstone records during backfill scenarios where
+/// system_date is earlier than existing records' effective_from dates.
+///
+/// Each record is cut at its tombstone cutoff (`options.tombstone_cutoff_column` when set
+/// and non-null, otherwise the system cutoff). Under a full_state window only the part of
+/// `[cutoff, effective_to)` inside the window is removed, so coverage outside it survives.
+/// Skipped records represent "future" data from the perspective of the backfill date
+/// and should not be tombstoned (they remain unchanged in the database).
+fn filter_indices_for_tombstoning(
+    batch: &RecordBatch,
+    indices: &[usize],
+    system_date: NaiveDate,
+    options: &ProcessingOptions,
+) -> Result<Tombstones, String> {
+    let eff_from_array = batch.column_by_name("effective_from")
+        .ok_or("effective_from column not found")?;
+    let eff_to_array = batch.column_by_name("effective_to")
+        .ok_or("effective_to column not found")?;
+    let default_cutoff = tombstone_cutoff(batch, system_date, options);
+    let cutoff_array = match &options.tombstone_cutoff_column {
+        Some(name) => Some(batch.column_by_name(name)
+            .ok_or_else(|| format!("Tombstone cutoff column '{}' not found in current state", name))?),
+        None => None,
+    };
+
+    let mut tombstones = Tombstones {
+        expired: Vec::with_capacity(indices.len()),
+        kept: Vec::with_capacity(indices.len()),
+    };
+
+    for &idx in indices {
+        let effective_from = extract_datetime_flexible(eff_from_array.as_ref(), idx)?;
+        let cutoff = match cutoff_array {
+            Some(array) if !array.is_null(idx) => extract_datetime_flexible(array.as_ref(), idx)?,
+            _ => default_cutoff,
+        };
+        // Use strict less-than to avoid empty ranges where effective_from == system_date
+        // A tombstone sets effective_to = system_date, so we need effective_from < system_date
+        // to have a valid non-empty range [effective_from, system_date)
+        // Rows already ending by the cutoff are history the tombstone leaves alone
+        let effective_to = extract_datetime_flexible(eff_to_array.as_ref(), idx)?;
+        if effective_from >= cutoff || effective_to <= cutoff {
+            continue;
+        }
+
+        // The removed range, clipped to the window the feed speaks for
+        let (removed_from, removed_to) = match options.full_state_window {
+            Some((window_from, window_to)) => (cutoff.max(window_from), effective_to.min(window_to)),
+            None => (cutoff, effective_to),
+        };
+        if removed_from >= removed_to {
+            continue;
+        }
+
+        tombstones.expired.push(idx);
+        // The closing row keeps everything before the removed range; without one only the
+        // part before the window survives
+        let head_to = if options.emit_tombstones {
+            removed_from
+        } else {
+            options.full_state_window.map_or(effective_from, |(window_from, _)| window_from)
+        };
+        if effective_from < head_to {
+            tombstones.kept.push((idx, effective_from, head_to));
+        }
+        if removed_to < effective_to {
+            tombstones.kept.push((idx, removed_to, effective_to));
+        }
+    }
+
+    Ok(tombstones)
+}
+
+/// Instant at which full_state tombstones end: `options.system_time` or midnight of
+/// `system_date`, taken in `options.effective_timezone` for timestamp columns
+/// (date columns are already business dates, so they end at the date itself)
+fn tombstone_cutoff(batch: &RecordBatch, system_date: NaiveDate, options: &ProcessingOptions) -> NaiveDateTime {
+    let midnight = system_date.and_hms_opt(0, 0, 0).unwrap();
+    let is_date_column = batch.column_by_name("effective_to").is_some_and(|col| {
+        matches!(col.data_type(), arrow::datatypes::DataType::Date32 | arrow::datatypes::DataType::Date64)
+    });
+    if is_date_column {
+        return midnight;
+    }
+    let system_time = options.system_time.unwrap_or(midnight);
+    match options.effective_timezone {
+        Some(tz) => local_to_utc(tz, system_time),
+        None => system_time,
+    }
+}
+
+/// Convert a local wall-clock time to UTC. Ambiguous times take the earlier instant; times
+/// skipped by DST use the offset in force before the gap (so a skipped midnight starts the
+/// day at the end of the gap).
+fn local_to_utc(tz: chrono_tz::Tz, local: NaiveDateTime) -> NaiveDateTime {
+    use chrono::{Offset, TimeZone};
+    match tz.from_local_datetime(&local) {
+        chrono::LocalResult::Single(dt) | chrono::LocalResult::Ambiguous(dt, _) => dt.naive_utc(),
+        chrono::LocalResult::None => {
+            let before = tz.offset_from_utc_datetime(&(local - chrono::Duration::days(1))).fix();
+            local - chrono::Duration::seconds(before.local_minus_utc() as i64)
+        }
+    }
+}
+
+/// Filter row indices to the records whose effective range intersects the full_state window.
+/// Without a window every index is kept.
+fn filter_indices_to_window(
+    batch: &RecordBatch,
+    indices: &[usize],
+    window: Option<(NaiveDateTime, NaiveDateTime)>,
+) -> Result<Vec<usize>, String> {
+    let Some((window_from, window_to)) = window else {
+        return Ok(indices.to_vec());
+    };
+
+    let mut in_window = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let (effective_from, effective_to) = get_temporal_bounds(batch, idx)?;
+        if effective_from < window_to && effective_to > window_from {
+            in_window.push(idx);
+        }
+    }
+
+    Ok(in_window)
+}
+
+/// Filter row indices to the records that end after the backfill watermark.
+/// Without a watermark every index is kept.
+fn filter_indices_after_watermark(
+    batch: &RecordBatch,
+    indices: &[usize],
+    watermark: Option<NaiveDateTime>,
+) -> Result<Vec<usize>, String> {
+    let Some(watermark) = watermark else {
+        return Ok(indices.to_vec());
+    };
+
+    let mut after_watermark = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let (_, effective_to) = get_temporal_bounds(batch, idx)?;
+        if effective_to > watermark {
+            after_watermark.push(idx);
+        }
+    }
+
+    Ok(after_watermark)
+}
+
+/// Enforce the backfill watermark on the updates batch.
+///
+/// Reject fails on any row starting before the watermark. Clamp moves `effective_from`
+/// up to the watermark for rows that straddle it; rows ending at or before it are left
+/// as-is and dropped per ID group so their indices can be reported as skipped.
+fn apply_effective_watermark(
+    updates: RecordBatch,
+    watermark: Option<NaiveDateTime>,
+    policy: WatermarkPolicy,
+) -> Result<RecordBatch, String> {
+    let Some(watermark) = watermark else {
+        return Ok(updates);
+    };
+
+    let mut early_rows = Vec::new();
+    let mut straddling = Vec::with_capacity(updates.num_rows());
+    for row in 0..updates.num_rows() {
+        let (effective_from, effective_to) = get_temporal_bounds(&updates, row)?;
+        if effective_from < watermark {
+            early_rows.push(row);
+        }
+        straddling.push(effective_from < watermark && effective_to > watermark);
+    }
+
+    if early_rows.is_empty() {
+        return Ok(updates);
+    }
+    if policy == WatermarkPolicy::Reject {
+        return Err(format!(
+            "Update rows {:?} start before min_effective_from {}",
+            early_rows, watermark
+        ));
+    }
+
+    let schema = updates.schema();
+    let eff_from_idx = schema.index_of("effective_from")
+        .map_err(|_| "effective_from column not found".to_string())?;
+    let eff_from_array = updates.column(eff_from_idx);
+    let clamped = create_timestamp_array(eff_from_array.data_type(), watermark, updates.num_rows())?;
+    let new_eff_from = arrow::compute::kernels::zip::zip(
+        &arrow::array::BooleanArray::from(straddling),
+        &clamped,
+        eff_from_array,
+    ).map_err(|e| format!("Failed to clamp effective_from to watermark: {}", e))?;
+
+    let mut columns = updates.columns().to_vec();
+    columns[eff_from_idx] = new_eff_from;
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to clamp effective_from to watermark: {}", e))
+}
+
+/// Whether an effective range reaches into the `[clamp_from, clamp_to)` window
+fn reaches_clamp_window(
+    effective_from: NaiveDateTime,
+    effective_to: NaiveDateTime,
+    clamp_from: Option<NaiveDateTime>,
+    clamp_to: Option<NaiveDateTime>,
+) -> bool {
+    let after_from = match clamp_from {
+        Some(from) => effective_to > from,
+        None => true,
+    };
+    let before_to = match clamp_to {
+        Some(to) => effective_from < to,
+        None => true,
+    };
+    after_from && before_to
+}
+
+/// Filter row indices to the records that reach into the `[clamp_from, clamp_to)` window
+fn filter_indices_in_clamp_window(
+    batch: &RecordBatch,
+    indices: &[usize],
+    clamp_from: Option<NaiveDateTime>,
+    clamp_to: Option<NaiveDateTime>,
+) -> Result<Vec<usize>, String> {
+    let mut in_window = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let (effective_from, effective_to) = get_temporal_bounds(batch, idx)?;
+        if reaches_clamp_window(effective_from, effective_to, clamp_from, clamp_to) {
+            in_window.push(idx);
+        }
+    }
+
+    Ok(in_window)
 }
 
-/// Filter row indices to only include records whose effective_from <= system_date.
-/// This prevents creating invalid tombstone records during backfill scenarios where
-/// system_date is earlier than existing records' effective_from dates.
+/// Trim update ranges to the `[clamp_from, clamp_to)` processing window.
 ///
-/// Returns the filtered indices.
-/// Skipped records represent "future" data from the perspective of the backfill date
-/// and should not be tombstoned (they remain unchanged in the database).
-fn filter_indices_for_tombstoning(
+/// Rows straddling a bound have it moved to the bound and are returned as clamped. Rows
+/// entirely outside the window are left as-is and dropped per ID group so their indices can
+/// be reported as skipped.
+fn apply_clamp_window(
+    updates: RecordBatch,
+    clamp_from: Option<NaiveDateTime>,
+    clamp_to: Option<NaiveDateTime>,
+) -> Result<(RecordBatch, Vec<usize>), String> {
+    if clamp_from.is_none() && clamp_to.is_none() {
+        return Ok((updates, Vec::new()));
+    }
+
+    let mut clamped_rows = Vec::new();
+    let mut trim_from = Vec::with_capacity(updates.num_rows());
+    let mut trim_to = Vec::with_capacity(updates.num_rows());
+    for row in 0..updates.num_rows() {
+        let (effective_from, effective_to) = get_temporal_bounds(&updates, row)?;
+        let in_window = reaches_clamp_window(effective_from, effective_to, clamp_from, clamp_to);
+        let from = in_window && clamp_from.is_some_and(|from| effective_from < from);
+        let to = in_window && clamp_to.is_some_and(|to| effective_to > to);
+        if from || to {
+            clamped_rows.push(row);
+        }
+        trim_from.push(from);
+        trim_to.push(to);
+    }
+
+    if clamped_rows.is_empty() {
+        return Ok((updates, clamped_rows));
+    }
+
+    let schema = updates.schema();
+    let mut columns = updates.columns().to_vec();
+    for (column, bound, trim) in [("effective_from", clamp_from, trim_from), ("effective_to", clamp_to, trim_to)] {
+        let Some(bound) = bound else {
+            continue;
+        };
+        let idx = schema.index_of(column)
+            .map_err(|_| format!("{} column not found", column))?;
+        let clamped = create_timestamp_array(columns[idx].data_type(), bound, updates.num_rows())?;
+        columns[idx] = arrow::compute::kernels::zip::zip(
+            &arrow::array::BooleanArray::from(trim),
+            &clamped,
+            &columns[idx],
+        ).map_err(|e| format!("Failed to clamp {} to the clamp window: {}", column, e))?;
+    }
+    let updates = RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to clamp updates to the clamp window: {}", e))?;
+    Ok((updates, clamped_rows))
+}
+
+/// Keep the update rows whose `deletion_column` flag is false or null
+fn filter_indices_not_deleted(
     batch: &RecordBatch,
     indices: &[usize],
-    system_date: NaiveDate,
+    deletion_column: &str,
 ) -> Result<Vec<usize>, String> {
-    let eff_from_array = batch.column_by_name("effective_from")
-        .ok_or("effective_from column not found")?;
+    let flags = batch.column_by_name(deletion_column)
+        .ok_or_else(|| format!("Deletion column '{}' not found in updates", deletion_column))?
+        .as_any()
+        .downcast_ref::<arrow::array::BooleanArray>()
+        .ok_or_else(|| format!("Deletion column '{}' must be boolean", deletion_column))?;
+    Ok(indices.iter()
+        .copied()
+        .filter(|&idx| flags.is_null(idx) || !flags.value(idx))
+        .collect())
+}
+
+/// Collect the distinct partition keys present in a batch.
+/// Returns None when no partition columns are configured (unpartitioned full_state).
+fn collect_partition_keys(
+    batch: &RecordBatch,
+    partition_columns: &[String],
+) -> Result<Option<FxHashSet<String>>, String> {
+    if partition_columns.is_empty() {
+        return Ok(None);
+    }
+
+    let partition_arrays = partition_arrays(batch, partition_columns)?;
+    let mut keys = FxHashSet::default();
+    let mut key_buffer = String::with_capacity(64);
+    for row_idx in 0..batch.num_rows() {
+        create_id_key_with_buffer(&partition_arrays, row_idx, &mut key_buffer);
+        if !keys.contains(&key_buffer) {
+            keys.insert(key_buffer.clone());
+        }
+    }
 
-    let system_date_time = system_date.and_hms_opt(0, 0, 0).unwrap();
+    Ok(Some(keys))
+}
 
-    let mut valid_indices = Vec::with_capacity(indices.len());
-    let mut skipped_count = 0usize;
+/// Filter row indices to the records whose partition key is present in the updates.
+/// Without partitioning every index is kept.
+fn filter_indices_to_partitions(
+    batch: &RecordBatch,
+    indices: &[usize],
+    partition_columns: &[String],
+    present_partitions: Option<&FxHashSet<String>>,
+) -> Result<Vec<usize>, String> {
+    let Some(present_partitions) = present_partitions else {
+        return Ok(indices.to_vec());
+    };
 
+    let partition_arrays = partition_arrays(batch, partition_columns)?;
+    let mut key_buffer = String::with_capacity(64);
+    let mut in_partition = Vec::with_capacity(indices.len());
     for &idx in indices {
-        let effective_from = extract_datetime_flexible(eff_from_array.as_ref(), idx)?;
-        // Use strict less-than to avoid empty ranges where effective_from == system_date
-        // A tombstone sets effective_to = system_date, so we need effective_from < system_date
-        // to have a valid non-empty range [effective_from, system_date)
-        if effective_from < system_date_time {
-            valid_indices.push(idx);
-        } else {
-            skipped_count += 1;
+        create_id_key_with_buffer(&partition_arrays, idx, &mut key_buffer);
+        if present_partitions.contains(&key_buffer) {
+            in_partition.push(idx);
         }
     }
 
-    Ok(valid_indices)
+    Ok(in_partition)
+}
+
+fn partition_arrays(batch: &RecordBatch, partition_columns: &[String]) -> Result<Vec<arrow::array::ArrayRef>, String> {
+    crate::batch_utils::named_columns(batch, partition_columns, "Partition")
 }
 
 /// Extract temporal bounds (effective_from, effective_to) for a record
@@ -672,24 +1964,63 @@ fn get_temporal_bounds(
     Ok((from, to))
 }
 
+/// `get_temporal_bounds` in microseconds since the epoch, for comparisons without chrono
+fn get_temporal_bounds_micros(
+    batch: &RecordBatch,
+    row_idx: usize,
+) -> Result<(i64, i64), String> {
+    let eff_from_array = batch.column_by_name("effective_from")
+        .ok_or("effective_from column not found")?;
+    let eff_to_array = batch.column_by_name("effective_to")
+        .ok_or("effective_to column not found")?;
+
+    let from = extract_micros_flexible(eff_from_array.as_ref(), row_idx)?;
+    let to = extract_micros_flexible(eff_to_array.as_ref(), row_idx)?;
+
+    Ok((from, to))
+}
+
 /// Check if two temporal segments are adjacent (touching endpoints but not overlapping)
 /// Adjacent means one segment ends exactly where the other begins
 #[inline]
-fn are_segments_adjacent(
-    seg1_from: NaiveDateTime,
-    seg1_to: NaiveDateTime,
-    seg2_from: NaiveDateTime,
-    seg2_to: NaiveDateTime,
+fn are_segments_adjacent<T: TemporalEndpoint>(
+    seg1_from: T,
+    seg1_to: T,
+    seg2_from: T,
+    seg2_to: T,
 ) -> bool {
     seg1_to == seg2_from || seg2_to == seg1_from
 }
 
+/// 2200-01-01 in microseconds since the epoch, where `is_open_ended` starts
+const OPEN_ENDED_MICROS: i64 = 7_258_118_400_000_000;
+
+/// A point on the effective axis: a `NaiveDateTime`, or microseconds since the epoch as read
+/// by `extract_micros_flexible` for hot loops that only compare endpoints
+trait TemporalEndpoint: Copy + Ord {
+    fn is_open_ended(self) -> bool;
+}
+
+impl TemporalEndpoint for NaiveDateTime {
+    #[inline]
+    fn is_open_ended(self) -> bool {
+        self.date().year() >= 2200
+    }
+}
+
+impl TemporalEndpoint for i64 {
+    #[inline]
+    fn is_open_ended(self) -> bool {
+        self >= OPEN_ENDED_MICROS
+    }
+}
+
 /// Check if a temporal endpoint is "open-ended" (at or near infinity).
 /// We use year >= 2200 as the threshold to detect infinity timestamps, which accommodates
 /// both Python's INFINITY_TIMESTAMP (2260-12-31) and Rust's MAX_TIMESTAMP (2262-04-11).
 #[inline]
-fn is_open_ended(effective_to: NaiveDateTime) -> bool {
-    effective_to.date().year() >= 2200
+fn is_open_ended<T: TemporalEndpoint>(effective_to: T) -> bool {
+    effective_to.is_open_ended()
 }
 
 /// Check if merging two adjacent segments should be prevented.
@@ -703,29 +2034,21 @@ fn is_open_ended(effective_to: NaiveDateTime) -> bool {
 /// - An incoming update [2024-01-02, infinity) arrives (new knowledge)
 /// - Without this check, they would merge to [2024-01-01, infinity), losing the closure
 #[inline]
-fn should_prevent_merge(current_effective_to: NaiveDateTime, update_effective_to: NaiveDateTime) -> bool {
+fn should_prevent_merge<T: TemporalEndpoint>(current_effective_to: T, update_effective_to: T) -> bool {
     !is_open_ended(current_effective_to) && is_open_ended(update_effective_to)
 }
 
-/// Create a merged temporal segment from records across two batches
-/// Used when adjacent segments have identical values and should be coalesced
-fn create_merged_segment_cross_batch(
-    current_batch: &RecordBatch,
+/// Create the merged temporal segment covering a chain of adjacent records with identical
+/// values, taking the values from the update record and the range from `(merged_from, merged_to)`.
+/// `as_of_from` is stamped with `stamp_as_of_from`, or kept from the update record when None
+fn create_merged_segment(
     updates_batch: &RecordBatch,
-    current_idx: usize,
     update_idx: usize,
-    batch_timestamp: NaiveDateTime,
+    (merged_from, merged_to): (NaiveDateTime, NaiveDateTime),
+    stamp_as_of_from: Option<NaiveDateTime>,
 ) -> Result<RecordBatch, String> {
-    // Get temporal bounds from both records
-    let (curr_from, curr_to) = get_temporal_bounds(current_batch, current_idx)?;
-    let (upd_from, upd_to) = get_temporal_bounds(updates_batch, update_idx)?;
-
-    // Calculate merged temporal range (earliest from, latest to)
-    let merged_from = curr_from.min(upd_from);
-    let merged_to = curr_to.max(upd_to);
-
     // Use update record as the base (it has newer as_of information)
-    let indices = arrow::array::UInt64Array::from(vec![Some(update_idx as u64)]);
+    let indices = crate::batch_utils::take_indices([update_idx]);
     let base_batch = arrow::compute::take_record_batch(updates_batch, &indices)
         .map_err(|e| format!("Failed to extract update record: {}", e))?;
 
@@ -733,28 +2056,28 @@ fn create_merged_segment_cross_batch(
     let schema = base_batch.schema();
     let mut new_columns: Vec<arrow::array::ArrayRef> = Vec::with_capacity(schema.fields().len());
 
-    for field in schema.fields() {
+    for (col_idx, field) in schema.fields().iter().enumerate() {
         let col_name = field.name();
 
-        match col_name.as_str() {
-            "effective_from" => {
+        match (col_name.as_str(), stamp_as_of_from) {
+            ("effective_from", _) => {
                 // Set to merged start time
                 let array = create_timestamp_array(field.data_type(), merged_from, 1)?;
                 new_columns.push(array);
             },
-            "effective_to" => {
+            ("effective_to", _) => {
                 // Set to merged end time
                 let array = create_timestamp_array(field.data_type(), merged_to, 1)?;
                 new_columns.push(array);
             },
-            "as_of_from" => {
-                // Use batch_timestamp for the merged record (newer knowledge)
-                let array = create_timestamp_array(field.data_type(), batch_timestamp, 1)?;
+            ("as_of_from", Some(stamp)) => {
+                // Use the batch's as_of_from for the merged record (newer knowledge)
+                let array = create_timestamp_array(field.data_type(), stamp, 1)?;
                 new_columns.push(array);
             },
             _ => {
                 // Keep all other columns from the update record
-                new_columns.push(base_batch.column_by_name(col_name).unwrap().clone());
+                new_columns.push(base_batch.column(col_idx).clone());
             }
         }
     }
@@ -763,7 +2086,8 @@ fn create_merged_segment_cross_batch(
         .map_err(|e| format!("Failed to create merged batch: {}", e))
 }
 
-/// Create a timestamp array with a single value, preserving the original data type
+/// Create a timestamp array repeating a single value, preserving the original data type.
+/// The value is converted once and the array filled directly, without per-row work
 fn create_timestamp_array(
     data_type: &arrow::datatypes::DataType,
     datetime: NaiveDateTime,
@@ -772,6 +2096,52 @@ fn create_timestamp_array(
     use arrow::datatypes::TimeUnit;
     use arrow::array::*;
 
+    let since_epoch = datetime - chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
+
+    match data_type {
+        arrow::datatypes::DataType::Timestamp(time_unit, tz) => {
+            let timezone_str = tz.as_ref().map(|t| t.to_string());
+
+            let array: arrow::array::ArrayRef = match time_unit {
+                TimeUnit::Nanosecond => {
+                    let value = since_epoch.num_nanoseconds().ok_or("Timestamp overflow in nanoseconds")?;
+                    std::sync::Arc::new(TimestampNanosecondArray::from_value(value, length).with_timezone_opt(timezone_str))
+                }
+                TimeUnit::Microsecond => {
+                    let value = since_epoch.num_microseconds().ok_or("Timestamp overflow in microseconds")?;
+                    std::sync::Arc::new(TimestampMicrosecondArray::from_value(value, length).with_timezone_opt(timezone_str))
+                }
+                TimeUnit::Millisecond => {
+                    std::sync::Arc::new(TimestampMillisecondArray::from_value(since_epoch.num_milliseconds(), length)
+                        .with_timezone_opt(timezone_str))
+                }
+                TimeUnit::Second => {
+                    std::sync::Arc::new(TimestampSecondArray::from_value(since_epoch.num_seconds(), length)
+                        .with_timezone_opt(timezone_str))
+                }
+            };
+            Ok(array)
+        }
+        arrow::datatypes::DataType::Date32 => {
+            let days = (datetime.date() - chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32;
+            Ok(std::sync::Arc::new(Date32Array::from_value(days, length)))
+        }
+        arrow::datatypes::DataType::Date64 => {
+            Ok(std::sync::Arc::new(Date64Array::from_value(since_epoch.num_milliseconds(), length)))
+        }
+        _ => Err(format!("Unsupported temporal data type: {:?}", data_type))
+    }
+}
+
+/// Create a timestamp or date array from per-row values, preserving the original data type
+/// (time unit, timezone, or Date32/Date64 for date-granularity tables)
+fn create_timestamp_array_from_values(
+    data_type: &arrow::datatypes::DataType,
+    datetimes: &[NaiveDateTime],
+) -> Result<arrow::array::ArrayRef, String> {
+    use arrow::datatypes::TimeUnit;
+    use arrow::array::*;
+
     let epoch = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
 
     match data_type {
@@ -780,31 +2150,29 @@ fn create_timestamp_array(
 
             let array: arrow::array::ArrayRef = match time_unit {
                 TimeUnit::Nanosecond => {
-                    let nanoseconds = (datetime - epoch).num_nanoseconds()
-                        .ok_or("Timestamp overflow in nanoseconds")?;
-                    let values = vec![Some(nanoseconds); length];
+                    let values = datetimes.iter()
+                        .map(|dt| (*dt - epoch).num_nanoseconds().ok_or("Timestamp overflow in nanoseconds"))
+                        .collect::<Result<Vec<_>, _>>()?;
                     let array = TimestampNanosecondArray::from(values)
                         .with_timezone_opt(timezone_str);
                     std::sync::Arc::new(array)
                 }
                 TimeUnit::Microsecond => {
-                    let microseconds = (datetime - epoch).num_microseconds()
-                        .ok_or("Timestamp overflow in microseconds")?;
-                    let values = vec![Some(microseconds); length];
+                    let values = datetimes.iter()
+                        .map(|dt| (*dt - epoch).num_microseconds().ok_or("Timestamp overflow in microseconds"))
+                        .collect::<Result<Vec<_>, _>>()?;
                     let array = TimestampMicrosecondArray::from(values)
                         .with_timezone_opt(timezone_str);
                     std::sync::Arc::new(array)
                 }
                 TimeUnit::Millisecond => {
-                    let milliseconds = (datetime - epoch).num_milliseconds();
-                    let values = vec![Some(milliseconds); length];
+                    let values: Vec<i64> = datetimes.iter().map(|dt| (*dt - epoch).num_milliseconds()).collect();
                     let array = TimestampMillisecondArray::from(values)
                         .with_timezone_opt(timezone_str);
                     std::sync::Arc::new(array)
                 }
                 TimeUnit::Second => {
-                    let seconds = (datetime - epoch).num_seconds();
-                    let values = vec![Some(seconds); length];
+                    let values: Vec<i64> = datetimes.iter().map(|dt| (*dt - epoch).num_seconds()).collect();
                     let array = TimestampSecondArray::from(values)
                         .with_timezone_opt(timezone_str);
                     std::sync::Arc::new(array)
@@ -813,13 +2181,11 @@ fn create_timestamp_array(
             Ok(array)
         }
         arrow::datatypes::DataType::Date32 => {
-            let days = (datetime.date() - chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32;
-            let values = vec![Some(days); length];
+            let values: Vec<i32> = datetimes.iter().map(|dt| (dt.date() - epoch.date()).num_days() as i32).collect();
             Ok(std::sync::Arc::new(Date32Array::from(values)))
         }
         arrow::datatypes::DataType::Date64 => {
-            let millis = (datetime - epoch).num_milliseconds();
-            let values = vec![Some(millis); length];
+            let values: Vec<i64> = datetimes.iter().map(|dt| (*dt - epoch).num_milliseconds()).collect();
             Ok(std::sync::Arc::new(Date64Array::from(values)))
         }
         _ => Err(format!("Unsupported temporal data type: {:?}", data_type))
@@ -833,173 +2199,193 @@ fn process_full_state_optimized(
     update_row_indices: &[usize],
     current_batch: &RecordBatch,
     updates_batch: &RecordBatch,
-    value_columns: &[String],
-    _system_date: NaiveDate,
-    _batch_timestamp: chrono::NaiveDateTime,
-    expire_indices: &mut Vec<usize>,
+    columns: &crate::batch_utils::InputColumns,
+    merged_as_of_from: Option<chrono::NaiveDateTime>,
+    expire_indices: &mut Vec<(usize, ExpireReason)>,
     insert_batches: &mut Vec<RecordBatch>,
+    skipped_updates: &mut Vec<SkippedUpdate>,
+    value_rows: Option<&crate::arrow_hash::ValueRows>,
 ) -> Result<(), String> {
-    // For full state mode, we need to compare hashes efficiently
-    // Get value hash arrays if they exist
-    let current_hash_array = current_batch.column_by_name("value_hash")
-        .map(|col| col.as_any().downcast_ref::<arrow::array::StringArray>().unwrap());
-    let updates_hash_array = updates_batch.column_by_name("value_hash")
-        .map(|col| col.as_any().downcast_ref::<arrow::array::StringArray>().unwrap());
-    
-    if let (Some(current_hashes), Some(update_hashes)) = (current_hash_array, updates_hash_array) {
-        // Enhanced full_state mode with temporal awareness:
-        // - Different values (different hash) -> expire old, insert new
-        // - Same values (same hash) + adjacent temporal segments -> merge into single segment
-        // - Same values + non-adjacent temporal segments -> insert update as-is
-        // - Same values + exact same temporal range -> do nothing (true no-change)
-
-        // Track which updates need to be inserted (not merged)
-        let mut updates_to_insert = Vec::new();
-
-        // For each update, determine the relationship with current state
-        for &update_idx in update_row_indices {
-            let update_hash = update_hashes.value(update_idx);
-            let update_temporal = get_temporal_bounds(updates_batch, update_idx)?;
-
-            // Find if there's a matching current record (same hash)
-            // Keep track of the best match type found so far
-            // Priority: exact match > adjacent > any match
-            let mut best_match_idx: Option<usize> = None;
-            let mut best_is_exact = false;
-            let mut best_is_adjacent = false;
-
-            for &current_idx in current_row_indices {
-                let current_hash = current_hashes.value(current_idx);
-
-                if current_hash == update_hash {
-                    // Found a matching value hash
-                    let current_temporal = get_temporal_bounds(current_batch, current_idx)?;
-
-                    // Check temporal relationship
-                    if current_temporal == update_temporal {
-                        // Exact same temporal range with same values = no change
-                        // This is the best possible match - stop searching
-                        best_match_idx = Some(current_idx);
-                        best_is_exact = true;
-                        best_is_adjacent = false;
-                        break;
-                    } else if are_segments_adjacent(
-                        current_temporal.0, current_temporal.1,
-                        update_temporal.0, update_temporal.1
-                    ) {
-                        // Adjacent match is better than no temporal relationship
-                        // But keep looking in case there's an exact match
-                        if !best_is_exact {
-                            best_match_idx = Some(current_idx);
-                            best_is_adjacent = true;
-                        }
-                    } else if best_match_idx.is_none() {
-                        // No better match found yet, record this one
+    // For full state mode, we need to compare hashes efficiently. `prepare_inputs` hashes
+    // both inputs, so a missing hash column means they bypassed it.
+    let current_hashes = columns.current.value_hash(current_batch)
+        .ok_or_else(|| format!("{} column not found in current state", VALUE_HASH_COLUMN))
+        .and_then(|col| crate::batch_utils::downcast_column::<arrow::array::StringArray>(col, VALUE_HASH_COLUMN, "Utf8"))?;
+    let update_hashes = columns.updates.value_hash(updates_batch)
+        .ok_or_else(|| format!("{} column not found in updates", VALUE_HASH_COLUMN))
+        .and_then(|col| crate::batch_utils::downcast_column::<arrow::array::StringArray>(col, VALUE_HASH_COLUMN, "Utf8"))?;
+
+    // Enhanced full_state mode with temporal awareness:
+    // - Different values (different hash) -> expire old, insert new
+    // - Same values (same hash) + adjacent temporal segments -> merge the whole adjacent chain
+    // - Same values + non-adjacent temporal segments -> insert update as-is
+    // - Same values + exact same temporal range -> do nothing (true no-change)
+
+    // Track which updates need to be inserted (not merged)
+    let mut updates_to_insert = Vec::new();
+    // Current rows and later updates already coalesced into a merged segment
+    let mut merged_current: FxHashSet<usize> = FxHashSet::default();
+    let mut absorbed_updates: FxHashSet<usize> = FxHashSet::default();
+
+    // For each update, determine the relationship with current state
+    for (position, &update_idx) in update_row_indices.iter().enumerate() {
+        if absorbed_updates.contains(&update_idx) {
+            continue;
+        }
+        let update_hash = update_hashes.value(update_idx);
+        let update_temporal = columns.updates.temporal_micros(updates_batch, update_idx)?;
+
+        // Find if there's a matching current record (same hash)
+        // Keep track of the best match type found so far
+        // Priority: exact match > adjacent > any match
+        let mut best_match_idx: Option<usize> = None;
+        let mut best_is_exact = false;
+        let mut best_is_adjacent = false;
+
+        for &current_idx in current_row_indices {
+            let current_hash = current_hashes.value(current_idx);
+
+            if current_hash == update_hash
+                && crate::arrow_hash::confirm_hash_match(value_rows, current_idx, update_idx)
+            {
+                // Found a matching value hash
+                let current_temporal = columns.current.temporal_micros(current_batch, current_idx)?;
+
+                // Check temporal relationship
+                if current_temporal == update_temporal {
+                    // Exact same temporal range with same values = no change
+                    // This is the best possible match - stop searching
+                    best_match_idx = Some(current_idx);
+                    best_is_exact = true;
+                    best_is_adjacent = false;
+                    break;
+                } else if are_segments_adjacent(
+                    current_temporal.0, current_temporal.1,
+                    update_temporal.0, update_temporal.1
+                ) {
+                    // Adjacent match is better than no temporal relationship
+                    // But keep looking in case there's an exact match
+                    if !best_is_exact {
                         best_match_idx = Some(current_idx);
+                        best_is_adjacent = true;
                     }
+                } else if best_match_idx.is_none() {
+                    // No better match found yet, record this one
+                    best_match_idx = Some(current_idx);
                 }
             }
+        }
 
-            // Decision logic based on the best match found
-            match (best_match_idx, best_is_adjacent, best_is_exact) {
-                (Some(current_idx), true, _) => {
-                    // Case 1: Adjacent segments with same values
-                    // Check if we should prevent merging (tombstone + open-ended update)
-                    let current_temporal = get_temporal_bounds(current_batch, current_idx)?;
-
-                    if should_prevent_merge(current_temporal.1, update_temporal.1) {
-                        // Current is a tombstone (bounded) and update is open-ended
-                        // DON'T merge - this preserves the historical tombstone and adds
-                        // the new record as a distinct temporal segment
-                        // (important for backfill scenarios)
-                        updates_to_insert.push(update_idx);
-                    } else {
-                        // Safe to merge: either both bounded, both open, or extending backward
-                        expire_indices.push(current_idx);
-                        let merged_batch = create_merged_segment_cross_batch(
-                            current_batch,
-                            updates_batch,
-                            current_idx,
-                            update_idx,
-                            _batch_timestamp,
-                        )?;
-                        insert_batches.push(merged_batch);
-                    }
-                },
-                (Some(_), false, true) => {
-                    // Case 2: Exact same temporal range with same values -> NO CHANGE
-                    // Do nothing (no expire, no insert)
-                },
-                (Some(current_idx), false, false) => {
-                    // Case 3: Same values (same hash) but different non-adjacent temporal ranges
-                    let current_temporal = get_temporal_bounds(current_batch, current_idx)?;
-
-                    if current_temporal.0 <= update_temporal.0 && current_temporal.1 >= update_temporal.1 {
-                        // Update is fully contained within current record with same values
-                        // This is a no-change scenario (current already covers this period)
-                        // Do nothing - don't insert
-                    } else if current_temporal.0 == update_temporal.0 && update_temporal.1 > current_temporal.1 {
-                        // Update starts at same point but EXTENDS BEYOND current (same values)
-                        // e.g., current [2025-10-10, 2025-10-11), update [2025-10-10, infinity)
-                        // Since values are the same, this is effectively "reopening" the record
-                        // Expire current and insert the extended update to avoid overlap
-                        expire_indices.push(current_idx);
-                        updates_to_insert.push(update_idx);
-                    } else {
-                        // Different temporal ranges that don't overlap at start
-                        // Insert the update as a separate temporal segment
-                        updates_to_insert.push(update_idx);
-                    }
-                },
-                (None, _, _) => {
-                    // Case 4: Different values -> expire all current, insert update
-                    if updates_to_insert.is_empty() {
-                        // Only expire once for this ID group
-                        expire_indices.extend(current_row_indices.iter().cloned());
+        // Decision logic based on the best match found
+        match (best_match_idx, best_is_adjacent, best_is_exact) {
+            (Some(current_idx), true, _) => {
+                // Case 1: Adjacent segments with same values
+                // Check if we should prevent merging (tombstone + open-ended update)
+                let current_temporal = columns.current.temporal_micros(current_batch, current_idx)?;
+
+                if should_prevent_merge(current_temporal.1, update_temporal.1) {
+                    // Current is a tombstone (bounded) and update is open-ended
+                    // DON'T merge - this preserves the historical tombstone and adds
+                    // the new record as a distinct temporal segment
+                    // (important for backfill scenarios)
+                    updates_to_insert.push(update_idx);
+                } else {
+                    // Safe to merge: either both bounded, both open, or extending backward.
+                    // Keep coalescing same-valued current rows and later updates adjacent to
+                    // either end, so current A + update B + current C become one segment.
+                    let mut merged_range = (current_temporal.0.min(update_temporal.0), current_temporal.1.max(update_temporal.1));
+                    let mut chained_current = vec![current_idx];
+                    loop {
+                        let mut extended = false;
+                        for &other_idx in current_row_indices {
+                            if chained_current.contains(&other_idx) || merged_current.contains(&other_idx)
+                                || current_hashes.value(other_idx) != update_hash
+                                || !crate::arrow_hash::confirm_hash_match(value_rows, other_idx, update_idx)
+                            {
+                                continue;
+                            }
+                            let other = columns.current.temporal_micros(current_batch, other_idx)?;
+                            if are_segments_adjacent(other.0, other.1, merged_range.0, merged_range.1)
+                                && !should_prevent_merge(other.1, merged_range.1)
+                            {
+                                merged_range = (merged_range.0.min(other.0), merged_range.1.max(other.1));
+                                chained_current.push(other_idx);
+                                extended = true;
+                            }
+                        }
+                        for &other_idx in &update_row_indices[position + 1..] {
+                            if absorbed_updates.contains(&other_idx) || update_hashes.value(other_idx) != update_hash
+                                || !crate::arrow_hash::confirm_hash_match(value_rows, current_idx, other_idx)
+                            {
+                                continue;
+                            }
+                            // An open-ended update would reopen the bounded current rows in the chain
+                            let other = columns.updates.temporal_micros(updates_batch, other_idx)?;
+                            if are_segments_adjacent(other.0, other.1, merged_range.0, merged_range.1)
+                                && !is_open_ended(other.1)
+                            {
+                                merged_range = (merged_range.0.min(other.0), merged_range.1.max(other.1));
+                                absorbed_updates.insert(other_idx);
+                                extended = true;
+                            }
+                        }
+                        if !extended {
+                            break;
+                        }
                     }
+                    expire_indices.extend(chained_current.iter().map(|&idx| (idx, ExpireReason::Merged)));
+                    merged_current.extend(chained_current);
+                    let merged_range = (datetime_from_micros(merged_range.0)?, datetime_from_micros(merged_range.1)?);
+                    let merged_batch = create_merged_segment(updates_batch, update_idx, merged_range, merged_as_of_from)?;
+                    insert_batches.push(merged_batch);
+                }
+            },
+            (Some(_), false, true) => {
+                // Case 2: Exact same temporal range with same values -> NO CHANGE
+                // Do nothing (no expire, no insert)
+                skipped_updates.push(SkippedUpdate { update_index: update_idx, reason: SkipReason::ExactMatch });
+            },
+            (Some(current_idx), false, false) => {
+                // Case 3: Same values (same hash) but different non-adjacent temporal ranges
+                let current_temporal = columns.current.temporal_micros(current_batch, current_idx)?;
+
+                if current_temporal.0 <= update_temporal.0 && current_temporal.1 >= update_temporal.1 {
+                    // Update is fully contained within current record with same values
+                    // This is a no-change scenario (current already covers this period)
+                    // Do nothing - don't insert
+                    skipped_updates.push(SkippedUpdate { update_index: update_idx, reason: SkipReason::CoveredByCurrent });
+                } else if current_temporal.0 == update_temporal.0 && update_temporal.1 > current_temporal.1 {
+                    // Update starts at same point but EXTENDS BEYOND current (same values)
+                    // e.g., current [2025-10-10, 2025-10-11), update [2025-10-10, infinity)
+                    // Since values are the same, this is effectively "reopening" the record
+                    // Expire current and insert the extended update to avoid overlap
+                    expire_indices.push((current_idx, ExpireReason::Merged));
                     updates_to_insert.push(update_idx);
-                },
-            }
+                } else {
+                    // Different temporal ranges that don't overlap at start
+                    // Insert the update as a separate temporal segment
+                    updates_to_insert.push(update_idx);
+                }
+            },
+            (None, _, _) => {
+                // Case 4: Different values -> expire all current, insert update
+                if updates_to_insert.is_empty() {
+                    // Only expire once for this ID group
+                    expire_indices.extend(current_row_indices.iter().map(|&idx| (idx, ExpireReason::Overwritten)));
+                }
+                updates_to_insert.push(update_idx);
+            },
         }
+    }
 
-        // Insert updates that weren't merged
-        if !updates_to_insert.is_empty() {
-            let indices_array = arrow::array::UInt64Array::from(
-                updates_to_insert.iter().map(|&i| Some(i as u64)).collect::<Vec<_>>()
-            );
-            let updates_slice = arrow::compute::take_record_batch(updates_batch, &indices_array)
-                .map_err(|e| format!("Failed to slice updates batch: {}", e))?;
-            insert_batches.push(updates_slice);
-        }
-        
-    } else {
-        // Fallback to expensive comparison if no hash columns
-        // Convert to BitemporalRecords only when needed
-        let _current_records = create_bitemporal_records_from_indices(
-            current_row_indices,
-            current_batch,
-            &[], // Don't need ID columns for comparison
-            value_columns,
-        )?;
-        let _update_records = create_bitemporal_records_from_indices(
-            update_row_indices,
-            updates_batch,
-            &[],
-            value_columns,
-        )?;
-        
-        // Do full state comparison logic (implementation would go here)
-        // For now, expire all current and insert all updates
-        expire_indices.extend(current_row_indices.iter().cloned());
-        
-        let indices_array = arrow::array::UInt64Array::from(
-            update_row_indices.iter().map(|&i| Some(i as u64)).collect::<Vec<_>>()
-        );
+    // Insert updates that weren't merged
+    if !updates_to_insert.is_empty() {
+        let indices_array = crate::batch_utils::take_indices(updates_to_insert.iter().copied());
         let updates_slice = arrow::compute::take_record_batch(updates_batch, &indices_array)
             .map_err(|e| format!("Failed to slice updates batch: {}", e))?;
         insert_batches.push(updates_slice);
     }
-    
+
     Ok(())
 }
 
@@ -1010,6 +2396,10 @@ fn extract_datetime_flexible(array: &dyn arrow::array::Array, idx: usize) -> Res
     use arrow::array::*;
     use arrow::datatypes::TimeUnit;
     
+    if array.is_null(idx) {
+        return Err(format!("Null timestamp at row {}", idx));
+    }
+
     match array.data_type() {
         // Date32 - days since epoch
         arrow::datatypes::DataType::Date32 => {
@@ -1078,45 +2468,95 @@ fn extract_datetime_flexible(array: &dyn arrow::array::Array, idx: usize) -> Res
     }
 }
 
+/// Read a date/timestamp value as microseconds since the epoch without going through chrono.
+/// `Timestamp(Microsecond)`, the layout of nearly every input, is read directly; other units
+/// are scaled, with nanoseconds truncated to the microsecond.
+#[inline(always)]
+fn extract_micros_flexible(array: &dyn arrow::array::Array, idx: usize) -> Result<i64, String> {
+    use arrow::array::*;
+    use arrow::datatypes::TimeUnit;
+
+    if array.is_null(idx) {
+        return Err(format!("Null timestamp at row {}", idx));
+    }
+    if let Some(arr) = array.as_any().downcast_ref::<TimestampMicrosecondArray>() {
+        return Ok(arr.value(idx));
+    }
+
+    let (value, micros_per_unit) = match array.data_type() {
+        arrow::datatypes::DataType::Date32 => {
+            let arr = array.as_any().downcast_ref::<Date32Array>()
+                .ok_or("Failed to downcast to Date32Array")?;
+            (arr.value(idx) as i64, 86_400_000_000)
+        }
+        arrow::datatypes::DataType::Date64 => {
+            let arr = array.as_any().downcast_ref::<Date64Array>()
+                .ok_or("Failed to downcast to Date64Array")?;
+            (arr.value(idx), 1_000)
+        }
+        arrow::datatypes::DataType::Timestamp(TimeUnit::Second, _) => {
+            let arr = array.as_any().downcast_ref::<TimestampSecondArray>()
+                .ok_or("Failed to downcast to TimestampSecondArray")?;
+            (arr.value(idx), 1_000_000)
+        }
+        arrow::datatypes::DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            let arr = array.as_any().downcast_ref::<TimestampMillisecondArray>()
+                .ok_or("Failed to downcast to TimestampMillisecondArray")?;
+            (arr.value(idx), 1_000)
+        }
+        arrow::datatypes::DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            let arr = array.as_any().downcast_ref::<TimestampNanosecondArray>()
+                .ok_or("Failed to downcast to TimestampNanosecondArray")?;
+            return Ok(arr.value(idx).div_euclid(1_000));
+        }
+        _ => return extract_datetime_flexible(array, idx).map(|value| value.and_utc().timestamp_micros()),
+    };
+    value.checked_mul(micros_per_unit)
+        .ok_or_else(|| format!("Timestamp out of range at row {}", idx))
+}
+
+/// The `NaiveDateTime` of microseconds since the epoch read by `extract_micros_flexible`
+fn datetime_from_micros(micros: i64) -> Result<NaiveDateTime, String> {
+    chrono::DateTime::from_timestamp_micros(micros)
+        .map(|value| value.naive_utc())
+        .ok_or_else(|| format!("Invalid timestamp microseconds: {}", micros))
+}
+
 /// Create BitemporalRecords only when needed for temporal processing
-fn create_bitemporal_records_from_indices(
+/// Refills `records` in place so callers can reuse its allocation across ID groups
+fn fill_bitemporal_records(
     row_indices: &[usize],
     batch: &RecordBatch,
-    id_columns: &[String],
-    _value_columns: &[String],
-) -> Result<Vec<BitemporalRecord>, String> {
+    columns: &crate::batch_utils::ColumnIndices,
+    records: &mut Vec<BitemporalRecord>,
+) -> Result<(), String> {
+    records.clear();
     if row_indices.is_empty() {
-        return Ok(Vec::new());
+        return Ok(());
     }
-    
-    let mut records = Vec::with_capacity(row_indices.len());
+    records.reserve(row_indices.len());
     
     // Extract arrays once - now flexible with types
-    let eff_from_array = batch.column_by_name("effective_from")
-        .ok_or("effective_from column not found")?;
-    let eff_to_array = batch.column_by_name("effective_to")
-        .ok_or("effective_to column not found")?;
-    let as_of_from_array = batch.column_by_name("as_of_from")
-        .ok_or("as_of_from column not found")?;
+    let eff_from_array = columns.effective_from(batch)?;
+    let eff_to_array = columns.effective_to(batch)?;
+    let as_of_from_array = columns.as_of_from(batch)?;
     
     // Get the pre-computed hash column - it should always exist due to ensure_hash_column
-    let hash_array = batch.column_by_name("value_hash")
+    let hash_array = columns.value_hash(batch)
         .ok_or_else(|| "value_hash column not found - this should not happen".to_string())?
         .as_any().downcast_ref::<arrow::array::StringArray>()
         .ok_or_else(|| "value_hash column is not a StringArray".to_string())?;
+
+    let id_arrays = columns.id_arrays(batch)?;
     
     for &row_idx in row_indices {
-        let mut id_values = Vec::new();
-        for id_col in id_columns {
-            let col_idx = batch.schema().index_of(id_col)
-                .map_err(|_| format!("ID column {} not found", id_col))?;
-            let array = batch.column(col_idx);
-            id_values.push(ScalarValue::from_array(array, row_idx));
-        }
+        let id_values = id_arrays.iter()
+            .map(|array| ScalarValue::from_array(array, row_idx))
+            .collect();
         
         let record = BitemporalRecord {
             id_values,
-            value_hash: hash_array.value(row_idx).to_string(),
+            value_hash: crate::arrow_hash::value_hash_key(hash_array.value(row_idx)),
             effective_from: extract_datetime_flexible(eff_from_array.as_ref(), row_idx)?,
             effective_to: extract_datetime_flexible(eff_to_array.as_ref(), row_idx)?,
             as_of_from: extract_datetime_flexible(as_of_from_array.as_ref(), row_idx)?,
@@ -1127,175 +2567,146 @@ fn create_bitemporal_records_from_indices(
         records.push(record);
     }
     
-    Ok(records)
+    Ok(())
 }
 
 /// Fast ID key creation using string concatenation instead of expensive ScalarValue conversions
 /// PERFORMANCE: Inlined because this is called 850,000+ times (once per row)
 #[inline(always)]
-fn create_id_key_with_buffer(id_arrays: &[arrow::array::ArrayRef], row_idx: usize, buffer: &mut String) {
+pub(crate) fn create_id_key_with_buffer(id_arrays: &[arrow::array::ArrayRef], row_idx: usize, buffer: &mut String) {
     buffer.clear(); // Reuse existing allocation
     
     for (i, array) in id_arrays.iter().enumerate() {
         if i > 0 {
             buffer.push('|'); // Separator
         }
-        
-        // Fast string extraction without ScalarValue conversion
-        match array.data_type() {
-            arrow::datatypes::DataType::Utf8 => {
-                let string_array = array.as_any().downcast_ref::<arrow::array::StringArray>().unwrap();
-                if string_array.is_null(row_idx) {
-                    buffer.push_str("NULL");
-                } else {
-                    buffer.push_str(string_array.value(row_idx));
-                }
-            }
-            arrow::datatypes::DataType::Int32 => {
-                let int_array = array.as_any().downcast_ref::<arrow::array::Int32Array>().unwrap();
-                if int_array.is_null(row_idx) {
-                    buffer.push_str("NULL");
-                } else {
-                    buffer.push_str(&int_array.value(row_idx).to_string());
-                }
-            }
-            arrow::datatypes::DataType::Int64 => {
-                let int_array = array.as_any().downcast_ref::<arrow::array::Int64Array>().unwrap();
-                if int_array.is_null(row_idx) {
-                    buffer.push_str("NULL");
-                } else {
-                    buffer.push_str(&int_array.value(row_idx).to_string());
-                }
-            }
-            arrow::datatypes::DataType::Float64 => {
-                let float_array = array.as_any().downcast_ref::<arrow::array::Float64Array>().unwrap();
-                if float_array.is_null(row_idx) {
-                    buffer.push_str("NULL");
-                } else {
-                    buffer.push_str(&float_array.value(row_idx).to_string());
-                }
-            }
-            _ => {
-                // Fallback to ScalarValue for other types (but most ID columns are strings/ints)
-                let scalar = ScalarValue::from_array(array, row_idx);
-                buffer.push_str(&format!("{:?}", scalar));
-            }
-        }
+        push_id_value(array.as_ref(), row_idx, buffer);
     }
 }
 
-#[pyfunction]
-fn compute_changes(
-    current_state: PyRecordBatch,
-    updates: PyRecordBatch,
-    id_columns: Vec<String>,
-    value_columns: Vec<String>,
-    system_date: String,
-    update_mode: String,
-    conflate_inputs: Option<bool>,
-) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
-    compute_changes_with_hash_algorithm(current_state, updates, id_columns, value_columns, system_date, update_mode, None, conflate_inputs)
-}
-
-#[pyfunction]
-fn compute_changes_with_hash_algorithm(
-    current_state: PyRecordBatch,
-    updates: PyRecordBatch,
-    id_columns: Vec<String>,
-    value_columns: Vec<String>,
-    system_date: String,
-    update_mode: String,
-    hash_algorithm: Option<String>,
-    conflate_inputs: Option<bool>,
-) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
-    // Convert PyRecordBatch to Arrow RecordBatch
-    let current_batch = current_state.as_ref().clone();
-    let updates_batch = updates.as_ref().clone();
-
-    // Parse system_date
-    let system_date = chrono::NaiveDate::parse_from_str(&system_date, "%Y-%m-%d")
-        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid date format: {}", e)))?;
-
-    // Parse update_mode
-    let mode = match update_mode.as_str() {
-        "delta" => UpdateMode::Delta,
-        "full_state" => UpdateMode::FullState,
-        _ => return Err(pyo3::exceptions::PyValueError::new_err("Invalid update_mode. Must be 'delta' or 'full_state'")),
-    };
+/// Append one ID value to a key without allocating. Values that are equal across the Arrow
+/// types current state and updates may disagree on (Int32/Int64, Utf8/LargeUtf8, date and
+/// timestamp units) render identically; dates and timestamps are written as nanoseconds.
+/// UUIDs render in canonical lowercase form whether they arrive as FixedSizeBinary(16) or as
+/// text in either case.
+#[inline(always)]
+pub(crate) fn push_id_value(array: &dyn Array, row_idx: usize, buffer: &mut String) {
+    use arrow::array::AsArray;
+    use arrow::datatypes::*;
+    use std::fmt::Write;
+
+    // NUL cannot collide with the string "NULL" (or any realistic ID)
+    if array.is_null(row_idx) {
+        buffer.push('\0');
+        return;
+    }
 
-    // Parse hash algorithm
-    let algorithm = match hash_algorithm {
-        Some(algo_str) => HashAlgorithm::from_str(&algo_str)
-            .map_err(pyo3::exceptions::PyValueError::new_err)?,
-        None => HashAlgorithm::default(),
+    // Writing into a String cannot fail
+    let _ = match array.data_type() {
+        DataType::Utf8 => {
+            push_id_str(array.as_string::<i32>().value(row_idx), buffer);
+            Ok(())
+        }
+        DataType::LargeUtf8 => {
+            push_id_str(array.as_string::<i64>().value(row_idx), buffer);
+            Ok(())
+        }
+        DataType::Utf8View => {
+            push_id_str(array.as_string_view().value(row_idx), buffer);
+            Ok(())
+        }
+        DataType::Int8 => write!(buffer, "{}", array.as_primitive::<Int8Type>().value(row_idx)),
+        DataType::Int16 => write!(buffer, "{}", array.as_primitive::<Int16Type>().value(row_idx)),
+        DataType::Int32 => write!(buffer, "{}", array.as_primitive::<Int32Type>().value(row_idx)),
+        DataType::Int64 => write!(buffer, "{}", array.as_primitive::<Int64Type>().value(row_idx)),
+        DataType::UInt8 => write!(buffer, "{}", array.as_primitive::<UInt8Type>().value(row_idx)),
+        DataType::UInt16 => write!(buffer, "{}", array.as_primitive::<UInt16Type>().value(row_idx)),
+        DataType::UInt32 => write!(buffer, "{}", array.as_primitive::<UInt32Type>().value(row_idx)),
+        DataType::UInt64 => write!(buffer, "{}", array.as_primitive::<UInt64Type>().value(row_idx)),
+        DataType::Float32 => write!(buffer, "{}", array.as_primitive::<Float32Type>().value(row_idx)),
+        DataType::Float64 => write!(buffer, "{}", array.as_primitive::<Float64Type>().value(row_idx)),
+        DataType::Boolean => {
+            buffer.push_str(if array.as_boolean().value(row_idx) { "true" } else { "false" });
+            Ok(())
+        }
+        DataType::Date32 => {
+            let days = array.as_primitive::<Date32Type>().value(row_idx) as i128;
+            write!(buffer, "{}", days * 86_400_000_000_000)
+        }
+        DataType::Date64 => {
+            let millis = array.as_primitive::<Date64Type>().value(row_idx) as i128;
+            write!(buffer, "{}", millis * 1_000_000)
+        }
+        DataType::Timestamp(unit, _) => {
+            let nanos = match unit {
+                TimeUnit::Second => array.as_primitive::<TimestampSecondType>().value(row_idx) as i128 * 1_000_000_000,
+                TimeUnit::Millisecond => array.as_primitive::<TimestampMillisecondType>().value(row_idx) as i128 * 1_000_000,
+                TimeUnit::Microsecond => array.as_primitive::<TimestampMicrosecondType>().value(row_idx) as i128 * 1_000,
+                TimeUnit::Nanosecond => array.as_primitive::<TimestampNanosecondType>().value(row_idx) as i128,
+            };
+            write!(buffer, "{}", nanos)
+        }
+        DataType::Decimal128(_, scale) => {
+            write!(buffer, "{}e-{}", array.as_primitive::<Decimal128Type>().value(row_idx), scale)
+        }
+        DataType::Decimal256(_, scale) => {
+            write!(buffer, "{}e-{}", array.as_primitive::<Decimal256Type>().value(row_idx), scale)
+        }
+        DataType::FixedSizeBinary(16) => {
+            buffer.extend(canonical_uuid(array.as_fixed_size_binary().value(row_idx)).map(char::from));
+            Ok(())
+        }
+        DataType::FixedSizeBinary(_) => array
+            .as_fixed_size_binary()
+            .value(row_idx)
+            .iter()
+            .try_for_each(|byte| write!(buffer, "{:02x}", byte)),
+        _ => {
+            // Uncommon ID types go through Arrow's display formatter
+            match arrow::util::display::array_value_to_string(array, row_idx) {
+                Ok(value) => {
+                    buffer.push_str(&value);
+                    Ok(())
+                }
+                Err(_) => write!(buffer, "{:?}", array.slice(row_idx, 1)),
+            }
+        }
     };
-
-    // Parse conflate_inputs parameter (default to false for backward compatibility)
-    let conflate = conflate_inputs.unwrap_or(false);
-
-    // Call the process_updates function
-    let changeset = process_updates_with_algorithm(
-        current_batch,
-        updates_batch,
-        id_columns,
-        value_columns,
-        system_date,
-        mode,
-        algorithm,
-        conflate,
-    ).map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
-    
-    // Convert the result back to Python types
-    let expire_indices = changeset.to_expire;
-    let insert_batches: Vec<PyRecordBatch> = changeset.to_insert
-        .into_iter()
-        .map(PyRecordBatch::new)
-        .collect();
-    let expired_batches: Vec<PyRecordBatch> = changeset.expired_records
-        .into_iter()
-        .map(PyRecordBatch::new)
-        .collect();
-    
-    Ok((expire_indices, insert_batches, expired_batches))
 }
 
-#[pyfunction]
-fn add_hash_key(
-    record_batch: PyRecordBatch,
-    value_fields: Vec<String>,
-) -> PyResult<PyRecordBatch> {
-    add_hash_key_with_algorithm(record_batch, value_fields, None)
+/// String IDs go in as-is, except UUIDs, which are lowercased to their canonical form
+#[inline(always)]
+fn push_id_str(value: &str, buffer: &mut String) {
+    if is_uuid_text(value) {
+        buffer.extend(value.chars().map(|c| c.to_ascii_lowercase()));
+    } else {
+        buffer.push_str(value);
+    }
 }
 
-#[pyfunction]
-fn add_hash_key_with_algorithm(
-    record_batch: PyRecordBatch,
-    value_fields: Vec<String>,
-    hash_algorithm: Option<String>,
-) -> PyResult<PyRecordBatch> {
-    // Convert PyRecordBatch to Arrow RecordBatch
-    let batch = record_batch.as_ref().clone();
-    
-    // Parse hash algorithm
-    let algorithm = match hash_algorithm {
-        Some(algo_str) => HashAlgorithm::from_str(&algo_str)
-            .map_err(pyo3::exceptions::PyValueError::new_err)?,
-        None => HashAlgorithm::default(),
-    };
-    
-    // Call the fast Arrow-direct hash function
-    let batch_with_hash = crate::arrow_hash::add_hash_column_arrow_direct(&batch, &value_fields, algorithm)
-        .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
-    
-    // Convert back to PyRecordBatch
-    Ok(PyRecordBatch::new(batch_with_hash))
+/// Whether `value` is a UUID in the hyphenated 8-4-4-4-12 hex layout, in either case
+#[inline(always)]
+fn is_uuid_text(value: &str) -> bool {
+    value.len() == 36
+        && value.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
 }
 
-#[pymodule]
-fn pytemporal(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(compute_changes, m)?)?;
-    m.add_function(wrap_pyfunction!(compute_changes_with_hash_algorithm, m)?)?;
-    m.add_function(wrap_pyfunction!(add_hash_key, m)?)?;
-    m.add_function(wrap_pyfunction!(add_hash_key_with_algorithm, m)?)?;
-    Ok(())
+/// Canonical text of a 16-byte UUID: lowercase hex, hyphenated 8-4-4-4-12
+#[inline(always)]
+pub(crate) fn canonical_uuid(bytes: &[u8]) -> [u8; 36] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = [b'-'; 36];
+    let mut pos = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            pos += 1;
+        }
+        out[pos] = HEX[(byte >> 4) as usize];
+        out[pos + 1] = HEX[(byte & 0x0f) as usize];
+        pos += 2;
+    }
+    out
 }
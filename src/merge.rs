@@ -0,0 +1,135 @@
+use crate::types::ChangeSet;
+use crate::{create_id_key_with_buffer, create_timestamp_array_from_values, extract_datetime_flexible};
+use arrow::array::{Array, ArrayRef, BooleanArray, RecordBatch};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+
+/// A row as `changeset_to_sql` finds it: ID key, `effective_from` and `as_of_from`
+type RowKey = (String, NaiveDateTime, NaiveDateTime);
+
+/// Combine changesets computed one after another (each against the state the previous one
+/// left behind) into the net changeset that takes the first one's current state to the
+/// last one's result.
+///
+/// An expiry of a row inserted by an earlier changeset cancels against that insert: the row
+/// is inserted already closed at the later `as_of_to`, or not at all when it was closed at
+/// the system time it was inserted. Every other expiry and insert is kept, in order.
+///
+/// Only `to_insert` and `expired_records` are merged, so apply the result through
+/// `changeset_to_sql` or `to_cdc_batch`. `to_expire` is left empty because each input's
+/// indices refer to a different current state; per-run reports and metrics are dropped.
+pub fn merge_changesets(changesets: Vec<ChangeSet>, id_columns: &[String]) -> Result<ChangeSet, String> {
+    let mut id_key_buffer = String::with_capacity(64);
+    let mut inserts: Vec<(RecordBatch, Vec<Option<NaiveDateTime>>)> = Vec::new();
+    // Inserted rows still open to a later expiry -> (insert batch, row)
+    let mut open_inserts: FxHashMap<RowKey, (usize, usize)> = FxHashMap::default();
+    let mut expired_records = Vec::new();
+
+    for changeset in changesets {
+        for batch in changeset.expired_records {
+            let keys = row_keys(&batch, id_columns, &mut id_key_buffer)?;
+            let as_of_to = batch.column_by_name("as_of_to")
+                .ok_or_else(|| "as_of_to column not found in expired records".to_string())?;
+            let mut keep = Vec::with_capacity(keys.len());
+            for (row, key) in keys.into_iter().enumerate() {
+                match open_inserts.remove(&key) {
+                    Some((insert_batch, insert_row)) => {
+                        inserts[insert_batch].1[insert_row] = Some(extract_datetime_flexible(as_of_to.as_ref(), row)?);
+                        keep.push(false);
+                    }
+                    None => keep.push(true),
+                }
+            }
+            let kept = filter_rows(&batch, keep)?;
+            if kept.num_rows() > 0 {
+                expired_records.push(kept);
+            }
+        }
+
+        for batch in changeset.to_insert {
+            for (row, key) in row_keys(&batch, id_columns, &mut id_key_buffer)?.into_iter().enumerate() {
+                open_inserts.insert(key, (inserts.len(), row));
+            }
+            let closed_at = vec![None; batch.num_rows()];
+            inserts.push((batch, closed_at));
+        }
+    }
+
+    let mut to_insert = Vec::with_capacity(inserts.len());
+    for (batch, closed_at) in inserts {
+        let batch = close_inserts(batch, &closed_at)?;
+        if batch.num_rows() > 0 {
+            to_insert.push(batch);
+        }
+    }
+
+    Ok(ChangeSet { to_insert, expired_records, ..Default::default() })
+}
+
+fn row_keys(batch: &RecordBatch, id_columns: &[String], id_key_buffer: &mut String) -> Result<Vec<RowKey>, String> {
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)
+        .map_err(|e| format!("{} in changeset batch", e))?;
+    let temporal = |name: &str| batch.column_by_name(name)
+        .ok_or_else(|| format!("{} column not found in changeset batch", name));
+    let (effective_from, as_of_from) = (temporal("effective_from")?, temporal("as_of_from")?);
+
+    (0..batch.num_rows())
+        .map(|row| {
+            create_id_key_with_buffer(&id_arrays, row, id_key_buffer);
+            Ok((
+                id_key_buffer.clone(),
+                extract_datetime_flexible(effective_from.as_ref(), row)?,
+                extract_datetime_flexible(as_of_from.as_ref(), row)?,
+            ))
+        })
+        .collect()
+}
+
+/// Set `as_of_to` of the rows closed by a later changeset, dropping those closed when they
+/// were inserted
+fn close_inserts(batch: RecordBatch, closed_at: &[Option<NaiveDateTime>]) -> Result<RecordBatch, String> {
+    if closed_at.iter().all(Option::is_none) {
+        return Ok(batch);
+    }
+
+    let schema = batch.schema();
+    let as_of_to_idx = schema.index_of("as_of_to")
+        .map_err(|_| "as_of_to column not found in inserted rows".to_string())?;
+    let as_of_from = batch.column_by_name("as_of_from")
+        .ok_or_else(|| "as_of_from column not found in inserted rows".to_string())?;
+    let as_of_to = batch.column(as_of_to_idx);
+
+    let mut values = Vec::with_capacity(batch.num_rows());
+    let mut keep = Vec::with_capacity(batch.num_rows());
+    for (row, closed) in closed_at.iter().enumerate() {
+        match closed {
+            Some(closed) => {
+                keep.push(*closed > extract_datetime_flexible(as_of_from.as_ref(), row)?);
+                values.push(*closed);
+            }
+            None => {
+                keep.push(true);
+                // Placeholder; the original value is kept below
+                values.push(NaiveDateTime::MIN);
+            }
+        }
+    }
+    let closed = create_timestamp_array_from_values(as_of_to.data_type(), &values)?;
+    let is_closed = BooleanArray::from(closed_at.iter().map(Option::is_some).collect::<Vec<_>>());
+    let new_as_of_to: ArrayRef = arrow::compute::kernels::zip::zip(&is_closed, &closed, as_of_to)
+        .map_err(|e| format!("Failed to close merged inserts: {}", e))?;
+
+    let mut columns = batch.columns().to_vec();
+    columns[as_of_to_idx] = new_as_of_to;
+    let batch = RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to close merged inserts: {}", e))?;
+    filter_rows(&batch, keep)
+}
+
+fn filter_rows(batch: &RecordBatch, keep: Vec<bool>) -> Result<RecordBatch, String> {
+    if keep.iter().all(|&k| k) {
+        return Ok(batch.clone());
+    }
+    arrow::compute::filter_record_batch(batch, &BooleanArray::from(keep))
+        .map_err(|e| format!("Failed to filter changeset rows: {}", e))
+}
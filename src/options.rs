@@ -0,0 +1,616 @@
+use crate::{HashAlgorithm, MAX_DATETIME, VALUE_HASH_COLUMN};
+use chrono::NaiveDateTime;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// How overlapping update rows for the same ID within one batch are resolved (delta mode)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ConflictResolution {
+    /// Legacy behaviour: whichever update the timeline sweep saw first wins
+    #[default]
+    EventOrder,
+    /// The update with the latest `as_of_from` wins (ties go to the later input row)
+    LatestAsOf,
+    /// The update with the highest value in the named integer column wins
+    /// (ties fall back to latest `as_of_from`, nulls rank lowest)
+    Priority(String),
+}
+
+/// What happens to update rows that reach before `ProcessingOptions::min_effective_from`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatermarkPolicy {
+    /// Trim `effective_from` up to the watermark; rows entirely before it are skipped
+    #[default]
+    Clamp,
+    /// Fail the whole load, listing the offending update rows
+    Reject,
+}
+
+/// A hash column over its own subset of the value columns (see `ProcessingOptions::hash_groups`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashGroup {
+    /// Name of the hash column, e.g. `price_hash`
+    pub column: String,
+    /// Value columns it hashes, in order
+    pub value_columns: Vec<String>,
+}
+
+impl HashGroup {
+    pub fn new(column: impl Into<String>, value_columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        HashGroup { column: column.into(), value_columns: value_columns.into_iter().map(Into::into).collect() }
+    }
+}
+
+/// How far a numeric value column may move before it counts as a change
+/// (see `ProcessingOptions::value_tolerances`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueTolerance {
+    pub column: String,
+    /// Largest absolute difference treated as no change
+    pub absolute: f64,
+    /// Largest difference relative to the larger magnitude of the two values
+    pub relative: f64,
+}
+
+impl ValueTolerance {
+    pub fn new(column: impl Into<String>, absolute: f64, relative: f64) -> Self {
+        ValueTolerance { column: column.into(), absolute, relative }
+    }
+}
+
+/// When a row counts as changed under `ProcessingOptions::hash_groups`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashGroupChange {
+    /// Any group's hash differs, as with the single value hash
+    #[default]
+    Any,
+    /// Every group's hash differs
+    All,
+}
+
+/// Whether ID groups are processed on the rayon thread pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parallelism {
+    /// Parallel for more than 25 ID groups or more than 5,000 input rows, serial otherwise
+    #[default]
+    Auto,
+    /// Always on the calling thread
+    Serial,
+    /// Always on the thread pool
+    Parallel,
+}
+
+/// Source of the batch timestamp a run stamps on expired rows (`as_of_to`), tombstones and
+/// filled `as_of_from` values
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// The system clock, in UTC (the default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        chrono::Utc::now().naive_utc()
+    }
+}
+
+/// A clock stopped at one instant, so tests can assert exact batch timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub NaiveDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+/// Where and how often `process_updates` checkpoints finished ID groups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Directory for the manifest and one Arrow IPC file per finished chunk of ID groups
+    pub dir: std::path::PathBuf,
+    /// ID groups per chunk; a checkpoint is written after every chunk
+    pub groups_per_chunk: usize,
+}
+
+impl Checkpoint {
+    /// Checkpoint into `dir` every 10,000 ID groups
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Checkpoint { dir: dir.into(), groups_per_chunk: 10_000 }
+    }
+}
+
+/// Replay protection for redelivered update batches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyLedger {
+    /// Directory holding one Arrow IPC entry per processed batch ID
+    pub dir: std::path::PathBuf,
+    /// Caller-supplied ID of this update batch (e.g. a queue message or file ID)
+    pub batch_id: String,
+}
+
+/// How rows with a null in an ID column are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullIdPolicy {
+    /// Null is a value of its own: rows with nulls in the same ID columns (and equal values
+    /// elsewhere) form one ID group
+    #[default]
+    Group,
+    /// Fail the load, listing the rows with null IDs
+    Reject,
+}
+
+/// Cooperative cancellation flag, checked between ID groups; clones share the flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the run to stop; it fails with "Processing cancelled" before the next ID group
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Current-state value hashes kept between runs (see `ProcessingOptions::hash_cache`);
+/// clones share the cache
+#[derive(Debug, Clone)]
+pub struct HashCache {
+    pub(crate) entries: Arc<Mutex<VecDeque<crate::hash_cache::CachedHashes>>>,
+    pub(crate) capacity: usize,
+    pub(crate) hits: Arc<AtomicUsize>,
+}
+
+impl Default for HashCache {
+    fn default() -> Self {
+        HashCache::with_capacity(8)
+    }
+}
+
+impl HashCache {
+    /// A cache of the 8 most recently hashed batches
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache of the `capacity` most recently hashed batches
+    pub fn with_capacity(capacity: usize) -> Self {
+        HashCache { entries: Arc::default(), capacity, hits: Arc::default() }
+    }
+
+    /// Batches currently cached
+    pub fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Runs that reused cached hashes instead of computing them
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Drop every entry, releasing the arrays they keep alive
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+/// Receives the percentage (0-100) of ID groups processed, once per whole percent.
+/// Called from worker threads when ID groups are processed in parallel.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(f64) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(f64) + Send + Sync + 'static) -> Self {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, percent: f64) {
+        (self.0)(percent)
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Row-level validation of the updates batch before processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// No validation pass (invalid rows are skipped or fail deep inside processing)
+    #[default]
+    Off,
+    /// Drop invalid rows and report them in `ChangeSet::validation_issues`
+    Lenient,
+    /// Fail the whole load with a report of every invalid row
+    Strict,
+}
+
+/// What happens to update rows whose effective range is empty (`effective_from >= effective_to`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyRangePolicy {
+    /// Drop them during processing (listed as `empty_range` skips when
+    /// `report_skipped_updates` is set)
+    #[default]
+    Drop,
+    /// Fail the whole load with a report of every empty-range row
+    Error,
+    /// Drop them and report them in `ChangeSet::validation_issues`, whatever the validation mode
+    Report,
+}
+
+/// Calendar period that inserted segments are split at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarBoundary {
+    /// Split at the first of every month
+    Month,
+    /// Split at the first of January, April, July and October
+    Quarter,
+}
+
+/// What routes an output row to a shard
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ShardKey {
+    /// The ID columns, so every row of an ID lands in the same shard
+    #[default]
+    IdColumns,
+    /// A partition column of the output rows (e.g. `source_system`)
+    Column(String),
+}
+
+/// Split of the output for writers that each own a shard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSharding {
+    /// Number of shards (at least 1)
+    pub shards: usize,
+    pub key: ShardKey,
+}
+
+/// How a temporal column missing from the updates batch is populated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TemporalFill {
+    /// The column must be present (legacy behaviour)
+    #[default]
+    Required,
+    /// Open-ended sentinel (2262-04-11 23:59:59)
+    Max,
+    /// The timestamp the batch is processed at
+    BatchTimestamp,
+    /// A fixed value for every row
+    Value(NaiveDateTime),
+}
+
+impl TemporalFill {
+    pub(crate) fn resolve(&self, batch_timestamp: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            TemporalFill::Required => None,
+            TemporalFill::Max => Some(MAX_DATETIME),
+            TemporalFill::BatchTimestamp => Some(batch_timestamp),
+            TemporalFill::Value(value) => Some(*value),
+        }
+    }
+}
+
+/// Per-column fill rules for temporal columns that producers leave out of the updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MissingTemporalColumns {
+    pub effective_to: TemporalFill,
+    pub as_of_from: TemporalFill,
+    pub as_of_to: TemporalFill,
+}
+
+impl MissingTemporalColumns {
+    /// Open-ended `effective_to` / `as_of_to` and `as_of_from` stamped with the batch timestamp
+    pub fn populate_defaults() -> Self {
+        MissingTemporalColumns {
+            effective_to: TemporalFill::Max,
+            as_of_from: TemporalFill::BatchTimestamp,
+            as_of_to: TemporalFill::Max,
+        }
+    }
+}
+
+/// Shape of the update rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// Each row carries its own `effective_from` / `effective_to` range
+    #[default]
+    Ranges,
+    /// Each row is an observation at a single `effective_date` (or `effective_from`);
+    /// rows are chained per ID so each one runs until the next observation
+    Events,
+}
+
+/// How `effective_to` is stored in the tables being processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntervalEnd {
+    /// Closed-open `[from, to)`: adjacent segments share a boundary (`to == next.from`)
+    #[default]
+    Exclusive,
+    /// Closed-closed `[from, to]`: adjacent segments are `step` apart (`to + step == next.from`),
+    /// where `step` is the table's granularity (one day for date tables)
+    Inclusive { step: chrono::Duration },
+}
+
+impl IntervalEnd {
+    /// Closed-closed intervals at day granularity
+    pub fn inclusive_days() -> Self {
+        IntervalEnd::Inclusive { step: chrono::Duration::days(1) }
+    }
+}
+
+/// Optional processing settings that extend the core `process_updates` arguments.
+///
+/// Construct with struct update syntax so new settings stay backward compatible:
+/// `ProcessingOptions { conflate_inputs: true, ..Default::default() }`
+#[derive(Debug, Clone)]
+pub struct ProcessingOptions {
+    /// Hash algorithm used when the value_hash column has to be computed
+    pub hash_algorithm: HashAlgorithm,
+    /// Float value columns hashed by their exact bits. By default a float with no fractional
+    /// part hashes like the equal Int64, so 1.0 and 1 count as the same value; listed columns
+    /// opt out of that. Recorded on the value_hash field, and hashes tagged with another list
+    /// are refused rather than compared.
+    pub exact_float_columns: Vec<String>,
+    /// Compare the value columns whenever two rows' hashes match, so a hash collision is
+    /// treated as a change instead of silently suppressing it. Always on when `hash_algorithm`
+    /// is not xxhash, since those digests are narrowed to 64-bit keys for comparison.
+    pub verify_hash_matches: bool,
+    /// When current state has no value_hash column, hash only the current rows of IDs that also
+    /// have update rows; the others get an empty hash. Saves a pass over large, mostly untouched
+    /// state in delta mode. Ignored in full_state mode (IDs missing from the updates are expired
+    /// and keep their hashes) and with `conflate_inputs`.
+    pub lazy_hashing: bool,
+    /// Reuse the value hashes computed for a current state without a value_hash column when
+    /// a later run gets value columns backed by the same Arrow buffers (the same batch again).
+    /// Entries keep those arrays alive until evicted or cleared. Never hits together with
+    /// `open_current_rows_only`, which copies the open rows into fresh buffers on every run.
+    pub hash_cache: Option<HashCache>,
+    /// Glob (`px_*`) or `re:`-prefixed regex patterns selecting further ID / value columns
+    /// from the updates schema; each pattern must match at least one column
+    pub id_column_patterns: Vec<String>,
+    pub value_column_patterns: Vec<String>,
+    /// Name of the value hash column in inputs and outputs (default `value_hash`)
+    pub hash_column: String,
+    /// Drop the hash column from `to_insert` / `expired_records` before returning
+    pub exclude_hash_column: bool,
+    /// Further hash columns, each over its own subset of the value columns (e.g. `price_hash`
+    /// and `attr_hash`). They are computed where missing or empty and carried through to the
+    /// output next to the value hash.
+    pub hash_groups: Vec<HashGroup>,
+    /// Whether a row changes when any hash group differs (default) or only when all of them do.
+    /// Under `All` an update that overlaps a current row of its ID and shares a group hash with
+    /// it is skipped as `no_change`, leaving that row in place.
+    pub hash_group_change: HashGroupChange,
+    /// Numeric value columns whose changes within an absolute or relative tolerance are
+    /// ignored. After hashing, an update that overlaps a current row of its ID, has equal
+    /// values in every other column and is within tolerance on these takes that row's values
+    /// and hash, so float jitter from upstream recalculations does not churn history.
+    pub value_tolerances: Vec<ValueTolerance>,
+    /// Merge consecutive update rows with the same ID and values before processing
+    pub conflate_inputs: bool,
+    /// Largest gap between an update row's `effective_to` and the next row's `effective_from`
+    /// that `conflate_inputs` still merges across (default zero: only touching rows). The merged
+    /// row covers the gap, so use it only where gaps are known to be non-business time, such
+    /// as weekends and holidays.
+    pub conflate_gap_tolerance: chrono::Duration,
+    /// After processing, stretch each inserted row over untouched current rows of its ID that
+    /// it abuts with the same value hash, expiring them as merged. Without it a delta insert
+    /// ending where an identical current segment begins leaves two adjacent copies of the
+    /// same values. Bounded current rows are never joined to an open-ended insert.
+    pub merge_adjacent_current: bool,
+    /// Column naming the tenant (client) each row belongs to. Every ID group must carry a
+    /// single tenant across current state and updates, or the run fails before anything is
+    /// merged, so no merge or conflation ever joins rows of different tenants. Output row
+    /// counts per tenant are reported in `ProcessingMetrics::tenant_stats`.
+    pub tenant_column: Option<String>,
+    /// Effective window `[from, to)` that a full_state update describes.
+    /// Current rows outside the window are neither compared nor tombstoned, and a tombstone
+    /// only removes the part of a row inside it.
+    pub full_state_window: Option<(NaiveDateTime, NaiveDateTime)>,
+    /// Columns whose values partition a full_state feed (e.g. `source_system`).
+    /// Only current rows in partitions present in the updates are compared or tombstoned.
+    pub partition_columns: Vec<String>,
+    /// Policy for overlapping updates within the same batch; losers are reported in
+    /// `ChangeSet::overridden_updates`
+    pub conflict_resolution: ConflictResolution,
+    /// Report update rows that were intentionally ignored in `ChangeSet::skipped_updates`
+    pub report_skipped_updates: bool,
+    /// Append an `expire_reason` column (`OVERWRITTEN`, `TOMBSTONED`, `MERGED`, `CARVED`)
+    /// to `ChangeSet::expired_records`
+    pub report_expire_reasons: bool,
+    /// Append a `changed_columns` list to `ChangeSet::to_insert` naming the value columns that
+    /// differ from the expired row(s) the insert replaces (null for rows that replace nothing)
+    pub report_changed_columns: bool,
+    /// Backfill watermark: current rows ending at or before it are never expired, and
+    /// updates starting before it are clamped or rejected per `watermark_policy`
+    pub min_effective_from: Option<NaiveDateTime>,
+    pub watermark_policy: WatermarkPolicy,
+    /// Processing window `[clamp_from, clamp_to)` for updates: rows straddling a bound are
+    /// trimmed to it (reported in `ChangeSet::clamped_updates`), rows entirely outside are
+    /// skipped. Current rows are untouched; full_state loads pair this with `full_state_window`.
+    pub clamp_from: Option<NaiveDateTime>,
+    pub clamp_to: Option<NaiveDateTime>,
+    /// Validate update rows (null IDs, unreadable timestamps, inverted ranges) up front
+    pub validation: ValidationMode,
+    /// Fail up front, listing every difference, unless `current_state` and the updates have
+    /// the same columns with identical types (units and timezones included). Columns one side
+    /// may lack by design - the hash column, hash group columns, `tombstone_cutoff_column`,
+    /// `deletion_column` and a priority column - are only compared when both have them.
+    /// Checked after event chaining and `missing_temporal_columns` fills.
+    pub strict_schemas: bool,
+    /// Drop, reject or report zero-width and inverted update ranges
+    pub empty_ranges: EmptyRangePolicy,
+    /// Treat `current_state` as a full history table: only rows with a null or max-sentinel
+    /// `as_of_to` take part, and `to_expire` still indexes the rows as passed in. The open rows
+    /// are copied out, so a `hash_cache` never hits for such a current state.
+    pub open_current_rows_only: bool,
+    /// Group or reject rows with null IDs. Null `effective_from` / `effective_to` /
+    /// `as_of_from` values are always an error listing the rows.
+    pub null_ids: NullIdPolicy,
+    /// Fill rules for `effective_to` / `as_of_from` / `as_of_to` when the updates omit them
+    pub missing_temporal_columns: MissingTemporalColumns,
+    /// Whether updates are ranges or point observations to chain into ranges
+    pub input_mode: InputMode,
+    /// Timezone that business dates like `system_date` are expressed in. Tombstones on
+    /// timestamp columns end at local midnight of `system_date` (as a UTC instant, DST aware)
+    /// instead of UTC midnight. Date32/Date64 effective columns are unaffected.
+    pub effective_timezone: Option<chrono_tz::Tz>,
+    /// Intraday system time on `system_date` for same-day loads. Full_state tombstones end
+    /// (and only rows starting before it are tombstoned) at this instant instead of midnight;
+    /// read as local time when `effective_timezone` is set. Date32/Date64 columns still end at the date.
+    pub system_time: Option<NaiveDateTime>,
+    /// Current-state column (timestamp or date) holding a per-row tombstone cutoff, e.g. the
+    /// `last_seen` date of the feed a row came from. Non-null values replace the cutoff
+    /// derived from `system_date`, so feeds in one batch can be closed at different dates.
+    pub tombstone_cutoff_column: Option<String>,
+    /// Full_state only: boolean update column flagging deletions (e.g. `is_deleted`). Flagged
+    /// rows are never inserted; an ID whose rows are all flagged is tombstoned as if it were
+    /// missing from the feed (its partition still counts as present). Nulls count as not
+    /// deleted, and the column is dropped from the output.
+    pub deletion_column: Option<String>,
+    /// Whether `effective_to` is exclusive (default) or inclusive in both inputs and outputs
+    pub interval_end: IntervalEnd,
+    /// Insert closing rows for full_state disappearances (default). When false the current
+    /// rows are still listed in `to_expire` / `expired_records`, but no tombstone is inserted.
+    pub emit_tombstones: bool,
+    /// Keep the update row's own `as_of_from` on full_state segments merged with an adjacent
+    /// current row of the same values, rather than stamping the batch's `as_of_from` (the
+    /// first update row's, or the batch timestamp when that is null). Stamped values are
+    /// converted to the column's unit and timezone, so they match its precision either way.
+    pub preserve_as_of_from: bool,
+    /// Constant `(column, value)` pairs stamped onto every inserted and expired row, e.g.
+    /// `("batch_id", "2024-04-01-a")`. New columns are Utf8; existing columns keep their type.
+    pub lineage_columns: Vec<(String, String)>,
+    /// Deduplicate and consolidate the accumulated insert batches whenever more than this many
+    /// pile up while ID groups are processed (default 200). `None` defers all consolidation to
+    /// the end of the run: fewer passes, higher peak memory.
+    pub consolidation_threshold: Option<usize>,
+    /// Rows per consolidated `to_insert` batch (default 10,000)
+    pub consolidation_target_rows: usize,
+    /// Return `to_insert` and `expired_records` as exactly `shards` batches each (when there
+    /// are any rows on that side), batch `i` holding the rows whose shard key hashes to `i`.
+    /// Replaces the `consolidation_target_rows` batching of the output.
+    pub output_sharding: Option<OutputSharding>,
+    /// Split inserted segments at calendar boundaries so each lies within one month or
+    /// quarter (of the effective timeline). Open-ended rows are split up to their first
+    /// boundary and stay open from there. Expired records are untouched.
+    pub segment_boundary: Option<CalendarBoundary>,
+    /// Serial or parallel processing of ID groups; `Auto` picks by input size
+    pub parallelism: Parallelism,
+    /// Stops the run between ID groups once cancelled
+    pub cancellation: Option<CancellationToken>,
+    /// Progress reports while ID groups are processed
+    pub progress: Option<ProgressCallback>,
+    /// Checkpoint finished ID groups so a crashed run can resume. A rerun over the same inputs
+    /// picks up from the last checkpoint (with the original batch timestamp); the checkpoint
+    /// is removed once every group is processed.
+    pub checkpoint: Option<Checkpoint>,
+    /// Record the changeset of `batch_id` in a ledger. Processing a batch ID the ledger has
+    /// seen returns the recorded changeset (with `metrics.replayed` set) instead of
+    /// recomputing it against a state that may already hold its changes.
+    pub idempotency: Option<IdempotencyLedger>,
+    /// Memory budget for the insert batches accumulated while ID groups are processed. Past it
+    /// they are consolidated and spilled to temporary Arrow IPC files (under the system temp
+    /// directory), which are read back once every group is processed. The budget bounds only
+    /// that working set: the returned changeset holds every insert in memory again, after the
+    /// spilled batches went through the same deduplication and conflation as the rest.
+    /// `None` keeps everything in memory.
+    pub max_memory_bytes: Option<usize>,
+    /// Check the changeset with `verify_changeset_invariants` before returning it and fail
+    /// the run on a violation. A debugging aid: it costs another pass over inputs and output.
+    pub verify_invariants: bool,
+    /// Where the batch timestamp comes from (default `SystemClock`). A resumed checkpoint keeps
+    /// the timestamp its run started with.
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for ProcessingOptions {
+    fn default() -> Self {
+        ProcessingOptions {
+            hash_algorithm: HashAlgorithm::default(),
+            exact_float_columns: Vec::new(),
+            verify_hash_matches: false,
+            lazy_hashing: false,
+            hash_cache: None,
+            id_column_patterns: Vec::new(),
+            value_column_patterns: Vec::new(),
+            hash_column: VALUE_HASH_COLUMN.to_string(),
+            exclude_hash_column: false,
+            hash_groups: Vec::new(),
+            hash_group_change: HashGroupChange::default(),
+            value_tolerances: Vec::new(),
+            conflate_inputs: false,
+            conflate_gap_tolerance: chrono::Duration::zero(),
+            merge_adjacent_current: false,
+            tenant_column: None,
+            full_state_window: None,
+            partition_columns: Vec::new(),
+            conflict_resolution: ConflictResolution::default(),
+            report_skipped_updates: false,
+            report_expire_reasons: false,
+            report_changed_columns: false,
+            min_effective_from: None,
+            watermark_policy: WatermarkPolicy::default(),
+            clamp_from: None,
+            clamp_to: None,
+            validation: ValidationMode::default(),
+            strict_schemas: false,
+            empty_ranges: EmptyRangePolicy::default(),
+            open_current_rows_only: false,
+            null_ids: NullIdPolicy::default(),
+            missing_temporal_columns: MissingTemporalColumns::default(),
+            input_mode: InputMode::default(),
+            effective_timezone: None,
+            system_time: None,
+            tombstone_cutoff_column: None,
+            deletion_column: None,
+            interval_end: IntervalEnd::default(),
+            emit_tombstones: true,
+            preserve_as_of_from: false,
+            lineage_columns: Vec::new(),
+            consolidation_threshold: Some(200),
+            consolidation_target_rows: 10_000,
+            output_sharding: None,
+            segment_boundary: None,
+            parallelism: Parallelism::default(),
+            cancellation: None,
+            progress: None,
+            checkpoint: None,
+            idempotency: None,
+            max_memory_bytes: None,
+            verify_invariants: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl ProcessingOptions {
+    /// Algorithm and float policy used whenever a value hash is computed
+    pub(crate) fn hash_spec(&self) -> crate::arrow_hash::HashSpec<'_> {
+        crate::arrow_hash::HashSpec { algorithm: self.hash_algorithm, exact_floats: &self.exact_float_columns }
+    }
+
+    /// Whether matching hashes are confirmed on the value columns: on request, and for any
+    /// algorithm whose digests don't fit the 64-bit keys records are compared on
+    pub(crate) fn verifies_hash_matches(&self) -> bool {
+        self.verify_hash_matches || self.hash_algorithm != HashAlgorithm::XxHash
+    }
+}
@@ -1,5 +1,9 @@
 use crate::types::*;
-use arrow::array::RecordBatch;
+use crate::options::ConflictResolution;
+use crate::arrow_hash::{confirm_hash_match, ValueRows};
+use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::datatypes::{DataType, Int8Type, Int16Type, Int32Type, Int64Type, UInt8Type, UInt16Type, UInt32Type, UInt64Type};
+use chrono::NaiveDateTime;
 
 /// Determines if two records have any temporal intersection
 pub fn has_temporal_intersection(current: &BitemporalRecord, update: &BitemporalRecord) -> bool {
@@ -15,10 +19,24 @@ pub fn can_conflate_records(current: &BitemporalRecord, update: &BitemporalRecor
     same_values && (is_extension || is_reverse_extension)
 }
 
+/// Determines if a current record and an update hold the same values. Hashes decide unless
+/// `value_rows` is given, in which case matching hashes are confirmed on the value columns.
+pub fn same_values(current: &BitemporalRecord, update: &BitemporalRecord, value_rows: Option<&ValueRows>) -> bool {
+    current.value_hash == update.value_hash
+        && match (current.original_index, update.original_index) {
+            (Some(current_row), Some(update_row)) => confirm_hash_match(value_rows, current_row, update_row),
+            _ => true,
+        }
+}
+
 /// Determines if an update represents a no-change scenario (intersects with same values)
-pub fn is_no_change_update(current_records: &[BitemporalRecord], update: &BitemporalRecord) -> bool {
+pub fn is_no_change_update(
+    current_records: &[BitemporalRecord],
+    update: &BitemporalRecord,
+    value_rows: Option<&ValueRows>,
+) -> bool {
     current_records.iter().any(|current| {
-        has_temporal_intersection(current, update) && current.value_hash == update.value_hash
+        has_temporal_intersection(current, update) && same_values(current, update, value_rows)
     })
 }
 
@@ -95,7 +113,10 @@ pub fn process_non_overlapping_updates(
     
     // Collect all update records and their source rows
     let records: Vec<BitemporalRecord> = updates.iter().map(|&r| (*r).clone()).collect();
-    let source_rows: Vec<usize> = updates.iter().map(|r| r.original_index.unwrap()).collect();
+    let source_rows = updates.iter()
+        .map(|r| r.original_index
+            .ok_or_else(|| "Cannot emit an update without its row index in the updates batch".to_string()))
+        .collect::<Result<Vec<usize>, String>>()?;
     
     // Create a single batch from all non-overlapping updates
     let batch = crate::batch_utils::create_record_batch_from_records(
@@ -107,15 +128,25 @@ pub fn process_non_overlapping_updates(
     Ok(vec![batch])
 }
 
+fn record_skipped(skipped_updates: &mut Vec<SkippedUpdate>, update: &BitemporalRecord, reason: SkipReason) {
+    if let Some(update_index) = update.original_index {
+        skipped_updates.push(SkippedUpdate { update_index, reason });
+    }
+}
+
 /// Categorizes updates and current records based on overlap relationships.
 ///
 /// This function uses context-aware overlap detection to correctly handle:
 /// - **Extension scenario**: Single current record + adjacent update → merge (overlapping)
 /// - **Backfill scenario**: Multiple current records + update that intersects one →
 ///   only that one is overlapping, not adjacent ones with same values
+///
+/// Updates that are dropped here are recorded in `skipped_updates` with the reason.
 pub fn categorize_records<'a>(
     current_records: &'a [BitemporalRecord],
     update_records: &'a [BitemporalRecord],
+    skipped_updates: &mut Vec<SkippedUpdate>,
+    value_rows: Option<&ValueRows>,
 ) -> (Vec<&'a BitemporalRecord>, Vec<&'a BitemporalRecord>, Vec<&'a BitemporalRecord>) {
     let mut overlapping_current = Vec::new();
     let mut overlapping_updates = Vec::new();
@@ -126,10 +157,12 @@ pub fn categorize_records<'a>(
         // Skip empty ranges (effective_from >= effective_to)
         // These represent zero-width time periods and are invalid
         if update_record.effective_from >= update_record.effective_to {
+            record_skipped(skipped_updates, update_record, SkipReason::EmptyRange);
             continue;
         }
 
-        if is_no_change_update(current_records, update_record) {
+        if is_no_change_update(current_records, update_record, value_rows) {
+            record_skipped(skipped_updates, update_record, SkipReason::NoChange);
             continue; // Skip no-change updates
         }
 
@@ -154,4 +187,110 @@ pub fn categorize_records<'a>(
     }
 
     (overlapping_current, overlapping_updates, non_overlapping_updates)
+}
+
+/// Ranking key for an update under a conflict resolution policy - the highest key wins
+/// (priorities are widened to i128 so every signed and unsigned 64-bit value fits)
+type UpdateRank = (Option<i128>, NaiveDateTime, usize);
+
+/// Read an integer priority for an update row (nulls rank below every value)
+fn read_priority(updates_batch: &RecordBatch, column: &str, row: usize) -> Result<Option<i128>, String> {
+    let array = updates_batch.column_by_name(column)
+        .ok_or_else(|| format!("Priority column '{}' not found in updates", column))?;
+    if array.is_null(row) {
+        return Ok(None);
+    }
+
+    let value = match array.data_type() {
+        DataType::Int8 => array.as_primitive::<Int8Type>().value(row).into(),
+        DataType::Int16 => array.as_primitive::<Int16Type>().value(row).into(),
+        DataType::Int32 => array.as_primitive::<Int32Type>().value(row).into(),
+        DataType::Int64 => array.as_primitive::<Int64Type>().value(row).into(),
+        DataType::UInt8 => array.as_primitive::<UInt8Type>().value(row).into(),
+        DataType::UInt16 => array.as_primitive::<UInt16Type>().value(row).into(),
+        DataType::UInt32 => array.as_primitive::<UInt32Type>().value(row).into(),
+        DataType::UInt64 => array.as_primitive::<UInt64Type>().value(row).into(),
+        dt => return Err(format!("Priority column '{}' must be an integer type, got {:?}", column, dt)),
+    };
+    Ok(Some(value))
+}
+
+fn update_rank(
+    record: &BitemporalRecord,
+    conflict_resolution: &ConflictResolution,
+    updates_batch: &RecordBatch,
+) -> Result<UpdateRank, String> {
+    let row = record.original_index
+        .ok_or_else(|| "Cannot rank an update without its row index in the updates batch".to_string())?;
+    let priority = match conflict_resolution {
+        ConflictResolution::Priority(column) => read_priority(updates_batch, column, row)?,
+        _ => None,
+    };
+    Ok((priority, record.as_of_from, row))
+}
+
+/// Pick the update that should be emitted for a segment where several updates are active
+pub fn select_winning_update<'a>(
+    active_updates: &[&'a BitemporalRecord],
+    conflict_resolution: &ConflictResolution,
+    updates_batch: &RecordBatch,
+) -> Result<Option<&'a BitemporalRecord>, String> {
+    if *conflict_resolution == ConflictResolution::EventOrder || active_updates.len() <= 1 {
+        return Ok(active_updates.first().copied());
+    }
+
+    let mut winner: Option<(&BitemporalRecord, UpdateRank)> = None;
+    for &update in active_updates {
+        let rank = update_rank(update, conflict_resolution, updates_batch)?;
+        let beats_winner = match &winner {
+            Some((_, best)) => rank > *best,
+            None => true,
+        };
+        if beats_winner {
+            winner = Some((update, rank));
+        }
+    }
+    Ok(winner.map(|(update, _)| update))
+}
+
+/// Find update rows that overlap a higher-ranked update with different values.
+///
+/// These rows lose (at least part of) their effective range to the winner and are
+/// reported back to callers. The legacy event-order policy reports nothing.
+pub fn find_overridden_updates(
+    update_records: &[BitemporalRecord],
+    conflict_resolution: &ConflictResolution,
+    updates_batch: &RecordBatch,
+) -> Result<Vec<usize>, String> {
+    let mut overridden = Vec::new();
+    if *conflict_resolution == ConflictResolution::EventOrder {
+        return Ok(overridden);
+    }
+
+    // Rank each update once, then sweep in effective_from order so every update is only
+    // compared with the earlier updates whose ranges are still open
+    let mut ranked = update_records.iter()
+        .map(|record| Ok((record, update_rank(record, conflict_resolution, updates_batch)?)))
+        .collect::<Result<Vec<(&BitemporalRecord, UpdateRank)>, String>>()?;
+    ranked.sort_by_key(|(record, _)| record.effective_from);
+
+    let mut open: Vec<&(&BitemporalRecord, UpdateRank)> = Vec::with_capacity(ranked.len());
+    for entry in &ranked {
+        let (update, rank) = entry;
+        open.retain(|(other, _)| other.effective_to > update.effective_from);
+        for (other, other_rank) in open.iter().copied() {
+            if other.value_hash == update.value_hash || !has_temporal_intersection(other, update) {
+                continue;
+            }
+            let loser = if other_rank > rank { update } else { other };
+            if let Some(idx) = loser.original_index {
+                overridden.push(idx);
+            }
+        }
+        open.push(entry);
+    }
+
+    overridden.sort_unstable();
+    overridden.dedup();
+    Ok(overridden)
 }
\ No newline at end of file
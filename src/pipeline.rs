@@ -0,0 +1,112 @@
+//! Overlapping value hashing with ID grouping
+//!
+//! Hashing is independent per row, so the rows to hash are cut into chunks that hashing
+//! threads work through while the calling thread builds the ID groups. Finished chunks flow
+//! through a bounded channel to an assembler that appends them to the `value_hash` column in
+//! row order, so only a few hashed chunks ever wait in memory. Processing the ID groups still
+//! starts once both are done: a group is only complete after its last row has been grouped.
+
+use crate::arrow_hash::hash_values_batch_arrow_direct;
+use arrow::array::{ArrayRef, RecordBatch, StringBuilder};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
+
+/// Rows hashed per chunk
+const CHUNK_ROWS: usize = 16_384;
+/// Hashed chunks that may wait for the assembler before hashing threads block
+const CHANNEL_BOUND: usize = 8;
+
+/// Hash `value_columns` of every batch in `batches` on background threads while `overlapped`
+/// runs on the calling thread. Returns one `value_hash` array per batch, in order. Inputs of a
+/// single chunk are hashed inline, where threads would cost more than they save.
+pub(crate) fn hash_alongside<T>(
+    batches: &[&RecordBatch],
+    value_columns: &[String],
+    spec: crate::arrow_hash::HashSpec<'_>,
+    overlapped: impl FnOnce() -> T,
+) -> Result<(Vec<ArrayRef>, T), String> {
+    for batch in batches {
+        for col_name in value_columns {
+            if batch.schema().index_of(col_name).is_err() {
+                return Err(format!("Column '{}' not found in RecordBatch", col_name));
+            }
+        }
+    }
+
+    // (batch, first row, rows) for every chunk, in output order
+    let chunks: Vec<(usize, usize, usize)> = batches.iter().enumerate()
+        .flat_map(|(batch_idx, batch)| {
+            (0..batch.num_rows()).step_by(CHUNK_ROWS)
+                .map(move |start| (batch_idx, start, CHUNK_ROWS.min(batch.num_rows() - start)))
+        })
+        .collect();
+    if chunks.len() <= 1 {
+        let hashes = batches.iter()
+            .map(|batch| {
+                let rows: Vec<usize> = (0..batch.num_rows()).collect();
+                let hashes = hash_values_batch_arrow_direct(batch, &rows, value_columns, spec);
+                Arc::new(arrow::array::StringArray::from(hashes)) as ArrayRef
+            })
+            .collect();
+        return Ok((hashes, overlapped()));
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |threads| threads.get().saturating_sub(1))
+        .clamp(1, chunks.len().max(1));
+    let next_chunk = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = sync_channel::<(usize, Vec<String>)>(CHANNEL_BOUND);
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (chunks, next_chunk) = (&chunks, &next_chunk);
+            scope.spawn(move || {
+                let _span = tracing::debug_span!("hash_values_pipelined").entered();
+                loop {
+                    let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(batch_idx, start, len)) = chunks.get(chunk) else {
+                        break;
+                    };
+                    let rows: Vec<usize> = (start..start + len).collect();
+                    let hashes = hash_values_batch_arrow_direct(batches[batch_idx], &rows, value_columns, spec);
+                    // The assembler only hangs up when it panicked, which the scope re-raises
+                    if sender.send((chunk, hashes)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let chunks = &chunks;
+        let assembler = scope.spawn(move || {
+            let mut builders: Vec<StringBuilder> = batches.iter()
+                .map(|batch| StringBuilder::with_capacity(batch.num_rows(), batch.num_rows() * 16))
+                .collect();
+            // Chunks that arrived ahead of their turn
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (chunk, hashes) in receiver {
+                pending.insert(chunk, hashes);
+                while let Some(hashes) = pending.remove(&next) {
+                    let builder = &mut builders[chunks[next].0];
+                    for hash in hashes {
+                        builder.append_value(hash);
+                    }
+                    next += 1;
+                }
+            }
+            builders.into_iter()
+                .map(|mut builder| Arc::new(builder.finish()) as ArrayRef)
+                .collect::<Vec<_>>()
+        });
+
+        let result = overlapped();
+        let hashes = assembler.join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+        Ok((hashes, result))
+    })
+}
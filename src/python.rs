@@ -0,0 +1,1338 @@
+//! Python bindings, compiled only with the `python` feature
+
+use crate::*;
+use chrono::{NaiveDate, NaiveDateTime};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_arrow::{PyArray, PyRecordBatch, PyRecordBatchReader};
+
+#[pyfunction]
+#[pyo3(signature = (current_state, updates, id_columns, value_columns, system_date, update_mode, conflate_inputs=None, **options))]
+#[allow(clippy::too_many_arguments)]
+fn compute_changes(
+    py: Python<'_>,
+    current_state: PyRecordBatch,
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    conflate_inputs: Option<bool>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    compute_changes_with_hash_algorithm(py, current_state, updates, id_columns, value_columns, system_date, update_mode, None, conflate_inputs, options)
+}
+
+#[pyfunction]
+#[pyo3(signature = (current_state, updates, id_columns, value_columns, system_date, update_mode, hash_algorithm=None, conflate_inputs=None, **options))]
+#[allow(clippy::too_many_arguments)]
+fn compute_changes_with_hash_algorithm(
+    py: Python<'_>,
+    current_state: PyRecordBatch,
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    hash_algorithm: Option<String>,
+    conflate_inputs: Option<bool>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = parse_processing_options(hash_algorithm, conflate_inputs, options)?;
+    let changeset = run_compute_changes(
+        py, vec![current_state.into_inner()], updates, id_columns, value_columns, system_date, update_mode, &options
+    )?;
+    
+    // Convert the result back to Python types; batches are exported through the Arrow
+    // PyCapsule interface without copying
+    let expire_indices = expire_indices_output(py, changeset.to_expire)?;
+    let insert_batches: Vec<PyRecordBatch> = changeset.to_insert
+        .into_iter()
+        .map(PyRecordBatch::new)
+        .collect();
+    let expired_batches: Vec<PyRecordBatch> = changeset.expired_records
+        .into_iter()
+        .map(PyRecordBatch::new)
+        .collect();
+    
+    if options.report_skipped_updates {
+        let skipped = skipped_updates_output(&changeset.skipped_updates);
+        Ok((expire_indices, insert_batches, expired_batches, skipped).into_py(py))
+    } else {
+        Ok((expire_indices, insert_batches, expired_batches).into_py(py))
+    }
+}
+
+/// Variant of `compute_changes` that returns pyarrow `RecordBatchReader`s for the inserts
+/// and expired records, for consumers such as `pyarrow.dataset.write_dataset` or ADBC that
+/// take readers. The changeset is computed in full before the readers are returned.
+#[pyfunction]
+#[pyo3(signature = (current_state, updates, id_columns, value_columns, system_date, update_mode, hash_algorithm=None, conflate_inputs=None, **options))]
+#[allow(clippy::too_many_arguments)]
+fn compute_changes_reader(
+    py: Python<'_>,
+    current_state: PyRecordBatch,
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    hash_algorithm: Option<String>,
+    conflate_inputs: Option<bool>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    // Empty outputs still need a schema: the one the run reports for them, else the input's
+    let insert_schema = updates.as_ref().schema();
+    let expired_schema = current_state.as_ref().schema();
+
+    let options = parse_processing_options(hash_algorithm, conflate_inputs, options)?;
+    let changeset = run_compute_changes(
+        py, vec![current_state.into_inner()], updates, id_columns, value_columns, system_date, update_mode, &options
+    )?;
+
+    let expire_indices = expire_indices_output(py, changeset.to_expire)?;
+    let insert_schema = changeset.insert_schema.unwrap_or(insert_schema);
+    let expired_schema = changeset.expired_schema.unwrap_or(expired_schema);
+    let insert_reader = record_batch_reader(changeset.to_insert, insert_schema)?.to_pyarrow(py)?;
+    let expired_reader = record_batch_reader(changeset.expired_records, expired_schema)?.to_pyarrow(py)?;
+
+    if options.report_skipped_updates {
+        let skipped = skipped_updates_output(&changeset.skipped_updates);
+        Ok((expire_indices, insert_reader, expired_reader, skipped).into_py(py))
+    } else {
+        Ok((expire_indices, insert_reader, expired_reader).into_py(py))
+    }
+}
+
+/// Variant of `compute_changes` for a current state split across several batches, e.g. as
+/// fetched by a database driver, so it never has to be concatenated. Expired rows are returned
+/// as `(batch_index, row_index)` pairs into `current_state`.
+#[pyfunction]
+#[pyo3(signature = (current_state, updates, id_columns, value_columns, system_date, update_mode, hash_algorithm=None, conflate_inputs=None, **options))]
+#[allow(clippy::too_many_arguments)]
+fn compute_changes_chunked(
+    py: Python<'_>,
+    current_state: Vec<PyRecordBatch>,
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    hash_algorithm: Option<String>,
+    conflate_inputs: Option<bool>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = parse_processing_options(hash_algorithm, conflate_inputs, options)?;
+    let chunks: Vec<RecordBatch> = current_state.into_iter().map(PyRecordBatch::into_inner).collect();
+    let changeset = run_compute_changes(
+        py, chunks.clone(), updates, id_columns, value_columns, system_date, update_mode, &options
+    )?;
+
+    let expire_addresses = changeset.expire_addresses(&chunks);
+    let insert_batches: Vec<PyRecordBatch> = changeset.to_insert.into_iter().map(PyRecordBatch::new).collect();
+    let expired_batches: Vec<PyRecordBatch> = changeset.expired_records.into_iter().map(PyRecordBatch::new).collect();
+
+    if options.report_skipped_updates {
+        let skipped = skipped_updates_output(&changeset.skipped_updates);
+        Ok((expire_addresses, insert_batches, expired_batches, skipped).into_py(py))
+    } else {
+        Ok((expire_addresses, insert_batches, expired_batches).into_py(py))
+    }
+}
+
+/// Apply several update batches in order against the evolving state in one call, returning one
+/// `(expire_indices, insert_batches, expired_batches)` tuple per batch, the indices as a pyarrow
+/// `UInt64Array`, plus the batch's skipped updates with `report_skipped_updates` like
+/// `compute_changes`. Expire indices of later batches refer to the evolved state, so write
+/// expiries from `expired_batches`.
+#[pyfunction]
+#[pyo3(signature = (current_state, updates, id_columns, value_columns, system_date, update_mode, hash_algorithm=None, conflate_inputs=None, **options))]
+#[allow(clippy::too_many_arguments)]
+fn compute_changes_sequence(
+    py: Python<'_>,
+    current_state: PyRecordBatch,
+    updates: Vec<PyRecordBatch>,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    hash_algorithm: Option<String>,
+    conflate_inputs: Option<bool>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<PyObject>> {
+    let mut options = parse_processing_options(hash_algorithm, conflate_inputs, options)?;
+    let (system_date, mode) = parse_run_arguments(&system_date, &update_mode, &mut options)?;
+    let current_state = current_state.into_inner();
+    let updates: Vec<RecordBatch> = updates.into_iter().map(PyRecordBatch::into_inner).collect();
+    let changesets = py.allow_threads(|| catch_panic(|| process_update_sequence(
+        current_state, updates, id_columns, value_columns, system_date, mode, &options
+    ).map_err(pyo3::exceptions::PyRuntimeError::new_err)))?;
+    for changeset in &changesets {
+        record_python_metrics(py, &changeset.metrics, &update_mode);
+    }
+    changesets.into_iter()
+        .map(|changeset| {
+            let expire_indices = expire_indices_output(py, changeset.to_expire)?;
+            let insert_batches: Vec<PyRecordBatch> = changeset.to_insert.into_iter().map(PyRecordBatch::new).collect();
+            let expired_batches: Vec<PyRecordBatch> = changeset.expired_records.into_iter().map(PyRecordBatch::new).collect();
+            if options.report_skipped_updates {
+                let skipped = skipped_updates_output(&changeset.skipped_updates);
+                Ok((expire_indices, insert_batches, expired_batches, skipped).into_py(py))
+            } else {
+                Ok((expire_indices, insert_batches, expired_batches).into_py(py))
+            }
+        })
+        .collect()
+}
+
+/// Parse the string arguments shared by the Python entry points and run the processor.
+/// A current state of several batches goes through `process_updates_chunked`.
+#[allow(clippy::too_many_arguments)]
+fn run_compute_changes(
+    py: Python<'_>,
+    mut current_state: Vec<RecordBatch>,
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: String,
+    update_mode: String,
+    options: &ProcessingOptions,
+) -> PyResult<ChangeSet> {
+    // Take the Arrow RecordBatch out of the wrapper (its arrays are shared, not copied)
+    let updates_batch = updates.into_inner();
+    let mut options = options.clone();
+    let (system_date, mode) = parse_run_arguments(&system_date, &update_mode, &mut options)?;
+
+    // Process on a worker thread with the GIL released, so this thread can watch for Ctrl-C
+    // and cancel the run between ID groups
+    let token = options.cancellation.get_or_insert_with(CancellationToken::new).clone();
+    let options = &options;
+    let mut interrupt = None;
+    let result = py.allow_threads(|| std::thread::scope(|scope| {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let worker = scope.spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                if current_state.len() == 1 {
+                    let current_batch = current_state.remove(0);
+                    process_updates_with_options(current_batch, updates_batch, id_columns, value_columns, system_date, mode, options)
+                } else {
+                    process_updates_chunked(&current_state, updates_batch, id_columns, value_columns, system_date, mode, options)
+                }
+            }));
+            let _ = sender.send(result.unwrap_or_else(|payload| Err(panic_message(payload))));
+        });
+        loop {
+            match receiver.recv_timeout(std::time::Duration::from_millis(50)) {
+                Ok(result) => return result,
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                    if interrupt.is_none() {
+                        if let Err(err) = Python::with_gil(|py| py.check_signals()) {
+                            interrupt = Some(err);
+                            token.cancel();
+                        }
+                    }
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                    std::panic::resume_unwind(worker.join().unwrap_err());
+                }
+            }
+        }
+    }));
+
+    let changeset = match interrupt {
+        Some(err) => return Err(err),
+        None => result.map_err(pyo3::exceptions::PyRuntimeError::new_err)?,
+    };
+    record_python_metrics(py, &changeset.metrics, &update_mode);
+    Ok(changeset)
+}
+
+/// Parse `system_date` (a full timestamp marks an intraday load, setting `options.system_time`)
+/// and `update_mode`
+fn parse_run_arguments(system_date: &str, update_mode: &str, options: &mut ProcessingOptions) -> PyResult<(NaiveDate, UpdateMode)> {
+    let system_date = match NaiveDate::parse_from_str(system_date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => {
+            let system_time = parse_py_datetime(system_date)?;
+            options.system_time = Some(system_time);
+            system_time.date()
+        }
+    };
+    let mode = match update_mode {
+        "delta" => UpdateMode::Delta,
+        "full_state" => UpdateMode::FullState,
+        _ => return Err(pyo3::exceptions::PyValueError::new_err("Invalid update_mode. Must be 'delta' or 'full_state'")),
+    };
+    Ok((system_date, mode))
+}
+
+/// Hand the run's metrics to `pytemporal.telemetry.record`, which exports them through the
+/// Python OpenTelemetry API when it is installed. Failures go to `sys.unraisablehook`.
+fn record_python_metrics(py: Python<'_>, metrics: &ProcessingMetrics, update_mode: &str) {
+    let result = (|| -> PyResult<()> {
+        let values = PyDict::new_bound(py);
+        values.set_item("current_rows", metrics.current_rows)?;
+        values.set_item("update_rows", metrics.update_rows)?;
+        values.set_item("conflated_updates", metrics.conflated_updates)?;
+        values.set_item("clamped_updates", metrics.clamped_updates)?;
+        values.set_item("tolerated_updates", metrics.tolerated_updates)?;
+        values.set_item("unhashed_current_rows", metrics.unhashed_current_rows)?;
+        let tenants: Vec<(&str, usize, usize)> = metrics.tenant_stats.iter()
+            .map(|stats| (stats.tenant.as_str(), stats.expired_rows, stats.inserted_rows))
+            .collect();
+        values.set_item("tenants", tenants)?;
+        values.set_item("id_groups", metrics.id_groups)?;
+        values.set_item("expired_rows", metrics.expired_rows)?;
+        values.set_item("inserted_rows", metrics.inserted_rows)?;
+        values.set_item("group_ids_seconds", metrics.group_ids_duration.as_secs_f64())?;
+        values.set_item("process_id_groups_seconds", metrics.process_groups_duration.as_secs_f64())?;
+        values.set_item("build_changeset_seconds", metrics.build_changeset_duration.as_secs_f64())?;
+        values.set_item("total_seconds", metrics.total_duration.as_secs_f64())?;
+        py.import_bound("pytemporal.telemetry")?.call_method1("record", (values, update_mode))?;
+        Ok(())
+    })();
+    if let Err(err) = result {
+        err.write_unraisable_bound(py, None);
+    }
+}
+
+/// Run `f`, turning a Rust panic into a `RuntimeError`. Without this pyo3 raises `PanicException`,
+/// a `BaseException` that `except Exception` handlers in batch jobs do not catch.
+fn catch_panic<T>(f: impl FnOnce() -> PyResult<T>) -> PyResult<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(pyo3::exceptions::PyRuntimeError::new_err(panic_message(payload))))
+}
+
+/// Forward progress to a Python callable. If it raises, the error goes to
+/// `sys.unraisablehook` and the run is cancelled.
+fn python_progress(callback: Py<PyAny>, token: CancellationToken) -> ProgressCallback {
+    ProgressCallback::new(move |percent| {
+        Python::with_gil(|py| {
+            if let Err(err) = callback.call1(py, (percent,)) {
+                err.write_unraisable_bound(py, Some(callback.bind(py)));
+                token.cancel();
+            }
+        })
+    })
+}
+
+/// Expire indices as a pyarrow `UInt64Array`, handed over through the Arrow C data interface
+/// instead of as a list of Python ints (which costs seconds for millions of expiries)
+fn expire_indices_output(py: Python<'_>, indices: Vec<usize>) -> PyResult<PyObject> {
+    let array = crate::batch_utils::take_indices(indices);
+    PyArray::from_array_ref(std::sync::Arc::new(array)).to_pyarrow(py)
+}
+
+/// Expire indices passed back in from Python: a list of ints, or an Arrow integer array as
+/// returned by `compute_changes`
+fn extract_expire_indices(indices: &Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+    if let Ok(indices) = indices.extract::<Vec<usize>>() {
+        return Ok(indices);
+    }
+    let array = indices.extract::<PyArray>()?;
+    let array = arrow::compute::cast(array.array(), &arrow::datatypes::DataType::UInt64)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Expire indices must be non-negative integers: {}", e)))?;
+    if array.null_count() > 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err("Expire indices must not contain nulls"));
+    }
+    let array = array.as_any().downcast_ref::<arrow::array::UInt64Array>()
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("Expire indices must be non-negative integers"))?;
+    array.values().iter()
+        .map(|&idx| crate::batch_utils::row_index(idx).map_err(pyo3::exceptions::PyOverflowError::new_err))
+        .collect()
+}
+
+/// Optional fourth output: (update row indices, skip reasons)
+fn skipped_updates_output(skipped_updates: &[SkippedUpdate]) -> (Vec<usize>, Vec<&'static str>) {
+    skipped_updates
+        .iter()
+        .map(|skipped| (skipped.update_index, skipped.reason.as_str()))
+        .unzip()
+}
+
+/// Current-state hashes reused across `compute_changes` calls given the same pyarrow batch
+/// (pass as `hash_cache=`). Batches converted from pandas on each call never hit, nor do runs
+/// with `open_current_rows_only`, which copy the open rows out first.
+#[pyclass(name = "HashCache")]
+#[derive(Clone)]
+struct PyHashCache(HashCache);
+
+#[pymethods]
+impl PyHashCache {
+    #[new]
+    #[pyo3(signature = (capacity=8))]
+    fn new(capacity: usize) -> Self {
+        PyHashCache(HashCache::with_capacity(capacity))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    #[getter]
+    fn hits(&self) -> usize {
+        self.0.hits()
+    }
+
+    fn clear(&self) {
+        self.0.clear()
+    }
+}
+
+/// Wrap output batches in a reader, casting them to one unified schema
+fn record_batch_reader(
+    batches: Vec<RecordBatch>,
+    fallback_schema: arrow::datatypes::SchemaRef,
+) -> PyResult<PyRecordBatchReader> {
+    let reader = crate::batch_utils::unified_batch_reader(batches, fallback_schema)
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    Ok(PyRecordBatchReader::new(Box::new(reader)))
+}
+
+/// Build ProcessingOptions from the explicit Python arguments plus any keyword options
+fn parse_processing_options(
+    hash_algorithm: Option<String>,
+    conflate_inputs: Option<bool>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<ProcessingOptions> {
+    let mut options = ProcessingOptions::default();
+
+    // Parse hash algorithm
+    if let Some(algo_str) = hash_algorithm {
+        options.hash_algorithm = algo_str.parse::<HashAlgorithm>()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+    }
+
+    // Parse conflate_inputs parameter (default to false for backward compatibility)
+    options.conflate_inputs = conflate_inputs.unwrap_or(false);
+
+    let Some(kwargs) = kwargs else {
+        return Ok(options);
+    };
+
+    let mut conflict_resolution: Option<String> = None;
+    let mut priority_column: Option<String> = None;
+    let mut checkpoint_dir: Option<String> = None;
+    let mut checkpoint_every: Option<usize> = None;
+    let mut ledger_dir: Option<String> = None;
+    let mut batch_id: Option<String> = None;
+    let mut output_shards: Option<usize> = None;
+    let mut shard_column: Option<String> = None;
+
+    for (key, value) in kwargs.iter() {
+        let key: String = key.extract()?;
+        if value.is_none() {
+            continue;
+        }
+        match key.as_str() {
+            "merge_adjacent_current" => {
+                options.merge_adjacent_current = value.extract()?;
+            }
+            "tenant_column" => {
+                options.tenant_column = value.extract()?;
+            }
+            "full_state_window" => {
+                let (from, to): (String, String) = value.extract()?;
+                options.full_state_window = Some((parse_py_datetime(&from)?, parse_py_datetime(&to)?));
+            }
+            "partition_columns" => {
+                options.partition_columns = value.extract()?;
+            }
+            "conflict_resolution" => {
+                conflict_resolution = Some(value.extract()?);
+            }
+            "priority_column" => {
+                priority_column = Some(value.extract()?);
+            }
+            "report_skipped_updates" => {
+                options.report_skipped_updates = value.extract()?;
+            }
+            "report_expire_reasons" => {
+                options.report_expire_reasons = value.extract()?;
+            }
+            "report_changed_columns" => {
+                options.report_changed_columns = value.extract()?;
+            }
+            "min_effective_from" => {
+                let watermark: String = value.extract()?;
+                options.min_effective_from = Some(parse_py_datetime(&watermark)?);
+            }
+            "clamp_from" => {
+                let bound: String = value.extract()?;
+                options.clamp_from = Some(parse_py_datetime(&bound)?);
+            }
+            "clamp_to" => {
+                let bound: String = value.extract()?;
+                options.clamp_to = Some(parse_py_datetime(&bound)?);
+            }
+            "missing_temporal_columns" => {
+                options.missing_temporal_columns = parse_missing_temporal_columns(&value)?;
+            }
+            "input_mode" => {
+                let mode: String = value.extract()?;
+                options.input_mode = match mode.as_str() {
+                    "ranges" => InputMode::Ranges,
+                    "events" => InputMode::Events,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid input_mode '{}'. Must be 'ranges' or 'events'", mode
+                        )));
+                    }
+                };
+            }
+            "effective_timezone" => {
+                let name: String = value.extract()?;
+                options.effective_timezone = Some(name.parse::<chrono_tz::Tz>()
+                    .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid effective_timezone '{}': {}", name, e)))?);
+            }
+            "tombstone_cutoff_column" => {
+                options.tombstone_cutoff_column = Some(value.extract()?);
+            }
+            "deletion_column" => {
+                options.deletion_column = Some(value.extract()?);
+            }
+            "id_column_patterns" => {
+                options.id_column_patterns = value.extract()?;
+            }
+            "value_column_patterns" => {
+                options.value_column_patterns = value.extract()?;
+            }
+            "hash_column" => {
+                options.hash_column = value.extract()?;
+            }
+            "exclude_hash_column" => {
+                options.exclude_hash_column = value.extract()?;
+            }
+            "hash_groups" => {
+                // Keep the dict's order: {hash column: [value columns]}
+                for (column, value_columns) in value.downcast::<PyDict>()?.iter() {
+                    options.hash_groups.push(HashGroup { column: column.extract()?, value_columns: value_columns.extract()? });
+                }
+            }
+            "hash_group_change" => {
+                let mode: String = value.extract()?;
+                options.hash_group_change = match mode.as_str() {
+                    "any" => HashGroupChange::Any,
+                    "all" => HashGroupChange::All,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid hash_group_change '{}'. Must be 'any' or 'all'", mode
+                        )));
+                    }
+                };
+            }
+            "value_tolerances" => {
+                // {column: (absolute, relative)}
+                for (column, bounds) in value.downcast::<PyDict>()?.iter() {
+                    let (absolute, relative): (f64, f64) = bounds.extract()?;
+                    options.value_tolerances.push(ValueTolerance { column: column.extract()?, absolute, relative });
+                }
+            }
+            "exact_float_columns" => {
+                options.exact_float_columns = value.extract()?;
+            }
+            "verify_hash_matches" => {
+                options.verify_hash_matches = value.extract()?;
+            }
+            "lazy_hashing" => {
+                options.lazy_hashing = value.extract()?;
+            }
+            "hash_cache" => {
+                options.hash_cache = Some(value.extract::<PyHashCache>()?.0);
+            }
+            "checkpoint_dir" => {
+                checkpoint_dir = Some(value.extract()?);
+            }
+            "checkpoint_every" => {
+                checkpoint_every = Some(value.extract()?);
+            }
+            "ledger_dir" => {
+                ledger_dir = Some(value.extract()?);
+            }
+            "batch_id" => {
+                batch_id = Some(value.extract()?);
+            }
+            "conflate_gap_days" => {
+                let days: i64 = value.extract()?;
+                if days < 0 {
+                    return Err(pyo3::exceptions::PyValueError::new_err("conflate_gap_days must not be negative"));
+                }
+                options.conflate_gap_tolerance = chrono::Duration::days(days);
+            }
+            "segment_boundary" => {
+                let boundary: String = value.extract()?;
+                options.segment_boundary = Some(match boundary.as_str() {
+                    "month" => CalendarBoundary::Month,
+                    "quarter" => CalendarBoundary::Quarter,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid segment_boundary '{}'. Must be 'month' or 'quarter'", boundary
+                        )));
+                    }
+                });
+            }
+            "output_shards" => {
+                output_shards = Some(value.extract()?);
+            }
+            "shard_column" => {
+                shard_column = Some(value.extract()?);
+            }
+            "open_current_rows_only" => {
+                options.open_current_rows_only = value.extract()?;
+            }
+            "emit_tombstones" => {
+                options.emit_tombstones = value.extract()?;
+            }
+            "preserve_as_of_from" => {
+                options.preserve_as_of_from = value.extract()?;
+            }
+            "consolidation_threshold" => {
+                options.consolidation_threshold = value.extract()?;
+            }
+            "consolidation_target_rows" => {
+                options.consolidation_target_rows = value.extract()?;
+            }
+            "max_memory_bytes" => {
+                options.max_memory_bytes = value.extract()?;
+            }
+            "verify_invariants" => {
+                options.verify_invariants = value.extract()?;
+            }
+            "fixed_clock" => {
+                let instant: String = value.extract()?;
+                options.clock = std::sync::Arc::new(FixedClock(parse_py_datetime(&instant)?));
+            }
+            "progress" => {
+                let token = options.cancellation.get_or_insert_with(CancellationToken::new).clone();
+                options.progress = Some(python_progress(value.clone().unbind(), token));
+            }
+            "parallelism" => {
+                let mode: String = value.extract()?;
+                options.parallelism = match mode.as_str() {
+                    "auto" => Parallelism::Auto,
+                    "serial" => Parallelism::Serial,
+                    "parallel" => Parallelism::Parallel,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid parallelism '{}'. Must be 'auto', 'serial' or 'parallel'", mode
+                        )));
+                    }
+                };
+            }
+            "lineage_columns" => {
+                // Keep the dict's order; values are stamped as their string form
+                for (column, constant) in value.downcast::<PyDict>()?.iter() {
+                    options.lineage_columns.push((column.extract()?, constant.str()?.to_string()));
+                }
+            }
+            "interval_end" => {
+                let mode: String = value.extract()?;
+                options.interval_end = match mode.as_str() {
+                    "exclusive" => IntervalEnd::Exclusive,
+                    "inclusive" => IntervalEnd::inclusive_days(),
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid interval_end '{}'. Must be 'exclusive' or 'inclusive'", mode
+                        )));
+                    }
+                };
+            }
+            "strict_schemas" => {
+                options.strict_schemas = value.extract()?;
+            }
+            "validation" => {
+                let mode: String = value.extract()?;
+                options.validation = match mode.as_str() {
+                    "off" => ValidationMode::Off,
+                    "lenient" => ValidationMode::Lenient,
+                    "strict" => ValidationMode::Strict,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid validation '{}'. Must be 'off', 'lenient' or 'strict'", mode
+                        )));
+                    }
+                };
+            }
+            "empty_ranges" => {
+                let policy: String = value.extract()?;
+                options.empty_ranges = match policy.as_str() {
+                    "drop" => EmptyRangePolicy::Drop,
+                    "error" => EmptyRangePolicy::Error,
+                    "report" => EmptyRangePolicy::Report,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid empty_ranges '{}'. Must be 'drop', 'error' or 'report'", policy
+                        )));
+                    }
+                };
+            }
+            "null_ids" => {
+                let policy: String = value.extract()?;
+                options.null_ids = match policy.as_str() {
+                    "group" => NullIdPolicy::Group,
+                    "reject" => NullIdPolicy::Reject,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid null_ids '{}'. Must be 'group' or 'reject'", policy
+                        )));
+                    }
+                };
+            }
+            "watermark_policy" => {
+                let policy: String = value.extract()?;
+                options.watermark_policy = match policy.as_str() {
+                    "clamp" => WatermarkPolicy::Clamp,
+                    "reject" => WatermarkPolicy::Reject,
+                    _ => {
+                        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                            "Invalid watermark_policy '{}'. Must be 'clamp' or 'reject'", policy
+                        )));
+                    }
+                };
+            }
+            _ => {
+                return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+                    "compute_changes() got an unexpected keyword argument '{}'", key
+                )));
+            }
+        }
+    }
+
+    options.conflict_resolution = match (conflict_resolution.as_deref(), priority_column) {
+        (None | Some("event_order"), None) => ConflictResolution::EventOrder,
+        (Some("latest_as_of_from"), None) => ConflictResolution::LatestAsOf,
+        (None | Some("priority"), Some(column)) => ConflictResolution::Priority(column),
+        (Some("priority"), None) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "conflict_resolution='priority' requires priority_column"
+            ));
+        }
+        (Some(other @ ("event_order" | "latest_as_of_from")), Some(_)) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "priority_column cannot be combined with conflict_resolution='{}'", other
+            )));
+        }
+        (Some(other), _) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Invalid conflict_resolution '{}'. Must be 'event_order', 'latest_as_of_from' or 'priority'", other
+            )));
+        }
+    };
+
+    options.checkpoint = match (checkpoint_dir, checkpoint_every) {
+        (Some(dir), every) => {
+            let mut checkpoint = Checkpoint::new(dir);
+            checkpoint.groups_per_chunk = every.unwrap_or(checkpoint.groups_per_chunk);
+            Some(checkpoint)
+        }
+        (None, Some(_)) => {
+            return Err(pyo3::exceptions::PyValueError::new_err("checkpoint_every requires checkpoint_dir"));
+        }
+        (None, None) => None,
+    };
+
+    options.idempotency = match (ledger_dir, batch_id) {
+        (Some(dir), Some(batch_id)) => Some(IdempotencyLedger { dir: dir.into(), batch_id }),
+        (None, None) => None,
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err("ledger_dir and batch_id must be given together"));
+        }
+    };
+
+    options.output_sharding = match (output_shards, shard_column) {
+        (Some(shards), column) => Some(OutputSharding {
+            shards,
+            key: column.map_or(ShardKey::IdColumns, ShardKey::Column),
+        }),
+        (None, Some(_)) => {
+            return Err(pyo3::exceptions::PyValueError::new_err("shard_column requires output_shards"));
+        }
+        (None, None) => None,
+    };
+
+    Ok(options)
+}
+
+/// Parse `missing_temporal_columns`: True for the standard fills, or a dict mapping
+/// effective_to / as_of_from / as_of_to to "max", "batch_timestamp", "required" or a date string
+fn parse_missing_temporal_columns(value: &Bound<'_, PyAny>) -> PyResult<MissingTemporalColumns> {
+    if let Ok(enabled) = value.extract::<bool>() {
+        return Ok(if enabled { MissingTemporalColumns::populate_defaults() } else { MissingTemporalColumns::default() });
+    }
+
+    let mut fills = MissingTemporalColumns::default();
+    let rules: std::collections::HashMap<String, String> = value.extract()?;
+    for (column, rule) in rules {
+        let fill = match rule.as_str() {
+            "required" => TemporalFill::Required,
+            "max" => TemporalFill::Max,
+            "batch_timestamp" => TemporalFill::BatchTimestamp,
+            other => TemporalFill::Value(parse_py_datetime(other)?),
+        };
+        match column.as_str() {
+            "effective_to" => fills.effective_to = fill,
+            "as_of_from" => fills.as_of_from = fill,
+            "as_of_to" => fills.as_of_to = fill,
+            _ => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Cannot populate column '{}'. Must be 'effective_to', 'as_of_from' or 'as_of_to'", column
+                )));
+            }
+        }
+    }
+    Ok(fills)
+}
+
+/// Parse a date ("YYYY-MM-DD") or datetime ("YYYY-MM-DD HH:MM:SS[.f]") string from Python
+fn parse_py_datetime(value: &str) -> PyResult<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid datetime '{}': {}", value, e)))
+}
+
+#[pyfunction]
+fn add_hash_key(
+    record_batch: PyRecordBatch,
+    value_fields: Vec<String>,
+) -> PyResult<PyRecordBatch> {
+    add_hash_key_with_algorithm(record_batch, value_fields, None, None)
+}
+
+#[pyfunction]
+#[pyo3(signature = (record_batch, value_fields, hash_algorithm=None, exact_float_columns=None))]
+fn add_hash_key_with_algorithm(
+    record_batch: PyRecordBatch,
+    value_fields: Vec<String>,
+    hash_algorithm: Option<String>,
+    exact_float_columns: Option<Vec<String>>,
+) -> PyResult<PyRecordBatch> {
+    // Convert PyRecordBatch to Arrow RecordBatch
+    let batch = record_batch.as_ref().clone();
+    
+    // Parse hash algorithm
+    let algorithm = match hash_algorithm {
+        Some(algo_str) => algo_str.parse::<HashAlgorithm>()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?,
+        None => HashAlgorithm::default(),
+    };
+    let exact_floats = exact_float_columns.unwrap_or_default();
+    let spec = crate::arrow_hash::HashSpec { algorithm, exact_floats: &exact_floats };
+    
+    // Call the fast Arrow-direct hash function
+    let batch_with_hash = catch_panic(|| crate::arrow_hash::add_hash_column_arrow_direct(&batch, &value_fields, spec)
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err))?;
+    
+    // Convert back to PyRecordBatch
+    Ok(PyRecordBatch::new(batch_with_hash))
+}
+
+/// `add_hash_key` for a pyarrow Table, RecordBatchReader or any Arrow stream: hashes one chunk
+/// at a time and returns a pyarrow RecordBatchReader, so large histories never need to be
+/// concatenated into a single batch.
+#[pyfunction]
+#[pyo3(signature = (data, value_fields, hash_algorithm=None, exact_float_columns=None))]
+fn add_hash_key_reader(
+    py: Python<'_>,
+    data: pyo3_arrow::input::AnyRecordBatch,
+    value_fields: Vec<String>,
+    hash_algorithm: Option<String>,
+    exact_float_columns: Option<Vec<String>>,
+) -> PyResult<PyObject> {
+    let algorithm = match hash_algorithm {
+        Some(algo_str) => algo_str.parse::<HashAlgorithm>()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?,
+        None => HashAlgorithm::default(),
+    };
+    let exact_floats = exact_float_columns.unwrap_or_default();
+    let reader = data.into_reader()?;
+
+    let input_schema = reader.schema();
+    if let Some(missing) = value_fields.iter().find(|column| input_schema.index_of(column).is_err()) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!("Column '{}' not found in input", missing)));
+    }
+    // Empty chunks get an empty value_hash column instead of the single-batch error
+    let empty_hashes = || arrow::array::new_empty_array(&arrow::datatypes::DataType::Utf8);
+    let spec = crate::arrow_hash::HashSpec { algorithm, exact_floats: &exact_floats };
+    let schema = crate::arrow_hash::with_hash_column(&RecordBatch::new_empty(input_schema), empty_hashes(), spec)
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)?
+        .schema();
+
+    let batches = reader.map(move |batch| {
+        batch.and_then(|batch| {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let spec = crate::arrow_hash::HashSpec { algorithm, exact_floats: &exact_floats };
+                if batch.num_rows() == 0 {
+                    crate::arrow_hash::with_hash_column(&batch, empty_hashes(), spec)
+                } else {
+                    crate::arrow_hash::add_hash_column_arrow_direct(&batch, &value_fields, spec)
+                }
+            }))
+            .unwrap_or_else(|payload| Err(panic_message(payload)))
+            .map_err(arrow::error::ArrowError::ComputeError)
+        })
+    });
+    let output = arrow::array::RecordBatchIterator::new(batches, schema);
+    PyRecordBatchReader::new(Box::new(output)).to_pyarrow(py)
+}
+
+/// Generate the transactional SQL for the batches returned by `compute_changes`
+#[pyfunction]
+#[pyo3(name = "changeset_to_sql")]
+fn py_changeset_to_sql(
+    insert_batches: Vec<PyRecordBatch>,
+    expired_batches: Vec<PyRecordBatch>,
+    table: String,
+    id_columns: Vec<String>,
+) -> PyResult<Vec<String>> {
+    let changeset = ChangeSet {
+        to_insert: insert_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        expired_records: expired_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        ..Default::default()
+    };
+    catch_panic(|| changeset_to_sql(&changeset, &table, &id_columns)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Roll back the results of `compute_changes`: returns `(expire_indices, insert_batches,
+/// expired_batches)` of the inverse, ready for `changeset_to_sql`
+#[pyfunction]
+#[pyo3(name = "invert_changeset")]
+fn py_invert_changeset(
+    expire_indices: &Bound<'_, PyAny>,
+    insert_batches: Vec<PyRecordBatch>,
+    current_state: PyRecordBatch,
+) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
+    let changeset = ChangeSet {
+        to_expire: extract_expire_indices(expire_indices)?,
+        to_insert: insert_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        ..Default::default()
+    };
+    let inverse = catch_panic(|| invert_changeset(&changeset, current_state.as_ref())
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok((
+        inverse.to_expire,
+        inverse.to_insert.into_iter().map(PyRecordBatch::new).collect(),
+        inverse.expired_records.into_iter().map(PyRecordBatch::new).collect(),
+    ))
+}
+
+/// Net out the `(insert_batches, expired_batches)` of loads run one after another into a
+/// single `(insert_batches, expired_batches)` pair, ready for `changeset_to_sql`
+#[pyfunction]
+#[pyo3(name = "merge_changesets")]
+fn py_merge_changesets(
+    changesets: Vec<(Vec<PyRecordBatch>, Vec<PyRecordBatch>)>,
+    id_columns: Vec<String>,
+) -> PyResult<(Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
+    let changesets = changesets.into_iter()
+        .map(|(insert_batches, expired_batches)| ChangeSet {
+            to_insert: insert_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+            expired_records: expired_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+            ..Default::default()
+        })
+        .collect();
+    let merged = catch_panic(|| merge_changesets(changesets, &id_columns)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok((
+        merged.to_insert.into_iter().map(PyRecordBatch::new).collect(),
+        merged.expired_records.into_iter().map(PyRecordBatch::new).collect(),
+    ))
+}
+
+/// Order the batches returned by `compute_changes` for writing: a list of `(kind, batch)`
+/// steps, kind being 'expire', 'tombstone' or 'insert' (see `ChangeSet::write_plan`)
+#[pyfunction]
+#[pyo3(name = "write_plan", signature = (insert_batches, expired_batches, id_columns, hash_column=None))]
+fn py_write_plan(
+    insert_batches: Vec<PyRecordBatch>,
+    expired_batches: Vec<PyRecordBatch>,
+    id_columns: Vec<String>,
+    hash_column: Option<String>,
+) -> PyResult<Vec<(&'static str, PyRecordBatch)>> {
+    let changeset = ChangeSet {
+        to_insert: insert_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        expired_records: expired_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        ..Default::default()
+    };
+    let plan = catch_panic(|| changeset.write_plan(&id_columns, hash_column.as_deref().unwrap_or(VALUE_HASH_COLUMN))
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok(plan.steps.into_iter()
+        .map(|step| (step.kind.as_str(), PyRecordBatch::new(step.batch)))
+        .collect())
+}
+
+/// Build the Debezium-style change stream batch for the batches returned by `compute_changes`
+#[pyfunction]
+#[pyo3(name = "changeset_to_cdc", signature = (insert_batches, expired_batches, id_columns, hash_column=None))]
+fn py_changeset_to_cdc(
+    insert_batches: Vec<PyRecordBatch>,
+    expired_batches: Vec<PyRecordBatch>,
+    id_columns: Vec<String>,
+    hash_column: Option<String>,
+) -> PyResult<PyRecordBatch> {
+    let changeset = ChangeSet {
+        to_insert: insert_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        expired_records: expired_batches.into_iter().map(|batch| batch.as_ref().clone()).collect(),
+        ..Default::default()
+    };
+    catch_panic(|| changeset.to_cdc_batch(&id_columns, hash_column.as_deref().unwrap_or(VALUE_HASH_COLUMN))
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Join two bitemporal tables on ID and overlapping effective ranges
+#[pyfunction]
+#[pyo3(name = "temporal_join")]
+fn py_temporal_join(
+    left: PyRecordBatch,
+    right: PyRecordBatch,
+    id_columns: Vec<String>,
+) -> PyResult<PyRecordBatch> {
+    catch_panic(|| temporal_join(left.as_ref(), right.as_ref(), &id_columns)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// One point-in-time snapshot per date; `effective_dates` defaults to `as_of_dates`
+#[pyfunction]
+#[pyo3(name = "snapshot_series", signature = (batch, as_of_dates, effective_dates=None))]
+fn py_snapshot_series(
+    batch: PyRecordBatch,
+    as_of_dates: Vec<String>,
+    effective_dates: Option<Vec<String>>,
+) -> PyResult<Vec<PyRecordBatch>> {
+    let effective_dates = effective_dates.unwrap_or_else(|| as_of_dates.clone());
+    if effective_dates.len() != as_of_dates.len() {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "as_of_dates and effective_dates must have the same length"
+        ));
+    }
+    let points = as_of_dates.iter()
+        .zip(&effective_dates)
+        .map(|(as_of, effective)| Ok(SnapshotPoint {
+            as_of: parse_py_datetime(as_of)?,
+            effective: parse_py_datetime(effective)?,
+        }))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let snapshots = catch_panic(|| snapshot_series(batch.as_ref(), &points)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok(snapshots.into_iter().map(PyRecordBatch::new).collect())
+}
+
+/// One row per ID: the latest known version, in effect at `effective_date` when given
+#[pyfunction]
+#[pyo3(name = "latest_view", signature = (batch, id_columns, effective_date=None))]
+fn py_latest_view(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+    effective_date: Option<String>,
+) -> PyResult<PyRecordBatch> {
+    let effective_date = effective_date.as_deref().map(parse_py_datetime).transpose()?;
+    catch_panic(|| latest_view(batch.as_ref(), &id_columns, effective_date)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Every version known at some point during the knowledge window `(from, to)`, grouped by ID
+#[pyfunction]
+#[pyo3(name = "known_between")]
+fn py_known_between(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+    window: (String, String),
+) -> PyResult<PyRecordBatch> {
+    let window = (parse_py_datetime(&window.0)?, parse_py_datetime(&window.1)?);
+    catch_panic(|| known_between(batch.as_ref(), &id_columns, window)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Changes between two knowledge times as `(expire_indices, insert_batches, expired_batches)`:
+/// the rows known at `as_of_a` but no longer at `as_of_b`, and the rows newly known at `as_of_b`
+#[pyfunction]
+#[pyo3(name = "diff_as_of")]
+fn py_diff_as_of(
+    batch: PyRecordBatch,
+    as_of_a: String,
+    as_of_b: String,
+) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
+    let (as_of_a, as_of_b) = (parse_py_datetime(&as_of_a)?, parse_py_datetime(&as_of_b)?);
+    let diff = catch_panic(|| diff_as_of(batch.as_ref(), as_of_a, as_of_b)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok((
+        diff.to_expire,
+        diff.to_insert.into_iter().map(PyRecordBatch::new).collect(),
+        diff.expired_records.into_iter().map(PyRecordBatch::new).collect(),
+    ))
+}
+
+/// Effective-time gaps per ID inside the window `(from, to)`
+#[pyfunction]
+#[pyo3(name = "find_gaps")]
+fn py_find_gaps(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+    window: (String, String),
+) -> PyResult<PyRecordBatch> {
+    let window = (parse_py_datetime(&window.0)?, parse_py_datetime(&window.1)?);
+    catch_panic(|| find_gaps(batch.as_ref(), &id_columns, window)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Carry each segment forward over the gap before the next one, flagging fillers in `is_filler`
+#[pyfunction]
+#[pyo3(name = "fill_gaps_forward")]
+fn py_fill_gaps_forward(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+) -> PyResult<PyRecordBatch> {
+    catch_panic(|| fill_gaps_forward(batch.as_ref(), &id_columns)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Drop versions no longer known after `retain_as_of_after`, optionally keeping the latest one per segment
+#[pyfunction]
+#[pyo3(name = "purge_history", signature = (batch, id_columns, retain_as_of_after, keep_latest_superseded=false))]
+fn py_purge_history(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+    retain_as_of_after: String,
+    keep_latest_superseded: bool,
+) -> PyResult<PyRecordBatch> {
+    let horizon = parse_py_datetime(&retain_as_of_after)?;
+    catch_panic(|| purge_history(batch.as_ref(), &id_columns, horizon, keep_latest_superseded)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Move effective and/or as_of dates by `offset_days` or through a `{old: new}` date mapping
+#[pyfunction]
+#[pyo3(name = "rebase_dates", signature = (batch, offset_days=None, mapping=None, axes="effective"))]
+fn py_rebase_dates(
+    batch: PyRecordBatch,
+    offset_days: Option<i64>,
+    mapping: Option<std::collections::HashMap<String, String>>,
+    axes: &str,
+) -> PyResult<PyRecordBatch> {
+    let shift = match (offset_days, mapping) {
+        (Some(days), None) => DateShift::Offset(chrono::Duration::days(days)),
+        (None, Some(mapping)) => DateShift::Mapping(mapping.iter()
+            .map(|(from, to)| Ok((parse_py_datetime(from)?, parse_py_datetime(to)?)))
+            .collect::<PyResult<_>>()?),
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Exactly one of offset_days and mapping must be given"
+            ));
+        }
+    };
+    let axes = match axes {
+        "effective" => RebaseAxes::Effective,
+        "as_of" => RebaseAxes::AsOf,
+        "both" => RebaseAxes::Both,
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Invalid axes '{}'. Must be 'effective', 'as_of' or 'both'", axes
+            )));
+        }
+    };
+    catch_panic(|| rebase_dates(batch.as_ref(), &shift, axes)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Resolve overlapping current segments of a table, returning the corrective `(expire_indices,
+/// insert_batches, expired_batches)`. The latest `as_of_from` wins unless `priority_column` is given.
+#[pyfunction]
+#[pyo3(name = "repair_overlaps", signature = (batch, id_columns, repair_time, priority_column=None))]
+fn py_repair_overlaps(
+    batch: PyRecordBatch,
+    id_columns: Vec<String>,
+    repair_time: String,
+    priority_column: Option<String>,
+) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
+    let repair_time = parse_py_datetime(&repair_time)?;
+    let policy = priority_column.map_or(ConflictResolution::LatestAsOf, ConflictResolution::Priority);
+    let repair = catch_panic(|| repair_overlaps(batch.as_ref(), &id_columns, policy, repair_time)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok((
+        repair.to_expire,
+        repair.to_insert.into_iter().map(PyRecordBatch::new).collect(),
+        repair.expired_records.into_iter().map(PyRecordBatch::new).collect(),
+    ))
+}
+
+/// Remove effective coverage for the `(ID, effective_from, effective_to)` rows of `ranges`,
+/// returning `(expire_indices, insert_batches, expired_batches)` known from `as_of`
+#[pyfunction]
+#[pyo3(name = "carve_out")]
+fn py_carve_out(
+    current_state: PyRecordBatch,
+    ranges: PyRecordBatch,
+    id_columns: Vec<String>,
+    as_of: String,
+) -> PyResult<(Vec<usize>, Vec<PyRecordBatch>, Vec<PyRecordBatch>)> {
+    let as_of = parse_py_datetime(&as_of)?;
+    let carved = catch_panic(|| carve_out(current_state.as_ref(), ranges.as_ref(), &id_columns, as_of)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    Ok((
+        carved.to_expire,
+        carved.to_insert.into_iter().map(PyRecordBatch::new).collect(),
+        carved.expired_records.into_iter().map(PyRecordBatch::new).collect(),
+    ))
+}
+
+/// Recompute value_hash with another algorithm, one batch at a time. Accepts a RecordBatch or
+/// any Arrow stream and returns a pyarrow RecordBatchReader, so tables larger than memory can be
+/// migrated straight from a dataset scanner into a writer.
+#[pyfunction]
+#[pyo3(name = "rehash_table")]
+fn py_rehash_table(
+    py: Python<'_>,
+    data: pyo3_arrow::input::AnyRecordBatch,
+    value_columns: Vec<String>,
+    from_algorithm: String,
+    to_algorithm: String,
+) -> PyResult<PyObject> {
+    let from = from_algorithm.parse::<HashAlgorithm>().map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let to = to_algorithm.parse::<HashAlgorithm>().map_err(pyo3::exceptions::PyValueError::new_err)?;
+    let reader = data.into_reader()?;
+
+    let input_schema = reader.schema();
+    let hash_idx = input_schema.index_of(crate::types::VALUE_HASH_COLUMN)
+        .map_err(|_| pyo3::exceptions::PyValueError::new_err("value_hash column not found"))?;
+    // rehash_table keeps the exact float columns the input was tagged with
+    let exact_floats = crate::arrow_hash::hash_exact_floats_tag(&RecordBatch::new_empty(input_schema.clone()))
+        .unwrap_or_default();
+    let mut fields = input_schema.fields().to_vec();
+    fields[hash_idx] = std::sync::Arc::new(crate::arrow_hash::hash_field(
+        crate::arrow_hash::HashSpec { algorithm: to, exact_floats: &exact_floats }
+    ));
+    let schema = std::sync::Arc::new(arrow::datatypes::Schema::new_with_metadata(fields, input_schema.metadata().clone()));
+
+    let batches = reader.map(move |batch| {
+        batch.and_then(|batch| {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| rehash_table(&batch, &value_columns, from, to)))
+                .unwrap_or_else(|payload| Err(panic_message(payload)))
+                .map_err(arrow::error::ArrowError::ComputeError)
+        })
+    });
+    let output = arrow::array::RecordBatchIterator::new(batches, schema);
+    PyRecordBatchReader::new(Box::new(output)).to_pyarrow(py)
+}
+
+/// Columns matching glob (`px_*`) or `re:`-prefixed regex patterns, in `columns` order
+#[pyfunction]
+#[pyo3(name = "select_columns")]
+fn py_select_columns(columns: Vec<String>, patterns: Vec<String>) -> PyResult<Vec<String>> {
+    catch_panic(|| select_columns(&columns, &patterns).map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// (row indices, columns, kinds, offending values)
+type ValidationIssuesOutput = (Vec<usize>, Vec<String>, Vec<&'static str>, Vec<String>);
+
+/// Row-level issues in an updates batch
+#[pyfunction]
+#[pyo3(name = "validate_updates")]
+fn py_validate_updates(
+    updates: PyRecordBatch,
+    id_columns: Vec<String>,
+) -> PyResult<ValidationIssuesOutput> {
+    let issues = catch_panic(|| validate_updates(updates.as_ref(), &id_columns)
+        .map_err(pyo3::exceptions::PyValueError::new_err))?;
+    let mut output: ValidationIssuesOutput = Default::default();
+    for issue in issues {
+        output.0.push(issue.row_index);
+        output.1.push(issue.column);
+        output.2.push(issue.kind.as_str());
+        output.3.push(issue.value);
+    }
+    Ok(output)
+}
+
+/// Expected peak memory and runtime band for a run, as a dict (see `estimate_processing`)
+#[pyfunction]
+#[pyo3(name = "estimate_processing")]
+fn py_estimate_processing(py: Python<'_>, current_rows: usize, update_rows: usize, id_cardinality: usize) -> PyResult<PyObject> {
+    let estimate = estimate_processing(current_rows, update_rows, id_cardinality);
+    let values = PyDict::new_bound(py);
+    values.set_item("peak_memory_bytes", estimate.peak_memory_bytes)?;
+    values.set_item("min_runtime_seconds", estimate.min_runtime.as_secs_f64())?;
+    values.set_item("max_runtime_seconds", estimate.max_runtime.as_secs_f64())?;
+    values.set_item("parallel", estimate.parallel)?;
+    Ok(values.into_py(py))
+}
+
+/// (id, field, mv, price, effective_from, effective_to, as_of_from, as_of_to)
+type ScenarioRowInput = (i32, String, i32, i32, String, String, String, String);
+
+/// Scenario-schema batch built from tuple rows (see `pytemporal.testing`)
+#[pyfunction]
+#[pyo3(name = "scenario_batch")]
+fn py_scenario_batch(rows: Vec<ScenarioRowInput>) -> PyResult<PyRecordBatch> {
+    let rows: Vec<crate::testing::ScenarioRow> = rows.iter()
+        .map(|(id, field, mv, price, eff_from, eff_to, as_of_from, as_of_to)| {
+            (*id, field.as_str(), *mv, *price, eff_from.as_str(), eff_to.as_str(), as_of_from.as_str(), as_of_to.as_str())
+        })
+        .collect();
+    catch_panic(|| crate::testing::scenario_batch(&rows)
+        .map(PyRecordBatch::new)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+/// Order-independent diff of two sets of batches on `columns`; None when they hold the same rows
+#[pyfunction]
+#[pyo3(name = "diff_rows")]
+fn py_diff_rows(
+    actual: Vec<PyRecordBatch>,
+    expected: Vec<PyRecordBatch>,
+    columns: Vec<String>,
+) -> PyResult<Option<String>> {
+    let actual: Vec<_> = actual.into_iter().map(|batch| batch.as_ref().clone()).collect();
+    let expected: Vec<_> = expected.into_iter().map(|batch| batch.as_ref().clone()).collect();
+    catch_panic(|| crate::testing::diff_rows(&actual, &expected, &columns)
+        .map_err(pyo3::exceptions::PyValueError::new_err))
+}
+
+#[pymodule]
+fn pytemporal(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyHashCache>()?;
+    m.add_function(wrap_pyfunction!(compute_changes, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_changes_with_hash_algorithm, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_changes_reader, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_changes_chunked, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_changes_sequence, m)?)?;
+    m.add_function(wrap_pyfunction!(py_estimate_processing, m)?)?;
+    m.add_function(wrap_pyfunction!(py_changeset_to_sql, m)?)?;
+    m.add_function(wrap_pyfunction!(py_changeset_to_cdc, m)?)?;
+    m.add_function(wrap_pyfunction!(py_invert_changeset, m)?)?;
+    m.add_function(wrap_pyfunction!(py_merge_changesets, m)?)?;
+    m.add_function(wrap_pyfunction!(py_write_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(py_temporal_join, m)?)?;
+    m.add_function(wrap_pyfunction!(py_snapshot_series, m)?)?;
+    m.add_function(wrap_pyfunction!(py_latest_view, m)?)?;
+    m.add_function(wrap_pyfunction!(py_known_between, m)?)?;
+    m.add_function(wrap_pyfunction!(py_diff_as_of, m)?)?;
+    m.add_function(wrap_pyfunction!(py_find_gaps, m)?)?;
+    m.add_function(wrap_pyfunction!(py_fill_gaps_forward, m)?)?;
+    m.add_function(wrap_pyfunction!(py_purge_history, m)?)?;
+    m.add_function(wrap_pyfunction!(py_rebase_dates, m)?)?;
+    m.add_function(wrap_pyfunction!(py_repair_overlaps, m)?)?;
+    m.add_function(wrap_pyfunction!(py_carve_out, m)?)?;
+    m.add_function(wrap_pyfunction!(py_validate_updates, m)?)?;
+    m.add_function(wrap_pyfunction!(py_rehash_table, m)?)?;
+    m.add_function(wrap_pyfunction!(py_select_columns, m)?)?;
+    m.add_function(wrap_pyfunction!(py_scenario_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(py_diff_rows, m)?)?;
+    m.add_function(wrap_pyfunction!(add_hash_key, m)?)?;
+    m.add_function(wrap_pyfunction!(add_hash_key_with_algorithm, m)?)?;
+    m.add_function(wrap_pyfunction!(add_hash_key_reader, m)?)?;
+    #[cfg(feature = "python-logging")]
+    m.add_function(wrap_pyfunction!(crate::python_logging::enable_logging, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Forwards tracing spans and events to Python's `logging` module (`python-logging` feature)
+
+use pyo3::prelude::*;
+use std::fmt::Write;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+const LOGGER: &str = "pytemporal";
+
+/// Logs events to the `pytemporal` logger, and every span's duration at DEBUG when it closes
+struct PythonLoggingLayer;
+
+/// Stored on each span: when it started and its formatted fields
+struct SpanTiming {
+    start: Instant,
+    fields: String,
+}
+
+/// Collects an event's `message` and renders the other fields as ` key=value`
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+impl<S> Layer<S> for PythonLoggingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut visitor = FieldVisitor::default();
+            attrs.record(&mut visitor);
+            span.extensions_mut().insert(SpanTiming { start: Instant::now(), fields: visitor.fields });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let scope = ctx.event_span(event).map(|span| format!("{}: ", span.name())).unwrap_or_default();
+        log(event.metadata().level(), format!("{}{}{}", scope, visitor.message, visitor.fields));
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id) {
+            if let Some(timing) = span.extensions().get::<SpanTiming>() {
+                let millis = timing.start.elapsed().as_secs_f64() * 1000.0;
+                log(&Level::DEBUG, format!("{} finished in {:.3} ms{}", span.name(), millis, timing.fields));
+            }
+        }
+    }
+}
+
+fn log(level: &Level, message: String) {
+    let level = match *level {
+        Level::TRACE => 5,
+        Level::DEBUG => 10,
+        Level::INFO => 20,
+        Level::WARN => 30,
+        Level::ERROR => 40,
+    };
+    Python::with_gil(|py| {
+        let result = py.import_bound("logging")
+            .and_then(|logging| logging.call_method1("getLogger", (LOGGER,)))
+            .and_then(|logger| logger.call_method1("log", (level, message)));
+        if let Err(err) = result {
+            err.write_unraisable_bound(py, None);
+        }
+    });
+}
+
+/// Route pytemporal's spans and events at `level` or above to `logging.getLogger("pytemporal")`.
+/// Installs the process-wide tracing subscriber, so it can only be called once.
+#[pyfunction]
+#[pyo3(signature = (level="INFO"))]
+pub(crate) fn enable_logging(level: &str) -> PyResult<()> {
+    let filter = match level.to_ascii_uppercase().as_str() {
+        "TRACE" => LevelFilter::TRACE,
+        "DEBUG" => LevelFilter::DEBUG,
+        "INFO" => LevelFilter::INFO,
+        "WARN" | "WARNING" => LevelFilter::WARN,
+        "ERROR" | "CRITICAL" => LevelFilter::ERROR,
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Invalid level '{}'. Must be 'TRACE', 'DEBUG', 'INFO', 'WARNING' or 'ERROR'", level
+            )));
+        }
+    };
+    let subscriber = tracing_subscriber::registry().with(PythonLoggingLayer.with_filter(filter));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(format!("Logging is already enabled: {}", e)))
+}
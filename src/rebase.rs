@@ -0,0 +1,100 @@
+use crate::{create_timestamp_array_from_values, extract_datetime_flexible, is_open_ended};
+use arrow::array::{Array, ArrayRef, RecordBatch};
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+
+/// How `rebase_dates` moves each date
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateShift {
+    /// Add a fixed offset (negative to move dates back)
+    Offset(chrono::Duration),
+    /// Replace each date with its entry; a date without one is an error
+    Mapping(BTreeMap<NaiveDateTime, NaiveDateTime>),
+}
+
+/// Which temporal axes `rebase_dates` moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebaseAxes {
+    Effective,
+    AsOf,
+    Both,
+}
+
+/// Move the `effective_from`/`effective_to` and/or `as_of_from`/`as_of_to` dates of a batch
+/// through `shift`, e.g. to migrate a table between fiscal-calendar conventions.
+///
+/// Every endpoint is moved on its own, so segments that touched before still touch afterwards.
+/// Open-ended endpoints and nulls are kept as they are. Fails when a moved range would end up
+/// empty or inverted (a mapping that does not preserve order).
+pub fn rebase_dates(batch: &RecordBatch, shift: &DateShift, axes: RebaseAxes) -> Result<RecordBatch, String> {
+    let pairs: &[(&str, &str)] = match axes {
+        RebaseAxes::Effective => &[("effective_from", "effective_to")],
+        RebaseAxes::AsOf => &[("as_of_from", "as_of_to")],
+        RebaseAxes::Both => &[("effective_from", "effective_to"), ("as_of_from", "as_of_to")],
+    };
+
+    let schema = batch.schema();
+    let mut columns = batch.columns().to_vec();
+    for &(from_column, to_column) in pairs {
+        let (from_idx, from_before, from_after) = rebase_column(batch, from_column, shift)?;
+        let (to_idx, to_before, to_after) = rebase_column(batch, to_column, shift)?;
+        for row in 0..batch.num_rows() {
+            if let (Some(from_before), Some(to_before), Some(from_after), Some(to_after)) =
+                (from_before[row], to_before[row], from_after[row], to_after[row]) {
+                if from_before < to_before && from_after >= to_after {
+                    return Err(format!(
+                        "Rebasing row {} turns [{}, {}) into the empty or inverted range [{}, {})",
+                        row, from_before, to_before, from_after, to_after
+                    ));
+                }
+            }
+        }
+        columns[from_idx] = rebased_array(batch.column(from_idx), &from_after)?;
+        columns[to_idx] = rebased_array(batch.column(to_idx), &to_after)?;
+    }
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to rebase dates: {}", e))
+}
+
+/// Column position plus its values before and after the shift (None for nulls)
+type RebasedColumn = (usize, Vec<Option<NaiveDateTime>>, Vec<Option<NaiveDateTime>>);
+
+fn rebase_column(batch: &RecordBatch, column: &str, shift: &DateShift) -> Result<RebasedColumn, String> {
+    let idx = batch.schema().index_of(column)
+        .map_err(|_| format!("{} column not found", column))?;
+    let array = batch.column(idx);
+    let mut before = Vec::with_capacity(batch.num_rows());
+    let mut after = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        if array.is_null(row) {
+            before.push(None);
+            after.push(None);
+            continue;
+        }
+        let value = extract_datetime_flexible(array.as_ref(), row)?;
+        let moved = if is_open_ended(value) {
+            value
+        } else {
+            match shift {
+                DateShift::Offset(offset) => value + *offset,
+                DateShift::Mapping(mapping) => *mapping.get(&value)
+                    .ok_or_else(|| format!("No mapping for {} {}", column, value))?,
+            }
+        };
+        before.push(Some(value));
+        after.push(Some(moved));
+    }
+    Ok((idx, before, after))
+}
+
+fn rebased_array(original: &ArrayRef, values: &[Option<NaiveDateTime>]) -> Result<ArrayRef, String> {
+    let filled: Vec<NaiveDateTime> = values.iter().map(|value| value.unwrap_or_default()).collect();
+    let array = create_timestamp_array_from_values(original.data_type(), &filled)?;
+    if original.null_count() == 0 {
+        return Ok(array);
+    }
+    let nulls = arrow::compute::is_null(original.as_ref())
+        .map_err(|e| format!("Failed to rebase dates: {}", e))?;
+    arrow::compute::nullif(array.as_ref(), &nulls)
+        .map_err(|e| format!("Failed to rebase dates: {}", e))
+}
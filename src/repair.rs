@@ -0,0 +1,152 @@
+use crate::options::{ConflictResolution, ProcessingOptions};
+use crate::types::{ChangeSet, UpdateMode, VALUE_HASH_COLUMN};
+use crate::{create_id_key_with_buffer, create_timestamp_array, extract_datetime_flexible, get_temporal_bounds};
+use arrow::array::{Array, RecordBatch, StringArray, UInt64Array};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+
+/// Resolve overlapping current segments within an existing table, returning the corrective
+/// changeset.
+///
+/// The open rows (`as_of_to` null or open-ended) of each ID are checked for overlapping
+/// effective ranges. The rows of every overlap are run back through the timeline as updates
+/// against an empty state, `policy` (`LatestAsOf` or `Priority`) picking the winner over each
+/// stretch. Rows that lose any of their range are expired at `repair_time` and their surviving
+/// pieces inserted as known from `repair_time`; rows that win outright are left alone.
+/// `to_expire` indexes into `batch`.
+pub fn repair_overlaps(
+    batch: &RecordBatch,
+    id_columns: &[String],
+    policy: ConflictResolution,
+    repair_time: NaiveDateTime,
+) -> Result<ChangeSet, String> {
+    if policy == ConflictResolution::EventOrder {
+        return Err("repair_overlaps needs the LatestAsOf or Priority policy".to_string());
+    }
+    let (open, open_rows) = crate::batch_utils::filter_open_rows(batch.clone())?;
+    let overlapping = overlapping_rows(&open, id_columns)?;
+    if overlapping.is_empty() {
+        return Ok(ChangeSet::default());
+    }
+
+    let options = ProcessingOptions { conflict_resolution: policy, ..Default::default() };
+    let value_columns = crate::infer_value_columns(&open, id_columns, &options)?;
+    let indices = crate::batch_utils::take_indices(overlapping.iter().copied());
+    let rows = take_rows(&open, &indices)?;
+    let rows = crate::ensure_hash_column_with_algorithm(rows, &value_columns, options.hash_spec())?;
+
+    let resolved = crate::process_updates_with_options(
+        rows.slice(0, 0), rows.clone(), id_columns.to_vec(), value_columns,
+        repair_time.date(), UpdateMode::Delta, &options,
+    )?;
+
+    // A row emitted whole (same ID, range, values and as_of_from) won outright and stays
+    let row_keys = segment_keys(&rows, id_columns)?;
+    let mut kept = vec![false; rows.num_rows()];
+    let mut to_insert = Vec::new();
+    for insert in &resolved.to_insert {
+        let insert_keys = segment_keys(insert, id_columns)?;
+        let mut new_rows = Vec::new();
+        for (insert_row, key) in insert_keys.iter().enumerate() {
+            match (0..row_keys.len()).find(|&row| !kept[row] && row_keys[row] == *key) {
+                Some(row) => kept[row] = true,
+                None => new_rows.push(insert_row),
+            }
+        }
+        if !new_rows.is_empty() {
+            to_insert.push(restamp_as_of_from(take_rows(insert, &crate::batch_utils::take_indices(new_rows))?, repair_time)?);
+        }
+    }
+
+    let to_expire: Vec<usize> = overlapping.iter()
+        .zip(&kept)
+        .filter(|(_, &kept)| !kept)
+        .map(|(&row, _)| open_rows[row])
+        .collect();
+    let expired_records = if to_expire.is_empty() {
+        Vec::new()
+    } else {
+        vec![crate::batch_utils::create_expired_records_batch(batch, &to_expire, repair_time, None)?]
+    };
+    if batch.column_by_name(VALUE_HASH_COLUMN).is_none() {
+        to_insert = to_insert.into_iter()
+            .map(|batch| crate::batch_utils::drop_column(batch, VALUE_HASH_COLUMN))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(ChangeSet { to_expire, to_insert, expired_records, ..Default::default() })
+}
+
+/// ID key, effective range, value hash and `as_of_from` of a row
+type SegmentKey = (String, NaiveDateTime, NaiveDateTime, String, NaiveDateTime);
+
+fn segment_keys(batch: &RecordBatch, id_columns: &[String]) -> Result<Vec<SegmentKey>, String> {
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+    let hashes = batch.column_by_name(VALUE_HASH_COLUMN)
+        .and_then(|array| array.as_any().downcast_ref::<StringArray>())
+        .ok_or("value_hash column not found")?;
+    let as_of_from = batch.column_by_name("as_of_from")
+        .ok_or("as_of_from column not found")?;
+    let mut id_key_buffer = String::with_capacity(64);
+    (0..batch.num_rows())
+        .map(|row| {
+            let (from, to) = get_temporal_bounds(batch, row)?;
+            create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+            let known_from = extract_datetime_flexible(as_of_from.as_ref(), row)?;
+            Ok((id_key_buffer.clone(), from, to, hashes.value(row).to_string(), known_from))
+        })
+        .collect()
+}
+
+/// Rows of `batch` whose effective range overlaps another row of the same ID, in row order
+fn overlapping_rows(batch: &RecordBatch, id_columns: &[String]) -> Result<Vec<usize>, String> {
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+    let mut by_id: FxHashMap<String, Vec<(NaiveDateTime, NaiveDateTime, usize)>> = FxHashMap::default();
+    let mut id_key_buffer = String::with_capacity(64);
+    for row in 0..batch.num_rows() {
+        let (from, to) = get_temporal_bounds(batch, row)?;
+        create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+        by_id.entry(id_key_buffer.clone()).or_default().push((from, to, row));
+    }
+
+    let mut overlapping = Vec::new();
+    for mut segments in by_id.into_values() {
+        segments.sort_unstable();
+        // Sweep clusters of segments chained by overlaps; singletons are clean
+        let mut cluster: Vec<usize> = Vec::new();
+        let mut cluster_end = NaiveDateTime::MIN;
+        for (from, to, row) in segments {
+            if from >= cluster_end {
+                if cluster.len() > 1 {
+                    overlapping.append(&mut cluster);
+                }
+                cluster.clear();
+            }
+            cluster.push(row);
+            cluster_end = cluster_end.max(to);
+        }
+        if cluster.len() > 1 {
+            overlapping.append(&mut cluster);
+        }
+    }
+    overlapping.sort_unstable();
+    Ok(overlapping)
+}
+
+fn take_rows(batch: &RecordBatch, indices: &UInt64Array) -> Result<RecordBatch, String> {
+    let columns = batch.columns().iter()
+        .map(|column| arrow::compute::take(column.as_ref(), indices, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to repair overlaps: {}", e))?;
+    RecordBatch::try_new(batch.schema(), columns)
+        .map_err(|e| format!("Failed to repair overlaps: {}", e))
+}
+
+fn restamp_as_of_from(batch: RecordBatch, repair_time: NaiveDateTime) -> Result<RecordBatch, String> {
+    let schema = batch.schema();
+    let idx = schema.index_of("as_of_from")
+        .map_err(|_| "as_of_from column not found".to_string())?;
+    let mut columns = batch.columns().to_vec();
+    columns[idx] = create_timestamp_array(columns[idx].data_type(), repair_time, batch.num_rows())?;
+    RecordBatch::try_new(schema, columns)
+        .map_err(|e| format!("Failed to repair overlaps: {}", e))
+}
@@ -0,0 +1,46 @@
+use crate::{create_id_key_with_buffer, extract_datetime_flexible, get_temporal_bounds};
+use arrow::array::{BooleanArray, RecordBatch};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+
+/// Drop versions whose `as_of_to` ended before `retain_as_of_after`.
+///
+/// Current rows and anything still known at or after the horizon are kept. With
+/// `keep_latest_superseded`, each segment (ID plus effective range) also keeps the most
+/// recent of its purged versions, so the last known value before the horizon survives.
+pub fn purge_history(
+    batch: &RecordBatch,
+    id_columns: &[String],
+    retain_as_of_after: NaiveDateTime,
+    keep_latest_superseded: bool,
+) -> Result<RecordBatch, String> {
+    let as_of_to = batch.column_by_name("as_of_to")
+        .ok_or_else(|| "as_of_to column not found".to_string())?;
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+
+    let mut keep = vec![false; batch.num_rows()];
+    // Latest purged version per (ID, effective_from, effective_to)
+    let mut latest_purged: FxHashMap<(String, NaiveDateTime, NaiveDateTime), (usize, NaiveDateTime)> = FxHashMap::default();
+    let mut id_key_buffer = String::with_capacity(64);
+
+    for (row, keep_row) in keep.iter_mut().enumerate() {
+        let known_until = extract_datetime_flexible(as_of_to.as_ref(), row)?;
+        if known_until >= retain_as_of_after {
+            *keep_row = true;
+            continue;
+        }
+        if keep_latest_superseded {
+            let (from, to) = get_temporal_bounds(batch, row)?;
+            create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+            latest_purged.entry((id_key_buffer.clone(), from, to))
+                .and_modify(|latest| if known_until > latest.1 { *latest = (row, known_until) })
+                .or_insert((row, known_until));
+        }
+    }
+    for (row, _) in latest_purged.into_values() {
+        keep[row] = true;
+    }
+
+    arrow::compute::filter_record_batch(batch, &BooleanArray::from(keep))
+        .map_err(|e| format!("Failed to purge history: {}", e))
+}
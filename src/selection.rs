@@ -0,0 +1,87 @@
+use crate::options::ProcessingOptions;
+use crate::{TEMPORAL_COLUMNS, VALUE_HASH_COLUMN};
+use arrow::datatypes::Schema;
+
+/// Expand column patterns against `columns`, returning matches in `columns` order.
+///
+/// Patterns are globs (`px_*`, `leg_?`) unless prefixed with `re:`, in which case the rest is a
+/// regular expression that must match the whole name (`re:px_(bid|ask)`). Every pattern has to
+/// match at least one column.
+pub fn select_columns(columns: &[String], patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut matched = vec![false; columns.len()];
+    for pattern in patterns {
+        let regex = compile_pattern(pattern)?;
+        let mut any = false;
+        for (column, matched) in columns.iter().zip(matched.iter_mut()) {
+            if regex.is_match(column) {
+                *matched = true;
+                any = true;
+            }
+        }
+        if !any {
+            return Err(format!("Column pattern '{}' matched none of the columns {:?}", pattern, columns));
+        }
+    }
+
+    Ok(columns.iter().zip(matched)
+        .filter(|(_, matched)| *matched)
+        .map(|(column, _)| column.clone())
+        .collect())
+}
+
+fn compile_pattern(pattern: &str) -> Result<regex::Regex, String> {
+    let expression = match pattern.strip_prefix("re:") {
+        Some(expression) => format!("^(?:{})$", expression),
+        None => {
+            let mut expression = String::from("^");
+            for c in pattern.chars() {
+                match c {
+                    '*' => expression.push_str(".*"),
+                    '?' => expression.push('.'),
+                    c => expression.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+                }
+            }
+            expression.push('$');
+            expression
+        }
+    };
+    regex::Regex::new(&expression)
+        .map_err(|e| format!("Invalid column pattern '{}': {}", pattern, e))
+}
+
+/// Add the columns matched by `options.id_column_patterns` / `value_column_patterns` to the
+/// explicit lists. Temporal and hash columns are never matched, and value patterns skip IDs.
+pub(crate) fn resolve_column_patterns(
+    schema: &Schema,
+    mut id_columns: Vec<String>,
+    mut value_columns: Vec<String>,
+    options: &ProcessingOptions,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    if options.id_column_patterns.is_empty() && options.value_column_patterns.is_empty() {
+        return Ok((id_columns, value_columns));
+    }
+
+    let candidates: Vec<String> = schema.fields().iter()
+        .map(|field| field.name().clone())
+        .filter(|name| {
+            !TEMPORAL_COLUMNS.contains(&name.as_str()) && *name != options.hash_column && name != VALUE_HASH_COLUMN
+        })
+        .collect();
+
+    for column in select_columns(&candidates, &options.id_column_patterns)? {
+        if !id_columns.contains(&column) {
+            id_columns.push(column);
+        }
+    }
+
+    let value_candidates: Vec<String> = candidates.into_iter()
+        .filter(|name| !id_columns.contains(name))
+        .collect();
+    for column in select_columns(&value_candidates, &options.value_column_patterns)? {
+        if !value_columns.contains(&column) {
+            value_columns.push(column);
+        }
+    }
+
+    Ok((id_columns, value_columns))
+}
@@ -0,0 +1,80 @@
+//! Several update batches (e.g. one per source file) applied one after another
+
+use crate::types::{ChangeSet, UpdateMode, VALUE_HASH_COLUMN};
+use crate::{process_updates_with_options, ProcessingOptions};
+use arrow::array::{BooleanArray, RecordBatch};
+use chrono::NaiveDate;
+
+/// Apply `updates` in order, each against the state left by the ones before it, returning one
+/// changeset per batch.
+///
+/// The state is hashed once up front and evolved in memory: after each batch its expired rows
+/// are dropped and its inserts appended (projected onto the state's columns). `to_expire` of
+/// changeset `k` therefore indexes into that evolved state - the rows surviving batches `0..k`
+/// in their previous order, followed by the rows batch `k - 1` inserted - so writers should
+/// address expiries through `expired_records`. Options that drop state columns from the
+/// inserts (`exclude_hash_column`) are rejected.
+pub fn process_update_sequence(
+    current_state: RecordBatch,
+    updates: Vec<RecordBatch>,
+    id_columns: Vec<String>,
+    value_columns: Vec<String>,
+    system_date: NaiveDate,
+    update_mode: UpdateMode,
+    options: &ProcessingOptions,
+) -> Result<Vec<ChangeSet>, String> {
+    if options.exclude_hash_column {
+        return Err("exclude_hash_column cannot be combined with sequential application".to_string());
+    }
+    let Some(first) = updates.first() else {
+        return Ok(Vec::new());
+    };
+
+    // Resolve the value columns against the first batch so the state can be hashed once
+    let (id_columns, value_columns) = crate::selection::resolve_column_patterns(
+        &first.schema(), id_columns, value_columns, options
+    )?;
+    let value_columns = if value_columns.is_empty() {
+        crate::infer_value_columns(first, &id_columns, options)?
+    } else {
+        value_columns
+    };
+    let mut state = if options.hash_column == VALUE_HASH_COLUMN {
+        crate::ensure_hash_column_with_algorithm(current_state, &value_columns, options.hash_spec())?
+    } else {
+        current_state
+    };
+
+    let mut changesets = Vec::with_capacity(updates.len());
+    for (batch_index, batch) in updates.into_iter().enumerate() {
+        let changeset = process_updates_with_options(
+            state.clone(), batch, id_columns.clone(), value_columns.clone(), system_date, update_mode, options
+        ).map_err(|e| format!("Update batch {}: {}", batch_index, e))?;
+        state = apply_to_state(state, &changeset)?;
+        changesets.push(changeset);
+    }
+    Ok(changesets)
+}
+
+/// The state with the changeset's expired rows removed and its inserts appended
+fn apply_to_state(state: RecordBatch, changeset: &ChangeSet) -> Result<RecordBatch, String> {
+    let mut keep = vec![true; state.num_rows()];
+    for &idx in &changeset.to_expire {
+        keep[idx] = false;
+    }
+    let survivors = arrow::compute::filter_record_batch(&state, &BooleanArray::from(keep))
+        .map_err(|e| format!("Failed to drop expired rows from the state: {}", e))?;
+
+    let schema = state.schema();
+    let mut batches = vec![survivors];
+    for insert in &changeset.to_insert {
+        let columns = schema.fields().iter()
+            .map(|field| insert.column_by_name(field.name()).cloned()
+                .ok_or_else(|| format!("Inserted rows are missing state column '{}'", field.name())))
+            .collect::<Result<Vec<_>, _>>()?;
+        batches.push(RecordBatch::try_new(schema.clone(), columns)
+            .map_err(|e| format!("Failed to append inserted rows to the state: {}", e))?);
+    }
+    arrow::compute::concat_batches(&schema, &batches)
+        .map_err(|e| format!("Failed to append inserted rows to the state: {}", e))
+}
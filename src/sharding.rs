@@ -0,0 +1,43 @@
+use crate::options::{OutputSharding, ShardKey};
+use crate::types::ChangeSet;
+use crate::create_id_key_with_buffer;
+use arrow::array::RecordBatch;
+
+/// Split `to_insert` and `expired_records` into `sharding.shards` batches each: batch `i`
+/// holds the rows whose shard key hashes to `i`. A side with no batches stays empty.
+pub(crate) fn shard_output(mut changeset: ChangeSet, id_columns: &[String], sharding: &OutputSharding) -> Result<ChangeSet, String> {
+    let key_columns = match &sharding.key {
+        ShardKey::IdColumns => id_columns.to_vec(),
+        ShardKey::Column(column) => vec![column.clone()],
+    };
+    changeset.to_insert = shard_batches(std::mem::take(&mut changeset.to_insert), &key_columns, sharding.shards)?;
+    changeset.expired_records = shard_batches(std::mem::take(&mut changeset.expired_records), &key_columns, sharding.shards)?;
+    Ok(changeset)
+}
+
+fn shard_batches(batches: Vec<RecordBatch>, key_columns: &[String], shards: usize) -> Result<Vec<RecordBatch>, String> {
+    let Some(first) = batches.first() else {
+        return Ok(batches);
+    };
+    let combined = arrow::compute::concat_batches(&first.schema(), &batches)
+        .map_err(|e| format!("Failed to combine output batches for sharding: {}", e))?;
+    let key_arrays = crate::batch_utils::named_columns(&combined, key_columns, "Shard")
+        .map_err(|e| format!("{} in output", e))?;
+
+    let mut shard_rows: Vec<Vec<usize>> = vec![Vec::new(); shards];
+    let mut key_buffer = String::with_capacity(64);
+    for row in 0..combined.num_rows() {
+        create_id_key_with_buffer(&key_arrays, row, &mut key_buffer);
+        shard_rows[shard_of(&key_buffer, shards)].push(row);
+    }
+
+    shard_rows.into_iter()
+        .map(|rows| arrow::compute::take_record_batch(&combined, &crate::batch_utils::take_indices(rows))
+            .map_err(|e| format!("Failed to split output into shards: {}", e)))
+        .collect()
+}
+
+/// Shard of a rendered key; xxh64 keeps the assignment stable across runs and platforms
+fn shard_of(key: &str, shards: usize) -> usize {
+    (xxhash_rust::xxh64::xxh64(key.as_bytes(), 0) % shards as u64) as usize
+}
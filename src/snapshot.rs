@@ -0,0 +1,200 @@
+use crate::types::ChangeSet;
+use crate::{create_id_key_with_buffer, extract_datetime_flexible};
+use arrow::array::{Array, BooleanArray, RecordBatch, UInt64Array};
+use chrono::NaiveDateTime;
+use rustc_hash::FxHashMap;
+
+/// Point-in-time view of a bitemporal table: rows known at `as_of` and in effect at `effective`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapshotPoint {
+    pub as_of: NaiveDateTime,
+    pub effective: NaiveDateTime,
+}
+
+impl SnapshotPoint {
+    /// Snapshot where knowledge time and effective time are the same instant (e.g. a month end)
+    pub fn at(date: NaiveDateTime) -> Self {
+        SnapshotPoint { as_of: date, effective: date }
+    }
+}
+
+/// Produce one snapshot batch per point, in the order given.
+///
+/// A row belongs to a snapshot when `as_of_from <= as_of < as_of_to` and
+/// `effective_from <= effective < effective_to`. The temporal columns are decoded once,
+/// so a long series costs one pass per point over plain timestamps.
+pub fn snapshot_series(batch: &RecordBatch, points: &[SnapshotPoint]) -> Result<Vec<RecordBatch>, String> {
+    let column = |name: &str| -> Result<Vec<NaiveDateTime>, String> {
+        let array = batch.column_by_name(name)
+            .ok_or_else(|| format!("{} column not found", name))?;
+        (0..batch.num_rows())
+            .map(|row| extract_datetime_flexible(array.as_ref(), row))
+            .collect()
+    };
+    let effective_from = column("effective_from")?;
+    let effective_to = column("effective_to")?;
+    let as_of_from = column("as_of_from")?;
+    let as_of_to = column("as_of_to")?;
+
+    points.iter()
+        .map(|point| {
+            let mask: BooleanArray = (0..batch.num_rows())
+                .map(|row| {
+                    Some(as_of_from[row] <= point.as_of && point.as_of < as_of_to[row]
+                        && effective_from[row] <= point.effective && point.effective < effective_to[row])
+                })
+                .collect();
+            arrow::compute::filter_record_batch(batch, &mask)
+                .map_err(|e| format!("Failed to filter snapshot: {}", e))
+        })
+        .collect()
+}
+
+/// Project a bitemporal table down to one row per ID: the row with the latest `as_of_from`
+/// among those in effect at `effective_date`, or among all rows when it is `None` (ties go
+/// to the later `effective_from`, then the later row). IDs with no such row are left out;
+/// the rest come back in order of first appearance.
+pub fn latest_view(
+    batch: &RecordBatch,
+    id_columns: &[String],
+    effective_date: Option<NaiveDateTime>,
+) -> Result<RecordBatch, String> {
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+    let column = |name: &str| batch.column_by_name(name)
+        .ok_or_else(|| format!("{} column not found", name));
+    let effective_from = column("effective_from")?;
+    let effective_to = column("effective_to")?;
+    let as_of_from = column("as_of_from")?;
+
+    // Best row per ID with its (as_of_from, effective_from) rank, plus first-seen order
+    let mut latest: FxHashMap<String, (usize, (NaiveDateTime, NaiveDateTime))> = FxHashMap::default();
+    let mut order = Vec::new();
+    let mut id_key_buffer = String::with_capacity(64);
+    for row in 0..batch.num_rows() {
+        let from = extract_datetime_flexible(effective_from.as_ref(), row)?;
+        if let Some(date) = effective_date {
+            let to = extract_datetime_flexible(effective_to.as_ref(), row)?;
+            if !(from <= date && date < to) {
+                continue;
+            }
+        }
+        let rank = (extract_datetime_flexible(as_of_from.as_ref(), row)?, from);
+        create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+        match latest.get_mut(id_key_buffer.as_str()) {
+            Some(best) => if rank >= best.1 {
+                *best = (row, rank);
+            },
+            None => {
+                order.push(id_key_buffer.clone());
+                latest.insert(id_key_buffer.clone(), (row, rank));
+            }
+        }
+    }
+
+    let indices = crate::batch_utils::take_indices(order.iter().map(|key| latest[key].0));
+    let columns = batch.columns().iter()
+        .map(|column| arrow::compute::take(column.as_ref(), &indices, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to project latest view: {}", e))?;
+    RecordBatch::try_new(batch.schema(), columns)
+        .map_err(|e| format!("Failed to project latest view: {}", e))
+}
+
+/// Every version whose as_of range `[as_of_from, as_of_to)` intersects the knowledge window
+/// `[from, to)`: what was believed about each ID at some point during the window. Rows are
+/// grouped by ID in order of first appearance, each ID's versions sorted by `as_of_from` then
+/// `effective_from`. A null `as_of_to` counts as still current.
+pub fn known_between(
+    batch: &RecordBatch,
+    id_columns: &[String],
+    window: (NaiveDateTime, NaiveDateTime),
+) -> Result<RecordBatch, String> {
+    let (window_from, window_to) = window;
+    if window_from >= window_to {
+        return Err(format!("Knowledge window [{}, {}) is empty", window_from, window_to));
+    }
+    let id_arrays = crate::batch_utils::id_arrays(batch, id_columns)?;
+    let column = |name: &str| batch.column_by_name(name)
+        .ok_or_else(|| format!("{} column not found", name));
+    let effective_from = column("effective_from")?;
+    let as_of_from = column("as_of_from")?;
+    let as_of_to = column("as_of_to")?;
+
+    let mut id_order: FxHashMap<String, usize> = FxHashMap::default();
+    let mut id_key_buffer = String::with_capacity(64);
+    let mut known = Vec::new();
+    for row in 0..batch.num_rows() {
+        let known_from = extract_datetime_flexible(as_of_from.as_ref(), row)?;
+        let known_until = if as_of_to.is_null(row) {
+            None
+        } else {
+            Some(extract_datetime_flexible(as_of_to.as_ref(), row)?)
+        };
+        if known_from >= window_to || known_until.is_some_and(|until| until <= window_from) {
+            continue;
+        }
+        create_id_key_with_buffer(&id_arrays, row, &mut id_key_buffer);
+        let next_id = id_order.len();
+        let id = *id_order.entry(id_key_buffer.clone()).or_insert(next_id);
+        known.push((id, known_from, extract_datetime_flexible(effective_from.as_ref(), row)?, row));
+    }
+    known.sort_unstable();
+
+    let indices = crate::batch_utils::take_indices(known.iter().map(|&(_, _, _, row)| row));
+    let columns = batch.columns().iter()
+        .map(|column| arrow::compute::take(column.as_ref(), &indices, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to select known versions: {}", e))?;
+    RecordBatch::try_new(batch.schema(), columns)
+        .map_err(|e| format!("Failed to select known versions: {}", e))
+}
+
+/// What changed between knowledge times `as_of_a` and `as_of_b`, as a changeset that turns
+/// the table as known at `as_of_a` into the table as known at `as_of_b`.
+///
+/// `to_expire` lists the rows of `batch` known at `as_of_a` but not at `as_of_b`, with those
+/// rows as-is in `expired_records`; `to_insert` holds the rows known at `as_of_b` but not at
+/// `as_of_a`. Rows known at both times are unchanged and left out. A null `as_of_to` counts as
+/// still current.
+pub fn diff_as_of(batch: &RecordBatch, as_of_a: NaiveDateTime, as_of_b: NaiveDateTime) -> Result<ChangeSet, String> {
+    let as_of_from = batch.column_by_name("as_of_from")
+        .ok_or("as_of_from column not found")?;
+    let as_of_to = batch.column_by_name("as_of_to")
+        .ok_or("as_of_to column not found")?;
+
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for row in 0..batch.num_rows() {
+        let known_from = extract_datetime_flexible(as_of_from.as_ref(), row)?;
+        let known_until = if as_of_to.is_null(row) {
+            None
+        } else {
+            Some(extract_datetime_flexible(as_of_to.as_ref(), row)?)
+        };
+        let known_at = |at: NaiveDateTime| known_from <= at && match known_until {
+            Some(until) => at < until,
+            None => true,
+        };
+        match (known_at(as_of_a), known_at(as_of_b)) {
+            (true, false) => removed.push(row),
+            (false, true) => added.push(row),
+            _ => {}
+        }
+    }
+
+    let take_rows = |rows: UInt64Array| -> Result<Vec<RecordBatch>, String> {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let columns = batch.columns().iter()
+            .map(|column| arrow::compute::take(column.as_ref(), &rows, None))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to diff as-of snapshots: {}", e))?;
+        RecordBatch::try_new(batch.schema(), columns)
+            .map(|batch| vec![batch])
+            .map_err(|e| format!("Failed to diff as-of snapshots: {}", e))
+    };
+    let expired_records = take_rows(crate::batch_utils::take_indices(removed.iter().copied()))?;
+    let to_insert = take_rows(crate::batch_utils::take_indices(added))?;
+    Ok(ChangeSet { to_expire: removed, to_insert, expired_records, ..Default::default() })
+}
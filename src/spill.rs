@@ -0,0 +1,89 @@
+//! Spilling accumulated insert batches to disk (`ProcessingOptions::max_memory_bytes`)
+
+use crate::ipc::{write_batches, FrameReader};
+use arrow::array::{Array, RecordBatch};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_SPILL_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Insert batches moved out of memory once they outgrew the budget, plus the running size of
+/// the batches still held in memory. The temporary files are removed on drop.
+#[derive(Default)]
+pub(crate) struct SpilledInserts {
+    files: Vec<PathBuf>,
+    in_memory_bytes: usize,
+}
+
+impl SpilledInserts {
+    /// Account for `batches[from..]`, just appended. Once the batches held in memory exceed
+    /// `budget` they are deduplicated, consolidated and written to a temporary IPC file.
+    pub(crate) fn track(
+        &mut self,
+        batches: &mut Vec<RecordBatch>,
+        from: usize,
+        budget: Option<usize>,
+        id_columns: &[String],
+        target_rows: usize,
+    ) -> Result<(), String> {
+        let Some(budget) = budget else {
+            return Ok(());
+        };
+        self.in_memory_bytes += batches[from..].iter().map(batch_bytes).sum::<usize>();
+        if self.in_memory_bytes <= budget {
+            return Ok(());
+        }
+
+        let spilled = crate::conflation::deduplicate_record_batches(std::mem::take(batches), id_columns)?;
+        let spilled = crate::conflation::consolidate_final_batches(spilled, target_rows)?;
+        let mut bytes = Vec::new();
+        write_batches(&mut bytes, &spilled)?;
+        let path = std::env::temp_dir().join(format!(
+            "pytemporal-spill-{}-{}.ipc",
+            std::process::id(),
+            NEXT_SPILL_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, bytes)
+            .map_err(|e| format!("Failed to write spill file {}: {}", path.display(), e))?;
+        tracing::debug!(path = %path.display(), bytes = self.in_memory_bytes, "spilled insert batches");
+        self.files.push(path);
+        self.in_memory_bytes = 0;
+        Ok(())
+    }
+
+    /// Bytes of the tracked batches still held in memory
+    pub(crate) fn in_memory_bytes(&self) -> usize {
+        self.in_memory_bytes
+    }
+
+    /// Take over the spill files of `other`
+    pub(crate) fn append(&mut self, mut other: SpilledInserts) {
+        self.files.append(&mut other.files);
+    }
+
+    /// Read every spilled batch back, in spill order
+    pub(crate) fn read_back(&self) -> Result<Vec<RecordBatch>, String> {
+        let mut batches = Vec::new();
+        for path in &self.files {
+            let bytes = std::fs::read(path)
+                .map_err(|e| format!("Failed to read spill file {}: {}", path.display(), e))?;
+            batches.extend(FrameReader::new(&bytes).read_batches()?);
+        }
+        Ok(batches)
+    }
+}
+
+impl Drop for SpilledInserts {
+    fn drop(&mut self) {
+        for path in &self.files {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Bytes a batch actually references; slices of the updates batch only count their own rows
+fn batch_bytes(batch: &RecordBatch) -> usize {
+    batch.columns().iter()
+        .map(|column| column.to_data().get_slice_memory_size().unwrap_or_else(|_| column.get_array_memory_size()))
+        .sum()
+}
@@ -0,0 +1,189 @@
+use crate::types::ChangeSet;
+use crate::write_plan::{WritePlan, WriteStepKind};
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::datatypes::DataType;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+
+// Keep individual INSERT statements to a manageable size
+const INSERT_CHUNK_ROWS: usize = 1000;
+
+/// Generate the SQL that applies a changeset to a bitemporal table, in one transaction.
+///
+/// Expiries come first (`UPDATE ... SET as_of_to = ...` keyed on the ID columns plus
+/// `effective_from` and `as_of_from` of each expired record), followed by `INSERT`s for
+/// every insert batch. The table name is used verbatim so it may be schema-qualified.
+pub fn changeset_to_sql(
+    changeset: &ChangeSet,
+    table: &str,
+    id_columns: &[String],
+) -> Result<Vec<String>, String> {
+    let mut statements = vec!["BEGIN".to_string()];
+
+    for batch in &changeset.expired_records {
+        statements.extend(expire_statements(batch, table, id_columns)?);
+    }
+    for batch in &changeset.to_insert {
+        statements.extend(insert_statements(batch, table)?);
+    }
+
+    statements.push("COMMIT".to_string());
+    Ok(statements)
+}
+
+/// Generate the SQL for a write plan, one transaction with the steps in plan order
+pub fn write_plan_to_sql(plan: &WritePlan, table: &str, id_columns: &[String]) -> Result<Vec<String>, String> {
+    let mut statements = vec!["BEGIN".to_string()];
+    for step in &plan.steps {
+        statements.extend(match step.kind {
+            WriteStepKind::Expire => expire_statements(&step.batch, table, id_columns)?,
+            WriteStepKind::Tombstone | WriteStepKind::Insert => insert_statements(&step.batch, table)?,
+        });
+    }
+    statements.push("COMMIT".to_string());
+    Ok(statements)
+}
+
+fn expire_statements(batch: &RecordBatch, table: &str, id_columns: &[String]) -> Result<Vec<String>, String> {
+    let key_columns: Vec<&str> = id_columns.iter()
+        .map(|col| col.as_str())
+        .chain(["effective_from", "as_of_from"])
+        .collect();
+    let key_literals = key_columns.iter()
+        .map(|col| column_literals(batch, col))
+        .collect::<Result<Vec<_>, _>>()?;
+    let as_of_to = column_literals(batch, "as_of_to")?;
+
+    let statements = (0..batch.num_rows())
+        .map(|row| {
+            let predicate = key_columns.iter()
+                .zip(&key_literals)
+                .map(|(col, literals)| match literals[row].as_str() {
+                    // Null IDs are allowed, and `= NULL` would match nothing
+                    "NULL" => format!("{} IS NULL", quote_identifier(col)),
+                    literal => format!("{} = {}", quote_identifier(col), literal),
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            format!(
+                "UPDATE {} SET {} = {} WHERE {}",
+                table,
+                quote_identifier("as_of_to"),
+                as_of_to[row],
+                predicate,
+            )
+        })
+        .collect();
+    Ok(statements)
+}
+
+fn insert_statements(batch: &RecordBatch, table: &str) -> Result<Vec<String>, String> {
+    if batch.num_rows() == 0 {
+        return Ok(Vec::new());
+    }
+
+    let schema = batch.schema();
+    let column_list = schema.fields().iter()
+        .map(|field| quote_identifier(field.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let literals = schema.fields().iter()
+        .map(|field| column_literals(batch, field.name()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rows: Vec<String> = (0..batch.num_rows())
+        .map(|row| {
+            let values = literals.iter().map(|col| col[row].as_str()).collect::<Vec<_>>().join(", ");
+            format!("({})", values)
+        })
+        .collect();
+
+    Ok(rows.chunks(INSERT_CHUNK_ROWS)
+        .map(|chunk| format!("INSERT INTO {} ({}) VALUES {}", table, column_list, chunk.join(", ")))
+        .collect())
+}
+
+/// Render every value of a column as a SQL literal. Non-finite floats are cast from their
+/// text form and binary values become hex literals; nested types have no portable literal
+/// and are refused.
+fn column_literals(batch: &RecordBatch, column: &str) -> Result<Vec<String>, String> {
+    let array = batch.column_by_name(column)
+        .ok_or_else(|| format!("Column '{}' not found in changeset batch", column))?;
+    let array: ArrayRef = match array.data_type() {
+        DataType::Dictionary(_, value_type) => arrow::compute::cast(array, value_type)
+            .map_err(|e| format!("Cannot decode dictionary column '{}': {}", column, e))?,
+        _ => array.clone(),
+    };
+    match array.data_type() {
+        DataType::Float32 | DataType::Float64 => return float_literals(&array, column),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::FixedSizeBinary(_) => {
+            return binary_literals(&array, column);
+        }
+        data_type if data_type.is_nested() => {
+            return Err(format!("Column '{}' has type {}, which has no SQL literal", column, data_type));
+        }
+        _ => {}
+    }
+    let formatter = ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default())
+        .map_err(|e| format!("Cannot format column '{}' as SQL: {}", column, e))?;
+    let quoted = !matches!(
+        array.data_type(),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64
+            | DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64
+            | DataType::Decimal128(_, _) | DataType::Boolean
+    );
+
+    Ok((0..array.len())
+        .map(|row| {
+            if array.is_null(row) {
+                "NULL".to_string()
+            } else if quoted {
+                format!("'{}'", formatter.value(row).to_string().replace('\'', "''"))
+            } else {
+                formatter.value(row).to_string()
+            }
+        })
+        .collect())
+}
+
+fn float_literals(array: &ArrayRef, column: &str) -> Result<Vec<String>, String> {
+    let formatter = ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default())
+        .map_err(|e| format!("Cannot format column '{}' as SQL: {}", column, e))?;
+    // Widening to f64 keeps NaN and the infinities, which are all that is looked at here
+    let values = arrow::compute::cast(array, &DataType::Float64)
+        .map_err(|e| format!("Cannot format column '{}' as SQL: {}", column, e))?;
+    let values = values.as_primitive::<arrow::datatypes::Float64Type>();
+    Ok((0..values.len())
+        .map(|row| {
+            let value = values.value(row);
+            if values.is_null(row) {
+                "NULL".to_string()
+            } else if value.is_nan() {
+                "CAST('NaN' AS DOUBLE PRECISION)".to_string()
+            } else if value.is_infinite() {
+                let text = if value > 0.0 { "Infinity" } else { "-Infinity" };
+                format!("CAST('{}' AS DOUBLE PRECISION)", text)
+            } else {
+                formatter.value(row).to_string()
+            }
+        })
+        .collect())
+}
+
+fn binary_literals(array: &ArrayRef, column: &str) -> Result<Vec<String>, String> {
+    let values = arrow::compute::cast(array, &DataType::LargeBinary)
+        .map_err(|e| format!("Cannot format column '{}' as SQL: {}", column, e))?;
+    let values = values.as_binary::<i64>();
+    Ok((0..values.len())
+        .map(|row| {
+            if values.is_null(row) {
+                return "NULL".to_string();
+            }
+            let hex: String = values.value(row).iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        })
+        .collect())
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,68 @@
+//! Exports `ProcessingMetrics` through the OpenTelemetry global meter provider (`opentelemetry` feature)
+
+use crate::types::{ProcessingMetrics, UpdateMode};
+use opentelemetry::{global, KeyValue};
+
+const METER: &str = "pytemporal";
+
+/// Record one run's metrics. Instruments are looked up per call so that a meter provider
+/// installed after the first run is still picked up; without a provider this is a no-op.
+pub(crate) fn record(metrics: &ProcessingMetrics, mode: UpdateMode) {
+    let meter = global::meter(METER);
+    let mode = KeyValue::new("mode", match mode {
+        UpdateMode::Delta => "delta",
+        UpdateMode::FullState => "full_state",
+    });
+
+    let rows_in = meter.u64_counter("pytemporal.rows_in")
+        .with_description("Rows passed in, by side (current or updates)")
+        .build();
+    rows_in.add(metrics.current_rows as u64, &[mode.clone(), KeyValue::new("side", "current")]);
+    rows_in.add(metrics.update_rows as u64, &[mode.clone(), KeyValue::new("side", "updates")]);
+
+    let counters = [
+        ("pytemporal.id_groups", "ID groups processed", metrics.id_groups),
+        ("pytemporal.rows_expired", "Current rows expired", metrics.expired_rows),
+        ("pytemporal.rows_inserted", "Rows inserted", metrics.inserted_rows),
+        ("pytemporal.updates_conflated", "Update rows merged by conflate_inputs", metrics.conflated_updates),
+        ("pytemporal.updates_clamped", "Update rows trimmed to the clamp window", metrics.clamped_updates),
+        ("pytemporal.updates_tolerated", "Update rows within value tolerances of current state", metrics.tolerated_updates),
+        ("pytemporal.current_rows_unhashed", "Current rows left unhashed by lazy_hashing", metrics.unhashed_current_rows),
+    ];
+    for (name, description, value) in counters {
+        meter.u64_counter(name).with_description(description).build()
+            .add(value as u64, std::slice::from_ref(&mode));
+    }
+
+    if !metrics.tenant_stats.is_empty() {
+        let expired = meter.u64_counter("pytemporal.tenant.rows_expired")
+            .with_description("Current rows expired, by tenant")
+            .build();
+        let inserted = meter.u64_counter("pytemporal.tenant.rows_inserted")
+            .with_description("Rows inserted, by tenant")
+            .build();
+        for stats in &metrics.tenant_stats {
+            let attributes = [mode.clone(), KeyValue::new("tenant", stats.tenant.clone())];
+            expired.add(stats.expired_rows as u64, &attributes);
+            inserted.add(stats.inserted_rows as u64, &attributes);
+        }
+    }
+
+    let phases = meter.f64_histogram("pytemporal.phase.duration")
+        .with_description("Time spent in each processing phase")
+        .with_unit("s")
+        .build();
+    for (phase, duration) in [
+        ("group_ids", metrics.group_ids_duration),
+        ("process_id_groups", metrics.process_groups_duration),
+        ("build_changeset", metrics.build_changeset_duration),
+    ] {
+        phases.record(duration.as_secs_f64(), &[mode.clone(), KeyValue::new("phase", phase)]);
+    }
+
+    meter.f64_histogram("pytemporal.duration")
+        .with_description("Total time of a process_updates call")
+        .with_unit("s")
+        .build()
+        .record(metrics.total_duration.as_secs_f64(), &[mode]);
+}
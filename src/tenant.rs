@@ -0,0 +1,80 @@
+use crate::{create_id_key_with_buffer, push_id_value};
+use crate::types::{ChangeSet, TenantStats};
+use arrow::array::{ArrayRef, RecordBatch};
+use rustc_hash::FxHashMap;
+
+/// Fail unless every ID group, across current state and updates, belongs to a single tenant
+/// (see `ProcessingOptions::tenant_column`). Groups never span tenants afterwards, so no merge
+/// or conflation can join rows of different tenants, whatever their value hashes.
+pub(crate) fn check_tenant_isolation(
+    current_state: &RecordBatch,
+    updates: &RecordBatch,
+    id_columns: &[String],
+    value_columns: &[String],
+    tenant_column: &str,
+) -> Result<(), String> {
+    if value_columns.iter().any(|column| column == tenant_column) {
+        return Err(format!("Tenant column '{}' cannot be a value column", tenant_column));
+    }
+
+    // ID key -> (tenant, side and row that set it)
+    let mut tenants: FxHashMap<String, (String, &str, usize)> = FxHashMap::default();
+    let (mut key, mut tenant) = (String::new(), String::new());
+    for (batch, side) in [(current_state, "current state"), (updates, "updates")] {
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        let tenant_array = batch.column_by_name(tenant_column)
+            .ok_or_else(|| format!("Tenant column '{}' not found in {}", tenant_column, side))?;
+        if let Some(rows) = crate::batch_utils::null_rows(tenant_array) {
+            return Err(format!("Null tenant column '{}' in {} at row(s) {}", tenant_column, side, rows));
+        }
+        let id_arrays = id_columns.iter()
+            .map(|column| crate::batch_utils::required_column(batch, column).cloned())
+            .collect::<Result<Vec<ArrayRef>, String>>()?;
+        for row in 0..batch.num_rows() {
+            create_id_key_with_buffer(&id_arrays, row, &mut key);
+            tenant.clear();
+            push_id_value(tenant_array.as_ref(), row, &mut tenant);
+            match tenants.get(&key) {
+                Some((first, first_side, first_row)) if *first != tenant => {
+                    return Err(format!(
+                        "ID '{}' belongs to tenant '{}' in {} row {} but to tenant '{}' in {} row {}",
+                        key, first, first_side, first_row, tenant, side, row
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    tenants.insert(key.clone(), (tenant.clone(), side, row));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Inserted and expired row counts per tenant, ordered by tenant
+pub(crate) fn tenant_stats(changeset: &ChangeSet, tenant_column: &str) -> Result<Vec<TenantStats>, String> {
+    let mut stats: FxHashMap<String, TenantStats> = FxHashMap::default();
+    let mut tenant = String::new();
+    let outputs = [(&changeset.to_insert, true), (&changeset.expired_records, false)];
+    for (batches, inserted) in outputs {
+        for batch in batches.iter().filter(|batch| batch.num_rows() > 0) {
+            let tenant_array = crate::batch_utils::required_column(batch, tenant_column)?;
+            for row in 0..batch.num_rows() {
+                tenant.clear();
+                push_id_value(tenant_array.as_ref(), row, &mut tenant);
+                let entry = stats.entry(tenant.clone())
+                    .or_insert_with(|| TenantStats { tenant: tenant.clone(), ..Default::default() });
+                if inserted {
+                    entry.inserted_rows += 1;
+                } else {
+                    entry.expired_rows += 1;
+                }
+            }
+        }
+    }
+    let mut stats: Vec<TenantStats> = stats.into_values().collect();
+    stats.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+    Ok(stats)
+}
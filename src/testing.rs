@@ -0,0 +1,133 @@
+//! Helpers for tests written against the engine: the canonical scenario schema, batches built
+//! from tuple rows, and order-independent row diffs. The Python `pytemporal.testing` module
+//! wraps the same functions.
+
+use crate::arrow_hash::hash_values_batch_arrow_direct;
+use crate::types::VALUE_HASH_COLUMN;
+use crate::{HashAlgorithm, MAX_DATETIME};
+use arrow::array::{ArrayRef, Int32Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// One scenario row: (id, field, mv, price, effective_from, effective_to, as_of_from, as_of_to).
+/// Times are `YYYY-MM-DD`, `YYYY-MM-DD HH:MM:SS` or `max`.
+pub type ScenarioRow<'a> = (i32, &'a str, i32, i32, &'a str, &'a str, &'a str, &'a str);
+
+/// ID columns of the scenario schema
+pub const SCENARIO_ID_COLUMNS: [&str; 2] = ["id", "field"];
+/// Value columns of the scenario schema
+pub const SCENARIO_VALUE_COLUMNS: [&str; 2] = ["mv", "price"];
+
+/// `id: Int32, field: Utf8, mv: Int32, price: Int32`, the four temporal columns as
+/// microsecond timestamps, and `value_hash`
+pub fn scenario_schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, None);
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int32, false),
+        Field::new("field", DataType::Utf8, false),
+        Field::new("mv", DataType::Int32, false),
+        Field::new("price", DataType::Int32, false),
+        Field::new("effective_from", timestamp.clone(), false),
+        Field::new("effective_to", timestamp.clone(), false),
+        Field::new("as_of_from", timestamp.clone(), false),
+        Field::new("as_of_to", timestamp, false),
+        Field::new(VALUE_HASH_COLUMN, DataType::Utf8, false),
+    ]))
+}
+
+/// Build a scenario-schema batch from tuple rows, hashing `mv` and `price` into `value_hash`
+pub fn scenario_batch(rows: &[ScenarioRow]) -> Result<RecordBatch, String> {
+    let mut temporal: [Vec<i64>; 4] = Default::default();
+    for row in rows {
+        for (column, value) in temporal.iter_mut().zip([row.4, row.5, row.6, row.7]) {
+            column.push(parse_scenario_time(value)?.and_utc().timestamp_micros());
+        }
+    }
+
+    let schema = scenario_schema();
+    let [effective_from, effective_to, as_of_from, as_of_to] = temporal;
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(|row| row.0))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.1))),
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(|row| row.2))),
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(|row| row.3))),
+        Arc::new(TimestampMicrosecondArray::from(effective_from)),
+        Arc::new(TimestampMicrosecondArray::from(effective_to)),
+        Arc::new(TimestampMicrosecondArray::from(as_of_from)),
+        Arc::new(TimestampMicrosecondArray::from(as_of_to)),
+    ];
+    let unhashed = RecordBatch::try_new(Arc::new(Schema::new(schema.fields()[..8].to_vec())), columns.clone())
+        .map_err(|e| format!("Failed to build scenario batch: {}", e))?;
+    let all_rows: Vec<usize> = (0..rows.len()).collect();
+    let value_columns = SCENARIO_VALUE_COLUMNS.map(String::from);
+    let hashes = hash_values_batch_arrow_direct(&unhashed, &all_rows, &value_columns, HashAlgorithm::default().into());
+    columns.push(Arc::new(StringArray::from(hashes)));
+
+    RecordBatch::try_new(schema, columns).map_err(|e| format!("Failed to build scenario batch: {}", e))
+}
+
+fn parse_scenario_time(value: &str) -> Result<NaiveDateTime, String> {
+    if value == "max" {
+        return Ok(MAX_DATETIME);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| format!("Invalid scenario time '{}': expected YYYY-MM-DD, YYYY-MM-DD HH:MM:SS or max", value))
+}
+
+/// Compare the rows of `actual` and `expected` on `columns`, ignoring row order and batch
+/// boundaries. Returns `None` when they hold the same rows, otherwise a diff listing the
+/// missing and unexpected rows.
+pub fn diff_rows(actual: &[RecordBatch], expected: &[RecordBatch], columns: &[String]) -> Result<Option<String>, String> {
+    // Row rendering -> (expected count - actual count)
+    let mut balance: BTreeMap<String, i64> = BTreeMap::new();
+    for (batches, delta) in [(expected, 1), (actual, -1)] {
+        for row in render_rows(batches, columns)? {
+            *balance.entry(row).or_default() += delta;
+        }
+    }
+    balance.retain(|_, count| *count != 0);
+    if balance.is_empty() {
+        return Ok(None);
+    }
+
+    let differing: i64 = balance.values().map(|count| count.abs()).sum();
+    let mut diff = format!("{} row(s) differ on [{}]:", differing, columns.join(", "));
+    for (label, missing) in [("missing:   ", true), ("unexpected:", false)] {
+        for (row, &count) in &balance {
+            if (count > 0) == missing {
+                for _ in 0..count.abs() {
+                    diff.push_str(&format!("\n  {} {}", label, row));
+                }
+            }
+        }
+    }
+    Ok(Some(diff))
+}
+
+/// Every row of `batches` rendered as `column=value, ...`
+fn render_rows(batches: &[RecordBatch], columns: &[String]) -> Result<Vec<String>, String> {
+    let options = FormatOptions::default().with_null("null");
+    let mut rows = Vec::new();
+    for batch in batches {
+        let formatters = columns.iter()
+            .map(|name| {
+                let array = batch.column_by_name(name)
+                    .ok_or_else(|| format!("Column '{}' not found", name))?;
+                ArrayFormatter::try_new(array.as_ref(), &options)
+                    .map_err(|e| format!("Cannot format column '{}': {}", name, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for row in 0..batch.num_rows() {
+            let values: Vec<String> = columns.iter().zip(&formatters)
+                .map(|(name, formatter)| format!("{}={}", name, formatter.value(row)))
+                .collect();
+            rows.push(values.join(", "));
+        }
+    }
+    Ok(rows)
+}
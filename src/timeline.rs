@@ -1,8 +1,18 @@
 use crate::types::*;
 use crate::overlap::*;
+use crate::options::ConflictResolution;
+use crate::arrow_hash::ValueRows;
 use arrow::array::RecordBatch;
 use chrono::NaiveDate;
+use rustc_hash::FxHashMap;
 
+/// Current row index and why it is expired
+type ExpiredIndex = (usize, ExpireReason);
+
+/// Part of a current row re-emitted with its own values: `[from, to)`
+type KeptSegment = (chrono::NaiveDateTime, chrono::NaiveDateTime);
+
+#[allow(clippy::too_many_arguments)]
 pub fn process_id_timeline(
     current_records: &[BitemporalRecord],
     update_records: &[BitemporalRecord],
@@ -11,12 +21,30 @@ pub fn process_id_timeline(
     id_columns: &[String],
     value_columns: &[String],
     system_date: NaiveDate,
-) -> Result<(Vec<usize>, Vec<RecordBatch>), String> {
+    conflict_resolution: &ConflictResolution,
+    skipped_updates: &mut Vec<SkippedUpdate>,
+    value_rows: Option<&ValueRows>,
+) -> Result<(Vec<ExpiredIndex>, Vec<RecordBatch>), String> {
     let mut expire_indices = Vec::new();
+    // Segments over which each overlapping current row's own values are re-emitted
+    let mut kept_current: FxHashMap<usize, Vec<KeptSegment>> = FxHashMap::default();
     
     // Categorize records based on overlap relationships
-    let (overlapping_current, overlapping_updates, non_overlapping_updates) = 
-        categorize_records(current_records, update_records);
+    let (overlapping_current, mut overlapping_updates, mut non_overlapping_updates) = 
+        categorize_records(current_records, update_records, skipped_updates, value_rows);
+
+    // Updates that conflict with each other need the sweep to pick a winner,
+    // even when they don't touch current state
+    if *conflict_resolution != ConflictResolution::EventOrder {
+        let (conflicting, independent): (Vec<_>, Vec<_>) = non_overlapping_updates.into_iter()
+            .partition(|update| {
+                update_records.iter().any(|other| {
+                    other.original_index != update.original_index && has_temporal_intersection(other, update)
+                })
+            });
+        overlapping_updates.extend(conflicting);
+        non_overlapping_updates = independent;
+    }
     
     // Process non-overlapping updates directly
     let mut insert_batches = process_non_overlapping_updates(&non_overlapping_updates, updates_batch)?;
@@ -68,22 +96,18 @@ pub fn process_id_timeline(
         }
     }
     
-    // Sort events chronologically, with specific ordering for same dates
-    events.sort_by(|a, b| {
-        match a.date.cmp(&b.date) {
-            std::cmp::Ordering::Equal => {
-                // For same date, process in order: CurrentEnd, UpdateStart, UpdateEnd, CurrentStart
-                use EventType::*;
-                let order = |t: &EventType| match t {
-                    CurrentEnd => 0,
-                    UpdateStart => 1,
-                    UpdateEnd => 2,
-                    CurrentStart => 3,
-                };
-                order(&a.event_type).cmp(&order(&b.event_type))
-            }
-            other => other,
-        }
+    // Sort events chronologically, with specific ordering for same dates. Each key is computed
+    // once, so the sort itself compares plain integers; equal keys keep their insertion order.
+    events.sort_by_cached_key(|event| {
+        // For same date, process in order: CurrentEnd, UpdateStart, UpdateEnd, CurrentStart
+        use EventType::*;
+        let order: u8 = match event.event_type {
+            CurrentEnd => 0,
+            UpdateStart => 1,
+            UpdateEnd => 2,
+            CurrentStart => 3,
+        };
+        (event.date.and_utc().timestamp_micros(), order)
     });
     
     // Track active records at each point in time
@@ -110,9 +134,11 @@ pub fn process_id_timeline(
                     id_columns,
                     value_columns,
                     system_date,
-                    &mut expire_indices,
+                    &mut kept_current,
                     &mut insert_batches,
                     update_as_of_from,
+                    conflict_resolution,
+                    value_rows,
                 )?;
             }
         }
@@ -157,9 +183,11 @@ pub fn process_id_timeline(
                 id_columns,
                 value_columns,
                 system_date,
-                &mut expire_indices,
+                &mut kept_current,
                 &mut insert_batches,
                 update_as_of_from,
+                conflict_resolution,
+                value_rows,
             )?;
         }
     }
@@ -167,7 +195,20 @@ pub fn process_id_timeline(
     // Expire all current records that had overlaps (we already computed this)
     for current_record in &overlapping_current {
         if let Some(orig_idx) = current_record.original_index {
-            expire_indices.push(orig_idx);
+            // The sweep can emit the same segment more than once, so count each one once
+            let kept = kept_current.remove(&orig_idx).map_or_else(chrono::Duration::zero, |mut segments| {
+                segments.sort_unstable();
+                segments.dedup();
+                segments.iter().map(|(from, to)| *to - *from).sum()
+            });
+            let reason = if kept.is_zero() {
+                ExpireReason::Overwritten
+            } else if kept >= current_record.effective_to - current_record.effective_from {
+                ExpireReason::Merged
+            } else {
+                ExpireReason::Carved
+            };
+            expire_indices.push((orig_idx, reason));
         }
     }
     
@@ -185,9 +226,11 @@ pub fn emit_segment(
     id_columns: &[String],
     value_columns: &[String],
     _system_date: NaiveDate,
-    _expire_indices: &mut [usize],
+    kept_current: &mut FxHashMap<usize, Vec<KeptSegment>>,
     insert_batches: &mut Vec<RecordBatch>,
     update_as_of_from: Option<chrono::NaiveDateTime>,
+    conflict_resolution: &ConflictResolution,
+    value_rows: Option<&ValueRows>,
 ) -> Result<(), String> {
     // Skip empty ranges (from_date == to_date)
     // These represent zero-width time periods and are invalid
@@ -196,11 +239,12 @@ pub fn emit_segment(
     }
 
     // Determine what record to emit
-    let (record_to_emit, use_current_batch) = if let Some(update_record) = active_updates.first() {
+    let winning_update = select_winning_update(active_updates, conflict_resolution, updates_batch)?;
+    let (record_to_emit, use_current_batch) = if let Some(update_record) = winning_update.as_ref() {
         // Check if the update has different values than current state
         let should_emit_update = if let Some(current_record) = active_current.first() {
             // Only emit if values have actually changed
-            update_record.value_hash != current_record.value_hash
+            !same_values(current_record, update_record, value_rows)
         } else {
             // No current state, always emit the update
             true
@@ -229,7 +273,7 @@ pub fn emit_segment(
     
     let segment_record = BitemporalRecord {
         id_values: record_to_emit.id_values.clone(),
-        value_hash: record_to_emit.value_hash.clone(),
+        value_hash: record_to_emit.value_hash,
         effective_from: from_date,
         effective_to: to_date,
         as_of_from,
@@ -237,6 +281,12 @@ pub fn emit_segment(
         original_index: None,
     };
 
+    if use_current_batch {
+        if let Some(orig_idx) = record_to_emit.original_index {
+            kept_current.entry(orig_idx).or_default().push((from_date, to_date));
+        }
+    }
+
     // Create new batch since segments require synthetic records
     let batch = if use_current_batch {
         crate::batch_utils::create_record_batch_from_record(
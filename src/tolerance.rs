@@ -0,0 +1,167 @@
+use crate::options::{ProcessingOptions, ValueTolerance};
+use crate::types::VALUE_HASH_COLUMN;
+use crate::{create_id_key_with_buffer, get_temporal_bounds_micros};
+use arrow::array::{Array, ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow::datatypes::DataType;
+use rustc_hash::FxHashMap;
+
+/// Check `options.value_tolerances` against the value columns of the updates
+fn check_value_tolerances(
+    updates: &RecordBatch,
+    value_columns: &[String],
+    tolerances: &[ValueTolerance],
+) -> Result<(), String> {
+    for (i, tolerance) in tolerances.iter().enumerate() {
+        if !value_columns.contains(&tolerance.column) {
+            return Err(format!("Tolerance column '{}' is not a value column", tolerance.column));
+        }
+        if !(tolerance.absolute.is_finite() && tolerance.absolute >= 0.0 && tolerance.relative.is_finite() && tolerance.relative >= 0.0) {
+            return Err(format!("Tolerances for column '{}' must be finite and non-negative", tolerance.column));
+        }
+        if tolerances[..i].iter().any(|other| other.column == tolerance.column) {
+            return Err(format!("Tolerance column '{}' is configured twice", tolerance.column));
+        }
+        if let Some(column) = updates.column_by_name(&tolerance.column) {
+            if !column.data_type().is_numeric() {
+                return Err(format!(
+                    "Tolerance column '{}' has type {:?}, expected a numeric type", tolerance.column, column.data_type()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// After hashing, give an update row the values and hash of an overlapping current row of its
+/// ID when they differ only within `options.value_tolerances`: every other value column is
+/// equal and each tolerance column is within its absolute or relative bound. The rest of the
+/// run then sees an unchanged row. Returns the updates and how many rows were snapped.
+pub(crate) fn snap_tolerated_updates(
+    current_state: &RecordBatch,
+    updates: RecordBatch,
+    id_columns: &[String],
+    value_columns: &[String],
+    options: &ProcessingOptions,
+) -> Result<(RecordBatch, usize), String> {
+    if options.value_tolerances.is_empty() {
+        return Ok((updates, 0));
+    }
+    check_value_tolerances(&updates, value_columns, &options.value_tolerances)?;
+    if current_state.num_rows() == 0 || updates.num_rows() == 0 {
+        return Ok((updates, 0));
+    }
+
+    let hashes = |batch: &RecordBatch| -> Result<StringArray, String> {
+        let column = crate::batch_utils::required_column(batch, VALUE_HASH_COLUMN)?;
+        Ok(crate::batch_utils::downcast_column::<StringArray>(column, VALUE_HASH_COLUMN, "Utf8")?.clone())
+    };
+    let id_arrays = |batch: &RecordBatch| -> Result<Vec<ArrayRef>, String> {
+        id_columns.iter().map(|column| crate::batch_utils::required_column(batch, column).cloned()).collect()
+    };
+    let numeric = |batch: &RecordBatch, column: &str| -> Result<Float64Array, String> {
+        let array = crate::batch_utils::required_column(batch, column)?;
+        let cast = arrow::compute::cast(array, &DataType::Float64)
+            .map_err(|e| format!("Failed to read tolerance column '{}' as Float64: {}", column, e))?;
+        Ok(cast.as_any().downcast_ref::<Float64Array>().expect("cast to Float64").clone())
+    };
+
+    // Value columns without a tolerance must match exactly
+    let exact_columns: Vec<String> = value_columns.iter()
+        .filter(|column| !options.value_tolerances.iter().any(|tolerance| &tolerance.column == *column))
+        .cloned()
+        .collect();
+    let exact_rows = if exact_columns.is_empty() {
+        None
+    } else {
+        Some(crate::arrow_hash::ValueRows::new(current_state, &updates, &exact_columns)?)
+    };
+    let tolerated: Vec<(&ValueTolerance, Float64Array, Float64Array)> = options.value_tolerances.iter()
+        .map(|tolerance| Ok((tolerance, numeric(current_state, &tolerance.column)?, numeric(&updates, &tolerance.column)?)))
+        .collect::<Result<_, String>>()?;
+
+    let current_hashes = hashes(current_state)?;
+    let current_ids = id_arrays(current_state)?;
+    let mut current_by_id: FxHashMap<String, Vec<usize>> = FxHashMap::default();
+    let mut key = String::new();
+    for row in 0..current_state.num_rows() {
+        create_id_key_with_buffer(&current_ids, row, &mut key);
+        current_by_id.entry(key.clone()).or_default().push(row);
+    }
+
+    let update_hashes = hashes(&updates)?;
+    let update_ids = id_arrays(&updates)?;
+    // (update row, current row it takes its values from)
+    let mut snapped: Vec<(usize, usize)> = Vec::new();
+    for row in 0..updates.num_rows() {
+        create_id_key_with_buffer(&update_ids, row, &mut key);
+        let Some(current_rows) = current_by_id.get(&key) else {
+            continue;
+        };
+        let (effective_from, effective_to) = get_temporal_bounds_micros(&updates, row)?;
+        let mut overlapping = Vec::new();
+        for &current_row in current_rows {
+            let (current_from, current_to) = get_temporal_bounds_micros(current_state, current_row)?;
+            if current_from < effective_to && current_to > effective_from {
+                overlapping.push(current_row);
+            }
+        }
+        // A row that already matches an overlapping current row is left to the normal path
+        if overlapping.iter().any(|&current_row| current_hashes.value(current_row) == update_hashes.value(row)) {
+            continue;
+        }
+        let within = overlapping.into_iter().find(|&current_row| {
+            crate::arrow_hash::confirm_hash_match(exact_rows.as_ref(), current_row, row)
+                && tolerated.iter().all(|(tolerance, current, update)| within_tolerance(tolerance, current, current_row, update, row))
+        });
+        if let Some(current_row) = within {
+            snapped.push((row, current_row));
+        }
+    }
+
+    if snapped.is_empty() {
+        return Ok((updates, 0));
+    }
+    let count = snapped.len();
+    let mut sources: Vec<(usize, usize)> = (0..updates.num_rows()).map(|row| (0, row)).collect();
+    for &(row, current_row) in &snapped {
+        sources[row] = (1, current_row);
+    }
+
+    let schema = updates.schema();
+    let mut columns = updates.columns().to_vec();
+    let replaced = options.value_tolerances.iter().map(|tolerance| tolerance.column.as_str())
+        .chain(std::iter::once(VALUE_HASH_COLUMN))
+        .chain(options.hash_groups.iter().map(|group| group.column.as_str()));
+    for column in replaced {
+        let idx = schema.index_of(column).map_err(|e| e.to_string())?;
+        let current_array = crate::batch_utils::required_column(current_state, column)?;
+        let current_array = if current_array.data_type() == columns[idx].data_type() {
+            current_array.clone()
+        } else {
+            arrow::compute::cast(current_array, columns[idx].data_type())
+                .map_err(|e| format!("Failed to cast column '{}' for tolerance matching: {}", column, e))?
+        };
+        columns[idx] = arrow::compute::interleave(&[columns[idx].as_ref(), current_array.as_ref()], &sources)
+            .map_err(|e| e.to_string())?;
+    }
+    let updates = RecordBatch::try_new(schema, columns).map_err(|e| e.to_string())?;
+    Ok((updates, count))
+}
+
+fn within_tolerance(
+    tolerance: &ValueTolerance,
+    current: &Float64Array,
+    current_row: usize,
+    update: &Float64Array,
+    update_row: usize,
+) -> bool {
+    match (current.is_null(current_row), update.is_null(update_row)) {
+        (true, true) => true,
+        (false, false) => {
+            let (a, b) = (current.value(current_row), update.value(update_row));
+            let difference = (a - b).abs();
+            difference <= tolerance.absolute || difference <= tolerance.relative * a.abs().max(b.abs())
+        }
+        _ => false,
+    }
+}
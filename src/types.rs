@@ -1,12 +1,14 @@
-use arrow::array::{Array, ArrayRef, Date32Array, Date64Array, TimestampMicrosecondArray, TimestampNanosecondArray, TimestampSecondArray, TimestampMillisecondArray, RecordBatch, StringArray, Int8Array, Int16Array, Int32Array, Int64Array, Float32Array, Float64Array, BooleanArray, Decimal128Array};
-use arrow::datatypes::DataType;
+use arrow::array::{Array, ArrayRef, Date32Array, Date64Array, TimestampMicrosecondArray, TimestampNanosecondArray, TimestampSecondArray, TimestampMillisecondArray, RecordBatch, StringArray, Int8Array, Int16Array, Int32Array, Int64Array, UInt8Array, UInt16Array, UInt32Array, UInt64Array, Float32Array, Float64Array, BooleanArray, Decimal128Array, Decimal256Array, FixedSizeBinaryArray};
+use arrow::datatypes::{DataType, SchemaRef};
 use chrono::{NaiveDate, NaiveDateTime};
 // ordered_float imported as part of ScalarValue but not used directly
 
 #[derive(Debug, Clone)]
 pub struct BitemporalRecord {
     pub id_values: Vec<ScalarValue>,
-    pub value_hash: String,
+    /// Key of the row's `value_hash` (see `arrow_hash::value_hash_key`); the hash string
+    /// itself stays in the source batch
+    pub value_hash: u64,
     pub effective_from: NaiveDateTime,
     pub effective_to: NaiveDateTime,
     pub as_of_from: NaiveDateTime,
@@ -27,6 +29,10 @@ pub enum ScalarValue {
     Int16(i16),
     Int32(i32),
     Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
     Float32(ordered_float::OrderedFloat<f32>),
     Float64(ordered_float::OrderedFloat<f64>),
     Date32(i32),
@@ -36,7 +42,11 @@ pub enum ScalarValue {
     TimestampMicrosecond(i64),
     TimestampNanosecond(i64),
     Decimal128(i128),
+    Decimal256(arrow::datatypes::i256),
+    FixedSizeBinary(Vec<u8>),
     Boolean(bool),
+    /// Any other type, rendered with Arrow's display formatter
+    Other(String),
     Null,
 }
 
@@ -67,6 +77,22 @@ impl ScalarValue {
                 let arr = array.as_any().downcast_ref::<Int64Array>().unwrap();
                 ScalarValue::Int64(arr.value(idx))
             }
+            DataType::UInt8 => {
+                let arr = array.as_any().downcast_ref::<UInt8Array>().unwrap();
+                ScalarValue::UInt8(arr.value(idx))
+            }
+            DataType::UInt16 => {
+                let arr = array.as_any().downcast_ref::<UInt16Array>().unwrap();
+                ScalarValue::UInt16(arr.value(idx))
+            }
+            DataType::UInt32 => {
+                let arr = array.as_any().downcast_ref::<UInt32Array>().unwrap();
+                ScalarValue::UInt32(arr.value(idx))
+            }
+            DataType::UInt64 => {
+                let arr = array.as_any().downcast_ref::<UInt64Array>().unwrap();
+                ScalarValue::UInt64(arr.value(idx))
+            }
             DataType::Float32 => {
                 let arr = array.as_any().downcast_ref::<Float32Array>().unwrap();
                 ScalarValue::Float32(ordered_float::OrderedFloat(arr.value(idx)))
@@ -108,6 +134,14 @@ impl ScalarValue {
                 let arr = array.as_any().downcast_ref::<Decimal128Array>().unwrap();
                 ScalarValue::Decimal128(arr.value(idx))
             }
+            DataType::Decimal256(_, _) => {
+                let arr = array.as_any().downcast_ref::<Decimal256Array>().unwrap();
+                ScalarValue::Decimal256(arr.value(idx))
+            }
+            DataType::FixedSizeBinary(_) => {
+                let arr = array.as_any().downcast_ref::<FixedSizeBinaryArray>().unwrap();
+                ScalarValue::FixedSizeBinary(arr.value(idx).to_vec())
+            }
             DataType::Boolean => {
                 let arr = array.as_any().downcast_ref::<BooleanArray>().unwrap();
                 ScalarValue::Boolean(arr.value(idx))
@@ -116,16 +150,173 @@ impl ScalarValue {
                 // Entire column is NULL type (all values are NULL)
                 ScalarValue::Null
             }
-            _ => panic!("Unsupported data type: {:?}", array.data_type()),
+            _ => ScalarValue::Other(
+                arrow::util::display::array_value_to_string(array, idx)
+                    .unwrap_or_else(|_| format!("{:?}", array.slice(idx, 1)))
+            ),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ChangeSet {
     pub to_expire: Vec<usize>,
     pub to_insert: Vec<RecordBatch>,
     pub expired_records: Vec<RecordBatch>,  // Expired records with updated as_of_to
+    pub overridden_updates: Vec<usize>,  // Update rows that lost an intra-batch conflict
+    pub skipped_updates: Vec<SkippedUpdate>,  // Update rows ignored on purpose (when requested)
+    pub validation_issues: Vec<ValidationIssue>,  // Invalid update rows dropped by lenient validation
+    pub clamped_updates: Vec<usize>,  // Update rows trimmed to the clamp_from / clamp_to window
+    pub insert_schema: Option<SchemaRef>,  // Schema `to_insert` is written with, set by a run even when nothing is inserted
+    pub expired_schema: Option<SchemaRef>,  // Schema `expired_records` is written with, likewise
+    pub metrics: ProcessingMetrics,  // Row counts and phase timings of this run
+}
+
+/// Row counts and phase timings for one `process_updates` call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessingMetrics {
+    pub current_rows: usize,
+    pub update_rows: usize,
+    /// Update rows merged into a neighbour by `conflate_inputs`
+    pub conflated_updates: usize,
+    /// Update rows trimmed to the `clamp_from` / `clamp_to` window
+    pub clamped_updates: usize,
+    /// Update rows whose values were within `value_tolerances` of a current row
+    pub tolerated_updates: usize,
+    /// Current rows left unhashed by `lazy_hashing` because their ID has no update rows
+    pub unhashed_current_rows: usize,
+    pub id_groups: usize,
+    pub expired_rows: usize,
+    pub inserted_rows: usize,
+    /// ID grouping; when it overlaps value hashing, the two together
+    pub group_ids_duration: std::time::Duration,
+    pub process_groups_duration: std::time::Duration,
+    pub build_changeset_duration: std::time::Duration,
+    pub total_duration: std::time::Duration,
+    /// The changeset was returned from the idempotency ledger rather than computed
+    pub replayed: bool,
+    /// Output row counts per tenant when `ProcessingOptions::tenant_column` is set
+    pub tenant_stats: Vec<TenantStats>,
+}
+
+/// Rows one tenant contributes to a changeset
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantStats {
+    pub tenant: String,
+    pub expired_rows: usize,
+    pub inserted_rows: usize,
+}
+
+/// Why an update row was intentionally not applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// effective_from >= effective_to - a zero-width period
+    EmptyRange,
+    /// Delta update intersects current state carrying the same values
+    NoChange,
+    /// Full state update has the same values and effective range as a current row
+    ExactMatch,
+    /// Full state update lies inside a current row with the same values
+    CoveredByCurrent,
+    /// Update lies entirely before the `min_effective_from` watermark (clamp policy)
+    BeforeWatermark,
+    /// Update row failed lenient validation (details in `ChangeSet::validation_issues`)
+    InvalidRow,
+    /// Update lies entirely outside the `clamp_from` / `clamp_to` window
+    OutsideClampWindow,
+    /// Update was merged into an adjacent update with the same values by `conflate_inputs`
+    Conflated,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::EmptyRange => "empty_range",
+            SkipReason::NoChange => "no_change",
+            SkipReason::ExactMatch => "exact_match",
+            SkipReason::CoveredByCurrent => "covered_by_current",
+            SkipReason::BeforeWatermark => "before_watermark",
+            SkipReason::OutsideClampWindow => "outside_clamp_window",
+            SkipReason::InvalidRow => "invalid_row",
+            SkipReason::Conflated => "conflated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkippedUpdate {
+    pub update_index: usize,
+    pub reason: SkipReason,
+}
+
+/// Name the engine gives the value hash column (see `ProcessingOptions::hash_column`)
+pub const VALUE_HASH_COLUMN: &str = "value_hash";
+
+/// The bitemporal columns every input and output batch carries
+pub const TEMPORAL_COLUMNS: [&str; 4] = ["effective_from", "effective_to", "as_of_from", "as_of_to"];
+
+/// Name of the optional reason column appended to `ChangeSet::expired_records`
+pub const EXPIRE_REASON_COLUMN: &str = "expire_reason";
+
+/// Name of the optional list column appended to `ChangeSet::to_insert` naming the
+/// value columns that changed relative to the expired rows
+pub const CHANGED_COLUMNS_COLUMN: &str = "changed_columns";
+
+/// Why a current row was expired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExpireReason {
+    /// An update replaced the row's values over its whole range
+    Overwritten,
+    /// The row's ID (or partition) disappeared from a full_state load
+    Tombstoned,
+    /// The row was combined with an update carrying the same values into a longer segment
+    Merged,
+    /// An update replaced part of the row; the rest is re-inserted around it
+    Carved,
+}
+
+impl ExpireReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExpireReason::Overwritten => "OVERWRITTEN",
+            ExpireReason::Tombstoned => "TOMBSTONED",
+            ExpireReason::Merged => "MERGED",
+            ExpireReason::Carved => "CARVED",
+        }
+    }
+}
+
+/// What is wrong with an update row found by `validate_updates`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationIssueKind {
+    /// An ID column is null
+    NullId,
+    /// A temporal column is null or holds a value that cannot be read as a timestamp
+    InvalidTimestamp,
+    /// effective_from is after effective_to
+    InvertedRange,
+    /// effective_from equals effective_to (only reported under `EmptyRangePolicy::Error` / `Report`)
+    EmptyRange,
+}
+
+impl ValidationIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationIssueKind::NullId => "null_id",
+            ValidationIssueKind::InvalidTimestamp => "invalid_timestamp",
+            ValidationIssueKind::InvertedRange => "inverted_range",
+            ValidationIssueKind::EmptyRange => "empty_range",
+        }
+    }
+}
+
+/// One offending value in an update row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub row_index: usize,
+    pub column: String,
+    pub kind: ValidationIssueKind,
+    pub value: String,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,191 @@
+use crate::extract_datetime_flexible;
+use crate::types::{ValidationIssue, ValidationIssueKind};
+use arrow::array::{Array, BooleanArray, RecordBatch};
+use arrow::datatypes::{DataType, Schema, TimeUnit};
+use rustc_hash::FxHashSet;
+
+/// Most issues spelled out in a strict-mode error before it is truncated
+const MAX_REPORTED_ISSUES: usize = 20;
+
+/// Check every update row for null IDs, null or unreadable temporal values and
+/// `effective_from > effective_to`. Returns the issues in row order.
+///
+/// Only errors when a required column is missing; bad values are reported, not raised.
+pub fn validate_updates(batch: &RecordBatch, id_columns: &[String]) -> Result<Vec<ValidationIssue>, String> {
+    let id_arrays = id_columns.iter()
+        .map(|col| batch.column_by_name(col)
+            .map(|array| (col.as_str(), array))
+            .ok_or_else(|| format!("ID column '{}' not found", col)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut temporal_arrays = Vec::with_capacity(4);
+    for name in ["effective_from", "effective_to", "as_of_from", "as_of_to"] {
+        match batch.column_by_name(name) {
+            Some(array) => temporal_arrays.push((name, array)),
+            // The as-of columns are optional on updates; the effective range is not
+            None if name.starts_with("effective") => return Err(format!("{} column not found", name)),
+            None => {}
+        }
+    }
+
+    let mut issues = Vec::new();
+    for row in 0..batch.num_rows() {
+        for &(column, array) in &id_arrays {
+            if array.is_null(row) {
+                issues.push(issue(row, column, ValidationIssueKind::NullId, "null".to_string()));
+            }
+        }
+
+        let mut effective_range = (None, None);
+        for &(column, array) in &temporal_arrays {
+            let value = if array.is_null(row) {
+                Err("null".to_string())
+            } else {
+                extract_datetime_flexible(array.as_ref(), row)
+            };
+            match value {
+                Ok(datetime) if column == "effective_from" => effective_range.0 = Some(datetime),
+                Ok(datetime) if column == "effective_to" => effective_range.1 = Some(datetime),
+                Ok(_) => {}
+                Err(message) => issues.push(issue(row, column, ValidationIssueKind::InvalidTimestamp, message)),
+            }
+        }
+
+        if let (Some(from), Some(to)) = effective_range {
+            if from > to {
+                issues.push(issue(
+                    row,
+                    "effective_from",
+                    ValidationIssueKind::InvertedRange,
+                    format!("{} > effective_to {}", from, to),
+                ));
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+fn issue(row_index: usize, column: &str, kind: ValidationIssueKind, value: String) -> ValidationIssue {
+    ValidationIssue { row_index, column: column.to_string(), kind, value }
+}
+
+/// Issues for update rows with an empty effective range (`effective_from >= effective_to`)
+/// that `flagged` doesn't already name. Rows with null or unreadable bounds are left to the
+/// other checks.
+pub(crate) fn empty_range_issues(batch: &RecordBatch, flagged: &[ValidationIssue]) -> Vec<ValidationIssue> {
+    let (Some(from_array), Some(to_array)) = (batch.column_by_name("effective_from"), batch.column_by_name("effective_to")) else {
+        return Vec::new();
+    };
+    let read = |array: &arrow::array::ArrayRef, row: usize| {
+        (!array.is_null(row)).then(|| extract_datetime_flexible(array.as_ref(), row).ok()).flatten()
+    };
+
+    let flagged: FxHashSet<usize> = flagged.iter().map(|issue| issue.row_index).collect();
+    let mut issues = Vec::new();
+    for row in 0..batch.num_rows() {
+        if flagged.contains(&row) {
+            continue;
+        }
+        let (Some(from), Some(to)) = (read(from_array, row), read(to_array, row)) else {
+            continue;
+        };
+        if from == to {
+            issues.push(issue(row, "effective_from", ValidationIssueKind::EmptyRange, format!("{} = effective_to", from)));
+        } else if from > to {
+            issues.push(issue(row, "effective_from", ValidationIssueKind::InvertedRange, format!("{} > effective_to {}", from, to)));
+        }
+    }
+    issues
+}
+
+/// Strict-mode error message listing the offending rows
+pub(crate) fn format_validation_error(issues: &[ValidationIssue]) -> String {
+    let mut message = format!("Update validation failed with {} issue(s):", issues.len());
+    for issue in issues.iter().take(MAX_REPORTED_ISSUES) {
+        message.push_str(&format!(
+            "\n  row {}: {} in '{}' ({})",
+            issue.row_index, issue.kind.as_str(), issue.column, issue.value
+        ));
+    }
+    if issues.len() > MAX_REPORTED_ISSUES {
+        message.push_str(&format!("\n  ... and {} more", issues.len() - MAX_REPORTED_ISSUES));
+    }
+    message
+}
+
+/// Drop the rows named in `issues`, returning the filtered batch and the original index
+/// of every kept row
+pub(crate) fn drop_invalid_rows(batch: RecordBatch, issues: &[ValidationIssue]) -> Result<(RecordBatch, Vec<usize>), String> {
+    let mut keep = vec![true; batch.num_rows()];
+    for issue in issues {
+        keep[issue.row_index] = false;
+    }
+    let kept_rows: Vec<usize> = keep.iter().enumerate().filter(|(_, &k)| k).map(|(row, _)| row).collect();
+    if kept_rows.len() == batch.num_rows() {
+        return Ok((batch, kept_rows));
+    }
+
+    let filtered = arrow::compute::filter_record_batch(&batch, &BooleanArray::from(keep))
+        .map_err(|e| format!("Failed to drop invalid update rows: {}", e))?;
+    Ok((filtered, kept_rows))
+}
+
+/// Differences between the current state and updates schemas, one readable line each:
+/// columns only one side has, and shared columns whose types differ (timestamp units and
+/// timezones included), e.g. `updates.effective_from is Timestamp(ns, UTC) but current is
+/// Timestamp(us, None)`. Column order, nullability and metadata are not compared.
+pub fn diff_schemas(current: &Schema, updates: &Schema) -> Vec<String> {
+    schema_differences(current, updates, |_| false)
+}
+
+/// `diff_schemas`, leaving out columns for which `one_sided` holds when only one side has them
+pub(crate) fn schema_differences(current: &Schema, updates: &Schema, one_sided: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut differences = Vec::new();
+    for field in current.fields() {
+        match updates.field_with_name(field.name()) {
+            Ok(update_field) if update_field.data_type() != field.data_type() => differences.push(format!(
+                "updates.{} is {} but current is {}",
+                field.name(), describe_type(update_field.data_type()), describe_type(field.data_type())
+            )),
+            Ok(_) => {}
+            Err(_) if one_sided(field.name()) => {}
+            Err(_) => differences.push(format!("current has column '{}' that updates lack", field.name())),
+        }
+    }
+    for field in updates.fields() {
+        if current.field_with_name(field.name()).is_err() && !one_sided(field.name()) {
+            differences.push(format!("updates has column '{}' that current lacks", field.name()));
+        }
+    }
+    differences
+}
+
+/// Error listing every schema difference, or None when there are none
+pub(crate) fn format_schema_error(differences: &[String]) -> Option<String> {
+    if differences.is_empty() {
+        return None;
+    }
+    let mut message = format!("Schemas of current_state and updates differ ({}):", differences.len());
+    for difference in differences {
+        message.push_str("\n  ");
+        message.push_str(difference);
+    }
+    Some(message)
+}
+
+/// Data type with timestamp units abbreviated and the timezone spelled out
+fn describe_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Timestamp(unit, tz) => {
+            let unit = match unit {
+                TimeUnit::Second => "s",
+                TimeUnit::Millisecond => "ms",
+                TimeUnit::Microsecond => "us",
+                TimeUnit::Nanosecond => "ns",
+            };
+            format!("Timestamp({}, {})", unit, tz.as_deref().unwrap_or("None"))
+        }
+        other => other.to_string(),
+    }
+}
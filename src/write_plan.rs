@@ -0,0 +1,84 @@
+//! Ordered write steps for targets that enforce non-overlap per ID (e.g. a Postgres exclusion
+//! constraint on the ID and effective range of current rows)
+
+use crate::types::ChangeSet;
+use arrow::array::{BooleanArray, RecordBatch};
+
+/// What a write step does to the target table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStepKind {
+    /// Set `as_of_to` on the rows keyed by the ID columns, `effective_from` and `as_of_from`
+    Expire,
+    /// Insert closing rows: truncated copies of expired rows that end a record's effective range
+    Tombstone,
+    /// Insert new and replacement rows
+    Insert,
+}
+
+impl WriteStepKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WriteStepKind::Expire => "expire",
+            WriteStepKind::Tombstone => "tombstone",
+            WriteStepKind::Insert => "insert",
+        }
+    }
+}
+
+/// One batch of rows to write, all of one kind
+#[derive(Debug, Clone)]
+pub struct WriteStep {
+    pub kind: WriteStepKind,
+    pub batch: RecordBatch,
+}
+
+/// The steps of a changeset in the order they must be written. See `ChangeSet::write_plan`.
+#[derive(Debug, Clone, Default)]
+pub struct WritePlan {
+    pub steps: Vec<WriteStep>,
+}
+
+impl ChangeSet {
+    /// Sequence the changeset as expire steps, then tombstone steps, then insert steps.
+    ///
+    /// Guarantees, for a changeset computed against the target's current rows:
+    ///
+    /// 1. Every expire step comes before any tombstone or insert step. Once they are applied, no
+    ///    current row left in the target overlaps any row still to be written for its ID.
+    /// 2. Tombstone and insert rows never overlap each other in effective time for one ID, so
+    ///    they may be written in any order or in parallel once the expiries are in.
+    /// 3. Steps within a kind are independent of one another.
+    ///
+    /// Writing every step in order inside one transaction therefore never violates an exclusion
+    /// constraint on (ID, effective range) over current rows. Empty steps are omitted.
+    /// Tombstones are told from inserts by the hashes in `hash_column`; without them every
+    /// new row is an insert step.
+    pub fn write_plan(&self, id_columns: &[String], hash_column: &str) -> Result<WritePlan, String> {
+        let mut steps: Vec<WriteStep> = self.expired_records.iter()
+            .filter(|batch| batch.num_rows() > 0)
+            .map(|batch| WriteStep { kind: WriteStepKind::Expire, batch: batch.clone() })
+            .collect();
+
+        let insert_matches = self.match_inserts(id_columns, hash_column)?;
+        let mut inserts = Vec::with_capacity(self.to_insert.len());
+        for (batch, matches) in self.to_insert.iter().zip(insert_matches) {
+            let tombstones = BooleanArray::from(matches.iter().map(|m| m.is_tombstone).collect::<Vec<_>>());
+            let others = arrow::compute::not(&tombstones)
+                .map_err(|e| format!("Failed to split tombstones from inserts: {}", e))?;
+            for (kind, mask) in [(WriteStepKind::Tombstone, &tombstones), (WriteStepKind::Insert, &others)] {
+                let rows = arrow::compute::filter_record_batch(batch, mask)
+                    .map_err(|e| format!("Failed to split tombstones from inserts: {}", e))?;
+                if rows.num_rows() == 0 {
+                    continue;
+                }
+                match kind {
+                    WriteStepKind::Tombstone => steps.push(WriteStep { kind, batch: rows }),
+                    _ => inserts.push(WriteStep { kind, batch: rows }),
+                }
+            }
+        }
+        steps.extend(inserts);
+
+        Ok(WritePlan { steps })
+    }
+}
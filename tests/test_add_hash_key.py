@@ -9,8 +9,9 @@ import pandas as pd
 import numpy as np
 from datetime import datetime, date
 import time
+import pyarrow as pa
 
-from pytemporal import add_hash_key, BitemporalTimeseriesProcessor, INFINITY_TIMESTAMP
+from pytemporal import add_hash_key, add_hash_key_reader, BitemporalTimeseriesProcessor, INFINITY_TIMESTAMP
 
 
 class TestAddHashKeyBasicFunctionality:
@@ -640,6 +641,45 @@ class TestHashAlgorithmParameter:
         assert all(len(h) == 16 for h in result['value_hash'])  # XxHash length
 
 
+
+class TestAddHashKeyReader:
+    """Test chunk-by-chunk hashing of tables and streams."""
+
+    def test_chunked_table_matches_single_batch(self):
+        """Hashing a multi-chunk table gives the same hashes as one DataFrame."""
+        df = pd.DataFrame({
+            'id': [1, 2, 3, 4, 5],
+            'price': [100.5, 200.0, 300.25, 400.0, 500.75],
+            'volume': [10, 20, 30, 40, 50]
+        })
+        table = pa.Table.from_pandas(df, preserve_index=False)
+        chunked = pa.Table.from_batches(table.to_batches(max_chunksize=2))
+        assert chunked.column('id').num_chunks == 3
+
+        reader = add_hash_key_reader(chunked, ['price', 'volume'])
+        assert isinstance(reader, pa.RecordBatchReader)
+        result = reader.read_all()
+
+        expected = add_hash_key(df, ['price', 'volume'])
+        assert result.column('value_hash').to_pylist() == expected['value_hash'].tolist()
+
+    def test_record_batch_reader_input(self):
+        """A RecordBatchReader is hashed lazily with the requested algorithm."""
+        df = pd.DataFrame({'id': [1, 2, 3], 'value': [1.0, 2.0, 3.0]})
+        table = pa.Table.from_pandas(df, preserve_index=False)
+        source = pa.RecordBatchReader.from_batches(table.schema, table.to_batches(max_chunksize=1))
+
+        result = add_hash_key_reader(source, ['value'], hash_algorithm='sha256').read_all()
+
+        expected = add_hash_key(df, ['value'], hash_algorithm='sha256')
+        assert result.column('value_hash').to_pylist() == expected['value_hash'].tolist()
+
+    def test_missing_column_raises(self):
+        """Unknown value fields are reported before any chunk is read."""
+        table = pa.table({'id': [1, 2], 'value': [1.0, 2.0]})
+        with pytest.raises(ValueError, match="missing"):
+            add_hash_key_reader(table, ['missing'])
+
 if __name__ == '__main__':
     # Run the tests if executed directly
     pytest.main([__file__, '-v'])
\ No newline at end of file
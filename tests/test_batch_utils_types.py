@@ -5,6 +5,7 @@ This test ensures that create_record_batch_from_records function correctly handl
 all PostgreSQL data types without falling back to the less efficient slice method.
 """
 
+import uuid
 import pandas as pd
 import pytest
 import numpy as np
@@ -230,8 +231,46 @@ def test_type_mixing_edge_cases():
     
     print("✅ Type mixing edge cases test passed")
 
+def test_uuid_id_columns():
+    """UUID objects and UUID strings in either case identify the same rows."""
+    ids = [uuid.UUID("67e55044-10b1-426f-9247-bb680e5fe0c8"), uuid.UUID("00000000-0000-4000-8000-000000000002")]
+
+    current_data = pd.DataFrame([
+        {
+            "id": ids[0], "price": 100,
+            "effective_from": pd.Timestamp("2024-01-01"), "effective_to": pd.Timestamp("2260-12-31"),
+            "as_of_from": pd.Timestamp("2024-01-01"), "as_of_to": pd.Timestamp("2260-12-31"),
+        },
+        {
+            "id": ids[1], "price": 200,
+            "effective_from": pd.Timestamp("2024-01-01"), "effective_to": pd.Timestamp("2260-12-31"),
+            "as_of_from": pd.Timestamp("2024-01-01"), "as_of_to": pd.Timestamp("2260-12-31"),
+        },
+    ])
+    updates_data = pd.DataFrame([
+        {
+            "id": str(ids[0]).upper(), "price": 100,
+            "effective_from": pd.Timestamp("2024-03-01"), "effective_to": pd.Timestamp("2260-12-31"),
+            "as_of_from": pd.Timestamp("2024-03-01"), "as_of_to": pd.Timestamp("2260-12-31"),
+        },
+        {
+            "id": str(ids[1]), "price": 250,
+            "effective_from": pd.Timestamp("2024-03-01"), "effective_to": pd.Timestamp("2260-12-31"),
+            "as_of_from": pd.Timestamp("2024-03-01"), "as_of_to": pd.Timestamp("2260-12-31"),
+        },
+    ])
+
+    processor = BitemporalTimeseriesProcessor(id_columns=["id"], value_columns=["price"])
+    expire_df, insert_df = processor.compute_changes(current_data, updates_data, update_mode="delta")
+
+    # Only the second ID changed; the first matched despite the case difference
+    assert list(expire_df["id"]) == [str(ids[1])]
+    assert len(insert_df) == 2
+    assert set(insert_df["id"]) == {str(ids[1])}
+
 if __name__ == "__main__":
     test_batch_utils_comprehensive_types()
     test_null_values_with_all_types()
     test_type_mixing_edge_cases()
+    test_uuid_id_columns()
     print("🎉 All batch utilities PostgreSQL types tests passed!")
\ No newline at end of file
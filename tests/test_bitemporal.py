@@ -1209,3 +1209,23 @@ def test_bounded_to_open_ended_extension_same_values():
     # Verify the insert is the open-ended version
     assert inserts.iloc[0]['effective_to'] == INFINITY_TIMESTAMP, \
         f"Expected insert to be open-ended, got effective_to={inserts.iloc[0]['effective_to']}"
+
+
+def test_fixed_clock_sets_exact_as_of_to():
+    """A fixed clock makes the expiry timestamp exact instead of 'some time after now'."""
+    processor = BitemporalTimeseriesProcessor(id_columns=['id', 'field'], value_columns=['mv', 'price'])
+    current_state = pd.DataFrame([
+        [1234, 'test', 300, 400, pd.Timestamp('2020-01-01'), INFINITY_TIMESTAMP,
+         pd.Timestamp('2025-01-01'), INFINITY_TIMESTAMP],
+    ], columns=default_columns)
+    updates = pd.DataFrame([
+        [1234, 'test', 500, 400, pd.Timestamp('2020-06-01'), INFINITY_TIMESTAMP,
+         pd.Timestamp('2025-07-27'), INFINITY_TIMESTAMP],
+    ], columns=default_columns)
+
+    expire, insert = processor.compute_changes(
+        current_state, updates, system_date='2025-07-27', fixed_clock='2025-07-27 09:30:15'
+    )
+
+    assert expire['as_of_to'].to_list() == [pd.Timestamp('2025-07-27 09:30:15')]
+    assert len(insert) == 2
@@ -0,0 +1,66 @@
+"""
+Tests for compute_changes_reader, the RecordBatchReader variant of compute_changes.
+"""
+from datetime import datetime
+
+import pyarrow as pa
+
+from pytemporal import compute_changes, compute_changes_reader
+
+
+def _batch(rows):
+    ts = pa.timestamp('us')
+    return pa.RecordBatch.from_pydict(
+        {
+            'id': pa.array([r[0] for r in rows], pa.int32()),
+            'mv': pa.array([r[1] for r in rows], pa.int32()),
+            'effective_from': pa.array([r[2] for r in rows], ts),
+            'effective_to': pa.array([r[3] for r in rows], ts),
+            'as_of_from': pa.array([r[4] for r in rows], ts),
+            'as_of_to': pa.array([r[5] for r in rows], ts),
+        }
+    )
+
+
+MAX = datetime(2262, 4, 11)
+
+
+def _inputs():
+    current = _batch([(1, 100, datetime(2024, 1, 1), MAX, datetime(2024, 1, 1), MAX)])
+    updates = _batch([(1, 200, datetime(2024, 3, 1), MAX, datetime(2024, 3, 1), MAX)])
+    return current, updates
+
+
+def test_reader_matches_list_output():
+    current, updates = _inputs()
+    args = (['id'], ['mv'], '2024-03-01', 'delta')
+
+    expire_indices, insert_batches, expired_batches = compute_changes(current, updates, *args)
+    reader_expire, insert_reader, expired_reader = compute_changes_reader(current, updates, *args)
+
+    assert isinstance(insert_reader, pa.RecordBatchReader)
+    assert isinstance(expired_reader, pa.RecordBatchReader)
+    assert reader_expire.type == pa.uint64()
+    assert reader_expire.to_pylist() == expire_indices.to_pylist()
+
+    inserted = insert_reader.read_all()
+    expected = pa.Table.from_batches([pa.record_batch(b) for b in insert_batches])
+    assert inserted.num_rows == expected.num_rows
+    assert sorted(inserted.column('mv').to_pylist()) == sorted(expected.column('mv').to_pylist())
+    assert expired_reader.read_all().num_rows == sum(pa.record_batch(b).num_rows for b in expired_batches)
+
+
+def test_reader_with_no_changes_is_empty():
+    current, _ = _inputs()
+    empty_updates = current.slice(0, 0)
+
+    expire_indices, insert_reader, expired_reader = compute_changes_reader(
+        current, empty_updates, ['id'], ['mv'], '2024-03-01', 'delta'
+    )
+
+    assert len(expire_indices) == 0
+    assert insert_reader.read_all().num_rows == 0
+    assert expired_reader.read_all().num_rows == 0
+    # Empty readers carry the schema the engine writes, including the computed hash
+    assert 'value_hash' in insert_reader.schema.names
+    assert 'value_hash' in expired_reader.schema.names
@@ -0,0 +1,66 @@
+"""Tests for the pytemporal.testing scenario helpers."""
+import pyarrow as pa
+import pytest
+
+from pytemporal.testing import (
+    COMPARED_COLUMNS,
+    assert_changes,
+    assert_scenario,
+    diff_changes,
+    run_scenario,
+    scenario_batch,
+    scenario_schema,
+)
+
+
+CURRENT = [(1, 'test', 100, 10, '2020-01-01', 'max', '2025-01-01')]
+UPDATES = [(1, 'test', 200, 10, '2020-06-01', 'max', '2025-07-27')]
+EXPECTED_INSERT = [
+    (1, 'test', 100, 10, '2020-01-01', '2020-06-01', '2025-07-27'),
+    (1, 'test', 200, 10, '2020-06-01', 'max', '2025-07-27'),
+]
+
+
+class TestScenarioHelpers:
+    """Scenario construction, running and diffing."""
+
+    def test_scenario_batch_uses_canonical_schema(self):
+        batch = scenario_batch(CURRENT)
+        assert batch.schema == scenario_schema()
+        assert batch.num_rows == 1
+        assert batch.column('value_hash')[0].as_py()
+        assert batch.column('as_of_to')[0].as_py().year == 2262
+
+    def test_run_scenario_returns_expired_and_inserted_rows(self):
+        expired, inserted = run_scenario(CURRENT, UPDATES)
+        assert expired.num_rows == 1
+        assert sum(batch.num_rows for batch in inserted) == 2
+        assert diff_changes(inserted, EXPECTED_INSERT) is None
+
+    def test_assert_scenario_passes(self):
+        assert_scenario(CURRENT, UPDATES, expected_expire=CURRENT, expected_insert=EXPECTED_INSERT)
+
+    def test_assert_scenario_reports_diff(self):
+        wrong_insert = [EXPECTED_INSERT[0], (1, 'test', 300, 10, '2020-06-01', 'max', '2025-07-27')]
+        with pytest.raises(AssertionError) as excinfo:
+            assert_scenario(CURRENT, UPDATES, expected_expire=CURRENT, expected_insert=wrong_insert)
+        message = str(excinfo.value)
+        assert message.startswith('inserted rows: 2 row(s) differ')
+        assert 'missing:    id=1, field=test, mv=300' in message
+        assert 'unexpected: id=1, field=test, mv=200' in message
+
+    def test_diff_is_order_independent(self):
+        rows = scenario_batch(EXPECTED_INSERT)
+        assert diff_changes(rows, list(reversed(EXPECTED_INSERT))) is None
+        assert_changes([rows.slice(0, 1), rows.slice(1)], EXPECTED_INSERT)
+
+    def test_diff_on_selected_columns(self):
+        rows = scenario_batch(EXPECTED_INSERT)
+        other_prices = [row[:3] + (99,) + row[4:] for row in EXPECTED_INSERT]
+        assert diff_changes(rows, other_prices) is not None
+        columns = [column for column in COMPARED_COLUMNS if column != 'price']
+        assert diff_changes(rows, other_prices, columns=columns) is None
+
+    def test_invalid_time_raises(self):
+        with pytest.raises(ValueError, match='2020-13-01'):
+            scenario_batch([(1, 'test', 1, 1, '2020-13-01', 'max', '2025-01-01')])
@@ -337,7 +337,7 @@ class TestTimezoneHandling:
                 'delta'
             )
             # If we get here without a schema error, the fix is working!
-            assert isinstance(expire_indices, list)
+            assert isinstance(expire_indices, pa.Array)
             assert expired_batch is not None
             assert insert_batch is not None
             